@@ -2,14 +2,15 @@
 set -o errexit -o nounset -o pipefail
 command -v shellcheck > /dev/null && shellcheck "$0"
 
-# Iterates over all example projects, builds and tests them.
+# Iterates over all example projects and the shared packages, builds and tests them.
 # This script is for development purposes only. In the CI, each example project
 # is configured separately.
 
 export RUST_BACKTRACE=1
 
 for example in ./*; do
-  if [[ -d "$example" ]]; then
+  # the shared packages are libraries without the wasm and schema aliases, see below
+  if [[ -d "$example" && "$example" != "./packages" ]]; then
     echo "Building and testing $example ..."
 
     (
@@ -30,3 +31,16 @@ for example in ./*; do
     )
   fi
 done
+
+for package in ./packages/*; do
+  if [[ -d "$package" ]]; then
+    echo "Building and testing $package ..."
+
+    (
+        cd "$package"
+        cargo fmt
+        cargo build
+        cargo test
+    )
+  fi
+done
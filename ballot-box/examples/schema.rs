@@ -0,0 +1,25 @@
+use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
+use std::env::current_dir;
+use std::fs::create_dir_all;
+
+use cw_ballot_box::msg::{
+    BallotBoxResponse, BallotBoxesResponse, ConfigResponse, HandleMsg, InitMsg, QueryMsg,
+    VotingInitMsg,
+};
+use cw_ballot_box::state::State;
+
+fn main() {
+    let mut out_dir = current_dir().unwrap();
+    out_dir.push("schema");
+    create_dir_all(&out_dir).unwrap();
+    remove_schemas(&out_dir).unwrap();
+
+    export_schema(&schema_for!(InitMsg), &out_dir);
+    export_schema(&schema_for!(HandleMsg), &out_dir);
+    export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(BallotBoxResponse), &out_dir);
+    export_schema(&schema_for!(BallotBoxesResponse), &out_dir);
+    export_schema(&schema_for!(VotingInitMsg), &out_dir);
+}
@@ -0,0 +1,279 @@
+use crate::msg::{
+    BallotBoxResponse, BallotBoxesResponse, ConfigResponse, HandleMsg, InitHook, InitMsg, QueryMsg,
+    VotingConfig, VotingInitMsg,
+};
+use crate::state::{
+    ballot_box, ballot_box_read, community, community_read, config, config_read, BallotBox, State,
+};
+use cosmwasm_std::{
+    log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, InitResponse, Order,
+    Querier, StdError, StdResult, Storage, WasmMsg,
+};
+
+const MIN_COMMUNITY_LENGTH: usize = 3;
+const MAX_COMMUNITY_LENGTH: usize = 64;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    let state = State {
+        owner: deps.api.canonical_address(&env.message.sender)?,
+        voting_code_id: msg.voting_code_id,
+        ballot_box_count: 0,
+    };
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        HandleMsg::CreateBallotBox {
+            community,
+            description,
+            denom,
+            config,
+        } => create_ballot_box(deps, env, community, description, denom, config),
+        HandleMsg::Register { id } => register(deps, env, id),
+        HandleMsg::Deregister { id } => deregister(deps, env, id),
+        HandleMsg::UpdateVotingCodeId { voting_code_id } => {
+            update_voting_code_id(deps, env, voting_code_id)
+        }
+    }
+}
+
+/// Saves the ballot box without an address and instantiates its voting contract, whose init
+/// hook then registers the address. If instantiation fails the whole transaction is reverted.
+fn create_ballot_box<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    community_name: String,
+    description: String,
+    denom: String,
+    voting_config: VotingConfig,
+) -> StdResult<HandleResponse> {
+    validate_community(&community_name)?;
+    if community_read(&deps.storage)
+        .may_load(community_name.as_bytes())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Community already has a ballot box"));
+    }
+
+    let mut state = config(&mut deps.storage).load()?;
+    state.ballot_box_count += 1;
+    let id = state.ballot_box_count;
+    config(&mut deps.storage).save(&state)?;
+
+    let a_ballot_box = BallotBox {
+        community: community_name.clone(),
+        description,
+        denom: denom.clone(),
+        creator: deps.api.canonical_address(&env.message.sender)?,
+        contract_addr: None,
+        created_at_height: env.block.height,
+    };
+    ballot_box(&mut deps.storage).save(&id.to_be_bytes(), &a_ballot_box)?;
+    community(&mut deps.storage).save(community_name.as_bytes(), &id)?;
+
+    let voting_init_msg = VotingInitMsg {
+        denom,
+        reward_denom: voting_config.reward_denom,
+        rate_source: voting_config.rate_source,
+        tally_executor: voting_config.tally_executor,
+        owner: Some(env.message.sender),
+        init_hook: Some(InitHook {
+            contract_addr: env.contract.address,
+            msg: to_binary(&HandleMsg::Register { id })?,
+        }),
+    };
+    let r = HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Instantiate {
+            code_id: state.voting_code_id,
+            msg: to_binary(&voting_init_msg)?,
+            send: vec![],
+            label: Some(community_name.clone()),
+        })],
+        log: vec![
+            log("action", "create_ballot_box"),
+            log("ballot_box_id", id),
+            log("community", community_name),
+        ],
+        data: Some(to_binary(&id)?),
+    };
+    Ok(r)
+}
+
+fn register<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    id: u64,
+) -> StdResult<HandleResponse> {
+    let key = id.to_be_bytes();
+    let mut a_ballot_box = match ballot_box_read(&deps.storage).may_load(&key)? {
+        Some(a_ballot_box) => a_ballot_box,
+        None => return Err(StdError::generic_err("Ballot box does not exist")),
+    };
+    if a_ballot_box.contract_addr.is_some() {
+        return Err(StdError::generic_err("Ballot box is already registered"));
+    }
+    a_ballot_box.contract_addr = Some(deps.api.canonical_address(&env.message.sender)?);
+    ballot_box(&mut deps.storage).save(&key, &a_ballot_box)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "register"),
+            log("ballot_box_id", id),
+            log("contract_addr", env.message.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+fn deregister<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    id: u64,
+) -> StdResult<HandleResponse> {
+    let key = id.to_be_bytes();
+    let a_ballot_box = match ballot_box_read(&deps.storage).may_load(&key)? {
+        Some(a_ballot_box) => a_ballot_box,
+        None => return Err(StdError::generic_err("Ballot box does not exist")),
+    };
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    let state = config_read(&deps.storage).load()?;
+    if sender != a_ballot_box.creator && sender != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    ballot_box(&mut deps.storage).remove(&key);
+    community(&mut deps.storage).remove(a_ballot_box.community.as_bytes());
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "deregister"), log("ballot_box_id", id)],
+        data: None,
+    };
+    Ok(r)
+}
+
+fn update_voting_code_id<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    voting_code_id: u64,
+) -> StdResult<HandleResponse> {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    state.voting_code_id = voting_code_id;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "update_voting_code_id"),
+            log("voting_code_id", voting_code_id),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// validate_community returns an error if the community name is too short or too long
+fn validate_community(community_name: &str) -> StdResult<()> {
+    if community_name.len() < MIN_COMMUNITY_LENGTH {
+        Err(StdError::generic_err("Community too short"))
+    } else if community_name.len() > MAX_COMMUNITY_LENGTH {
+        Err(StdError::generic_err("Community too long"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::BallotBox { id } => to_binary(&query_ballot_box(deps, id)?),
+        QueryMsg::BallotBoxes { start_after, limit } => {
+            to_binary(&query_ballot_boxes(deps, start_after, limit)?)
+        }
+    }
+}
+
+fn query_config<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ConfigResponse> {
+    let state = config_read(&deps.storage).load()?;
+    Ok(ConfigResponse {
+        owner: deps.api.human_address(&state.owner)?,
+        voting_code_id: state.voting_code_id,
+        ballot_box_count: state.ballot_box_count,
+    })
+}
+
+fn query_ballot_box<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    id: u64,
+) -> StdResult<BallotBoxResponse> {
+    match ballot_box_read(&deps.storage).may_load(&id.to_be_bytes())? {
+        Some(a_ballot_box) => to_ballot_box_response(&deps.api, id, a_ballot_box),
+        None => Err(StdError::generic_err("Ballot box does not exist")),
+    }
+}
+
+fn query_ballot_boxes<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<BallotBoxesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| {
+        let mut start = id.to_be_bytes().to_vec();
+        start.push(0);
+        start
+    });
+    let ballot_boxes = ballot_box_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, a_ballot_box) = item?;
+            let mut id = [0u8; 8];
+            id.copy_from_slice(&key);
+            to_ballot_box_response(&deps.api, u64::from_be_bytes(id), a_ballot_box)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BallotBoxesResponse { ballot_boxes })
+}
+
+fn to_ballot_box_response<A: Api>(
+    api: &A,
+    id: u64,
+    a_ballot_box: BallotBox,
+) -> StdResult<BallotBoxResponse> {
+    Ok(BallotBoxResponse {
+        id,
+        community: a_ballot_box.community,
+        description: a_ballot_box.description,
+        denom: a_ballot_box.denom,
+        creator: api.human_address(&a_ballot_box.creator)?,
+        contract_addr: a_ballot_box
+            .contract_addr
+            .map(|contract_addr| api.human_address(&contract_addr))
+            .transpose()?,
+        created_at_height: a_ballot_box.created_at_height,
+    })
+}
@@ -0,0 +1,91 @@
+use cosmwasm_std::{Binary, HumanAddr};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitMsg {
+    /// Code id of the voting contract to instantiate for each community
+    pub voting_code_id: u64,
+}
+
+/// Settings of a community's voting contract besides its staking denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingConfig {
+    pub reward_denom: Option<String>,
+    pub rate_source: Option<HumanAddr>,
+    pub tally_executor: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    /// Instantiates a voting contract owned by the signer for `community`, 3 to 64 bytes and
+    /// unique among registered ballot boxes
+    CreateBallotBox {
+        community: String,
+        description: String,
+        denom: String,
+        config: VotingConfig,
+    },
+    /// Called by a new voting contract's init hook to record its address
+    Register { id: u64 },
+    /// Drops a ballot box from the registry, leaving its voting contract running. Only its
+    /// creator or the owner can deregister it.
+    Deregister { id: u64 },
+    /// Owner only. Ballot boxes created afterwards use the new code.
+    UpdateVotingCodeId { voting_code_id: u64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    BallotBox {
+        id: u64,
+    },
+    /// Ballot boxes in creation order
+    BallotBoxes {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: HumanAddr,
+    pub voting_code_id: u64,
+    pub ballot_box_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BallotBoxResponse {
+    pub id: u64,
+    pub community: String,
+    pub description: String,
+    pub denom: String,
+    pub creator: HumanAddr,
+    pub contract_addr: Option<HumanAddr>,
+    pub created_at_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BallotBoxesResponse {
+    pub ballot_boxes: Vec<BallotBoxResponse>,
+}
+
+/// The part of the voting contract's InitMsg the factory sets
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingInitMsg {
+    pub denom: String,
+    pub reward_denom: Option<String>,
+    pub rate_source: Option<HumanAddr>,
+    pub tally_executor: Option<HumanAddr>,
+    pub owner: Option<HumanAddr>,
+    pub init_hook: Option<InitHook>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitHook {
+    pub contract_addr: HumanAddr,
+    pub msg: Binary,
+}
@@ -0,0 +1,56 @@
+use cosmwasm_std::{CanonicalAddr, Storage};
+use cosmwasm_storage::{
+    bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
+    Singleton,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+static CONFIG_KEY: &[u8] = b"config";
+static BALLOT_BOX_KEY: &[u8] = b"ballot_boxes";
+static COMMUNITY_KEY: &[u8] = b"communities";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    pub owner: CanonicalAddr,
+    /// Code id of the voting contract every ballot box is instantiated from
+    pub voting_code_id: u64,
+    pub ballot_box_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BallotBox {
+    pub community: String,
+    pub description: String,
+    pub denom: String,
+    pub creator: CanonicalAddr,
+    /// Set by the voting contract's init hook, in the same transaction that created the box
+    pub contract_addr: Option<CanonicalAddr>,
+    pub created_at_height: u64,
+}
+
+pub fn config<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, State> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn config_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+/// Ballot boxes keyed by id in big-endian, so they iterate in creation order
+pub fn ballot_box<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, BallotBox> {
+    bucket(BALLOT_BOX_KEY, storage)
+}
+
+pub fn ballot_box_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, BallotBox> {
+    bucket_read(BALLOT_BOX_KEY, storage)
+}
+
+/// The id of each community's ballot box
+pub fn community<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, u64> {
+    bucket(COMMUNITY_KEY, storage)
+}
+
+pub fn community_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, u64> {
+    bucket_read(COMMUNITY_KEY, storage)
+}
@@ -0,0 +1,245 @@
+#[cfg(test)]
+mod tests {
+    use crate::contract::{handle, init, query};
+    use crate::msg::{
+        BallotBoxResponse, BallotBoxesResponse, ConfigResponse, HandleMsg, InitHook, InitMsg,
+        QueryMsg, VotingConfig, VotingInitMsg,
+    };
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{
+        from_binary, log, to_binary, CosmosMsg, Extern, HumanAddr, StdError, WasmMsg,
+    };
+
+    const VOTING_CODE_ID: u64 = 7;
+    const TEST_OWNER: &str = "owner";
+    const TEST_CREATOR: &str = "creator";
+
+    fn mock_init(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let msg = InitMsg {
+            voting_code_id: VOTING_CODE_ID,
+        };
+        let env = mock_env(TEST_OWNER, &[]);
+        init(deps, env, msg).expect("contract successfully handles InitMsg");
+    }
+
+    fn create_ballot_box_msg(community: &str) -> HandleMsg {
+        HandleMsg::CreateBallotBox {
+            community: community.to_string(),
+            description: format!("Polls of {}", community),
+            denom: "ucommunity".to_string(),
+            config: VotingConfig {
+                reward_denom: None,
+                rate_source: None,
+                tally_executor: None,
+            },
+        }
+    }
+
+    /// Creates a ballot box and registers `contract_addr` as its voting contract
+    fn create_ballot_box(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        community: &str,
+        contract_addr: &str,
+    ) -> u64 {
+        let env = mock_env(TEST_CREATOR, &[]);
+        let res = handle(deps, env, create_ballot_box_msg(community)).unwrap();
+        let id: u64 = from_binary(&res.data.unwrap()).unwrap();
+
+        let env = mock_env(contract_addr, &[]);
+        handle(deps, env, HandleMsg::Register { id }).unwrap();
+        id
+    }
+
+    fn query_ballot_box(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        id: u64,
+    ) -> Result<BallotBoxResponse, StdError> {
+        query(deps, QueryMsg::BallotBox { id }).map(|res| from_binary(&res).unwrap())
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let res = query(&deps, QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            ConfigResponse {
+                owner: HumanAddr::from(TEST_OWNER),
+                voting_code_id: VOTING_CODE_ID,
+                ballot_box_count: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn create_ballot_box_instantiates_voting_contract() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(TEST_CREATOR, &[]);
+        let contract_address = env.contract.address.clone();
+        let res = handle(&mut deps, env, create_ballot_box_msg("cosmonauts")).unwrap();
+        let voting_init_msg = VotingInitMsg {
+            denom: "ucommunity".to_string(),
+            reward_denom: None,
+            rate_source: None,
+            tally_executor: None,
+            owner: Some(HumanAddr::from(TEST_CREATOR)),
+            init_hook: Some(InitHook {
+                contract_addr: contract_address,
+                msg: to_binary(&HandleMsg::Register { id: 1 }).unwrap(),
+            }),
+        };
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Instantiate {
+                code_id: VOTING_CODE_ID,
+                msg: to_binary(&voting_init_msg).unwrap(),
+                send: vec![],
+                label: Some("cosmonauts".to_string()),
+            })]
+        );
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "create_ballot_box"),
+                log("ballot_box_id", "1"),
+                log("community", "cosmonauts"),
+            ]
+        );
+        assert_eq!(query_ballot_box(&deps, 1).unwrap().contract_addr, None);
+
+        let env = mock_env("voting0001", &[]);
+        handle(&mut deps, env, HandleMsg::Register { id: 1 }).unwrap();
+        assert_eq!(
+            query_ballot_box(&deps, 1).unwrap(),
+            BallotBoxResponse {
+                id: 1,
+                community: "cosmonauts".to_string(),
+                description: "Polls of cosmonauts".to_string(),
+                denom: "ucommunity".to_string(),
+                creator: HumanAddr::from(TEST_CREATOR),
+                contract_addr: Some(HumanAddr::from("voting0001")),
+                created_at_height: 12_345,
+            }
+        );
+
+        let env = mock_env("intruder", &[]);
+        match handle(&mut deps, env, HandleMsg::Register { id: 1 }) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Ballot box is already registered")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_create_ballot_box_for_registered_community() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        create_ballot_box(&mut deps, "cosmonauts", "voting0001");
+
+        let env = mock_env("someone", &[]);
+        match handle(&mut deps, env, create_ballot_box_msg("cosmonauts")) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Community already has a ballot box")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        let env = mock_env("someone", &[]);
+        match handle(&mut deps, env, create_ballot_box_msg("ab")) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Community too short"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn deregister_by_creator_or_owner() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let first = create_ballot_box(&mut deps, "cosmonauts", "voting0001");
+        let second = create_ballot_box(&mut deps, "validators", "voting0002");
+
+        let env = mock_env("someone", &[]);
+        match handle(&mut deps, env, HandleMsg::Deregister { id: first }) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::Unauthorized { .. }) => {}
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        let env = mock_env(TEST_CREATOR, &[]);
+        handle(&mut deps, env, HandleMsg::Deregister { id: first }).unwrap();
+        let env = mock_env(TEST_OWNER, &[]);
+        let res = handle(&mut deps, env, HandleMsg::Deregister { id: second }).unwrap();
+        assert_eq!(
+            res.log,
+            vec![log("action", "deregister"), log("ballot_box_id", "2")]
+        );
+
+        match query_ballot_box(&deps, first) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Ballot box does not exist"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        // the community can get a new ballot box, with a new id
+        let third = create_ballot_box(&mut deps, "cosmonauts", "voting0003");
+        assert_eq!(third, 3);
+    }
+
+    #[test]
+    fn query_ballot_boxes_paginates() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        for (community, contract_addr) in &[
+            ("cosmonauts", "voting0001"),
+            ("validators", "voting0002"),
+            ("delegators", "voting0003"),
+        ] {
+            create_ballot_box(&mut deps, community, contract_addr);
+        }
+        let env = mock_env(TEST_OWNER, &[]);
+        handle(&mut deps, env, HandleMsg::Deregister { id: 2 }).unwrap();
+
+        let ballot_boxes = |start_after: Option<u64>, limit: Option<u32>| {
+            let res = query(&deps, QueryMsg::BallotBoxes { start_after, limit }).unwrap();
+            let value: BallotBoxesResponse = from_binary(&res).unwrap();
+            value
+                .ballot_boxes
+                .into_iter()
+                .map(|ballot_box| ballot_box.community)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ballot_boxes(None, None), vec!["cosmonauts", "delegators"]);
+        assert_eq!(ballot_boxes(None, Some(1)), vec!["cosmonauts"]);
+        assert_eq!(ballot_boxes(Some(1), Some(1)), vec!["delegators"]);
+        assert_eq!(ballot_boxes(Some(3), None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn update_voting_code_id_only_by_owner() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::UpdateVotingCodeId { voting_code_id: 8 };
+        let env = mock_env(TEST_CREATOR, &[]);
+        match handle(&mut deps, env, msg.clone()) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::Unauthorized { .. }) => {}
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        let env = mock_env(TEST_OWNER, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        let res = query(&deps, QueryMsg::Config {}).unwrap();
+        let value: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(value.voting_code_id, 8);
+    }
+}
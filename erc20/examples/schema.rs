@@ -4,7 +4,15 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use cw_erc20::contract::Constants;
-use cw_erc20::msg::{AllowanceResponse, BalanceResponse, HandleMsg, InitMsg, QueryMsg};
+use cw_erc20::msg::{
+    AccountMetadataResponse, AllAccountsResponse, AllAllowancesResponse, AllowanceResponse,
+    AllowanceTreeResponse, BalanceAtSnapshotResponse, BalanceHookMsg, BalanceHookResponse,
+    BalanceResponse, BalancesResponse, BootstrapResponse, BurnAddressesResponse, BurnResponse,
+    BurnerResponse, CheckpointResponse, ComplianceQueryMsg, ComplianceResponse, Event, HandleMsg,
+    HasRoleResponse, HoldersAtResponse, InitMsg, OutflowHeadroomResponse, PoolsResponse, QueryMsg,
+    ReceiptsResponse, ReceivedTokensResponse, RoleMembersResponse, SessionKeyResponse,
+    SimulateTransferFromResponse, TransferRestrictionResponse, VerifyMemoResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,7 +23,34 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(BalanceAtSnapshotResponse), &out_dir);
+    export_schema(&schema_for!(BalanceHookMsg), &out_dir);
+    export_schema(&schema_for!(BalanceHookResponse), &out_dir);
     export_schema(&schema_for!(BalanceResponse), &out_dir);
+    export_schema(&schema_for!(BalancesResponse), &out_dir);
+    export_schema(&schema_for!(AllAccountsResponse), &out_dir);
+    export_schema(&schema_for!(AllAllowancesResponse), &out_dir);
     export_schema(&schema_for!(AllowanceResponse), &out_dir);
+    export_schema(&schema_for!(AllowanceTreeResponse), &out_dir);
+    export_schema(&schema_for!(BootstrapResponse), &out_dir);
+    export_schema(&schema_for!(BurnAddressesResponse), &out_dir);
+    export_schema(&schema_for!(BurnResponse), &out_dir);
+    export_schema(&schema_for!(BurnerResponse), &out_dir);
+    export_schema(&schema_for!(AccountMetadataResponse), &out_dir);
+    export_schema(&schema_for!(CheckpointResponse), &out_dir);
+    export_schema(&schema_for!(Event), &out_dir);
+    export_schema(&schema_for!(HasRoleResponse), &out_dir);
+    export_schema(&schema_for!(HoldersAtResponse), &out_dir);
+    export_schema(&schema_for!(OutflowHeadroomResponse), &out_dir);
+    export_schema(&schema_for!(SimulateTransferFromResponse), &out_dir);
+    export_schema(&schema_for!(PoolsResponse), &out_dir);
+    export_schema(&schema_for!(ReceiptsResponse), &out_dir);
+    export_schema(&schema_for!(ReceivedTokensResponse), &out_dir);
+    export_schema(&schema_for!(RoleMembersResponse), &out_dir);
+    export_schema(&schema_for!(SessionKeyResponse), &out_dir);
+    export_schema(&schema_for!(TransferRestrictionResponse), &out_dir);
+    export_schema(&schema_for!(VerifyMemoResponse), &out_dir);
+    export_schema(&schema_for!(ComplianceQueryMsg), &out_dir);
+    export_schema(&schema_for!(ComplianceResponse), &out_dir);
     export_schema(&schema_for!(Constants), &out_dir);
 }
@@ -1,13 +1,27 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
 
-use crate::msg::{AllowanceResponse, BalanceResponse, HandleMsg, InitMsg, QueryMsg};
+use crate::cursor::{decode_cursor, next_cursor};
+use crate::msg::{
+    AccountBalance, AccountMetadataResponse, AllAccountsResponse, AllAllowancesResponse,
+    AllowanceNode, AllowanceResponse, AllowanceTreeResponse, BalanceAtSnapshotResponse,
+    BalanceHookMsg, BalanceHookResponse, BalanceResponse, BalanceResult, BalancesResponse,
+    BootstrapResponse, BurnAddressesResponse, BurnResponse, BurnerResponse, ChainHop,
+    CheckpointResponse, ComplianceQueryMsg, ComplianceResponse, Cw20ReceiveMsg, Event, HandleMsg,
+    HasRoleResponse, HoldersAtResponse, InitMsg, OutflowHeadroomResponse, PoolsResponse, QueryMsg,
+    ReceiptResponse, ReceiptsResponse, ReceivedToken, ReceivedTokensResponse, Role,
+    RoleMembersResponse, SessionKeyResponse, SimulateTransferFromResponse, SpenderAllowance,
+    SubAllowanceInfo, TransferConstraint, TransferRestrictionResponse, VerifyMemoResponse,
+};
 use cosmwasm_std::{
-    log, to_binary, to_vec, Api, Binary, CanonicalAddr, Env, Extern, HandleResponse, HumanAddr,
-    InitResponse, Querier, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+    from_slice, log, to_binary, to_vec, Api, BankMsg, Binary, BlockInfo, CanonicalAddr, Coin,
+    CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse, LogAttribute, Order, Querier,
+    QueryRequest, ReadonlyStorage, StdError, StdResult, Storage, Uint128, WasmMsg, WasmQuery,
 };
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use cw_math::{bytes_to_u128, bytes_to_u64, read_u128};
+use cw_storage_helpers::Deque;
+use sha2::{Digest, Sha256};
 
 #[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Constants {
@@ -16,26 +30,151 @@ pub struct Constants {
     pub decimals: u8,
 }
 
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ComplianceConfig {
+    pub contract: CanonicalAddr,
+    pub bypass: Vec<CanonicalAddr>,
+}
+
+/// Present while transfers are restricted to the whitelist
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct TransferRestriction {
+    pub whitelist: Vec<CanonicalAddr>,
+}
+
+/// Present while bootstrap grants are enabled
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct BootstrapConfig {
+    pub grant: Coin,
+    pub pool: Uint128,
+    /// Smallest transfer that pays the grant
+    #[serde(default)]
+    pub min_transfer: Uint128,
+}
+
+/// Present while outflows are capped
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct OutflowCap {
+    pub cap: Uint128,
+}
+
+/// Tokens an address sent during the day starting at `window_start`
+#[derive(Serialize, Debug, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+pub struct Outflow {
+    pub window_start: u64,
+    pub sent: Uint128,
+}
+
+/// Set by the account itself with SetAccountMetadata
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct AccountMetadata {
+    pub name: Option<String>,
+    pub avatar_hash: Option<String>,
+}
+
+/// Present while a burner is configured
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct BurnerConfig {
+    pub reserve: CanonicalAddr,
+}
+
+/// Contract notified when the owner's balance crosses `threshold`
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct BalanceHook {
+    pub contract: CanonicalAddr,
+    pub threshold: Uint128,
+}
+
+/// Liquidity pools trading the token, which admins manage
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct PoolRegistry {
+    pub pools: Vec<CanonicalAddr>,
+}
+
+/// Addresses whose incoming transfers are burned, which admins manage
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct BurnAddresses {
+    pub addresses: Vec<CanonicalAddr>,
+    pub redirected: Uint128,
+}
+
+/// Spending rights an owner delegated to a session key
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SessionKey {
+    pub remaining: Uint128,
+    pub expires_at_height: u64,
+}
+
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct Receipt {
+    pub payer: CanonicalAddr,
+    pub merchant: CanonicalAddr,
+    pub amount: Uint128,
+    pub reference: String,
+    pub reference_hashed: bool,
+    pub height: u64,
+}
+
 pub const PREFIX_CONFIG: &[u8] = b"config";
 pub const PREFIX_BALANCES: &[u8] = b"balances";
+pub const PREFIX_BALANCE_CHECKPOINTS: &[u8] = b"balance_checkpoints";
 pub const PREFIX_ALLOWANCES: &[u8] = b"allowances";
+pub const PREFIX_SUB_ALLOWANCES: &[u8] = b"sub_allowances";
+pub const PREFIX_RECEIPTS: &[u8] = b"receipts";
+pub const PREFIX_RECEIPTS_BY_ADDRESS: &[u8] = b"receipts_by_address";
+pub const PREFIX_RECEIVED_TOKENS: &[u8] = b"received_tokens";
+pub const PREFIX_TX_KEYS: &[u8] = b"tx_keys";
+pub const PREFIX_OUTFLOWS: &[u8] = b"outflows";
+pub const PREFIX_SESSION_KEYS: &[u8] = b"session_keys";
+pub const PREFIX_BALANCE_HOOKS: &[u8] = b"balance_hooks";
+pub const PREFIX_SNAPSHOTS: &[u8] = b"snapshots";
+pub const PREFIX_TX_KEY_QUEUES: &[u8] = b"tx_key_queues";
+pub const PREFIX_ACCOUNT_METADATA: &[u8] = b"account_metadata";
+pub const PREFIX_ROLES: &[u8] = b"roles";
 
 pub const KEY_CONSTANTS: &[u8] = b"constants";
 pub const KEY_TOTAL_SUPPLY: &[u8] = b"total_supply";
+pub const KEY_RECEIPT_COUNT: &[u8] = b"receipt_count";
+pub const KEY_COMPLIANCE: &[u8] = b"compliance";
+pub const KEY_BLOCKED_RECIPIENTS: &[u8] = b"blocked_recipients";
+pub const KEY_TRANSFER_RESTRICTION: &[u8] = b"transfer_restriction";
+pub const KEY_BOOTSTRAP: &[u8] = b"bootstrap";
+pub const KEY_OUTFLOW_CAP: &[u8] = b"outflow_cap";
+pub const KEY_POOL_REGISTRY: &[u8] = b"pool_registry";
+pub const KEY_SNAPSHOT_COUNT: &[u8] = b"snapshot_count";
+pub const KEY_BURNER: &[u8] = b"burner";
+pub const KEY_BURN_ADDRESSES: &[u8] = b"burn_addresses";
+pub const KEY_HASHED_REFERENCES: &[u8] = b"hashed_references";
+
+const MAX_REFERENCE_LENGTH: usize = 128;
+const MAX_TX_KEY_LENGTH: usize = 64;
+const MAX_ACCOUNT_NAME_LENGTH: usize = 32;
+const MAX_AVATAR_HASH_LENGTH: usize = 64;
+// Number of most recent transfer keys remembered per sender for replay protection
+const TX_KEY_RETENTION: u64 = 100;
+const OUTFLOW_WINDOW_SECONDS: u64 = 86_400;
+// Pagination settings for list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+const MAX_BALANCES_BATCH: usize = 30;
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    _env: Env,
+    env: Env,
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
     let mut total_supply: u128 = 0;
     {
         // Initial balances
-        let mut balances_store = PrefixedStorage::new(PREFIX_BALANCES, &mut deps.storage);
         for row in msg.initial_balances {
             let raw_address = deps.api.canonical_address(&row.address)?;
             let amount_raw = row.amount.u128();
-            balances_store.set(raw_address.as_slice(), &amount_raw.to_be_bytes());
+            write_balance(
+                &mut deps.storage,
+                &raw_address,
+                amount_raw,
+                env.block.height,
+            );
             total_supply += amount_raw;
         }
     }
@@ -55,7 +194,82 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         return Err(StdError::generic_err("Decimals must not exceed 18"));
     }
 
+    let compliance = match msg.compliance {
+        Some(compliance) => Some(ComplianceConfig {
+            contract: deps.api.canonical_address(&compliance.contract)?,
+            bypass: compliance
+                .bypass
+                .iter()
+                .map(|address| deps.api.canonical_address(address))
+                .collect::<StdResult<Vec<_>>>()?,
+        }),
+        None => None,
+    };
+    let blocked_recipients = msg
+        .blocked_recipients
+        .unwrap_or_default()
+        .iter()
+        .map(|address| deps.api.canonical_address(address))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let creator = deps.api.canonical_address(&env.message.sender)?;
+    set_role(&mut deps.storage, Role::Admin, &creator, true);
+    for address in msg.governance_contracts.unwrap_or_default() {
+        let address_raw = deps.api.canonical_address(&address)?;
+        set_role(&mut deps.storage, Role::Governance, &address_raw, true);
+    }
+    let burner = match msg.burner {
+        Some(role) => {
+            let burner_raw = deps.api.canonical_address(&role.burner)?;
+            set_role(&mut deps.storage, Role::Burner, &burner_raw, true);
+            Some(BurnerConfig {
+                reserve: deps.api.canonical_address(&role.reserve)?,
+            })
+        }
+        None => None,
+    };
+
     let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    if let Some(compliance) = compliance {
+        config_store.set(KEY_COMPLIANCE, &to_vec(&compliance)?);
+    }
+    if !blocked_recipients.is_empty() {
+        config_store.set(KEY_BLOCKED_RECIPIENTS, &to_vec(&blocked_recipients)?);
+    }
+    if let Some(burner) = burner {
+        config_store.set(KEY_BURNER, &to_vec(&burner)?);
+    }
+    if msg.hashed_references.unwrap_or(false) {
+        config_store.set(KEY_HASHED_REFERENCES, &[1]);
+    }
+    if msg.restricted_transfers.unwrap_or(false) {
+        let restriction = TransferRestriction { whitelist: vec![] };
+        config_store.set(KEY_TRANSFER_RESTRICTION, &to_vec(&restriction)?);
+    }
+    if let Some(grant) = msg.bootstrap_grant {
+        if grant.amount.is_zero() {
+            return Err(StdError::generic_err(
+                "Bootstrap grant must be greater than 0",
+            ));
+        }
+        let bootstrap = BootstrapConfig {
+            grant,
+            pool: Uint128::zero(),
+            min_transfer: msg.bootstrap_min_transfer.unwrap_or_default(),
+        };
+        config_store.set(KEY_BOOTSTRAP, &to_vec(&bootstrap)?);
+    }
+    if let Some(cap) = msg.daily_outflow_cap {
+        let outflow_cap = OutflowCap { cap };
+        config_store.set(KEY_OUTFLOW_CAP, &to_vec(&outflow_cap)?);
+    }
+    let pool_registry = PoolRegistry { pools: vec![] };
+    config_store.set(KEY_POOL_REGISTRY, &to_vec(&pool_registry)?);
+    let burn_addresses = BurnAddresses {
+        addresses: vec![],
+        redirected: Uint128::zero(),
+    };
+    config_store.set(KEY_BURN_ADDRESSES, &to_vec(&burn_addresses)?);
     let constants = to_vec(&Constants {
         name: msg.name,
         symbol: msg.symbol,
@@ -74,13 +288,80 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     match msg {
         HandleMsg::Approve { spender, amount } => try_approve(deps, env, &spender, &amount),
-        HandleMsg::Transfer { recipient, amount } => try_transfer(deps, env, &recipient, &amount),
+        HandleMsg::Transfer {
+            recipient,
+            amount,
+            tx_key,
+        } => try_transfer(deps, env, &recipient, &amount, tx_key),
+        HandleMsg::Send {
+            contract,
+            amount,
+            msg,
+            tx_key,
+        } => try_send(deps, env, &contract, &amount, msg, tx_key),
         HandleMsg::TransferFrom {
             owner,
             recipient,
             amount,
         } => try_transfer_from(deps, env, &owner, &recipient, &amount),
         HandleMsg::Burn { amount } => try_burn(deps, env, &amount),
+        HandleMsg::Pull {
+            owner,
+            amount,
+            reference,
+        } => try_pull(deps, env, &owner, &amount, reference),
+        HandleMsg::SubApprove {
+            owner,
+            spender,
+            amount,
+        } => try_sub_approve(deps, env, &owner, &spender, &amount),
+        HandleMsg::SubTransferFrom {
+            owner,
+            delegator,
+            recipient,
+            amount,
+        } => try_sub_transfer_from(deps, env, &owner, &delegator, &recipient, &amount),
+        HandleMsg::TransferFromChain {
+            hops,
+            recipient,
+            amount,
+        } => try_transfer_from_chain(deps, env, &hops, &recipient, &amount),
+        HandleMsg::SetWhitelisted {
+            address,
+            whitelisted,
+        } => try_set_whitelisted(deps, env, &address, whitelisted),
+        HandleMsg::LiftTransferRestriction {} => try_lift_transfer_restriction(deps, env),
+        HandleMsg::Receive(msg) => try_receive(deps, env, msg),
+        HandleMsg::WithdrawReceived { token, amount } => {
+            try_withdraw_received(deps, env, &token, amount)
+        }
+        HandleMsg::FundBootstrap {} => try_fund_bootstrap(deps, env),
+        HandleMsg::SetOutflowCap { cap } => try_set_outflow_cap(deps, env, cap),
+        HandleMsg::RegisterSessionKey {
+            key,
+            spend_limit,
+            expires_at_height,
+        } => try_register_session_key(deps, env, &key, spend_limit, expires_at_height),
+        HandleMsg::RevokeSessionKey { key } => try_revoke_session_key(deps, env, &key),
+        HandleMsg::RegisterPool { pool } => try_set_pool(deps, env, &pool, true),
+        HandleMsg::DeregisterPool { pool } => try_set_pool(deps, env, &pool, false),
+        HandleMsg::AddBurnAddress { address } => try_set_burn_address(deps, env, &address, true),
+        HandleMsg::RemoveBurnAddress { address } => {
+            try_set_burn_address(deps, env, &address, false)
+        }
+        HandleMsg::SetBalanceHook {
+            contract,
+            threshold,
+        } => try_set_balance_hook(deps, env, &contract, threshold),
+        HandleMsg::RemoveBalanceHook {} => try_remove_balance_hook(deps, env),
+        HandleMsg::Checkpoint {} => try_checkpoint(deps, env),
+        HandleMsg::BurnFromReserve { amount } => try_burn_from_reserve(deps, env, &amount),
+        HandleMsg::SetAccountMetadata { name, avatar_hash } => {
+            try_set_account_metadata(deps, env, name, avatar_hash)
+        }
+        HandleMsg::GrantRole { role, address } => try_set_role(deps, env, role, &address, true),
+        HandleMsg::RevokeRole { role, address } => try_set_role(deps, env, role, &address, false),
+        HandleMsg::RenounceRole { role } => try_renounce_role(deps, env, role),
     }
 }
 
@@ -97,6 +378,7 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
             })?;
             Ok(out)
         }
+        QueryMsg::Balances { addresses } => to_binary(&query_balances(deps, addresses)?),
         QueryMsg::Allowance { owner, spender } => {
             let owner_key = deps.api.canonical_address(&owner)?;
             let spender_key = deps.api.canonical_address(&spender)?;
@@ -106,7 +388,593 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
             })?;
             Ok(out)
         }
+        QueryMsg::Receipts {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_receipts(deps, &address, start_after, limit)?),
+        QueryMsg::VerifyMemo { tx_id, memo } => to_binary(&query_verify_memo(deps, tx_id, &memo)?),
+        QueryMsg::AllAccounts { start_after, limit } => {
+            to_binary(&query_all_accounts(deps, start_after, limit)?)
+        }
+        QueryMsg::HoldersAt {
+            height,
+            start_after,
+            limit,
+        } => to_binary(&query_holders_at(deps, height, start_after, limit)?),
+        QueryMsg::AllAllowances {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_all_allowances(deps, &owner, start_after, limit)?),
+        QueryMsg::AllowanceTree { owner } => to_binary(&query_allowance_tree(deps, &owner)?),
+        QueryMsg::TransferRestriction {} => to_binary(&query_transfer_restriction(deps)?),
+        QueryMsg::ReceivedTokens { address } => to_binary(&query_received_tokens(deps, &address)?),
+        QueryMsg::Bootstrap {} => to_binary(&query_bootstrap(deps)?),
+        QueryMsg::OutflowHeadroom { address } => {
+            to_binary(&query_outflow_headroom(deps, &address)?)
+        }
+        QueryMsg::SessionKey { owner, key } => to_binary(&query_session_key(deps, &owner, &key)?),
+        QueryMsg::SimulateTransferFrom {
+            owner,
+            spender,
+            recipient,
+            amount,
+            height,
+            time,
+        } => to_binary(&query_simulate_transfer_from(
+            deps, &owner, &spender, &recipient, &amount, height, time,
+        )?),
+        QueryMsg::Pools {} => to_binary(&query_pools(deps)?),
+        QueryMsg::BurnAddresses {} => to_binary(&query_burn_addresses(deps)?),
+        QueryMsg::BalanceHook { address } => to_binary(&query_balance_hook(deps, &address)?),
+        QueryMsg::BalanceAtSnapshot { id, address } => {
+            to_binary(&query_balance_at_snapshot(deps, id, &address)?)
+        }
+        QueryMsg::Burner {} => to_binary(&query_burner(deps)?),
+        QueryMsg::AccountMetadata { address } => {
+            to_binary(&query_account_metadata(deps, &address)?)
+        }
+        QueryMsg::HasRole { role, address } => {
+            let address_raw = deps.api.canonical_address(&address)?;
+            to_binary(&HasRoleResponse {
+                has_role: has_role(&deps.storage, role, &address_raw),
+            })
+        }
+        QueryMsg::RoleMembers {
+            role,
+            start_after,
+            limit,
+        } => to_binary(&query_role_members(deps, role, start_after, limit)?),
+    }
+}
+
+fn query_account_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<AccountMetadataResponse> {
+    let address_raw = deps.api.canonical_address(address)?;
+    let metadata = read_account_metadata(&deps.storage, &address_raw)?;
+    Ok(AccountMetadataResponse {
+        name: metadata.as_ref().and_then(|m| m.name.clone()),
+        avatar_hash: metadata.and_then(|m| m.avatar_hash),
+    })
+}
+
+fn query_burner<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<BurnerResponse> {
+    let reserve = match read_burner(&deps.storage)? {
+        Some(config) => Some(deps.api.human_address(&config.reserve)?),
+        None => None,
+    };
+    Ok(BurnerResponse { reserve })
+}
+
+fn query_role_members<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    role: Role,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<RoleMembersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let namespace = [PREFIX_ROLES, role_key(role)].concat();
+    let start = decode_cursor(&namespace, start_after)?;
+
+    let roles_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_ROLES, role_key(role)], &deps.storage);
+    let keys: Vec<Vec<u8>> = roles_store
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|(key, _)| key)
+        .collect();
+    let members = keys
+        .iter()
+        .map(|key| deps.api.human_address(&CanonicalAddr::from(key.as_slice())))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(RoleMembersResponse {
+        next_cursor: next_cursor(
+            &namespace,
+            keys.last().map(|k| k.as_slice()),
+            keys.len(),
+            limit,
+        ),
+        members,
+    })
+}
+
+fn query_balances<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    addresses: Vec<HumanAddr>,
+) -> StdResult<BalancesResponse> {
+    if addresses.len() > MAX_BALANCES_BATCH {
+        return Err(StdError::generic_err(format!(
+            "Too many addresses: max {}",
+            MAX_BALANCES_BATCH
+        )));
+    }
+    let balances = addresses
+        .into_iter()
+        .map(|address| {
+            let balance = deps
+                .api
+                .canonical_address(&address)
+                .and_then(|address_raw| read_balance(&deps.storage, &address_raw));
+            match balance {
+                Ok(balance) => BalanceResult {
+                    address,
+                    balance: Some(Uint128::from(balance)),
+                    error: None,
+                },
+                Err(err) => BalanceResult {
+                    address,
+                    balance: None,
+                    error: Some(err.to_string()),
+                },
+            }
+        })
+        .collect();
+    Ok(BalancesResponse { balances })
+}
+
+fn query_balance_at_snapshot<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    id: u64,
+    address: &HumanAddr,
+) -> StdResult<BalanceAtSnapshotResponse> {
+    let snapshots_store = ReadonlyPrefixedStorage::new(PREFIX_SNAPSHOTS, &deps.storage);
+    let height = match snapshots_store.get(&id.to_be_bytes()) {
+        Some(data) => bytes_to_u64(&data)?,
+        None => return Err(StdError::generic_err("Snapshot does not exist")),
+    };
+    let address_raw = deps.api.canonical_address(address)?;
+    let balance = read_balance_at(&deps.storage, &address_raw, height)?;
+    Ok(BalanceAtSnapshotResponse {
+        id,
+        height,
+        balance: Uint128::from(balance),
+    })
+}
+
+fn query_balance_hook<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<BalanceHookResponse> {
+    let address_raw = deps.api.canonical_address(address)?;
+    match read_balance_hook(&deps.storage, &address_raw)? {
+        Some(hook) => Ok(BalanceHookResponse {
+            contract: deps.api.human_address(&hook.contract)?,
+            threshold: hook.threshold,
+        }),
+        None => Err(StdError::generic_err("No balance hook registered")),
+    }
+}
+
+fn query_pools<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<PoolsResponse> {
+    let pools = read_pool_registry(&deps.storage)?
+        .pools
+        .iter()
+        .map(|pool| deps.api.human_address(pool))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PoolsResponse { pools })
+}
+
+fn query_burn_addresses<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<BurnAddressesResponse> {
+    let burn_addresses = read_burn_addresses(&deps.storage)?;
+    let addresses = burn_addresses
+        .addresses
+        .iter()
+        .map(|address| deps.api.human_address(address))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BurnAddressesResponse {
+        addresses,
+        total_redirected: burn_addresses.redirected,
+    })
+}
+
+fn query_session_key<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+    key: &HumanAddr,
+) -> StdResult<SessionKeyResponse> {
+    let owner_raw = deps.api.canonical_address(owner)?;
+    let key_raw = deps.api.canonical_address(key)?;
+    match read_session_key(&deps.storage, &owner_raw, &key_raw)? {
+        Some(session_key) => Ok(SessionKeyResponse {
+            remaining: session_key.remaining,
+            expires_at_height: session_key.expires_at_height,
+        }),
+        None => Err(StdError::generic_err("Session key not registered")),
+    }
+}
+
+fn query_outflow_headroom<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<OutflowHeadroomResponse> {
+    let address_raw = deps.api.canonical_address(address)?;
+    let outflow = read_outflow(&deps.storage, &address_raw)?;
+    let cap = read_outflow_cap(&deps.storage)?.map(|outflow_cap| outflow_cap.cap);
+    Ok(OutflowHeadroomResponse {
+        cap,
+        window_start: outflow.window_start,
+        sent: outflow.sent,
+        remaining: cap.map(|cap| Uint128::from(cap.u128().saturating_sub(outflow.sent.u128()))),
+    })
+}
+
+/// Runs the checks TransferFrom makes, in its order, without spending anything
+fn query_simulate_transfer_from<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+    spender: &HumanAddr,
+    recipient: &HumanAddr,
+    amount: &Uint128,
+    height: u64,
+    time: u64,
+) -> StdResult<SimulateTransferFromResponse> {
+    let owner_raw = deps.api.canonical_address(owner)?;
+    let spender_raw = deps.api.canonical_address(spender)?;
+    let recipient_raw = deps.api.canonical_address(recipient)?;
+    let blocked = |constraint: TransferConstraint, err: StdError| {
+        let reason = match err {
+            StdError::GenericErr { msg, .. } => msg,
+            err => err.to_string(),
+        };
+        Ok(SimulateTransferFromResponse {
+            allowed: false,
+            blocked_by: Some(constraint),
+            reason: Some(reason),
+        })
+    };
+
+    if let Err(err) = check_blocked_recipient(&deps.storage, &recipient_raw) {
+        return blocked(TransferConstraint::Recipient, err);
+    }
+    if let Err(err) = check_transfer_restriction(deps, &owner_raw, &recipient_raw) {
+        return blocked(TransferConstraint::Restriction, err);
+    }
+    if let Err(err) = check_compliance(deps, owner, recipient, amount) {
+        return blocked(TransferConstraint::Compliance, err);
+    }
+    let amount = amount.u128();
+    if let Err(err) = check_spend(&deps.storage, &owner_raw, &spender_raw, amount, height) {
+        return blocked(TransferConstraint::Allowance, err);
+    }
+    if let Some((cap, outflow)) = current_outflow(&deps.storage, &owner_raw, time)? {
+        if let Err(err) = check_outflow(cap, &outflow, amount) {
+            return blocked(TransferConstraint::Cap, err);
+        }
+    }
+
+    let balance = read_balance(&deps.storage, &owner_raw)?;
+    if balance < amount {
+        return blocked(
+            TransferConstraint::Balance,
+            StdError::generic_err(format!(
+                "Insufficient funds: balance={}, required={}",
+                balance, amount
+            )),
+        );
+    }
+
+    Ok(SimulateTransferFromResponse {
+        allowed: true,
+        blocked_by: None,
+        reason: None,
+    })
+}
+
+fn query_bootstrap<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<BootstrapResponse> {
+    match read_bootstrap(&deps.storage)? {
+        Some(bootstrap) => Ok(BootstrapResponse {
+            grant: Some(bootstrap.grant),
+            pool: bootstrap.pool,
+            min_transfer: bootstrap.min_transfer,
+        }),
+        None => Ok(BootstrapResponse {
+            grant: None,
+            pool: Uint128::zero(),
+            min_transfer: Uint128::zero(),
+        }),
+    }
+}
+
+fn query_received_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<ReceivedTokensResponse> {
+    let owner_raw = deps.api.canonical_address(address)?;
+    let received_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_RECEIVED_TOKENS, owner_raw.as_slice()],
+        &deps.storage,
+    );
+    let tokens = received_store
+        .range(None, None, Order::Ascending)
+        .map(|(key, value)| {
+            Ok(ReceivedToken {
+                token: deps
+                    .api
+                    .human_address(&CanonicalAddr::from(key.as_slice()))?,
+                amount: Uint128::from(bytes_to_u128(&value)?),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ReceivedTokensResponse { tokens })
+}
+
+fn query_transfer_restriction<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<TransferRestrictionResponse> {
+    match read_transfer_restriction(&deps.storage)? {
+        Some(restriction) => Ok(TransferRestrictionResponse {
+            restricted: true,
+            whitelist: restriction
+                .whitelist
+                .iter()
+                .map(|address| deps.api.human_address(address))
+                .collect::<StdResult<Vec<_>>>()?,
+        }),
+        None => Ok(TransferRestrictionResponse {
+            restricted: false,
+            whitelist: vec![],
+        }),
+    }
+}
+
+fn query_allowance_tree<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+) -> StdResult<AllowanceTreeResponse> {
+    let owner_raw = deps.api.canonical_address(owner)?;
+    let allowances_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ALLOWANCES, owner_raw.as_slice()],
+        &deps.storage,
+    );
+
+    let allowances = allowances_store
+        .range(None, None, Order::Ascending)
+        .map(|(spender_key, value)| {
+            let spender_raw = CanonicalAddr::from(spender_key);
+            let sub_allowances_store = ReadonlyPrefixedStorage::multilevel(
+                &[
+                    PREFIX_SUB_ALLOWANCES,
+                    owner_raw.as_slice(),
+                    spender_raw.as_slice(),
+                ],
+                &deps.storage,
+            );
+            let sub_allowances = sub_allowances_store
+                .range(None, None, Order::Ascending)
+                .map(|(sub_spender_key, sub_value)| {
+                    Ok(SubAllowanceInfo {
+                        spender: deps
+                            .api
+                            .human_address(&CanonicalAddr::from(sub_spender_key))?,
+                        allowance: Uint128::from(bytes_to_u128(&sub_value)?),
+                    })
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+            Ok(AllowanceNode {
+                spender: deps.api.human_address(&spender_raw)?,
+                allowance: Uint128::from(bytes_to_u128(&value)?),
+                sub_allowances,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllowanceTreeResponse { allowances })
+}
+
+fn query_receipts<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<ReceiptsResponse> {
+    let address_raw = deps.api.canonical_address(address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = decode_cursor(PREFIX_RECEIPTS, start_after)?;
+
+    let index_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_RECEIPTS_BY_ADDRESS, address_raw.as_slice()],
+        &deps.storage,
+    );
+    let receipts_store = ReadonlyPrefixedStorage::new(PREFIX_RECEIPTS, &deps.storage);
+    let keys: Vec<Vec<u8>> = index_store
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|(key, _)| key)
+        .collect();
+    let receipts = keys
+        .iter()
+        .map(|key| {
+            let data = receipts_store
+                .get(key)
+                .ok_or_else(|| StdError::not_found("Receipt"))?;
+            let receipt: Receipt = from_slice(&data)?;
+            Ok(ReceiptResponse {
+                id: bytes_to_u64(key)?,
+                payer: deps.api.human_address(&receipt.payer)?,
+                merchant: deps.api.human_address(&receipt.merchant)?,
+                amount: receipt.amount,
+                reference: receipt.reference,
+                reference_hashed: receipt.reference_hashed,
+                height: receipt.height,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ReceiptsResponse {
+        next_cursor: next_cursor(
+            PREFIX_RECEIPTS,
+            keys.last().map(|k| k.as_slice()),
+            keys.len(),
+            limit,
+        ),
+        receipts,
+    })
+}
+
+fn query_verify_memo<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    tx_id: u64,
+    memo: &str,
+) -> StdResult<VerifyMemoResponse> {
+    let receipts_store = ReadonlyPrefixedStorage::new(PREFIX_RECEIPTS, &deps.storage);
+    let data = receipts_store
+        .get(&tx_id.to_be_bytes())
+        .ok_or_else(|| StdError::not_found("Receipt"))?;
+    let receipt: Receipt = from_slice(&data)?;
+    let valid = if receipt.reference_hashed {
+        receipt.reference == hash_reference(memo)
+    } else {
+        receipt.reference == memo
+    };
+    Ok(VerifyMemoResponse { valid })
+}
+
+fn query_all_accounts<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<AllAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = decode_cursor(PREFIX_BALANCES, start_after)?;
+
+    let balances_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, &deps.storage);
+    let entries: Vec<(Vec<u8>, Vec<u8>)> = balances_store
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .collect();
+    let accounts = entries
+        .iter()
+        .map(|(key, value)| {
+            Ok(AccountBalance {
+                address: deps
+                    .api
+                    .human_address(&CanonicalAddr::from(key.as_slice()))?,
+                balance: Uint128::from(decode_balance(value)?),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllAccountsResponse {
+        next_cursor: next_cursor(
+            PREFIX_BALANCES,
+            entries.last().map(|(k, _)| k.as_slice()),
+            entries.len(),
+            limit,
+        ),
+        accounts,
+    })
+}
+
+fn query_holders_at<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    height: u64,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<HoldersAtResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = decode_cursor(PREFIX_BALANCES, start_after)?;
+
+    // Pages are taken over all known accounts so the cursor stays valid when some of them
+    // held nothing at `height` and are left out of the result
+    let balances_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, &deps.storage);
+    let keys: Vec<Vec<u8>> = balances_store
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|(key, _)| key)
+        .collect();
+    let mut holders = vec![];
+    for key in keys.iter() {
+        let address_raw = CanonicalAddr::from(key.as_slice());
+        let balance = read_balance_at(&deps.storage, &address_raw, height)?;
+        if balance > 0 {
+            holders.push(AccountBalance {
+                address: deps.api.human_address(&address_raw)?,
+                balance: Uint128::from(balance),
+            });
+        }
     }
+
+    Ok(HoldersAtResponse {
+        height,
+        holders,
+        next_cursor: next_cursor(
+            PREFIX_BALANCES,
+            keys.last().map(|k| k.as_slice()),
+            keys.len(),
+            limit,
+        ),
+    })
+}
+
+fn query_all_allowances<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+    start_after: Option<Binary>,
+    limit: Option<u32>,
+) -> StdResult<AllAllowancesResponse> {
+    let owner_raw = deps.api.canonical_address(owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = decode_cursor(PREFIX_ALLOWANCES, start_after)?;
+
+    let allowances_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_ALLOWANCES, owner_raw.as_slice()],
+        &deps.storage,
+    );
+    let entries: Vec<(Vec<u8>, Vec<u8>)> = allowances_store
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .collect();
+    let allowances = entries
+        .iter()
+        .map(|(key, value)| {
+            Ok(SpenderAllowance {
+                spender: deps
+                    .api
+                    .human_address(&CanonicalAddr::from(key.as_slice()))?,
+                allowance: Uint128::from(bytes_to_u128(value)?),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllAllowancesResponse {
+        next_cursor: next_cursor(
+            PREFIX_ALLOWANCES,
+            entries.last().map(|(k, _)| k.as_slice()),
+            entries.len(),
+            limit,
+        ),
+        allowances,
+    })
 }
 
 fn try_transfer<S: Storage, A: Api, Q: Querier>(
@@ -114,26 +982,135 @@ fn try_transfer<S: Storage, A: Api, Q: Querier>(
     env: Env,
     recipient: &HumanAddr,
     amount: &Uint128,
+    tx_key: Option<String>,
 ) -> StdResult<HandleResponse> {
     let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
     let recipient_address_raw = deps.api.canonical_address(recipient)?;
     let amount_raw = amount.u128();
 
-    perform_transfer(
+    if let Some(tx_key) = &tx_key {
+        if use_tx_key(&mut deps.storage, &sender_address_raw, tx_key)? {
+            let res = HandleResponse {
+                messages: vec![],
+                log: vec![
+                    log("action", "transfer"),
+                    log("sender", env.message.sender.as_str()),
+                    log("recipient", recipient.as_str()),
+                    log("idempotent_replay", "true"),
+                ],
+                data: event_data("transfer", &env, Some(recipient), None)?,
+            };
+            return Ok(res);
+        }
+    }
+
+    check_recipient(deps, &env, &recipient_address_raw)?;
+    check_transfer_restriction(deps, &sender_address_raw, &recipient_address_raw)?;
+    check_compliance(deps, &env.message.sender, recipient, amount)?;
+    let mut messages = bootstrap_grant(deps, &env, recipient, &recipient_address_raw, amount)?;
+    messages.extend(perform_transfer(
         &mut deps.storage,
+        &deps.api,
         &sender_address_raw,
         &recipient_address_raw,
         amount_raw,
+        &env.block,
+    )?);
+
+    let mut logs = vec![
+        log("action", "transfer"),
+        log("sender", env.message.sender.as_str()),
+        log("recipient", recipient.as_str()),
+    ];
+    logs.extend(name_logs(
+        &deps.storage,
+        &sender_address_raw,
+        &recipient_address_raw,
+    )?);
+
+    let res = HandleResponse {
+        messages,
+        log: logs,
+        data: event_data("transfer", &env, Some(recipient), Some(*amount))?,
+    };
+    Ok(res)
+}
+
+fn try_send<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: &HumanAddr,
+    amount: &Uint128,
+    msg: Option<Binary>,
+    tx_key: Option<String>,
+) -> StdResult<HandleResponse> {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let contract_address_raw = deps.api.canonical_address(contract)?;
+
+    // a replayed send neither moves the tokens nor calls the contract again
+    if let Some(tx_key) = &tx_key {
+        if use_tx_key(&mut deps.storage, &sender_address_raw, tx_key)? {
+            let res = HandleResponse {
+                messages: vec![],
+                log: vec![
+                    log("action", "send"),
+                    log("sender", env.message.sender.as_str()),
+                    log("contract", contract.as_str()),
+                    log("idempotent_replay", "true"),
+                ],
+                data: event_data("send", &env, Some(contract), None)?,
+            };
+            return Ok(res);
+        }
+    }
+
+    // the tokens would be burned, so there is nothing for the contract to receive
+    if read_burn_addresses(&deps.storage)?
+        .addresses
+        .contains(&contract_address_raw)
+    {
+        return Err(StdError::generic_err(
+            "Cannot send to a burn address, use Transfer or Burn",
+        ));
+    }
+    check_recipient(deps, &env, &contract_address_raw)?;
+    check_transfer_restriction(deps, &sender_address_raw, &contract_address_raw)?;
+    check_compliance(deps, &env.message.sender, contract, amount)?;
+    let mut messages = perform_transfer(
+        &mut deps.storage,
+        &deps.api,
+        &sender_address_raw,
+        &contract_address_raw,
+        amount.u128(),
+        &env.block,
     )?;
+    // the receiving contract is told about the tokens after any hook notifications
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract.clone(),
+        msg: to_binary(&HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: env.message.sender.clone(),
+            amount: *amount,
+            msg,
+        }))?,
+        send: vec![],
+    }));
+
+    let mut logs = vec![
+        log("action", "send"),
+        log("sender", env.message.sender.as_str()),
+        log("contract", contract.as_str()),
+        log("amount", amount),
+    ];
+    logs.extend(name_logs(
+        &deps.storage,
+        &sender_address_raw,
+        &contract_address_raw,
+    )?);
 
     let res = HandleResponse {
-        messages: vec![],
-        log: vec![
-            log("action", "transfer"),
-            log("sender", env.message.sender.as_str()),
-            log("recipient", recipient.as_str()),
-        ],
-        data: None,
+        messages,
+        log: logs,
+        data: event_data("send", &env, Some(contract), Some(*amount))?,
     };
     Ok(res)
 }
@@ -150,36 +1127,42 @@ fn try_transfer_from<S: Storage, A: Api, Q: Querier>(
     let recipient_address_raw = deps.api.canonical_address(recipient)?;
     let amount_raw = amount.u128();
 
-    let mut allowance = read_allowance(&deps.storage, &owner_address_raw, &spender_address_raw)?;
-    if allowance < amount_raw {
-        return Err(StdError::generic_err(format!(
-            "Insufficient allowance: allowance={}, required={}",
-            allowance, amount_raw
-        )));
-    }
-    allowance -= amount_raw;
-    write_allowance(
+    check_recipient(deps, &env, &recipient_address_raw)?;
+    check_transfer_restriction(deps, &owner_address_raw, &recipient_address_raw)?;
+    check_compliance(deps, owner, recipient, amount)?;
+    spend_allowance_or_session_key(
         &mut deps.storage,
         &owner_address_raw,
         &spender_address_raw,
-        allowance,
+        amount_raw,
+        env.block.height,
     )?;
-    perform_transfer(
+    let mut messages = bootstrap_grant(deps, &env, recipient, &recipient_address_raw, amount)?;
+    messages.extend(perform_transfer(
         &mut deps.storage,
+        &deps.api,
         &owner_address_raw,
         &recipient_address_raw,
         amount_raw,
-    )?;
+        &env.block,
+    )?);
+
+    let mut logs = vec![
+        log("action", "transfer_from"),
+        log("spender", &env.message.sender.as_str()),
+        log("sender", owner.as_str()),
+        log("recipient", recipient.as_str()),
+    ];
+    logs.extend(name_logs(
+        &deps.storage,
+        &owner_address_raw,
+        &recipient_address_raw,
+    )?);
 
     let res = HandleResponse {
-        messages: vec![],
-        log: vec![
-            log("action", "transfer_from"),
-            log("spender", &env.message.sender.as_str()),
-            log("sender", owner.as_str()),
-            log("recipient", recipient.as_str()),
-        ],
-        data: None,
+        messages,
+        log: logs,
+        data: event_data("transfer_from", &env, Some(recipient), Some(*amount))?,
     };
     Ok(res)
 }
@@ -205,69 +1188,1066 @@ fn try_approve<S: Storage, A: Api, Q: Querier>(
             log("owner", env.message.sender.as_str()),
             log("spender", spender.as_str()),
         ],
-        data: None,
+        data: event_data("approve", &env, Some(spender), Some(*amount))?,
     };
     Ok(res)
 }
 
-/// Burn tokens
+/// Sub-approve
 ///
-/// Remove `amount` tokens from the system irreversibly, from signer account
+/// Lets `spender` move up to `amount` of `owner`'s tokens out of the allowance `owner`
+/// granted to the signer. Spending it also consumes the signer's allowance.
 ///
-/// @param amount the amount of money to burn
-fn try_burn<S: Storage, A: Api, Q: Querier>(
+/// @param owner the account that granted the signer an allowance
+fn try_sub_approve<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    owner: &HumanAddr,
+    spender: &HumanAddr,
     amount: &Uint128,
 ) -> StdResult<HandleResponse> {
-    let owner_address_raw = &deps.api.canonical_address(&env.message.sender)?;
-    let amount_raw = amount.u128();
-
-    let mut account_balance = read_balance(&deps.storage, owner_address_raw)?;
+    let owner_address_raw = deps.api.canonical_address(owner)?;
+    let delegator_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let spender_address_raw = deps.api.canonical_address(spender)?;
 
-    if account_balance < amount_raw {
+    let allowance = read_allowance(&deps.storage, &owner_address_raw, &delegator_address_raw)?;
+    if allowance < amount.u128() {
         return Err(StdError::generic_err(format!(
-            "insufficient funds to burn: balance={}, required={}",
-            account_balance, amount_raw
+            "Sub-allowance exceeds allowance: allowance={}, required={}",
+            allowance,
+            amount.u128()
         )));
     }
-    account_balance -= amount_raw;
-
-    let mut balances_store = PrefixedStorage::new(PREFIX_BALANCES, &mut deps.storage);
-    balances_store.set(owner_address_raw.as_slice(), &account_balance.to_be_bytes());
-
-    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
-    let data = config_store
-        .get(KEY_TOTAL_SUPPLY)
-        .expect("no total supply data stored");
-    let mut total_supply = bytes_to_u128(&data).unwrap();
-
-    total_supply -= amount_raw;
-
-    config_store.set(KEY_TOTAL_SUPPLY, &total_supply.to_be_bytes());
+    write_sub_allowance(
+        &mut deps.storage,
+        &owner_address_raw,
+        &delegator_address_raw,
+        &spender_address_raw,
+        amount.u128(),
+    );
 
     let res = HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "burn"),
-            log("account", env.message.sender.as_str()),
-            log("amount", &amount.to_string()),
+            log("action", "sub_approve"),
+            log("owner", owner.as_str()),
+            log("delegator", env.message.sender.as_str()),
+            log("spender", spender.as_str()),
         ],
-        data: None,
+        data: event_data("sub_approve", &env, Some(spender), Some(*amount))?,
     };
-
     Ok(res)
 }
 
-fn perform_transfer<T: Storage>(
-    store: &mut T,
+fn try_transfer_from_chain<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    hops: &[ChainHop],
+    recipient: &HumanAddr,
+    amount: &Uint128,
+) -> StdResult<HandleResponse> {
+    if hops.is_empty() {
+        return Err(StdError::generic_err("Chain needs at least one hop"));
+    }
+    let spender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let amount_raw = amount.u128();
+
+    let mut messages = vec![];
+    let mut logs = vec![
+        log("action", "transfer_from_chain"),
+        log("spender", env.message.sender.as_str()),
+    ];
+    for (i, hop) in hops.iter().enumerate() {
+        if hop.allowance_spender != env.message.sender {
+            return Err(StdError::generic_err(format!(
+                "Hop {} must spend the signer's allowance",
+                i
+            )));
+        }
+        let to = hops.get(i + 1).map_or(recipient, |next| &next.owner);
+        let owner_address_raw = deps.api.canonical_address(&hop.owner)?;
+        let to_address_raw = deps.api.canonical_address(to)?;
+
+        check_recipient(deps, &env, &to_address_raw)?;
+        check_transfer_restriction(deps, &owner_address_raw, &to_address_raw)?;
+        check_compliance(deps, &hop.owner, to, amount)?;
+        spend_allowance_or_session_key(
+            &mut deps.storage,
+            &owner_address_raw,
+            &spender_address_raw,
+            amount_raw,
+            env.block.height,
+        )?;
+        messages.extend(bootstrap_grant(deps, &env, to, &to_address_raw, amount)?);
+        messages.extend(perform_transfer(
+            &mut deps.storage,
+            &deps.api,
+            &owner_address_raw,
+            &to_address_raw,
+            amount_raw,
+            &env.block,
+        )?);
+        logs.push(log("hop", i));
+        logs.push(log("sender", hop.owner.as_str()));
+        logs.push(log("recipient", to.as_str()));
+    }
+
+    let res = HandleResponse {
+        messages,
+        log: logs,
+        data: event_data("transfer_from_chain", &env, Some(recipient), Some(*amount))?,
+    };
+    Ok(res)
+}
+
+fn try_sub_transfer_from<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: &HumanAddr,
+    delegator: &HumanAddr,
+    recipient: &HumanAddr,
+    amount: &Uint128,
+) -> StdResult<HandleResponse> {
+    let spender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let owner_address_raw = deps.api.canonical_address(owner)?;
+    let delegator_address_raw = deps.api.canonical_address(delegator)?;
+    let recipient_address_raw = deps.api.canonical_address(recipient)?;
+    let amount_raw = amount.u128();
+
+    check_recipient(deps, &env, &recipient_address_raw)?;
+    check_transfer_restriction(deps, &owner_address_raw, &recipient_address_raw)?;
+    check_compliance(deps, owner, recipient, amount)?;
+
+    let sub_allowance = read_sub_allowance(
+        &deps.storage,
+        &owner_address_raw,
+        &delegator_address_raw,
+        &spender_address_raw,
+    )?;
+    if sub_allowance < amount_raw {
+        return Err(StdError::generic_err(format!(
+            "Insufficient sub-allowance: allowance={}, required={}",
+            sub_allowance, amount_raw
+        )));
+    }
+    write_sub_allowance(
+        &mut deps.storage,
+        &owner_address_raw,
+        &delegator_address_raw,
+        &spender_address_raw,
+        sub_allowance - amount_raw,
+    );
+    spend_allowance(
+        &mut deps.storage,
+        &owner_address_raw,
+        &delegator_address_raw,
+        amount_raw,
+    )?;
+    let mut messages = bootstrap_grant(deps, &env, recipient, &recipient_address_raw, amount)?;
+    messages.extend(perform_transfer(
+        &mut deps.storage,
+        &deps.api,
+        &owner_address_raw,
+        &recipient_address_raw,
+        amount_raw,
+        &env.block,
+    )?);
+
+    let mut logs = vec![
+        log("action", "sub_transfer_from"),
+        log("spender", env.message.sender.as_str()),
+        log("delegator", delegator.as_str()),
+        log("sender", owner.as_str()),
+        log("recipient", recipient.as_str()),
+    ];
+    logs.extend(name_logs(
+        &deps.storage,
+        &owner_address_raw,
+        &recipient_address_raw,
+    )?);
+
+    let res = HandleResponse {
+        messages,
+        log: logs,
+        data: event_data("sub_transfer_from", &env, Some(recipient), Some(*amount))?,
+    };
+    Ok(res)
+}
+
+/// Pull payment
+///
+/// Moves `amount` tokens from `owner` to the signer (the merchant) within the allowance
+/// `owner` granted to the merchant, and stores a receipt that both parties can query.
+///
+/// @param reference an arbitrary merchant reference, e.g. an invoice or subscription id
+fn try_pull<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: &HumanAddr,
+    amount: &Uint128,
+    reference: String,
+) -> StdResult<HandleResponse> {
+    if reference.len() > MAX_REFERENCE_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Reference too long: max {} bytes",
+            MAX_REFERENCE_LENGTH
+        )));
+    }
+
+    let merchant_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let owner_address_raw = deps.api.canonical_address(owner)?;
+    let amount_raw = amount.u128();
+
+    check_transfer_restriction(deps, &owner_address_raw, &merchant_address_raw)?;
+    check_compliance(deps, owner, &env.message.sender, amount)?;
+    spend_allowance_or_session_key(
+        &mut deps.storage,
+        &owner_address_raw,
+        &merchant_address_raw,
+        amount_raw,
+        env.block.height,
+    )?;
+    let messages = perform_transfer(
+        &mut deps.storage,
+        &deps.api,
+        &owner_address_raw,
+        &merchant_address_raw,
+        amount_raw,
+        &env.block,
+    )?;
+
+    let reference_hashed = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, &deps.storage)
+        .get(KEY_HASHED_REFERENCES)
+        .is_some();
+    let receipt = Receipt {
+        payer: owner_address_raw,
+        merchant: merchant_address_raw,
+        amount: *amount,
+        reference: if reference_hashed {
+            hash_reference(&reference)
+        } else {
+            reference
+        },
+        reference_hashed,
+        height: env.block.height,
+    };
+    let receipt_id = store_receipt(&mut deps.storage, &receipt)?;
+
+    let res = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "pull"),
+            log("merchant", env.message.sender.as_str()),
+            log("payer", owner.as_str()),
+            log("amount", &amount.to_string()),
+            log("receipt_id", &receipt_id.to_string()),
+        ],
+        data: event_data("pull", &env, Some(owner), Some(*amount))?,
+    };
+    Ok(res)
+}
+
+/// Burn tokens
+///
+/// Remove `amount` tokens from the system irreversibly, from signer account
+///
+/// @param amount the amount of money to burn
+fn try_burn<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: &Uint128,
+) -> StdResult<HandleResponse> {
+    let owner_address_raw = &deps.api.canonical_address(&env.message.sender)?;
+    let (total_supply_before, total_supply_after) = burn(
+        &mut deps.storage,
+        owner_address_raw,
+        amount.u128(),
+        env.block.height,
+    )?;
+    let burn_response = BurnResponse {
+        total_supply_before,
+        total_supply_after,
+        event: event("burn", &env, None, Some(*amount)),
+    };
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "burn"),
+            log("account", env.message.sender.as_str()),
+            log("amount", &amount.to_string()),
+        ],
+        data: Some(to_binary(&burn_response)?),
+    };
+
+    Ok(res)
+}
+
+/// Burns tokens held by the reserve account. Logged as a separate action so burner burns can be
+/// told apart from holders burning their own tokens.
+fn try_burn_from_reserve<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: &Uint128,
+) -> StdResult<HandleResponse> {
+    let config = match read_burner(&deps.storage)? {
+        Some(config) => config,
+        None => return Err(StdError::generic_err("No burner configured")),
+    };
+    require_role(deps, &env, Role::Burner)?;
+    let (total_supply_before, total_supply_after) = burn(
+        &mut deps.storage,
+        &config.reserve,
+        amount.u128(),
+        env.block.height,
+    )?;
+    let reserve = deps.api.human_address(&config.reserve)?;
+    let burn_response = BurnResponse {
+        total_supply_before,
+        total_supply_after,
+        event: event("burn_from_reserve", &env, Some(&reserve), Some(*amount)),
+    };
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "burn_from_reserve"),
+            log("burner", env.message.sender.as_str()),
+            log("reserve", reserve.as_str()),
+            log("amount", amount),
+        ],
+        data: Some(to_binary(&burn_response)?),
+    };
+    Ok(res)
+}
+
+fn try_set_role<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    role: Role,
+    address: &HumanAddr,
+    granted: bool,
+) -> StdResult<HandleResponse> {
+    require_role(deps, &env, Role::Admin)?;
+    let address_raw = deps.api.canonical_address(address)?;
+    let holds = has_role(&deps.storage, role, &address_raw);
+    if granted && holds {
+        return Err(StdError::generic_err("Address already has the role"));
+    }
+    if !granted && !holds {
+        return Err(StdError::generic_err("Address does not have the role"));
+    }
+    set_role(&mut deps.storage, role, &address_raw, granted);
+
+    let action = if granted { "grant_role" } else { "revoke_role" };
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", action),
+            log("role", role_name(role)),
+            log("address", address.as_str()),
+        ],
+        data: event_data(action, &env, Some(address), None)?,
+    };
+    Ok(res)
+}
+
+fn try_renounce_role<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    role: Role,
+) -> StdResult<HandleResponse> {
+    require_role(deps, &env, role)?;
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    set_role(&mut deps.storage, role, &sender_address_raw, false);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "renounce_role"),
+            log("role", role_name(role)),
+            log("address", env.message.sender.as_str()),
+        ],
+        data: event_data("renounce_role", &env, None, None)?,
+    };
+    Ok(res)
+}
+
+fn try_set_account_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: Option<String>,
+    avatar_hash: Option<String>,
+) -> StdResult<HandleResponse> {
+    if name.as_ref().map(|n| n.len()).unwrap_or(0) > MAX_ACCOUNT_NAME_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Name too long: max {} bytes",
+            MAX_ACCOUNT_NAME_LENGTH
+        )));
+    }
+    if avatar_hash.as_ref().map(|h| h.len()).unwrap_or(0) > MAX_AVATAR_HASH_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Avatar hash too long: max {} bytes",
+            MAX_AVATAR_HASH_LENGTH
+        )));
+    }
+
+    let address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let mut metadata_store = PrefixedStorage::new(PREFIX_ACCOUNT_METADATA, &mut deps.storage);
+    if name.is_none() && avatar_hash.is_none() {
+        metadata_store.remove(address_raw.as_slice());
+    } else {
+        let metadata = AccountMetadata { name, avatar_hash };
+        metadata_store.set(address_raw.as_slice(), &to_vec(&metadata)?);
+    }
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_account_metadata"),
+            log("account", env.message.sender.as_str()),
+        ],
+        data: event_data("set_account_metadata", &env, None, None)?,
+    };
+    Ok(res)
+}
+
+fn burn<S: Storage>(
+    store: &mut S,
+    account: &CanonicalAddr,
+    amount: u128,
+    height: u64,
+) -> StdResult<(Uint128, Uint128)> {
+    let mut account_balance = read_balance(store, account)?;
+
+    if account_balance < amount {
+        return Err(StdError::generic_err(format!(
+            "insufficient funds to burn: balance={}, required={}",
+            account_balance, amount
+        )));
+    }
+    account_balance -= amount;
+
+    write_balance(store, account, account_balance, height);
+
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, store);
+    let data = config_store
+        .get(KEY_TOTAL_SUPPLY)
+        .expect("no total supply data stored");
+    let total_supply_before = bytes_to_u128(&data).unwrap();
+
+    let total_supply = total_supply_before - amount;
+
+    config_store.set(KEY_TOTAL_SUPPLY, &total_supply.to_be_bytes());
+
+    Ok((
+        Uint128::from(total_supply_before),
+        Uint128::from(total_supply),
+    ))
+}
+
+fn event(
+    action: &str,
+    env: &Env,
+    counterparty: Option<&HumanAddr>,
+    amount: Option<Uint128>,
+) -> Event {
+    Event {
+        action: action.to_string(),
+        actor: env.message.sender.clone(),
+        counterparty: counterparty.cloned(),
+        amount,
+        height: env.block.height,
+    }
+}
+
+/// The response data of handle messages without a response of their own
+fn event_data(
+    action: &str,
+    env: &Env,
+    counterparty: Option<&HumanAddr>,
+    amount: Option<Uint128>,
+) -> StdResult<Option<Binary>> {
+    Ok(Some(to_binary(&event(action, env, counterparty, amount))?))
+}
+
+fn try_set_whitelisted<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: &HumanAddr,
+    whitelisted: bool,
+) -> StdResult<HandleResponse> {
+    let mut restriction = load_restriction_as_admin(deps, &env)?;
+    let address_raw = deps.api.canonical_address(address)?;
+
+    restriction.whitelist.retain(|entry| *entry != address_raw);
+    if whitelisted {
+        restriction.whitelist.push(address_raw);
+    }
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.set(KEY_TRANSFER_RESTRICTION, &to_vec(&restriction)?);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_whitelisted"),
+            log("address", address.as_str()),
+            log("whitelisted", whitelisted),
+        ],
+        data: event_data("set_whitelisted", &env, Some(address), None)?,
+    };
+    Ok(res)
+}
+
+fn try_lift_transfer_restriction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    load_restriction_as_admin(deps, &env)?;
+
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.remove(KEY_TRANSFER_RESTRICTION);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "lift_transfer_restriction")],
+        data: event_data("lift_transfer_restriction", &env, None, None)?,
+    };
+    Ok(res)
+}
+
+/// Loads the transfer restriction, failing if there is none or the signer is not an admin
+fn load_restriction_as_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<TransferRestriction> {
+    let restriction = match read_transfer_restriction(&deps.storage)? {
+        Some(restriction) => restriction,
+        None => return Err(StdError::generic_err("Transfers are not restricted")),
+    };
+    require_role(deps, env, Role::Admin)?;
+    Ok(restriction)
+}
+
+/// Credits CW20 tokens sent to this contract to the account that sent them.
+/// The signer is the token contract, `msg.sender` the account the tokens came from.
+fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: Cw20ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    let token_raw = deps.api.canonical_address(&env.message.sender)?;
+    let owner_raw = deps.api.canonical_address(&msg.sender)?;
+
+    let received = read_received(&deps.storage, &owner_raw, &token_raw)?;
+    write_received(
+        &mut deps.storage,
+        &owner_raw,
+        &token_raw,
+        received + msg.amount.u128(),
+    );
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "receive"),
+            log("token", env.message.sender.as_str()),
+            log("sender", msg.sender.as_str()),
+            log("amount", msg.amount),
+        ],
+        data: event_data("receive", &env, Some(&msg.sender), Some(msg.amount))?,
+    };
+    Ok(res)
+}
+
+fn try_withdraw_received<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token: &HumanAddr,
+    amount: Option<Uint128>,
+) -> StdResult<HandleResponse> {
+    let owner_raw = deps.api.canonical_address(&env.message.sender)?;
+    let token_raw = deps.api.canonical_address(token)?;
+
+    let received = read_received(&deps.storage, &owner_raw, &token_raw)?;
+    let amount_raw = amount.map(|amount| amount.u128()).unwrap_or(received);
+    if amount_raw == 0 {
+        return Err(StdError::generic_err("Nothing to withdraw"));
+    }
+    if amount_raw > received {
+        return Err(StdError::generic_err(format!(
+            "Insufficient received tokens: held={}, requested={}",
+            received, amount_raw
+        )));
+    }
+    write_received(
+        &mut deps.storage,
+        &owner_raw,
+        &token_raw,
+        received - amount_raw,
+    );
+
+    // CW20 tokens share this contract's Transfer message
+    let transfer = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token.clone(),
+        msg: to_binary(&HandleMsg::Transfer {
+            recipient: env.message.sender.clone(),
+            amount: Uint128::from(amount_raw),
+            tx_key: None,
+        })?,
+        send: vec![],
+    });
+
+    let res = HandleResponse {
+        messages: vec![transfer],
+        log: vec![
+            log("action", "withdraw_received"),
+            log("token", token.as_str()),
+            log("recipient", env.message.sender.as_str()),
+            log("amount", amount_raw),
+        ],
+        data: event_data(
+            "withdraw_received",
+            &env,
+            Some(token),
+            Some(Uint128::from(amount_raw)),
+        )?,
+    };
+    Ok(res)
+}
+
+fn try_fund_bootstrap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let mut bootstrap = match read_bootstrap(&deps.storage)? {
+        Some(bootstrap) => bootstrap,
+        None => return Err(StdError::generic_err("Bootstrap grants are disabled")),
+    };
+    require_role(deps, &env, Role::Admin)?;
+    let funds: u128 = env
+        .message
+        .sent_funds
+        .iter()
+        .filter(|coin| coin.denom == bootstrap.grant.denom)
+        .map(|coin| coin.amount.u128())
+        .sum();
+    if funds == 0 {
+        return Err(StdError::generic_err(format!(
+            "Must send {} to fund the bootstrap pool",
+            bootstrap.grant.denom
+        )));
+    }
+    bootstrap.pool += Uint128::from(funds);
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.set(KEY_BOOTSTRAP, &to_vec(&bootstrap)?);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "fund_bootstrap"),
+            log("amount", funds),
+            log("pool", bootstrap.pool),
+        ],
+        data: event_data("fund_bootstrap", &env, None, Some(Uint128::from(funds)))?,
+    };
+    Ok(res)
+}
+
+fn try_set_outflow_cap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    cap: Uint128,
+) -> StdResult<HandleResponse> {
+    let mut outflow_cap = match read_outflow_cap(&deps.storage)? {
+        Some(outflow_cap) => outflow_cap,
+        None => return Err(StdError::generic_err("Outflows are not capped")),
+    };
+    require_role(deps, &env, Role::Admin)?;
+    outflow_cap.cap = cap;
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.set(KEY_OUTFLOW_CAP, &to_vec(&outflow_cap)?);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_outflow_cap"), log("cap", cap)],
+        data: event_data("set_outflow_cap", &env, None, Some(cap))?,
+    };
+    Ok(res)
+}
+
+/// Snapshots are numbered from 1. Balances are already checkpointed per block as they change, so
+/// a snapshot only records the block it refers to.
+fn try_checkpoint<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    require_role(deps, &env, Role::Governance)?;
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+
+    let id = match config_store.get(KEY_SNAPSHOT_COUNT) {
+        Some(data) => bytes_to_u64(&data)? + 1,
+        None => 1,
+    };
+    config_store.set(KEY_SNAPSHOT_COUNT, &id.to_be_bytes());
+    // transfers earlier in the current block are not part of the snapshot
+    let height = env.block.height.saturating_sub(1);
+    let mut snapshots_store = PrefixedStorage::new(PREFIX_SNAPSHOTS, &mut deps.storage);
+    snapshots_store.set(&id.to_be_bytes(), &height.to_be_bytes());
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "checkpoint"),
+            log("snapshot_id", id),
+            log("height", height),
+        ],
+        data: Some(to_binary(&CheckpointResponse {
+            id,
+            height,
+            event: event("checkpoint", &env, None, None),
+        })?),
+    };
+    Ok(res)
+}
+
+fn try_set_balance_hook<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: &HumanAddr,
+    threshold: Uint128,
+) -> StdResult<HandleResponse> {
+    let owner_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let hook = BalanceHook {
+        contract: deps.api.canonical_address(contract)?,
+        threshold,
+    };
+    let mut hooks_store = PrefixedStorage::new(PREFIX_BALANCE_HOOKS, &mut deps.storage);
+    hooks_store.set(owner_address_raw.as_slice(), &to_vec(&hook)?);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_balance_hook"),
+            log("owner", env.message.sender.as_str()),
+            log("contract", contract.as_str()),
+            log("threshold", threshold),
+        ],
+        data: event_data("set_balance_hook", &env, Some(contract), Some(threshold))?,
+    };
+    Ok(res)
+}
+
+fn try_remove_balance_hook<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let owner_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    if read_balance_hook(&deps.storage, &owner_address_raw)?.is_none() {
+        return Err(StdError::generic_err("No balance hook registered"));
+    }
+    let mut hooks_store = PrefixedStorage::new(PREFIX_BALANCE_HOOKS, &mut deps.storage);
+    hooks_store.remove(owner_address_raw.as_slice());
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "remove_balance_hook"),
+            log("owner", env.message.sender.as_str()),
+        ],
+        data: event_data("remove_balance_hook", &env, None, None)?,
+    };
+    Ok(res)
+}
+
+fn try_set_pool<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pool: &HumanAddr,
+    registered: bool,
+) -> StdResult<HandleResponse> {
+    require_role(deps, &env, Role::Admin)?;
+    let mut pool_registry = read_pool_registry(&deps.storage)?;
+    let pool_raw = deps.api.canonical_address(pool)?;
+    let known = pool_registry.pools.contains(&pool_raw);
+    if registered && known {
+        return Err(StdError::generic_err("Pool already registered"));
+    }
+    if !registered && !known {
+        return Err(StdError::generic_err("Pool not registered"));
+    }
+
+    pool_registry.pools.retain(|entry| *entry != pool_raw);
+    if registered {
+        pool_registry.pools.push(pool_raw);
+    }
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.set(KEY_POOL_REGISTRY, &to_vec(&pool_registry)?);
+
+    let action = if registered {
+        "register_pool"
+    } else {
+        "deregister_pool"
+    };
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", action), log("pool", pool.as_str())],
+        data: event_data(action, &env, Some(pool), None)?,
+    };
+    Ok(res)
+}
+
+fn try_set_burn_address<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: &HumanAddr,
+    registered: bool,
+) -> StdResult<HandleResponse> {
+    require_role(deps, &env, Role::Admin)?;
+    let mut burn_addresses = read_burn_addresses(&deps.storage)?;
+    let address_raw = deps.api.canonical_address(address)?;
+    let known = burn_addresses.addresses.contains(&address_raw);
+    if registered && known {
+        return Err(StdError::generic_err("Burn address already added"));
+    }
+    if !registered && !known {
+        return Err(StdError::generic_err("Not a burn address"));
+    }
+
+    burn_addresses
+        .addresses
+        .retain(|entry| *entry != address_raw);
+    if registered {
+        burn_addresses.addresses.push(address_raw);
+    }
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.set(KEY_BURN_ADDRESSES, &to_vec(&burn_addresses)?);
+
+    let action = if registered {
+        "add_burn_address"
+    } else {
+        "remove_burn_address"
+    };
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", action), log("address", address.as_str())],
+        data: event_data(action, &env, Some(address), None)?,
+    };
+    Ok(res)
+}
+
+fn try_register_session_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: &HumanAddr,
+    spend_limit: Uint128,
+    expires_at_height: u64,
+) -> StdResult<HandleResponse> {
+    if expires_at_height <= env.block.height {
+        return Err(StdError::generic_err(
+            "Session key must expire after the current height",
+        ));
+    }
+    let owner_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let key_address_raw = deps.api.canonical_address(key)?;
+    let session_key = SessionKey {
+        remaining: spend_limit,
+        expires_at_height,
+    };
+    write_session_key(
+        &mut deps.storage,
+        &owner_address_raw,
+        &key_address_raw,
+        &session_key,
+    )?;
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "register_session_key"),
+            log("owner", env.message.sender.as_str()),
+            log("key", key.as_str()),
+            log("spend_limit", spend_limit),
+            log("expires_at_height", expires_at_height),
+        ],
+        data: event_data("register_session_key", &env, Some(key), Some(spend_limit))?,
+    };
+    Ok(res)
+}
+
+fn try_revoke_session_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: &HumanAddr,
+) -> StdResult<HandleResponse> {
+    let owner_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let key_address_raw = deps.api.canonical_address(key)?;
+    if read_session_key(&deps.storage, &owner_address_raw, &key_address_raw)?.is_none() {
+        return Err(StdError::generic_err("Session key not registered"));
+    }
+    let mut session_keys_store = PrefixedStorage::multilevel(
+        &[PREFIX_SESSION_KEYS, owner_address_raw.as_slice()],
+        &mut deps.storage,
+    );
+    session_keys_store.remove(key_address_raw.as_slice());
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "revoke_session_key"),
+            log("owner", env.message.sender.as_str()),
+            log("key", key.as_str()),
+        ],
+        data: event_data("revoke_session_key", &env, Some(key), None)?,
+    };
+    Ok(res)
+}
+
+/// Pays the bootstrap grant to a recipient that has never held tokens, while the pool lasts.
+/// Transfers of nothing, or of less than the configured minimum, don't pay it, so the pool
+/// can't be drained by sending dust to fresh addresses.
+/// Must run before the recipient's balance is written.
+fn bootstrap_grant<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    recipient: &HumanAddr,
+    recipient_raw: &CanonicalAddr,
+    amount: &Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut bootstrap = match read_bootstrap(&deps.storage)? {
+        Some(bootstrap) => bootstrap,
+        None => return Ok(vec![]),
+    };
+    if amount.is_zero() || *amount < bootstrap.min_transfer {
+        return Ok(vec![]);
+    }
+    let balances_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, &deps.storage);
+    if balances_store.get(recipient_raw.as_slice()).is_some()
+        || bootstrap.pool < bootstrap.grant.amount
+    {
+        return Ok(vec![]);
+    }
+
+    bootstrap.pool = (bootstrap.pool - bootstrap.grant.amount)?;
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, &mut deps.storage);
+    config_store.set(KEY_BOOTSTRAP, &to_vec(&bootstrap)?);
+
+    Ok(vec![CosmosMsg::Bank(BankMsg::Send {
+        from_address: env.contract.address.clone(),
+        to_address: recipient.clone(),
+        amount: vec![bootstrap.grant],
+    })])
+}
+
+/// Rejects transfers to the token contract itself and to any configured blocked recipient,
+/// as tokens sent there could never be moved again.
+fn check_recipient<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    recipient: &CanonicalAddr,
+) -> StdResult<()> {
+    if *recipient == deps.api.canonical_address(&env.contract.address)? {
+        return Err(StdError::generic_err(
+            "Cannot transfer to the token contract",
+        ));
+    }
+    check_blocked_recipient(&deps.storage, recipient)
+}
+
+fn check_blocked_recipient<S: Storage>(store: &S, recipient: &CanonicalAddr) -> StdResult<()> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    if let Some(data) = config_store.get(KEY_BLOCKED_RECIPIENTS) {
+        let blocked_recipients: Vec<CanonicalAddr> = from_slice(&data)?;
+        if blocked_recipients.contains(recipient) {
+            return Err(StdError::generic_err("Recipient is blocked"));
+        }
+    }
+    Ok(())
+}
+
+/// While transfers are restricted, both parties must be on the whitelist.
+fn check_transfer_restriction<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
     from: &CanonicalAddr,
     to: &CanonicalAddr,
-    amount: u128,
 ) -> StdResult<()> {
-    let mut balances_store = PrefixedStorage::new(PREFIX_BALANCES, store);
+    if let Some(restriction) = read_transfer_restriction(&deps.storage)? {
+        if !restriction.whitelist.contains(from) || !restriction.whitelist.contains(to) {
+            return Err(StdError::generic_err(
+                "Transfers are restricted to whitelisted addresses",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Asks the compliance contract, if one is configured, whether `from` may send `amount` to `to`.
+/// Transfers involving an address on the bypass list are not checked.
+fn check_compliance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: &Uint128,
+) -> StdResult<()> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, &deps.storage);
+    let compliance: ComplianceConfig = match config_store.get(KEY_COMPLIANCE) {
+        Some(data) => from_slice(&data)?,
+        None => return Ok(()),
+    };
+
+    let from_raw = deps.api.canonical_address(from)?;
+    let to_raw = deps.api.canonical_address(to)?;
+    if compliance
+        .bypass
+        .iter()
+        .any(|address| *address == from_raw || *address == to_raw)
+    {
+        return Ok(());
+    }
+
+    let request = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: deps.api.human_address(&compliance.contract)?,
+        msg: to_binary(&ComplianceQueryMsg::CheckTransfer {
+            from: from.clone(),
+            to: to.clone(),
+            amount: *amount,
+        })?,
+    });
+    let response: ComplianceResponse = deps.querier.query(&request)?;
+    if response.allowed {
+        Ok(())
+    } else {
+        Err(StdError::generic_err(format!(
+            "Transfer denied by compliance contract: {}",
+            response.reason.unwrap_or_default()
+        )))
+    }
+}
+
+/// Moves `amount` from `from` to `to`, returning the balance hook notifications it triggers
+fn perform_transfer<T: Storage, A: Api>(
+    store: &mut T,
+    api: &A,
+    from: &CanonicalAddr,
+    to: &CanonicalAddr,
+    amount: u128,
+    block: &BlockInfo,
+) -> StdResult<Vec<CosmosMsg>> {
+    record_outflow(store, from, amount, block)?;
+
+    let mut burn_addresses = read_burn_addresses(store)?;
+    if burn_addresses.addresses.contains(to) {
+        burn(store, from, amount, block.height)?;
+        burn_addresses.redirected += Uint128::from(amount);
+        let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, store);
+        config_store.set(KEY_BURN_ADDRESSES, &to_vec(&burn_addresses)?);
+
+        let from_balance = read_balance(store, from)?;
+        let hook_msg = balance_hook_msg(store, api, from, from_balance + amount, from_balance)?;
+        return Ok(hook_msg.into_iter().collect());
+    }
 
-    let mut from_balance = read_u128(&balances_store, from.as_slice())?;
+    let mut from_balance = read_balance(store, from)?;
     if from_balance < amount {
         return Err(StdError::generic_err(format!(
             "Insufficient funds: balance={}, required={}",
@@ -275,39 +2255,494 @@ fn perform_transfer<T: Storage>(
         )));
     }
     from_balance -= amount;
-    balances_store.set(from.as_slice(), &from_balance.to_be_bytes());
+    write_balance(store, from, from_balance, block.height);
 
-    let mut to_balance = read_u128(&balances_store, to.as_slice())?;
+    let mut to_balance = read_balance(store, to)?;
     to_balance += amount;
-    balances_store.set(to.as_slice(), &to_balance.to_be_bytes());
+    write_balance(store, to, to_balance, block.height);
+
+    // sending to yourself leaves the balance unchanged
+    let mut messages = vec![];
+    if from != to {
+        messages.extend(balance_hook_msg(
+            store,
+            api,
+            from,
+            from_balance + amount,
+            from_balance,
+        )?);
+        messages.extend(balance_hook_msg(
+            store,
+            api,
+            to,
+            to_balance - amount,
+            to_balance,
+        )?);
+    }
+    Ok(messages)
+}
+
+fn balance_hook_msg<S: Storage, A: Api>(
+    store: &S,
+    api: &A,
+    owner: &CanonicalAddr,
+    old_balance: u128,
+    new_balance: u128,
+) -> StdResult<Option<CosmosMsg>> {
+    let hook = match read_balance_hook(store, owner)? {
+        Some(hook) => hook,
+        None => return Ok(None),
+    };
+    let threshold = hook.threshold.u128();
+    let above = new_balance >= threshold;
+    if (old_balance >= threshold) == above {
+        return Ok(None);
+    }
+    let msg = BalanceHookMsg::BalanceThresholdCrossed {
+        address: api.human_address(owner)?,
+        balance: Uint128::from(new_balance),
+        threshold: hook.threshold,
+        above,
+    };
+    Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: api.human_address(&hook.contract)?,
+        msg: to_binary(&msg)?,
+        send: vec![],
+    })))
+}
+
+/// Adds `amount` to what `from` sent during the current day, failing if that exceeds the cap
+fn record_outflow<S: Storage>(
+    store: &mut S,
+    from: &CanonicalAddr,
+    amount: u128,
+    block: &BlockInfo,
+) -> StdResult<()> {
+    let (cap, mut outflow) = match current_outflow(store, from, block.time)? {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+    check_outflow(cap, &outflow, amount)?;
+    outflow.sent += Uint128::from(amount);
 
+    let mut outflows_store = PrefixedStorage::new(PREFIX_OUTFLOWS, store);
+    outflows_store.set(from.as_slice(), &to_vec(&outflow)?);
     Ok(())
 }
 
-// Converts 16 bytes value into u128
-// Errors if data found that is not 16 bytes
-pub fn bytes_to_u128(data: &[u8]) -> StdResult<u128> {
-    match data[0..16].try_into() {
-        Ok(bytes) => Ok(u128::from_be_bytes(bytes)),
-        Err(_) => Err(StdError::generic_err(
-            "Corrupted data found. 16 byte expected.",
-        )),
+/// The cap that applies to `from` and its outflow in the window containing `time`, which starts
+/// from zero once the window of its last transfer is over. None when no cap applies.
+fn current_outflow<S: Storage>(
+    store: &S,
+    from: &CanonicalAddr,
+    time: u64,
+) -> StdResult<Option<(u128, Outflow)>> {
+    let cap = match read_outflow_cap(store)? {
+        Some(outflow_cap) => outflow_cap.cap.u128(),
+        None => return Ok(None),
+    };
+    if read_pool_registry(store)?.pools.contains(from) {
+        return Ok(None);
+    }
+
+    let window_start = time - time % OUTFLOW_WINDOW_SECONDS;
+    let mut outflow = read_outflow(store, from)?;
+    if outflow.window_start != window_start {
+        outflow = Outflow {
+            window_start,
+            sent: Uint128::zero(),
+        };
+    }
+    Ok(Some((cap, outflow)))
+}
+
+fn check_outflow(cap: u128, outflow: &Outflow, amount: u128) -> StdResult<()> {
+    let remaining = cap.saturating_sub(outflow.sent.u128());
+    if amount > remaining {
+        return Err(StdError::generic_err(format!(
+            "Daily outflow cap exceeded: remaining={}, required={}",
+            remaining, amount
+        )));
     }
+    Ok(())
 }
 
-// Reads 16 byte storage value into u128
-// Returns zero if key does not exist. Errors if data found that is not 16 bytes
-pub fn read_u128<S: ReadonlyStorage>(store: &S, key: &[u8]) -> StdResult<u128> {
-    let result = store.get(key);
-    match result {
-        Some(data) => bytes_to_u128(&data),
-        None => Ok(0u128),
+/// Checks the spender can spend `amount` at `height`, returning the session key it spends from
+/// while that is valid. Without one it spends its allowance.
+fn check_spend<S: Storage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    spender: &CanonicalAddr,
+    amount: u128,
+    height: u64,
+) -> StdResult<Option<SessionKey>> {
+    match read_session_key(store, owner, spender)? {
+        Some(session_key) if height < session_key.expires_at_height => {
+            if session_key.remaining.u128() < amount {
+                return Err(StdError::generic_err(format!(
+                    "Session key spend limit exceeded: remaining={}, required={}",
+                    session_key.remaining, amount
+                )));
+            }
+            Ok(Some(session_key))
+        }
+        _ => check_allowance(store, owner, spender, amount).map(|_| None),
+    }
+}
+
+/// Spends from the spender's session key while it is valid, from its allowance otherwise
+fn spend_allowance_or_session_key<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    spender: &CanonicalAddr,
+    amount: u128,
+    height: u64,
+) -> StdResult<()> {
+    match check_spend(store, owner, spender, amount, height)? {
+        Some(mut session_key) => {
+            session_key.remaining = (session_key.remaining - Uint128::from(amount))?;
+            write_session_key(store, owner, spender, &session_key)
+        }
+        None => spend_allowance(store, owner, spender, amount),
+    }
+}
+
+// the spender's allowance, checked to cover `amount`
+fn check_allowance<S: Storage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    spender: &CanonicalAddr,
+    amount: u128,
+) -> StdResult<u128> {
+    let allowance = read_allowance(store, owner, spender)?;
+    if allowance < amount {
+        return Err(StdError::generic_err(format!(
+            "Insufficient allowance: allowance={}, required={}",
+            allowance, amount
+        )));
+    }
+    Ok(allowance)
+}
+
+fn spend_allowance<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    spender: &CanonicalAddr,
+    amount: u128,
+) -> StdResult<()> {
+    let allowance = check_allowance(store, owner, spender, amount)?;
+    write_allowance(store, owner, spender, allowance - amount)
+}
+
+// Hex encoded SHA-256 hash of a Pull reference
+fn hash_reference(reference: &str) -> String {
+    hex::encode(Sha256::digest(reference.as_bytes()))
+}
+
+// Stores the receipt under the next free id and indexes it for both parties
+fn store_receipt<S: Storage>(store: &mut S, receipt: &Receipt) -> StdResult<u64> {
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, store);
+    let receipt_id = match config_store.get(KEY_RECEIPT_COUNT) {
+        Some(data) => bytes_to_u64(&data)? + 1,
+        None => 1,
+    };
+    config_store.set(KEY_RECEIPT_COUNT, &receipt_id.to_be_bytes());
+
+    let id_key = receipt_id.to_be_bytes();
+    let mut receipts_store = PrefixedStorage::new(PREFIX_RECEIPTS, store);
+    receipts_store.set(&id_key, &to_vec(receipt)?);
+
+    for party in &[&receipt.payer, &receipt.merchant] {
+        let mut index_store =
+            PrefixedStorage::multilevel(&[PREFIX_RECEIPTS_BY_ADDRESS, party.as_slice()], store);
+        index_store.set(&id_key, &[1]);
+    }
+    Ok(receipt_id)
+}
+
+/// Checks the sender's transfer key and remembers it, returning whether one of their recent
+/// transfers or sends already used it
+fn use_tx_key<S: Storage>(store: &mut S, sender: &CanonicalAddr, tx_key: &str) -> StdResult<bool> {
+    if tx_key.len() > MAX_TX_KEY_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "Transaction key too long: max {} bytes",
+            MAX_TX_KEY_LENGTH
+        )));
+    }
+    if is_known_tx_key(store, sender, tx_key) {
+        return Ok(true);
+    }
+    record_tx_key(store, sender, tx_key)?;
+    Ok(false)
+}
+
+fn is_known_tx_key<S: Storage>(store: &S, sender: &CanonicalAddr, tx_key: &str) -> bool {
+    let tx_keys_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_TX_KEYS, sender.as_slice()], store);
+    tx_keys_store.get(tx_key.as_bytes()).is_some()
+}
+
+/// Remembers the sender's transfer key in their own queue of their last TX_KEY_RETENTION keys,
+/// forgetting their oldest one once the queue is full. Other senders' keys never evict it.
+fn record_tx_key<S: Storage>(store: &mut S, sender: &CanonicalAddr, tx_key: &str) -> StdResult<()> {
+    let mut queues_store = PrefixedStorage::new(PREFIX_TX_KEY_QUEUES, store);
+    let mut queue = Deque::new(&mut queues_store, sender.as_slice());
+    queue.push_back(&tx_key.to_string())?;
+    let evicted: Option<String> = if queue.len()? > TX_KEY_RETENTION {
+        queue.pop_front()?
+    } else {
+        None
+    };
+
+    let mut tx_keys_store =
+        PrefixedStorage::multilevel(&[PREFIX_TX_KEYS, sender.as_slice()], store);
+    if let Some(evicted_key) = evicted {
+        tx_keys_store.remove(evicted_key.as_bytes());
     }
+    tx_keys_store.set(tx_key.as_bytes(), &[1]);
+    Ok(())
 }
 
 fn read_balance<S: Storage>(store: &S, owner: &CanonicalAddr) -> StdResult<u128> {
     let balance_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, store);
-    read_u128(&balance_store, owner.as_slice())
+    match balance_store.get(owner.as_slice()) {
+        Some(data) => decode_balance(&data),
+        None => Ok(0),
+    }
+}
+
+// Balances are stored as 16 big-endian bytes. The json-balances feature stores them as JSON
+// strings instead, like Uint128, only to measure the difference: the JSON layout is 3 bytes for
+// a balance of 1 and 41 at u128::MAX, and costs more gas to parse. See tests/storage_benchmark.rs.
+#[cfg(not(feature = "json-balances"))]
+pub fn encode_balance(amount: u128) -> Vec<u8> {
+    amount.to_be_bytes().to_vec()
+}
+
+#[cfg(not(feature = "json-balances"))]
+pub fn decode_balance(data: &[u8]) -> StdResult<u128> {
+    bytes_to_u128(data)
+}
+
+#[cfg(feature = "json-balances")]
+pub fn encode_balance(amount: u128) -> Vec<u8> {
+    format!("\"{}\"", amount).into_bytes()
+}
+
+#[cfg(feature = "json-balances")]
+pub fn decode_balance(data: &[u8]) -> StdResult<u128> {
+    from_slice::<Uint128>(data).map(|amount| amount.u128())
+}
+
+fn read_outflow_cap<S: Storage>(store: &S) -> StdResult<Option<OutflowCap>> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    match config_store.get(KEY_OUTFLOW_CAP) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+fn read_account_metadata<S: Storage>(
+    store: &S,
+    address: &CanonicalAddr,
+) -> StdResult<Option<AccountMetadata>> {
+    let metadata_store = ReadonlyPrefixedStorage::new(PREFIX_ACCOUNT_METADATA, store);
+    match metadata_store.get(address.as_slice()) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Display names of the transfer parties, for those who have set one
+fn name_logs<S: Storage>(
+    store: &S,
+    sender: &CanonicalAddr,
+    recipient: &CanonicalAddr,
+) -> StdResult<Vec<LogAttribute>> {
+    let mut logs = vec![];
+    for (key, address) in &[("sender_name", sender), ("recipient_name", recipient)] {
+        if let Some(name) = read_account_metadata(store, address)?.and_then(|m| m.name) {
+            logs.push(log(key, name));
+        }
+    }
+    Ok(logs)
+}
+
+// roles are stored under PREFIX_ROLES, then the role, then the holder's address
+fn role_key(role: Role) -> &'static [u8] {
+    role_name(role).as_bytes()
+}
+
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::Admin => "admin",
+        Role::Burner => "burner",
+        Role::Governance => "governance",
+    }
+}
+
+fn has_role<S: Storage>(store: &S, role: Role, address: &CanonicalAddr) -> bool {
+    ReadonlyPrefixedStorage::multilevel(&[PREFIX_ROLES, role_key(role)], store)
+        .get(address.as_slice())
+        .is_some()
+}
+
+fn set_role<S: Storage>(store: &mut S, role: Role, address: &CanonicalAddr, granted: bool) {
+    let mut roles_store = PrefixedStorage::multilevel(&[PREFIX_ROLES, role_key(role)], store);
+    if granted {
+        roles_store.set(address.as_slice(), &[1]);
+    } else {
+        roles_store.remove(address.as_slice());
+    }
+}
+
+/// Fails with Unauthorized unless the signer holds `role`
+fn require_role<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    role: Role,
+) -> StdResult<()> {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    if !has_role(&deps.storage, role, &sender_address_raw) {
+        return Err(StdError::unauthorized());
+    }
+    Ok(())
+}
+
+fn read_burner<S: Storage>(store: &S) -> StdResult<Option<BurnerConfig>> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    match config_store.get(KEY_BURNER) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+fn read_balance_hook<S: Storage>(
+    store: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Option<BalanceHook>> {
+    let hooks_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCE_HOOKS, store);
+    match hooks_store.get(owner.as_slice()) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+fn read_burn_addresses<S: Storage>(store: &S) -> StdResult<BurnAddresses> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    let data = config_store
+        .get(KEY_BURN_ADDRESSES)
+        .ok_or_else(|| StdError::not_found("BurnAddresses"))?;
+    from_slice(&data)
+}
+
+fn read_pool_registry<S: Storage>(store: &S) -> StdResult<PoolRegistry> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    let data = config_store
+        .get(KEY_POOL_REGISTRY)
+        .ok_or_else(|| StdError::not_found("PoolRegistry"))?;
+    from_slice(&data)
+}
+
+fn read_outflow<S: Storage>(store: &S, owner: &CanonicalAddr) -> StdResult<Outflow> {
+    let outflows_store = ReadonlyPrefixedStorage::new(PREFIX_OUTFLOWS, store);
+    match outflows_store.get(owner.as_slice()) {
+        Some(data) => from_slice(&data),
+        None => Ok(Outflow::default()),
+    }
+}
+
+fn read_bootstrap<S: Storage>(store: &S) -> StdResult<Option<BootstrapConfig>> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    match config_store.get(KEY_BOOTSTRAP) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+fn read_transfer_restriction<S: Storage>(store: &S) -> StdResult<Option<TransferRestriction>> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    match config_store.get(KEY_TRANSFER_RESTRICTION) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+// Stores the balance and records it as the account's balance as of `height`
+fn write_balance<S: Storage>(store: &mut S, owner: &CanonicalAddr, amount: u128, height: u64) {
+    let mut balances_store = PrefixedStorage::new(PREFIX_BALANCES, store);
+    balances_store.set(owner.as_slice(), &encode_balance(amount));
+
+    let mut checkpoints_store =
+        PrefixedStorage::multilevel(&[PREFIX_BALANCE_CHECKPOINTS, owner.as_slice()], store);
+    checkpoints_store.set(&height.to_be_bytes(), &amount.to_be_bytes());
+}
+
+// Reads the balance from the last checkpoint at or before `height`
+fn read_balance_at<S: Storage>(store: &S, owner: &CanonicalAddr, height: u64) -> StdResult<u128> {
+    let checkpoints_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_BALANCE_CHECKPOINTS, owner.as_slice()], store);
+    // the smallest key after `height`
+    let mut end = height.to_be_bytes().to_vec();
+    end.push(0);
+    let last = checkpoints_store
+        .range(None, Some(&end), Order::Descending)
+        .next();
+    match last {
+        Some((_, data)) => bytes_to_u128(&data),
+        None => Ok(0u128),
+    }
+}
+
+fn read_received<S: Storage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    token: &CanonicalAddr,
+) -> StdResult<u128> {
+    let received_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_RECEIVED_TOKENS, owner.as_slice()], store);
+    read_u128(&received_store, token.as_slice())
+}
+
+// Fully withdrawn tokens are removed so they don't show up in ReceivedTokens
+fn write_received<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    token: &CanonicalAddr,
+    amount: u128,
+) {
+    let mut received_store =
+        PrefixedStorage::multilevel(&[PREFIX_RECEIVED_TOKENS, owner.as_slice()], store);
+    if amount == 0 {
+        received_store.remove(token.as_slice());
+    } else {
+        received_store.set(token.as_slice(), &amount.to_be_bytes());
+    }
+}
+
+fn read_session_key<S: Storage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    key: &CanonicalAddr,
+) -> StdResult<Option<SessionKey>> {
+    let session_keys_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_SESSION_KEYS, owner.as_slice()], store);
+    match session_keys_store.get(key.as_slice()) {
+        Some(data) => Ok(Some(from_slice(&data)?)),
+        None => Ok(None),
+    }
+}
+
+fn write_session_key<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    key: &CanonicalAddr,
+    session_key: &SessionKey,
+) -> StdResult<()> {
+    let mut session_keys_store =
+        PrefixedStorage::multilevel(&[PREFIX_SESSION_KEYS, owner.as_slice()], store);
+    session_keys_store.set(key.as_slice(), &to_vec(session_key)?);
+    Ok(())
 }
 
 fn read_allowance<S: Storage>(
@@ -332,6 +2767,46 @@ fn write_allowance<S: Storage>(
     Ok(())
 }
 
+fn read_sub_allowance<S: Storage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    delegator: &CanonicalAddr,
+    spender: &CanonicalAddr,
+) -> StdResult<u128> {
+    let sub_allowances_store = ReadonlyPrefixedStorage::multilevel(
+        &[
+            PREFIX_SUB_ALLOWANCES,
+            owner.as_slice(),
+            delegator.as_slice(),
+        ],
+        store,
+    );
+    read_u128(&sub_allowances_store, spender.as_slice())
+}
+
+// Empty sub-allowances are removed so they don't show up in the allowance tree
+fn write_sub_allowance<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    delegator: &CanonicalAddr,
+    spender: &CanonicalAddr,
+    amount: u128,
+) {
+    let mut sub_allowances_store = PrefixedStorage::multilevel(
+        &[
+            PREFIX_SUB_ALLOWANCES,
+            owner.as_slice(),
+            delegator.as_slice(),
+        ],
+        store,
+    );
+    if amount == 0 {
+        sub_allowances_store.remove(spender.as_slice());
+    } else {
+        sub_allowances_store.set(spender.as_slice(), &amount.to_be_bytes());
+    }
+}
+
 fn is_valid_name(name: &str) -> bool {
     let bytes = name.as_bytes();
     if bytes.len() < 3 || bytes.len() > 30 {
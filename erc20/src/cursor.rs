@@ -0,0 +1,41 @@
+use cosmwasm_std::{Binary, StdError, StdResult};
+use cosmwasm_storage::to_length_prefixed;
+
+// Pagination cursors are opaque to clients: they wrap the last key returned together with the
+// namespace of the list they belong to, so a cursor from one query can't be used in another.
+
+/// Builds the cursor pointing after `last_key` in the list stored under `namespace`
+pub fn encode_cursor(namespace: &[u8], last_key: &[u8]) -> Binary {
+    let mut cursor = to_length_prefixed(namespace);
+    cursor.extend_from_slice(last_key);
+    Binary(cursor)
+}
+
+/// Returns the exclusive range start encoded in `cursor`, or None to start at the beginning
+pub fn decode_cursor(namespace: &[u8], cursor: Option<Binary>) -> StdResult<Option<Vec<u8>>> {
+    let cursor = match cursor {
+        Some(cursor) => cursor,
+        None => return Ok(None),
+    };
+    let prefix = to_length_prefixed(namespace);
+    if cursor.len() <= prefix.len() || !cursor.as_slice().starts_with(&prefix) {
+        return Err(StdError::generic_err("Invalid pagination cursor"));
+    }
+    // the smallest key after the last one returned
+    let mut start = cursor.as_slice()[prefix.len()..].to_vec();
+    start.push(0);
+    Ok(Some(start))
+}
+
+/// A list of `limit` items may continue, so a cursor is only returned for full pages
+pub fn next_cursor(
+    namespace: &[u8],
+    last_key: Option<&[u8]>,
+    count: usize,
+    limit: usize,
+) -> Option<Binary> {
+    match last_key {
+        Some(key) if count == limit => Some(encode_cursor(namespace, key)),
+        _ => None,
+    }
+}
@@ -1,4 +1,5 @@
 pub mod contract;
+pub mod cursor;
 pub mod msg;
 
 #[cfg(test)]
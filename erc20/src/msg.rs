@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_std::{Binary, Coin, HumanAddr, Uint128};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct InitialBalance {
@@ -15,6 +15,54 @@ pub struct InitMsg {
     pub symbol: String,
     pub decimals: u8,
     pub initial_balances: Vec<InitialBalance>,
+    pub compliance: Option<Compliance>,
+    /// Addresses that can never receive tokens, in addition to the token contract itself
+    pub blocked_recipients: Option<Vec<HumanAddr>>,
+    /// While set, only addresses whitelisted by an admin can send or receive tokens
+    /// until an admin lifts the restriction for good
+    pub restricted_transfers: Option<bool>,
+    /// Native coins sent to every account receiving tokens for the first time, paid from a
+    /// pool admins fund with FundBootstrap. Disabled when not set.
+    pub bootstrap_grant: Option<Coin>,
+    /// Smallest transfer that pays the bootstrap grant. Transfers of nothing never pay it.
+    pub bootstrap_min_transfer: Option<Uint128>,
+    /// Most tokens any address can send per day, changeable by admins with
+    /// SetOutflowCap. Outflows are not capped when not set.
+    pub daily_outflow_cap: Option<Uint128>,
+    /// Contracts given the governance role, which takes balance snapshots with Checkpoint
+    pub governance_contracts: Option<Vec<HumanAddr>>,
+    /// Gives `burner` the burner role, which burns tokens held by `reserve` with
+    /// BurnFromReserve. Disabled when not set.
+    pub burner: Option<BurnerRole>,
+    /// Receipts keep only the SHA-256 hash of Pull references, which can later be checked
+    /// against a revealed reference with VerifyMemo. References are stored as sent when not set.
+    pub hashed_references: Option<bool>,
+}
+
+/// Privileged roles, each held by any number of addresses. The contract creator starts as admin.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Grants and revokes roles, and manages the whitelist, bootstrap pool, outflow cap, pools
+    /// and burn addresses
+    Admin,
+    /// Burns tokens held by the reserve with BurnFromReserve
+    Burner,
+    /// Takes balance snapshots with Checkpoint
+    Governance,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnerRole {
+    pub burner: HumanAddr,
+    pub reserve: HumanAddr,
+}
+
+/// Every transfer is checked against `contract` unless one of the parties is in `bypass`
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct Compliance {
+    pub contract: HumanAddr,
+    pub bypass: Vec<HumanAddr>,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -24,9 +72,22 @@ pub enum HandleMsg {
         spender: HumanAddr,
         amount: Uint128,
     },
+    /// A transfer repeating the `tx_key` of one of the signer's recent transfers is
+    /// accepted without moving tokens again, so integrations can safely retry.
     Transfer {
         recipient: HumanAddr,
         amount: Uint128,
+        tx_key: Option<String>,
+    },
+    /// Transfers `amount` to `contract` and calls its Receive handler with a Cw20ReceiveMsg
+    /// carrying `msg`, e.g. to stake the tokens in another contract. A `tx_key` is checked
+    /// against the same recent keys as Transfer's, and a repeat neither moves the tokens nor
+    /// calls the contract again. Burn addresses can't be sent to.
+    Send {
+        contract: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+        tx_key: Option<String>,
     },
     TransferFrom {
         owner: HumanAddr,
@@ -36,6 +97,121 @@ pub enum HandleMsg {
     Burn {
         amount: Uint128,
     },
+    Pull {
+        owner: HumanAddr,
+        amount: Uint128,
+        reference: String,
+    },
+    /// Lets `spender` use up to `amount` of the allowance `owner` granted to the signer
+    SubApprove {
+        owner: HumanAddr,
+        spender: HumanAddr,
+        amount: Uint128,
+    },
+    /// Spends a sub-allowance, consuming both the delegator's and the signer's allowance
+    SubTransferFrom {
+        owner: HumanAddr,
+        delegator: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+    },
+    /// Moves `amount` from each hop's owner to the next hop's owner and from the last one to
+    /// `recipient`, spending each owner's allowance. All hops succeed or none do. The signer
+    /// must be the allowance spender of every hop.
+    TransferFromChain {
+        hops: Vec<ChainHop>,
+        recipient: HumanAddr,
+        amount: Uint128,
+    },
+    /// Admin only, while transfers are restricted
+    SetWhitelisted {
+        address: HumanAddr,
+        whitelisted: bool,
+    },
+    /// Admin only. Opens transfers to everyone; the restriction cannot be enabled again.
+    LiftTransferRestriction {},
+    /// Sent by another CW20 token after moving its tokens to this contract. The tokens are
+    /// held for the original sender until withdrawn.
+    Receive(Cw20ReceiveMsg),
+    /// Sends tokens of `token` previously received for the signer back to them.
+    /// Withdraws everything by default.
+    WithdrawReceived {
+        token: HumanAddr,
+        amount: Option<Uint128>,
+    },
+    /// Admin only. Adds the sent coins of the grant denom to the bootstrap pool.
+    FundBootstrap {},
+    /// Admin only, while outflows are capped
+    SetOutflowCap {
+        cap: Uint128,
+    },
+    /// Lets `key` move up to `spend_limit` of the sender's tokens with TransferFrom or Pull until
+    /// `expires_at_height`, without an allowance. Registering a key again replaces it.
+    RegisterSessionKey {
+        key: HumanAddr,
+        spend_limit: Uint128,
+        expires_at_height: u64,
+    },
+    RevokeSessionKey {
+        key: HumanAddr,
+    },
+    /// Admin only. Registered pools are exempt from the daily outflow cap.
+    RegisterPool {
+        pool: HumanAddr,
+    },
+    /// Admin only
+    DeregisterPool {
+        pool: HumanAddr,
+    },
+    /// Admin only. Transfers to a burn address burn the tokens instead.
+    AddBurnAddress {
+        address: HumanAddr,
+    },
+    /// Admin only
+    RemoveBurnAddress {
+        address: HumanAddr,
+    },
+    /// Notifies `contract` with a BalanceHookMsg whenever a transfer moves the sender's balance
+    /// across `threshold`, in either direction. Replaces any hook set before.
+    SetBalanceHook {
+        contract: HumanAddr,
+        threshold: Uint128,
+    },
+    RemoveBalanceHook {},
+    /// Governance contracts only. Takes a snapshot of all balances as of the end of the previous
+    /// block and returns a CheckpointResponse in the response data.
+    Checkpoint {},
+    /// Burner role only. Burns `amount` from the reserve account and returns a BurnResponse.
+    BurnFromReserve {
+        amount: Uint128,
+    },
+    /// Display name (up to 32 bytes) and avatar hash (up to 64 bytes) shown by UIs for the
+    /// signer. Setting neither clears the metadata.
+    SetAccountMetadata {
+        name: Option<String>,
+        avatar_hash: Option<String>,
+    },
+    /// Admin only
+    GrantRole {
+        role: Role,
+        address: HumanAddr,
+    },
+    /// Admin only
+    RevokeRole {
+        role: Role,
+        address: HumanAddr,
+    },
+    /// Gives up a role the signer holds
+    RenounceRole {
+        role: Role,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20ReceiveMsg {
+    pub sender: HumanAddr,
+    pub amount: Uint128,
+    pub msg: Option<Binary>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -44,10 +220,88 @@ pub enum QueryMsg {
     Balance {
         address: HumanAddr,
     },
+    /// Balances of up to 30 addresses. An invalid address gets an error instead of failing
+    /// the whole query.
+    Balances {
+        addresses: Vec<HumanAddr>,
+    },
     Allowance {
         owner: HumanAddr,
         spender: HumanAddr,
     },
+    Receipts {
+        address: HumanAddr,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    /// Checks `memo` against the reference stored in receipt `tx_id`
+    VerifyMemo {
+        tx_id: u64,
+        memo: String,
+    },
+    AllAccounts {
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    AllAllowances {
+        owner: HumanAddr,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    /// Balances held at the end of block `height`, skipping accounts that held nothing then
+    HoldersAt {
+        height: u64,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    AllowanceTree {
+        owner: HumanAddr,
+    },
+    TransferRestriction {},
+    ReceivedTokens {
+        address: HumanAddr,
+    },
+    Bootstrap {},
+    OutflowHeadroom {
+        address: HumanAddr,
+    },
+    SessionKey {
+        owner: HumanAddr,
+        key: HumanAddr,
+    },
+    Pools {},
+    BurnAddresses {},
+    BalanceHook {
+        address: HumanAddr,
+    },
+    BalanceAtSnapshot {
+        id: u64,
+        address: HumanAddr,
+    },
+    Burner {},
+    AccountMetadata {
+        address: HumanAddr,
+    },
+    HasRole {
+        role: Role,
+        address: HumanAddr,
+    },
+    /// Holders of `role`, in address order
+    RoleMembers {
+        role: Role,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    },
+    /// Whether `spender` could move `amount` out of `owner`'s account to `recipient` with
+    /// TransferFrom in a block at `height` and `time`
+    SimulateTransferFrom {
+        owner: HumanAddr,
+        spender: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        height: u64,
+        time: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
@@ -55,7 +309,276 @@ pub struct BalanceResponse {
     pub balance: Uint128,
 }
 
+/// Either `balance` or `error` is set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceResult {
+    pub address: HumanAddr,
+    pub balance: Option<Uint128>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalancesResponse {
+    pub balances: Vec<BalanceResult>,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct AllowanceResponse {
     pub allowance: Uint128,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubAllowanceInfo {
+    pub spender: HumanAddr,
+    pub allowance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowanceNode {
+    pub spender: HumanAddr,
+    pub allowance: Uint128,
+    pub sub_allowances: Vec<SubAllowanceInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowanceTreeResponse {
+    pub allowances: Vec<AllowanceNode>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnResponse {
+    pub total_supply_before: Uint128,
+    pub total_supply_after: Uint128,
+    pub event: Event,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ChainHop {
+    pub owner: HumanAddr,
+    pub allowance_spender: HumanAddr,
+}
+
+/// Typed summary of a handled message. It is the response data of every handle message that
+/// has no response of its own; those responses include it as `event`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Event {
+    pub action: String,
+    /// The signer of the message
+    pub actor: HumanAddr,
+    pub counterparty: Option<HumanAddr>,
+    pub amount: Option<Uint128>,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccountMetadataResponse {
+    pub name: Option<String>,
+    pub avatar_hash: Option<String>,
+}
+
+/// The account holders of the burner role burn from, None when no burner is configured
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnerResponse {
+    pub reserve: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HasRoleResponse {
+    pub has_role: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoleMembersResponse {
+    pub members: Vec<HumanAddr>,
+    pub next_cursor: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceiptResponse {
+    pub id: u64,
+    pub payer: HumanAddr,
+    pub merchant: HumanAddr,
+    pub amount: Uint128,
+    /// The hex encoded SHA-256 hash of the reference when `reference_hashed` is set
+    pub reference: String,
+    pub reference_hashed: bool,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VerifyMemoResponse {
+    pub valid: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceiptsResponse {
+    pub receipts: Vec<ReceiptResponse>,
+    pub next_cursor: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccountBalance {
+    pub address: HumanAddr,
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllAccountsResponse {
+    pub accounts: Vec<AccountBalance>,
+    pub next_cursor: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferRestrictionResponse {
+    pub restricted: bool,
+    pub whitelist: Vec<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BootstrapResponse {
+    /// None when bootstrap grants are disabled
+    pub grant: Option<Coin>,
+    pub pool: Uint128,
+    pub min_transfer: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CheckpointResponse {
+    pub id: u64,
+    /// Balances are those at the end of this block
+    pub height: u64,
+    pub event: Event,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceAtSnapshotResponse {
+    pub id: u64,
+    pub height: u64,
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceHookResponse {
+    pub contract: HumanAddr,
+    pub threshold: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolsResponse {
+    pub pools: Vec<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnAddressesResponse {
+    pub addresses: Vec<HumanAddr>,
+    /// Total burned by transfers to burn addresses
+    pub total_redirected: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SessionKeyResponse {
+    /// What is left of the spend limit
+    pub remaining: Uint128,
+    /// The key can spend before this height
+    pub expires_at_height: u64,
+}
+
+/// Outflows are counted per day, starting at `window_start` (seconds since epoch). Once that day
+/// is over the address can send up to the full cap again.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OutflowHeadroomResponse {
+    /// None when outflows are not capped
+    pub cap: Option<Uint128>,
+    pub window_start: u64,
+    pub sent: Uint128,
+    pub remaining: Option<Uint128>,
+}
+
+/// A check TransferFrom makes before moving tokens
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferConstraint {
+    /// The recipient is blocked
+    Recipient,
+    /// The owner or recipient is not whitelisted while transfers are restricted
+    Restriction,
+    /// The compliance contract denied the transfer
+    Compliance,
+    /// The spender's allowance, or its session key's spend limit, is too low
+    Allowance,
+    /// The owner's daily outflow cap is used up
+    Cap,
+    /// The owner's balance is too low
+    Balance,
+}
+
+/// Checks are made in the order TransferFrom makes them and `blocked_by` is the first that
+/// fails, with `reason` the error TransferFrom would return. Session key expiry and the outflow
+/// window are judged by the block given in the query. Whether the recipient is the token
+/// contract itself is not simulated.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateTransferFromResponse {
+    pub allowed: bool,
+    pub blocked_by: Option<TransferConstraint>,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceivedToken {
+    pub token: HumanAddr,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceivedTokensResponse {
+    pub tokens: Vec<ReceivedToken>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HoldersAtResponse {
+    pub height: u64,
+    pub holders: Vec<AccountBalance>,
+    pub next_cursor: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SpenderAllowance {
+    pub spender: HumanAddr,
+    pub allowance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllAllowancesResponse {
+    pub allowances: Vec<SpenderAllowance>,
+    pub next_cursor: Option<Binary>,
+}
+
+/// The message a balance hook contract receives
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceHookMsg {
+    BalanceThresholdCrossed {
+        address: HumanAddr,
+        balance: Uint128,
+        threshold: Uint128,
+        /// true if the balance rose to the threshold or above, false if it fell below it
+        above: bool,
+    },
+}
+
+/// The query a compliance contract must answer before a transfer is completed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplianceQueryMsg {
+    CheckTransfer {
+        from: HumanAddr,
+        to: HumanAddr,
+        amount: Uint128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ComplianceResponse {
+    pub allowed: bool,
+    pub reason: Option<String>,
+}
@@ -1,14 +1,17 @@
-use cosmwasm_std::testing::{mock_dependencies, mock_env};
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
 use cosmwasm_std::{
-    from_slice, log, Api, Env, HumanAddr, ReadonlyStorage, StdError, Storage, Uint128,
+    from_slice, log, Api, Env, Extern, HumanAddr, ReadonlyStorage, StdError, Storage, Uint128,
 };
 use cosmwasm_storage::ReadonlyPrefixedStorage;
+use cw_math::{bytes_to_u128, read_u128};
 
 use crate::contract::{
-    bytes_to_u128, handle, init, query, read_u128, Constants, KEY_CONSTANTS, KEY_TOTAL_SUPPLY,
+    decode_balance, handle, init, query, Constants, KEY_CONSTANTS, KEY_TOTAL_SUPPLY,
     PREFIX_ALLOWANCES, PREFIX_BALANCES, PREFIX_CONFIG,
 };
-use crate::msg::{HandleMsg, InitMsg, InitialBalance, QueryMsg};
+use crate::msg::{HandleMsg, InitMsg, InitialBalance, QueryMsg, ReceiptResponse, ReceiptsResponse};
 
 static CANONICAL_LENGTH: usize = 20;
 
@@ -40,7 +43,10 @@ fn get_balance<S: ReadonlyStorage, A: Api>(api: &A, storage: &S, address: &Human
         .canonical_address(address)
         .expect("canonical_address failed");
     let balances_storage = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, storage);
-    return read_u128(&balances_storage, address_key.as_slice()).unwrap();
+    match balances_storage.get(address_key.as_slice()) {
+        Some(data) => decode_balance(&data).unwrap(),
+        None => 0,
+    }
 }
 
 fn get_allowance<S: ReadonlyStorage, A: Api>(
@@ -76,6 +82,15 @@ mod init {
                 amount: Uint128::from(11223344u128),
             }]
             .to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let res = init(&mut deps, env, init_msg).unwrap();
@@ -104,6 +119,15 @@ mod init {
             symbol: "CASH".to_string(),
             decimals: 9,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let res = init(&mut deps, env, init_msg).unwrap();
@@ -134,6 +158,15 @@ mod init {
                 },
             ]
             .to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let res = init(&mut deps, env, init_msg).unwrap();
@@ -171,6 +204,15 @@ mod init {
                 amount: Uint128::from(9007199254740993u128),
             }]
             .to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let res = init(&mut deps, env, init_msg).unwrap();
@@ -197,6 +239,15 @@ mod init {
                 amount: Uint128::from(100000000000000000000000000u128),
             }]
             .to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let res = init(&mut deps, env, init_msg).unwrap();
@@ -217,6 +268,15 @@ mod init {
             symbol: "CASH".to_string(),
             decimals: 42,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let result = init(&mut deps, env, init_msg);
@@ -235,6 +295,15 @@ mod init {
             symbol: "CASH".to_string(),
             decimals: 9,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let result = init(&mut deps, env, init_msg);
@@ -255,6 +324,15 @@ mod init {
             symbol: "CASH".to_string(),
             decimals: 9,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let result = init(&mut deps, env, init_msg);
@@ -275,6 +353,15 @@ mod init {
             symbol: "DD".to_string(),
             decimals: 9,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let result = init(&mut deps, env, init_msg);
@@ -295,6 +382,15 @@ mod init {
             symbol: "SUPERCOIN".to_string(),
             decimals: 9,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let result = init(&mut deps, env, init_msg);
@@ -315,6 +411,15 @@ mod init {
             symbol: "CaSH".to_string(),
             decimals: 9,
             initial_balances: [].to_vec(),
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         };
         let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
         let result = init(&mut deps, env, init_msg);
@@ -330,6 +435,7 @@ mod init {
 
 mod transfer {
     use super::*;
+    use cosmwasm_std::HandleResponse;
 
     fn make_init_msg() -> InitMsg {
         InitMsg {
@@ -350,6 +456,15 @@ mod transfer {
                     amount: Uint128::from(33u128),
                 },
             ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         }
     }
 
@@ -380,6 +495,7 @@ mod transfer {
         let transfer_msg = HandleMsg::Transfer {
             recipient: HumanAddr("addr1111".to_string()),
             amount: Uint128::from(1u128),
+            tx_key: None,
         };
         let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
         let transfer_result = handle(&mut deps, env2, transfer_msg).unwrap();
@@ -436,6 +552,7 @@ mod transfer {
         let transfer_msg = HandleMsg::Transfer {
             recipient: HumanAddr("addr2323".to_string()),
             amount: Uint128::from(1u128),
+            tx_key: None,
         };
         let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
         let transfer_result = handle(&mut deps, env2, transfer_msg).unwrap();
@@ -496,6 +613,7 @@ mod transfer {
         let transfer_msg = HandleMsg::Transfer {
             recipient: HumanAddr("addr1111".to_string()),
             amount: Uint128::from(0u128),
+            tx_key: None,
         };
         let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
         let transfer_result = handle(&mut deps, env2, transfer_msg).unwrap();
@@ -542,6 +660,7 @@ mod transfer {
         let transfer_msg = HandleMsg::Transfer {
             recipient: sender.clone(),
             amount: Uint128::from(3u128),
+            tx_key: None,
         };
         let env2 = mock_env_height(&sender, 450, 550);
         let transfer_result = handle(&mut deps, env2, transfer_msg).unwrap();
@@ -586,6 +705,7 @@ mod transfer {
         let transfer_msg = HandleMsg::Transfer {
             recipient: HumanAddr("addr1111".to_string()),
             amount: Uint128::from(12u128),
+            tx_key: None,
         };
         let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
         let transfer_result = handle(&mut deps, env2, transfer_msg);
@@ -612,6 +732,175 @@ mod transfer {
         );
         assert_eq!(get_total_supply(&deps.storage), 66);
     }
+    #[test]
+    fn fails_to_token_contract() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr(MOCK_CONTRACT_ADDR.to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env2, transfer_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Cannot transfer to the token contract")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_to_blocked_recipient() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let mut init_msg = make_init_msg();
+        init_msg.blocked_recipients = Some(vec![HumanAddr("deadbeef".to_string())]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("deadbeef".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env2, transfer_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Recipient is blocked"),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    fn transfer_with_key(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        sender: &str,
+        tx_key: &str,
+    ) -> HandleResponse {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr2323".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: Some(tx_key.to_string()),
+        };
+        let env = mock_env_height(&HumanAddr(sender.to_string()), 450, 550);
+        handle(deps, env, transfer_msg).unwrap()
+    }
+
+    #[test]
+    fn replayed_tx_key_is_noop() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        transfer_with_key(&mut deps, "addr0000", "order-1");
+        let res = transfer_with_key(&mut deps, "addr0000", "order-1");
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "transfer"),
+                log("sender", "addr0000"),
+                log("recipient", "addr2323"),
+                log("idempotent_replay", "true"),
+            ]
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            10
+        );
+
+        // keys are per sender
+        let res = transfer_with_key(&mut deps, "addr1111", "order-1");
+        assert_eq!(res.log.len(), 3);
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr2323".to_string())),
+            2
+        );
+    }
+
+    #[test]
+    fn tx_keys_are_forgotten_after_retention() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addrbbbb".to_string()),
+                amount: Uint128::from(1000u128),
+            }],
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        transfer_with_key(&mut deps, "addrbbbb", "first");
+        for i in 0..99 {
+            transfer_with_key(&mut deps, "addrbbbb", &format!("key-{}", i));
+        }
+        // still within the last 100 keys
+        let res = transfer_with_key(&mut deps, "addrbbbb", "first");
+        assert_eq!(res.log[3], log("idempotent_replay", "true"));
+
+        transfer_with_key(&mut deps, "addrbbbb", "key-99");
+        let res = transfer_with_key(&mut deps, "addrbbbb", "first");
+        assert_eq!(res.log.len(), 3);
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addrbbbb".to_string())),
+            1000 - 102
+        );
+    }
+
+    #[test]
+    fn other_senders_cannot_evict_tx_keys() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            initial_balances: vec![
+                InitialBalance {
+                    address: HumanAddr("addr0000".to_string()),
+                    amount: Uint128::from(10u128),
+                },
+                InitialBalance {
+                    address: HumanAddr("addrbbbb".to_string()),
+                    amount: Uint128::from(1000u128),
+                },
+            ],
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        transfer_with_key(&mut deps, "addr0000", "order-1");
+        for i in 0..150 {
+            transfer_with_key(&mut deps, "addrbbbb", &format!("key-{}", i));
+        }
+        let res = transfer_with_key(&mut deps, "addr0000", "order-1");
+        assert_eq!(res.log[3], log("idempotent_replay", "true"));
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            9
+        );
+    }
+
+    #[test]
+    fn fails_with_long_tx_key() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr2323".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: Some("k".repeat(65)),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, transfer_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Transaction key too long: max 64 bytes")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
 }
 
 mod approve {
@@ -636,6 +925,15 @@ mod approve {
                     amount: Uint128::from(33u128),
                 },
             ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         }
     }
 
@@ -742,6 +1040,8 @@ mod approve {
 
 mod transfer_from {
     use super::*;
+    use crate::msg::{ChainHop, SimulateTransferFromResponse, TransferConstraint};
+    use cosmwasm_std::{from_binary, Querier};
 
     fn make_init_msg() -> InitMsg {
         InitMsg {
@@ -762,6 +1062,15 @@ mod transfer_from {
                     amount: Uint128::from(33u128),
                 },
             ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         }
     }
 
@@ -925,171 +1234,3000 @@ mod transfer_from {
             Err(e) => panic!("unexpected error: {:?}", e),
         }
     }
-}
 
-mod burn {
-    use super::*;
+    fn approve_spender<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        owner: &str,
+        amount: u128,
+    ) {
+        let approve_msg = HandleMsg::Approve {
+            spender: make_spender(),
+            amount: Uint128::from(amount),
+        };
+        let env = mock_env_height(&HumanAddr(owner.to_string()), 450, 550);
+        handle(deps, env, approve_msg).unwrap();
+    }
 
-    fn make_init_msg() -> InitMsg {
-        InitMsg {
-            name: "Cash Token".to_string(),
-            symbol: "CASH".to_string(),
-            decimals: 9,
-            initial_balances: vec![
-                InitialBalance {
-                    address: HumanAddr("addr0000".to_string()),
-                    amount: Uint128::from(11u128),
-                },
-                InitialBalance {
-                    address: HumanAddr("addr1111".to_string()),
-                    amount: Uint128::from(22u128),
-                },
-            ],
+    fn make_hop(owner: &str) -> ChainHop {
+        ChainHop {
+            owner: HumanAddr(owner.to_string()),
+            allowance_spender: make_spender(),
         }
     }
 
     #[test]
-    fn can_burn_from_existing_account() {
+    fn chain_moves_through_every_hop() {
         let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
-        let init_msg = make_init_msg();
         let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
-        let res = init(&mut deps, env1, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
-
-        // Initial state
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
-            11
-        );
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
-            22
-        );
-        assert_eq!(get_total_supply(&deps.storage), 33);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+        approve_spender(&mut deps, "addr0000", 5);
+        approve_spender(&mut deps, "addr1111", 5);
 
-        // Burn
-        let burn_msg = HandleMsg::Burn {
-            amount: Uint128::from(1u128),
+        let chain_msg = HandleMsg::TransferFromChain {
+            hops: vec![make_hop("addr0000"), make_hop("addr1111")],
+            recipient: HumanAddr("addr1212".to_string()),
+            amount: Uint128::from(4u128),
         };
-        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
-        let burn_result = handle(&mut deps, env2, burn_msg).unwrap();
-        assert_eq!(burn_result.messages.len(), 0);
+        let env = mock_env_height(&make_spender(), 450, 550);
+        let res = handle(&mut deps, env, chain_msg).unwrap();
         assert_eq!(
-            burn_result.log,
+            res.log,
             vec![
-                log("action", "burn"),
-                log("account", "addr0000"),
-                log("amount", "1")
+                log("action", "transfer_from_chain"),
+                log("spender", make_spender().as_str()),
+                log("hop", "0"),
+                log("sender", "addr0000"),
+                log("recipient", "addr1111"),
+                log("hop", "1"),
+                log("sender", "addr1111"),
+                log("recipient", "addr1212"),
             ]
         );
 
-        // New state
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
-            10
-        ); // -1
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
-            22
-        );
-        assert_eq!(get_total_supply(&deps.storage), 32);
+        let balance =
+            |address: &str| get_balance(&deps.api, &deps.storage, &HumanAddr(address.to_string()));
+        assert_eq!(balance("addr0000"), 7);
+        assert_eq!(balance("addr1111"), 22);
+        assert_eq!(balance("addr1212"), 4);
     }
 
     #[test]
-    fn can_burn_zero_amount() {
+    fn chain_fails_as_a_whole() {
         let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
-        let init_msg = make_init_msg();
         let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
-        let res = init(&mut deps, env1, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        init(&mut deps, env1, make_init_msg()).unwrap();
+        approve_spender(&mut deps, "addr0000", 5);
+        approve_spender(&mut deps, "addr1111", 3);
 
-        // Initial state
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
-            11
-        );
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
-            22
-        );
-        assert_eq!(get_total_supply(&deps.storage), 33);
+        // the second hop's allowance is too low
+        let chain_msg = HandleMsg::TransferFromChain {
+            hops: vec![make_hop("addr0000"), make_hop("addr1111")],
+            recipient: HumanAddr("addr1212".to_string()),
+            amount: Uint128::from(4u128),
+        };
+        let env = mock_env_height(&make_spender(), 450, 550);
+        match handle(&mut deps, env, chain_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert!(msg.contains("Insufficient allowance"))
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
 
-        // Burn
-        let burn_msg = HandleMsg::Burn {
-            amount: Uint128::from(0u128),
+        let chain_msg = HandleMsg::TransferFromChain {
+            hops: vec![ChainHop {
+                owner: HumanAddr("addr0000".to_string()),
+                allowance_spender: HumanAddr("addr1111".to_string()),
+            }],
+            recipient: HumanAddr("addr1212".to_string()),
+            amount: Uint128::from(1u128),
         };
-        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
-        let burn_result = handle(&mut deps, env2, burn_msg).unwrap();
-        assert_eq!(burn_result.messages.len(), 0);
-        assert_eq!(
-            burn_result.log,
-            vec![
-                log("action", "burn"),
-                log("account", "addr0000"),
-                log("amount", "0"),
-            ]
-        );
+        let env = mock_env_height(&make_spender(), 450, 550);
+        match handle(&mut deps, env, chain_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Hop 0 must spend the signer's allowance")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
 
-        // New state (unchanged)
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
-            11
-        );
-        assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
-            22
-        );
-        assert_eq!(get_total_supply(&deps.storage), 33);
+    fn simulate_at<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        owner: &str,
+        amount: u128,
+        height: u64,
+        time: u64,
+    ) -> SimulateTransferFromResponse {
+        let query_msg = QueryMsg::SimulateTransferFrom {
+            owner: HumanAddr(owner.to_string()),
+            spender: make_spender(),
+            recipient: HumanAddr("addr1212".to_string()),
+            amount: Uint128::from(amount),
+            height,
+            time,
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    fn simulate<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        amount: u128,
+    ) -> SimulateTransferFromResponse {
+        simulate_at(deps, "addr0000", amount, 450, 550)
     }
 
     #[test]
-    fn fails_on_insufficient_balance() {
+    fn simulate_reports_first_blocking_constraint() {
         let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
-        let init_msg = make_init_msg();
+        let init_msg = InitMsg {
+            daily_outflow_cap: Some(Uint128::from(15u128)),
+            ..make_init_msg()
+        };
         let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
-        let res = init(&mut deps, env1, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        init(&mut deps, env1, init_msg).unwrap();
+        approve_spender(&mut deps, "addr0000", 20);
 
-        // Initial state
+        let blocked = |constraint: TransferConstraint, reason: &str| SimulateTransferFromResponse {
+            allowed: false,
+            blocked_by: Some(constraint),
+            reason: Some(reason.to_string()),
+        };
         assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
-            11
+            simulate(&deps, 25),
+            blocked(
+                TransferConstraint::Allowance,
+                "Insufficient allowance: allowance=20, required=25"
+            )
         );
         assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
-            22
+            simulate(&deps, 16),
+            blocked(
+                TransferConstraint::Cap,
+                "Daily outflow cap exceeded: remaining=15, required=16"
+            )
+        );
+        assert_eq!(
+            simulate(&deps, 12),
+            blocked(
+                TransferConstraint::Balance,
+                "Insufficient funds: balance=11, required=12"
+            )
+        );
+        assert_eq!(
+            simulate(&deps, 5),
+            SimulateTransferFromResponse {
+                allowed: true,
+                blocked_by: None,
+                reason: None,
+            }
         );
-        assert_eq!(get_total_supply(&deps.storage), 33);
 
-        // Burn
-        let burn_msg = HandleMsg::Burn {
+        // the simulation matches what TransferFrom does
+        let transfer_from_msg = HandleMsg::TransferFrom {
+            owner: HumanAddr("addr0000".to_string()),
+            recipient: HumanAddr("addr1212".to_string()),
             amount: Uint128::from(12u128),
         };
-        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
-        let burn_result = handle(&mut deps, env2, burn_msg);
-        match burn_result {
+        let env2 = mock_env_height(&make_spender(), 450, 550);
+        match handle(&mut deps, env2, transfer_from_msg) {
             Ok(_) => panic!("expected error"),
             Err(StdError::GenericErr { msg, .. }) => {
-                assert_eq!(msg, "insufficient funds to burn: balance=11, required=12")
+                assert_eq!(msg, "Insufficient funds: balance=11, required=12")
             }
             Err(e) => panic!("unexpected error: {:?}", e),
         }
+    }
 
-        // New state (unchanged)
+    #[test]
+    fn simulate_judges_session_keys_and_outflow_by_the_given_block() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            daily_outflow_cap: Some(Uint128::from(15u128)),
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+        approve_spender(&mut deps, "addrbbbb", 20);
+        let register_msg = HandleMsg::RegisterSessionKey {
+            key: make_spender(),
+            spend_limit: Uint128::from(3u128),
+            expires_at_height: 500,
+        };
+        let env = mock_env_height(&HumanAddr("addrbbbb".to_string()), 450, 550);
+        handle(&mut deps, env, register_msg).unwrap();
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(10u128),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addrbbbb".to_string()), 450, 550);
+        handle(&mut deps, env, transfer_msg).unwrap();
+
+        let blocked = |constraint: TransferConstraint, reason: &str| SimulateTransferFromResponse {
+            allowed: false,
+            blocked_by: Some(constraint),
+            reason: Some(reason.to_string()),
+        };
         assert_eq!(
-            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
-            11
+            simulate_at(&deps, "addrbbbb", 6, 450, 550),
+            blocked(
+                TransferConstraint::Allowance,
+                "Session key spend limit exceeded: remaining=3, required=6"
+            )
+        );
+        // once the session key expires the allowance is spent instead
+        assert_eq!(
+            simulate_at(&deps, "addrbbbb", 6, 500, 550),
+            blocked(
+                TransferConstraint::Cap,
+                "Daily outflow cap exceeded: remaining=5, required=6"
+            )
+        );
+        // and the outflow starts from zero the next day
+        let next_day = 550 + 86_400;
+        assert_eq!(
+            simulate_at(&deps, "addrbbbb", 6, 500, next_day),
+            SimulateTransferFromResponse {
+                allowed: true,
+                blocked_by: None,
+                reason: None,
+            }
+        );
+        let transfer_from_msg = HandleMsg::TransferFrom {
+            owner: HumanAddr("addrbbbb".to_string()),
+            recipient: HumanAddr("addr1212".to_string()),
+            amount: Uint128::from(6u128),
+        };
+        let env = mock_env_height(&make_spender(), 500, next_day);
+        handle(&mut deps, env, transfer_from_msg).unwrap();
+    }
+}
+
+mod sub_approve {
+    use super::*;
+    use crate::msg::{AllowanceNode, AllowanceTreeResponse, SubAllowanceInfo};
+    use cosmwasm_std::from_binary;
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(11u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn init_with_allowance(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(deps, env1, make_init_msg()).unwrap();
+
+        let approve_msg = HandleMsg::Approve {
+            spender: HumanAddr("delegator".to_string()),
+            amount: Uint128::from(10u128),
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(deps, env2, approve_msg).unwrap();
+    }
+
+    #[test]
+    fn works() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        init_with_allowance(&mut deps);
+        let owner = HumanAddr("addr0000".to_string());
+        let delegator = HumanAddr("delegator".to_string());
+        let sub_spender = HumanAddr("subspender".to_string());
+        let recipient = HumanAddr("addr1111".to_string());
+
+        let sub_approve_msg = HandleMsg::SubApprove {
+            owner: owner.clone(),
+            spender: sub_spender.clone(),
+            amount: Uint128::from(4u128),
+        };
+        let env = mock_env_height(&delegator, 450, 550);
+        let res = handle(&mut deps, env, sub_approve_msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "sub_approve"),
+                log("owner", owner.as_str()),
+                log("delegator", delegator.as_str()),
+                log("spender", sub_spender.as_str()),
+            ]
+        );
+
+        let transfer_msg = HandleMsg::SubTransferFrom {
+            owner: owner.clone(),
+            delegator: delegator.clone(),
+            recipient: recipient.clone(),
+            amount: Uint128::from(3u128),
+        };
+        let env = mock_env_height(&sub_spender, 450, 550);
+        handle(&mut deps, env, transfer_msg).unwrap();
+
+        // Both levels were consumed
+        assert_eq!(get_balance(&deps.api, &deps.storage, &owner), 8);
+        assert_eq!(get_balance(&deps.api, &deps.storage, &recipient), 3);
+        assert_eq!(
+            get_allowance(&deps.api, &deps.storage, &owner, &delegator),
+            7
+        );
+
+        let query_msg = QueryMsg::AllowanceTree {
+            owner: owner.clone(),
+        };
+        let tree: AllowanceTreeResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            tree,
+            AllowanceTreeResponse {
+                allowances: vec![AllowanceNode {
+                    spender: delegator,
+                    allowance: Uint128::from(7u128),
+                    sub_allowances: vec![SubAllowanceInfo {
+                        spender: sub_spender,
+                        allowance: Uint128::from(1u128),
+                    }],
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn fails_when_exceeding_allowance() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        init_with_allowance(&mut deps);
+
+        let sub_approve_msg = HandleMsg::SubApprove {
+            owner: HumanAddr("addr0000".to_string()),
+            spender: HumanAddr("subspender".to_string()),
+            amount: Uint128::from(11u128),
+        };
+        let env = mock_env_height(&HumanAddr("delegator".to_string()), 450, 550);
+        match handle(&mut deps, env, sub_approve_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Sub-allowance exceeds allowance: allowance=10, required=11"
+            ),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_when_sub_allowance_too_low() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        init_with_allowance(&mut deps);
+
+        let sub_approve_msg = HandleMsg::SubApprove {
+            owner: HumanAddr("addr0000".to_string()),
+            spender: HumanAddr("subspender".to_string()),
+            amount: Uint128::from(2u128),
+        };
+        let env = mock_env_height(&HumanAddr("delegator".to_string()), 450, 550);
+        handle(&mut deps, env, sub_approve_msg).unwrap();
+
+        let transfer_msg = HandleMsg::SubTransferFrom {
+            owner: HumanAddr("addr0000".to_string()),
+            delegator: HumanAddr("delegator".to_string()),
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(3u128),
+        };
+        let env = mock_env_height(&HumanAddr("subspender".to_string()), 450, 550);
+        match handle(&mut deps, env, transfer_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Insufficient sub-allowance: allowance=2, required=3")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
+
+mod pull {
+    use super::*;
+    use crate::msg::VerifyMemoResponse;
+    use cosmwasm_std::{from_binary, Binary};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(11u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn make_merchant() -> HumanAddr {
+        HumanAddr("merchant".to_string())
+    }
+
+    fn query_receipts_page(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        address: &HumanAddr,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    ) -> ReceiptsResponse {
+        let query_msg = QueryMsg::Receipts {
+            address: address.clone(),
+            start_after,
+            limit,
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    fn query_receipts(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        address: &HumanAddr,
+    ) -> Vec<ReceiptResponse> {
+        query_receipts_page(deps, address, None, None).receipts
+    }
+
+    #[test]
+    fn works() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let res = init(&mut deps, env1, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let owner = HumanAddr("addr0000".to_string());
+        let merchant = make_merchant();
+
+        let approve_msg = HandleMsg::Approve {
+            spender: merchant.clone(),
+            amount: Uint128::from(10u128),
+        };
+        let env2 = mock_env_height(&owner, 450, 550);
+        handle(&mut deps, env2, approve_msg).unwrap();
+
+        let pull_msg = HandleMsg::Pull {
+            owner: owner.clone(),
+            amount: Uint128::from(4u128),
+            reference: "invoice-1".to_string(),
+        };
+        let env3 = mock_env_height(&merchant, 451, 555);
+        let pull_result = handle(&mut deps, env3, pull_msg).unwrap();
+        assert_eq!(pull_result.messages.len(), 0);
+        assert_eq!(
+            pull_result.log,
+            vec![
+                log("action", "pull"),
+                log("merchant", merchant.as_str()),
+                log("payer", owner.as_str()),
+                log("amount", "4"),
+                log("receipt_id", "1"),
+            ]
+        );
+
+        // State changed
+        assert_eq!(get_balance(&deps.api, &deps.storage, &owner), 7);
+        assert_eq!(get_balance(&deps.api, &deps.storage, &merchant), 4);
+        assert_eq!(
+            get_allowance(&deps.api, &deps.storage, &owner, &merchant),
+            6
+        );
+
+        // Receipt visible to both parties
+        let expected = vec![ReceiptResponse {
+            id: 1,
+            payer: owner.clone(),
+            merchant: merchant.clone(),
+            amount: Uint128::from(4u128),
+            reference: "invoice-1".to_string(),
+            reference_hashed: false,
+            height: 451,
+        }];
+        assert_eq!(query_receipts(&deps, &owner), expected);
+        assert_eq!(query_receipts(&deps, &merchant), expected);
+        assert_eq!(
+            query_receipts(&deps, &HumanAddr("addr1111".to_string())),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn receipts_are_paginated() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let owner = HumanAddr("addr0000".to_string());
+        let merchant = make_merchant();
+        let approve_msg = HandleMsg::Approve {
+            spender: merchant.clone(),
+            amount: Uint128::from(10u128),
+        };
+        handle(&mut deps, mock_env_height(&owner, 450, 550), approve_msg).unwrap();
+
+        for month in 1..=3 {
+            let pull_msg = HandleMsg::Pull {
+                owner: owner.clone(),
+                amount: Uint128::from(1u128),
+                reference: format!("subscription-{}", month),
+            };
+            handle(
+                &mut deps,
+                mock_env_height(&merchant, 450 + month, 550),
+                pull_msg,
+            )
+            .unwrap();
+        }
+
+        let page = query_receipts_page(&deps, &merchant, None, Some(2));
+        assert_eq!(
+            page.receipts.iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        let page = query_receipts_page(&deps, &merchant, page.next_cursor, Some(2));
+        assert_eq!(page.receipts.len(), 1);
+        assert_eq!(page.receipts[0].id, 3);
+        assert_eq!(page.receipts[0].reference, "subscription-3");
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn fails_when_allowance_too_low() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let owner = HumanAddr("addr0000".to_string());
+        let merchant = make_merchant();
+
+        let pull_msg = HandleMsg::Pull {
+            owner: owner.clone(),
+            amount: Uint128::from(1u128),
+            reference: "invoice-1".to_string(),
+        };
+        let env2 = mock_env_height(&merchant, 450, 550);
+        match handle(&mut deps, env2, pull_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Insufficient allowance: allowance=0, required=1")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(query_receipts(&deps, &owner), vec![]);
+    }
+
+    #[test]
+    fn hashed_references_can_be_verified() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            hashed_references: Some(true),
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let owner = HumanAddr("addr0000".to_string());
+        let merchant = make_merchant();
+        let approve_msg = HandleMsg::Approve {
+            spender: merchant.clone(),
+            amount: Uint128::from(10u128),
+        };
+        handle(&mut deps, mock_env_height(&owner, 450, 550), approve_msg).unwrap();
+        let pull_msg = HandleMsg::Pull {
+            owner: owner.clone(),
+            amount: Uint128::from(4u128),
+            reference: "invoice-1".to_string(),
+        };
+        handle(&mut deps, mock_env_height(&merchant, 451, 555), pull_msg).unwrap();
+
+        // Only the hash is stored
+        let receipts = query_receipts(&deps, &owner);
+        assert_eq!(receipts.len(), 1);
+        assert!(receipts[0].reference_hashed);
+        assert_eq!(
+            receipts[0].reference,
+            "ed8d97c08bf3add20118c75a4e92eb49af62ed9b602a6e60b3e5e87e440f5726"
+        );
+
+        let verify = |memo: &str| -> bool {
+            let query_msg = QueryMsg::VerifyMemo {
+                tx_id: 1,
+                memo: memo.to_string(),
+            };
+            let res: VerifyMemoResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+            res.valid
+        };
+        assert!(verify("invoice-1"));
+        assert!(!verify("invoice-2"));
+    }
+
+    #[test]
+    fn verify_memo_works_for_plain_references() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let owner = HumanAddr("addr0000".to_string());
+        let merchant = make_merchant();
+        let approve_msg = HandleMsg::Approve {
+            spender: merchant.clone(),
+            amount: Uint128::from(10u128),
+        };
+        handle(&mut deps, mock_env_height(&owner, 450, 550), approve_msg).unwrap();
+        let pull_msg = HandleMsg::Pull {
+            owner,
+            amount: Uint128::from(4u128),
+            reference: "invoice-1".to_string(),
+        };
+        handle(&mut deps, mock_env_height(&merchant, 451, 555), pull_msg).unwrap();
+
+        let query_msg = QueryMsg::VerifyMemo {
+            tx_id: 1,
+            memo: "invoice-1".to_string(),
+        };
+        let res: VerifyMemoResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert!(res.valid);
+
+        // Unknown receipt
+        let query_msg = QueryMsg::VerifyMemo {
+            tx_id: 2,
+            memo: "invoice-1".to_string(),
+        };
+        match query(&deps, query_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::NotFound { kind, .. }) => assert_eq!(kind, "Receipt"),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_for_reference_too_long() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let pull_msg = HandleMsg::Pull {
+            owner: HumanAddr("addr0000".to_string()),
+            amount: Uint128::from(1u128),
+            reference: "x".repeat(129),
+        };
+        let env2 = mock_env_height(&make_merchant(), 450, 550);
+        match handle(&mut deps, env2, pull_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Reference too long: max 128 bytes")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
+
+mod compliance {
+    use super::*;
+    use crate::msg::{
+        Compliance, ComplianceQueryMsg, ComplianceResponse, SimulateTransferFromResponse,
+        TransferConstraint,
+    };
+    use cosmwasm_std::{
+        from_binary, to_binary, Empty, Querier, QuerierResult, QueryRequest, WasmQuery,
+    };
+
+    // Answers compliance checks for COMPLIANCE_CONTRACT, denying transfers to "blocked"
+    struct ComplianceQuerier {
+        base: MockQuerier,
+    }
+
+    const COMPLIANCE_CONTRACT: &str = "compliance";
+
+    impl Querier for ComplianceQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                    if contract_addr.as_str() == COMPLIANCE_CONTRACT =>
+                {
+                    let ComplianceQueryMsg::CheckTransfer { to, .. } = from_binary(msg).unwrap();
+                    let response = if to.as_str() == "blocked" {
+                        ComplianceResponse {
+                            allowed: false,
+                            reason: Some("recipient is sanctioned".to_string()),
+                        }
+                    } else {
+                        ComplianceResponse {
+                            allowed: true,
+                            reason: None,
+                        }
+                    };
+                    Ok(to_binary(&response))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    fn mock_dependencies_with_compliance() -> Extern<MockStorage, MockApi, ComplianceQuerier> {
+        Extern {
+            storage: MockStorage::default(),
+            api: MockApi::new(CANONICAL_LENGTH),
+            querier: ComplianceQuerier {
+                base: MockQuerier::new(&[]),
+            },
+        }
+    }
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![
+                InitialBalance {
+                    address: HumanAddr("addr0000".to_string()),
+                    amount: Uint128::from(11u128),
+                },
+                InitialBalance {
+                    address: HumanAddr("treasury".to_string()),
+                    amount: Uint128::from(22u128),
+                },
+            ],
+            compliance: Some(Compliance {
+                contract: HumanAddr(COMPLIANCE_CONTRACT.to_string()),
+                bypass: vec![HumanAddr("treasury".to_string())],
+            }),
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    #[test]
+    fn allowed_transfer_works() {
+        let mut deps = mock_dependencies_with_compliance();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(&mut deps, env2, transfer_msg).unwrap();
+
+        let addr1111 = HumanAddr("addr1111".to_string());
+        assert_eq!(get_balance(&deps.api, &deps.storage, &addr1111), 1);
+    }
+
+    #[test]
+    fn denied_transfer_fails() {
+        let mut deps = mock_dependencies_with_compliance();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("blocked".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env2, transfer_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Transfer denied by compliance contract: recipient is sanctioned"
+            ),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+
+        let addr0000 = HumanAddr("addr0000".to_string());
+        assert_eq!(get_balance(&deps.api, &deps.storage, &addr0000), 11);
+
+        // a simulated TransferFrom asks the compliance contract too
+        let query_msg = QueryMsg::SimulateTransferFrom {
+            owner: addr0000,
+            spender: HumanAddr("spender".to_string()),
+            recipient: HumanAddr("blocked".to_string()),
+            amount: Uint128::from(1u128),
+            height: 450,
+            time: 550,
+        };
+        let res: SimulateTransferFromResponse =
+            from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            SimulateTransferFromResponse {
+                allowed: false,
+                blocked_by: Some(TransferConstraint::Compliance),
+                reason: Some(
+                    "Transfer denied by compliance contract: recipient is sanctioned".to_string()
+                ),
+            }
+        );
+    }
+
+    #[test]
+    fn bypass_address_is_not_checked() {
+        let mut deps = mock_dependencies_with_compliance();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("blocked".to_string()),
+            amount: Uint128::from(2u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&HumanAddr("treasury".to_string()), 450, 550);
+        handle(&mut deps, env2, transfer_msg).unwrap();
+
+        let blocked = HumanAddr("blocked".to_string());
+        assert_eq!(get_balance(&deps.api, &deps.storage, &blocked), 2);
+    }
+
+    #[test]
+    fn fails_when_compliance_contract_missing() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        assert!(handle(&mut deps, env2, transfer_msg).is_err());
+    }
+}
+
+mod restricted_transfers {
+    use super::*;
+    use crate::msg::TransferRestrictionResponse;
+    use cosmwasm_std::{from_binary, HandleResponse, Querier, StdResult};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![
+                InitialBalance {
+                    address: HumanAddr("addr0000".to_string()),
+                    amount: Uint128::from(11u128),
+                },
+                InitialBalance {
+                    address: HumanAddr("addr1111".to_string()),
+                    amount: Uint128::from(22u128),
+                },
+            ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: Some(true),
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn transfer<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        sender: &str,
+        recipient: &str,
+    ) -> StdResult<HandleResponse> {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr(recipient.to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr(sender.to_string()), 450, 550);
+        handle(deps, env, transfer_msg)
+    }
+
+    fn set_whitelisted<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        sender: &str,
+        address: &str,
+        whitelisted: bool,
+    ) -> StdResult<HandleResponse> {
+        let msg = HandleMsg::SetWhitelisted {
+            address: HumanAddr(address.to_string()),
+            whitelisted,
+        };
+        let env = mock_env_height(&HumanAddr(sender.to_string()), 450, 550);
+        handle(deps, env, msg)
+    }
+
+    fn assert_restricted(result: StdResult<HandleResponse>) {
+        match result {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Transfers are restricted to whitelisted addresses")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn only_whitelisted_addresses_can_transfer() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        assert_restricted(transfer(&mut deps, "addr0000", "addr1111"));
+
+        // both parties need to be whitelisted
+        set_whitelisted(&mut deps, "creator", "addr0000", true).unwrap();
+        assert_restricted(transfer(&mut deps, "addr0000", "addr1111"));
+        assert_restricted(transfer(&mut deps, "addr1111", "addr0000"));
+        let res = set_whitelisted(&mut deps, "creator", "addr1111", true).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "set_whitelisted"),
+                log("address", "addr1111"),
+                log("whitelisted", "true"),
+            ]
+        );
+        transfer(&mut deps, "addr0000", "addr1111").unwrap();
+        transfer(&mut deps, "addr1111", "addr0000").unwrap();
+
+        // allowances are restricted the same way
+        let approve_msg = HandleMsg::Approve {
+            spender: HumanAddr("spender".to_string()),
+            amount: Uint128::from(5u128),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(&mut deps, env, approve_msg).unwrap();
+        let transfer_from_msg = HandleMsg::TransferFrom {
+            owner: HumanAddr("addr0000".to_string()),
+            recipient: HumanAddr("addr2222".to_string()),
+            amount: Uint128::from(1u128),
+        };
+        let env = mock_env_height(&HumanAddr("spender".to_string()), 450, 550);
+        assert_restricted(handle(&mut deps, env, transfer_from_msg));
+
+        set_whitelisted(&mut deps, "creator", "addr1111", false).unwrap();
+        assert_restricted(transfer(&mut deps, "addr0000", "addr1111"));
+
+        let query_msg = QueryMsg::TransferRestriction {};
+        let res: TransferRestrictionResponse =
+            from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            TransferRestrictionResponse {
+                restricted: true,
+                whitelist: vec![HumanAddr("addr0000".to_string())],
+            }
+        );
+    }
+
+    #[test]
+    fn only_admin_can_whitelist() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        match set_whitelisted(&mut deps, "addr0000", "addr0000", true) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, HandleMsg::LiftTransferRestriction {}) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn lifting_the_restriction_is_permanent() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let res = handle(&mut deps, env, HandleMsg::LiftTransferRestriction {}).unwrap();
+        assert_eq!(res.log, vec![log("action", "lift_transfer_restriction")]);
+
+        transfer(&mut deps, "addr0000", "addr1111").unwrap();
+
+        match set_whitelisted(&mut deps, "creator", "addr0000", true) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Transfers are not restricted")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        assert!(handle(&mut deps, env, HandleMsg::LiftTransferRestriction {}).is_err());
+
+        let query_msg = QueryMsg::TransferRestriction {};
+        let res: TransferRestrictionResponse =
+            from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert!(!res.restricted);
+    }
+
+    #[test]
+    fn unrestricted_by_default() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            restricted_transfers: None,
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        transfer(&mut deps, "addr0000", "addr1111").unwrap();
+    }
+}
+
+mod receive {
+    use super::*;
+    use crate::msg::{Cw20ReceiveMsg, ReceivedToken, ReceivedTokensResponse};
+    use cosmwasm_std::{from_binary, to_binary, Binary, CosmosMsg, Querier, WasmMsg};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(11u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn query_received_tokens<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        address: &str,
+    ) -> Vec<ReceivedToken> {
+        let query_msg = QueryMsg::ReceivedTokens {
+            address: HumanAddr(address.to_string()),
+        };
+        let res: ReceivedTokensResponse = from_binary(&query(deps, query_msg).unwrap()).unwrap();
+        res.tokens
+    }
+
+    #[test]
+    fn can_receive_and_withdraw_tokens() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        for amount in &[10u128, 5u128] {
+            let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+                sender: HumanAddr("addr1111".to_string()),
+                amount: Uint128::from(*amount),
+                msg: None,
+            });
+            let env = mock_env_height(&HumanAddr("othertoken".to_string()), 450, 550);
+            let res = handle(&mut deps, env, receive_msg).unwrap();
+            assert_eq!(res.messages.len(), 0);
+        }
+        assert_eq!(
+            query_received_tokens(&deps, "addr1111"),
+            vec![ReceivedToken {
+                token: HumanAddr("othertoken".to_string()),
+                amount: Uint128::from(15u128),
+            }]
+        );
+        // received tokens don't change balances of this token
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            0
+        );
+
+        let withdraw_msg = HandleMsg::WithdrawReceived {
+            token: HumanAddr("othertoken".to_string()),
+            amount: Some(Uint128::from(4u128)),
+        };
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 450, 550);
+        let res = handle(&mut deps, env, withdraw_msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr("othertoken".to_string()),
+                msg: to_binary(&HandleMsg::Transfer {
+                    recipient: HumanAddr("addr1111".to_string()),
+                    amount: Uint128::from(4u128),
+                    tx_key: None,
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+        assert_eq!(
+            query_received_tokens(&deps, "addr1111"),
+            vec![ReceivedToken {
+                token: HumanAddr("othertoken".to_string()),
+                amount: Uint128::from(11u128),
+            }]
+        );
+
+        // withdraws the rest by default
+        let withdraw_msg = HandleMsg::WithdrawReceived {
+            token: HumanAddr("othertoken".to_string()),
+            amount: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 450, 550);
+        let res = handle(&mut deps, env, withdraw_msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "withdraw_received"),
+                log("token", "othertoken"),
+                log("recipient", "addr1111"),
+                log("amount", "11"),
+            ]
+        );
+        assert_eq!(query_received_tokens(&deps, "addr1111"), vec![]);
+    }
+
+    #[test]
+    fn fails_to_withdraw_more_than_received() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(10u128),
+            msg: None,
+        });
+        let env = mock_env_height(&HumanAddr("othertoken".to_string()), 450, 550);
+        handle(&mut deps, env, receive_msg).unwrap();
+
+        let withdraw_msg = HandleMsg::WithdrawReceived {
+            token: HumanAddr("othertoken".to_string()),
+            amount: Some(Uint128::from(11u128)),
+        };
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 450, 550);
+        match handle(&mut deps, env, withdraw_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Insufficient received tokens: held=10, requested=11")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+
+        // tokens are held per sender
+        let withdraw_msg = HandleMsg::WithdrawReceived {
+            token: HumanAddr("othertoken".to_string()),
+            amount: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, withdraw_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Nothing to withdraw"),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn send_round_trip_through_receiving_contract() {
+        // "token" is the contract under test, "vault" another instance receiving its tokens
+        let mut token = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut token, env, make_init_msg()).unwrap();
+        let mut vault = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut vault, env, make_init_msg()).unwrap();
+
+        let send_msg = HandleMsg::Send {
+            contract: HumanAddr("vault".to_string()),
+            amount: Uint128::from(6u128),
+            msg: Some(Binary::from(b"stake".to_vec())),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let res = handle(&mut token, env, send_msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "send"),
+                log("sender", "addr0000"),
+                log("contract", "vault"),
+                log("amount", "6"),
+            ]
+        );
+        assert_eq!(
+            get_balance(
+                &token.api,
+                &token.storage,
+                &HumanAddr("addr0000".to_string())
+            ),
+            5
+        );
+        assert_eq!(
+            get_balance(&token.api, &token.storage, &HumanAddr("vault".to_string())),
+            6
+        );
+
+        let receive_msg = match &res.messages[..] {
+            [CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                send,
+            })] => {
+                assert_eq!(contract_addr, &HumanAddr("vault".to_string()));
+                assert!(send.is_empty());
+                from_binary::<HandleMsg>(msg).unwrap()
+            }
+            messages => panic!("Unexpected messages: {:?}", messages),
+        };
+        match &receive_msg {
+            HandleMsg::Receive(Cw20ReceiveMsg {
+                sender,
+                amount,
+                msg,
+            }) => {
+                assert_eq!(sender, &HumanAddr("addr0000".to_string()));
+                assert_eq!(amount, &Uint128::from(6u128));
+                assert_eq!(msg, &Some(Binary::from(b"stake".to_vec())));
+            }
+            _ => panic!("Expected a Receive message"),
+        }
+        let env = mock_env_height(&HumanAddr("token".to_string()), 450, 550);
+        handle(&mut vault, env, receive_msg).unwrap();
+        assert_eq!(
+            query_received_tokens(&vault, "addr0000"),
+            vec![ReceivedToken {
+                token: HumanAddr("token".to_string()),
+                amount: Uint128::from(6u128),
+            }]
+        );
+
+        // withdrawing from the vault transfers the tokens back
+        let withdraw_msg = HandleMsg::WithdrawReceived {
+            token: HumanAddr("token".to_string()),
+            amount: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let res = handle(&mut vault, env, withdraw_msg).unwrap();
+        let transfer_msg = match &res.messages[..] {
+            [CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })] => {
+                from_binary::<HandleMsg>(msg).unwrap()
+            }
+            messages => panic!("Unexpected messages: {:?}", messages),
+        };
+        let env = mock_env_height(&HumanAddr("vault".to_string()), 450, 550);
+        handle(&mut token, env, transfer_msg).unwrap();
+        assert_eq!(
+            get_balance(
+                &token.api,
+                &token.storage,
+                &HumanAddr("addr0000".to_string())
+            ),
+            11
+        );
+        assert_eq!(
+            get_balance(&token.api, &token.storage, &HumanAddr("vault".to_string())),
+            0
+        );
+    }
+
+    #[test]
+    fn replayed_send_is_noop() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let send_msg = || HandleMsg::Send {
+            contract: HumanAddr("vault".to_string()),
+            amount: Uint128::from(4u128),
+            msg: None,
+            tx_key: Some("order-1".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let res = handle(&mut deps, env.clone(), send_msg()).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        let res = handle(&mut deps, env.clone(), send_msg()).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "send"),
+                log("sender", "addr0000"),
+                log("contract", "vault"),
+                log("idempotent_replay", "true"),
+            ]
+        );
+
+        // sends share their keys with transfers
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr2323".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: Some("order-1".to_string()),
+        };
+        let res = handle(&mut deps, env, transfer_msg).unwrap();
+        assert_eq!(res.log[3], log("idempotent_replay", "true"));
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            7
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("vault".to_string())),
+            4
+        );
+    }
+
+    #[test]
+    fn fails_to_send_more_than_balance() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let send_msg = HandleMsg::Send {
+            contract: HumanAddr("vault".to_string()),
+            amount: Uint128::from(12u128),
+            msg: None,
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        assert!(handle(&mut deps, env, send_msg).is_err());
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            11
+        );
+    }
+}
+
+mod bootstrap {
+    use super::*;
+    use crate::msg::BootstrapResponse;
+    use cosmwasm_std::{coin, coins, from_binary, BankMsg, CosmosMsg, HandleResponse, Querier};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: Some(coin(5, "ucosm")),
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn transfer<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        recipient: &str,
+    ) -> HandleResponse {
+        transfer_amount(deps, recipient, 1)
+    }
+
+    fn transfer_amount<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        recipient: &str,
+        amount: u128,
+    ) -> HandleResponse {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr(recipient.to_string()),
+            amount: Uint128::from(amount),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(deps, env, transfer_msg).unwrap()
+    }
+
+    #[test]
+    fn first_time_recipients_get_grant() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let env = mock_env(HumanAddr("creator".to_string()), &coins(12, "ucosm"));
+        let res = handle(&mut deps, env, HandleMsg::FundBootstrap {}).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "fund_bootstrap"),
+                log("amount", "12"),
+                log("pool", "12"),
+            ]
+        );
+
+        let res = transfer(&mut deps, "addr1111");
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr(MOCK_CONTRACT_ADDR.to_string()),
+                to_address: HumanAddr("addr1111".to_string()),
+                amount: coins(5, "ucosm"),
+            })]
+        );
+        // only the first time
+        assert_eq!(transfer(&mut deps, "addr1111").messages.len(), 0);
+        assert_eq!(transfer(&mut deps, "addr2222").messages.len(), 1);
+        // the pool can't cover another grant
+        assert_eq!(transfer(&mut deps, "addr3333").messages.len(), 0);
+
+        let res: BootstrapResponse =
+            from_binary(&query(&deps, QueryMsg::Bootstrap {}).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            BootstrapResponse {
+                grant: Some(coin(5, "ucosm")),
+                pool: Uint128::from(2u128),
+                min_transfer: Uint128::zero(),
+            }
+        );
+    }
+
+    #[test]
+    fn small_transfers_do_not_get_grant() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            bootstrap_min_transfer: Some(Uint128::from(10u128)),
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+        let env = mock_env(HumanAddr("creator".to_string()), &coins(12, "ucosm"));
+        handle(&mut deps, env, HandleMsg::FundBootstrap {}).unwrap();
+
+        // sending nothing to a fresh address doesn't pay the grant
+        assert_eq!(transfer_amount(&mut deps, "addr1111", 0).messages.len(), 0);
+        assert_eq!(transfer_amount(&mut deps, "addr2222", 9).messages.len(), 0);
+        assert_eq!(transfer_amount(&mut deps, "addr3333", 10).messages.len(), 1);
+
+        let res: BootstrapResponse =
+            from_binary(&query(&deps, QueryMsg::Bootstrap {}).unwrap()).unwrap();
+        assert_eq!(res.pool, Uint128::from(7u128));
+        assert_eq!(res.min_transfer, Uint128::from(10u128));
+    }
+
+    #[test]
+    fn fails_to_fund_bootstrap() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let env = mock_env(HumanAddr("addr0000".to_string()), &coins(12, "ucosm"));
+        match handle(&mut deps, env, HandleMsg::FundBootstrap {}) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let env = mock_env(HumanAddr("creator".to_string()), &coins(12, "uatom"));
+        match handle(&mut deps, env, HandleMsg::FundBootstrap {}) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Must send ucosm to fund the bootstrap pool")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+
+        // without a pool, transfers go through without grants
+        assert_eq!(transfer(&mut deps, "addr1111").messages.len(), 0);
+    }
+
+    #[test]
+    fn fails_to_fund_when_disabled() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            bootstrap_grant: None,
+            ..make_init_msg()
+        };
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let env = mock_env(HumanAddr("creator".to_string()), &coins(12, "ucosm"));
+        match handle(&mut deps, env, HandleMsg::FundBootstrap {}) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Bootstrap grants are disabled")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
+
+mod outflow_cap {
+    use super::*;
+    use crate::msg::OutflowHeadroomResponse;
+    use cosmwasm_std::{from_binary, HandleResponse, Querier, StdResult};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: Some(Uint128::from(30u128)),
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn transfer<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        amount: u128,
+        time: u64,
+    ) -> StdResult<HandleResponse> {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(amount),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, time);
+        handle(deps, env, transfer_msg)
+    }
+
+    fn headroom<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> OutflowHeadroomResponse {
+        let query_msg = QueryMsg::OutflowHeadroom {
+            address: HumanAddr("addr0000".to_string()),
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn transfers_are_capped_per_day() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        transfer(&mut deps, 20, 86_400 + 10).unwrap();
+        assert_eq!(
+            headroom(&deps),
+            OutflowHeadroomResponse {
+                cap: Some(Uint128::from(30u128)),
+                window_start: 86_400,
+                sent: Uint128::from(20u128),
+                remaining: Some(Uint128::from(10u128)),
+            }
+        );
+
+        match transfer(&mut deps, 11, 86_400 + 20) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Daily outflow cap exceeded: remaining=10, required=11")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+        transfer(&mut deps, 10, 86_400 + 30).unwrap();
+
+        // the next day starts with a fresh allowance
+        transfer(&mut deps, 30, 2 * 86_400).unwrap();
+        let response = headroom(&deps);
+        assert_eq!(response.window_start, 2 * 86_400);
+        assert_eq!(response.remaining, Some(Uint128::zero()));
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            40
+        );
+    }
+
+    #[test]
+    fn admin_can_change_cap() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let set_msg = HandleMsg::SetOutflowCap {
+            cap: Uint128::from(50u128),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, set_msg) {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("expected unauthorized"),
+        }
+
+        let set_msg = HandleMsg::SetOutflowCap {
+            cap: Uint128::from(50u128),
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        handle(&mut deps, env, set_msg).unwrap();
+        transfer(&mut deps, 50, 600).unwrap();
+        assert_eq!(headroom(&deps).remaining, Some(Uint128::zero()));
+    }
+
+    #[test]
+    fn uncapped_by_default() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let init_msg = InitMsg {
+            daily_outflow_cap: None,
+            ..make_init_msg()
+        };
+        init(&mut deps, env1, init_msg).unwrap();
+
+        transfer(&mut deps, 100, 600).unwrap();
+        let response = headroom(&deps);
+        assert_eq!(response.cap, None);
+        assert_eq!(response.remaining, None);
+
+        let set_msg = HandleMsg::SetOutflowCap {
+            cap: Uint128::from(50u128),
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        match handle(&mut deps, env, set_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Outflows are not capped"),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
+
+mod pools {
+    use super::*;
+    use crate::msg::PoolsResponse;
+    use cosmwasm_std::{from_binary, Querier, StdResult};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("pool".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: Some(Uint128::from(30u128)),
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn set_pool<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        sender: &str,
+        registered: bool,
+    ) -> StdResult<()> {
+        let pool = HumanAddr("pool".to_string());
+        let msg = if registered {
+            HandleMsg::RegisterPool { pool }
+        } else {
+            HandleMsg::DeregisterPool { pool }
+        };
+        let env = mock_env_height(&HumanAddr(sender.to_string()), 450, 550);
+        handle(deps, env, msg).map(|_| ())
+    }
+
+    fn pool_transfer<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        amount: u128,
+    ) -> StdResult<()> {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(amount),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("pool".to_string()), 450, 550);
+        handle(deps, env, transfer_msg).map(|_| ())
+    }
+
+    fn query_pools<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> Vec<HumanAddr> {
+        let res: PoolsResponse = from_binary(&query(deps, QueryMsg::Pools {}).unwrap()).unwrap();
+        res.pools
+    }
+
+    #[test]
+    fn registered_pools_are_not_capped() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+        assert_eq!(query_pools(&deps), vec![]);
+
+        match set_pool(&mut deps, "pool", true) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        set_pool(&mut deps, "creator", true).unwrap();
+        match set_pool(&mut deps, "creator", true) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Pool already registered"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert_eq!(query_pools(&deps), vec![HumanAddr("pool".to_string())]);
+
+        pool_transfer(&mut deps, 40).unwrap();
+
+        set_pool(&mut deps, "creator", false).unwrap();
+        assert_eq!(query_pools(&deps), vec![]);
+        assert!(pool_transfer(&mut deps, 40).is_err());
+        match set_pool(&mut deps, "creator", false) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Pool not registered"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
+
+mod burn_addresses {
+    use super::*;
+    use crate::msg::BurnAddressesResponse;
+    use cosmwasm_std::{from_binary, Querier};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn query_burn_addresses<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+    ) -> BurnAddressesResponse {
+        from_binary(&query(deps, QueryMsg::BurnAddresses {}).unwrap()).unwrap()
+    }
+
+    fn transfer_to_dead<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, amount: u128) {
+        let msg = HandleMsg::Transfer {
+            recipient: HumanAddr("dead".to_string()),
+            amount: Uint128::from(amount),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(deps, env, msg).unwrap();
+    }
+
+    #[test]
+    fn transfers_to_burn_addresses_burn_tokens() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let add_msg = || HandleMsg::AddBurnAddress {
+            address: HumanAddr("dead".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, add_msg()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let res = handle(&mut deps, env, add_msg()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![log("action", "add_burn_address"), log("address", "dead")]
+        );
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        assert!(handle(&mut deps, env, add_msg()).is_err());
+
+        transfer_to_dead(&mut deps, 30);
+        transfer_to_dead(&mut deps, 10);
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            60
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("dead".to_string())),
+            0
+        );
+        assert_eq!(get_total_supply(&deps.storage), 60);
+
+        // sends are refused, as the burn address would be called without receiving anything
+        let msg = HandleMsg::Send {
+            contract: HumanAddr("dead".to_string()),
+            amount: Uint128::from(5u128),
+            msg: None,
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Cannot send to a burn address, use Transfer or Burn")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert_eq!(
+            query_burn_addresses(&deps),
+            BurnAddressesResponse {
+                addresses: vec![HumanAddr("dead".to_string())],
+                total_redirected: Uint128::from(40u128),
+            }
+        );
+    }
+
+    #[test]
+    fn removed_burn_addresses_receive_transfers() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let msg = HandleMsg::AddBurnAddress {
+            address: HumanAddr("dead".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        handle(&mut deps, env, msg).unwrap();
+        transfer_to_dead(&mut deps, 30);
+
+        let msg = HandleMsg::RemoveBurnAddress {
+            address: HumanAddr("dead".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        handle(&mut deps, env, msg).unwrap();
+        transfer_to_dead(&mut deps, 10);
+
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("dead".to_string())),
+            10
+        );
+        assert_eq!(get_total_supply(&deps.storage), 70);
+        let burn_addresses = query_burn_addresses(&deps);
+        assert!(burn_addresses.addresses.is_empty());
+        assert_eq!(burn_addresses.total_redirected, Uint128::from(30u128));
+    }
+}
+
+mod balance_hooks {
+    use super::*;
+    use crate::msg::{BalanceHookMsg, BalanceHookResponse};
+    use cosmwasm_std::{from_binary, to_binary, CosmosMsg, HandleResponse, Querier, WasmMsg};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn transfer<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        sender: &str,
+        recipient: &str,
+        amount: u128,
+    ) -> HandleResponse {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr(recipient.to_string()),
+            amount: Uint128::from(amount),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr(sender.to_string()), 450, 550);
+        handle(deps, env, transfer_msg).unwrap()
+    }
+
+    fn hook_msg(address: &str, balance: u128, above: bool) -> CosmosMsg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: HumanAddr("watcher".to_string()),
+            msg: to_binary(&BalanceHookMsg::BalanceThresholdCrossed {
+                address: HumanAddr(address.to_string()),
+                balance: Uint128::from(balance),
+                threshold: Uint128::from(50u128),
+                above,
+            })
+            .unwrap(),
+            send: vec![],
+        })
+    }
+
+    #[test]
+    fn hook_notified_when_threshold_crossed() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        for owner in &["addr0000", "addr1111"] {
+            let set_msg = HandleMsg::SetBalanceHook {
+                contract: HumanAddr("watcher".to_string()),
+                threshold: Uint128::from(50u128),
+            };
+            let env = mock_env_height(&HumanAddr(owner.to_string()), 450, 550);
+            handle(&mut deps, env, set_msg).unwrap();
+        }
+        let query_msg = QueryMsg::BalanceHook {
+            address: HumanAddr("addr0000".to_string()),
+        };
+        let hook: BalanceHookResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            hook,
+            BalanceHookResponse {
+                contract: HumanAddr("watcher".to_string()),
+                threshold: Uint128::from(50u128),
+            }
+        );
+
+        // neither balance crosses 50
+        let res = transfer(&mut deps, "addr0000", "addr1111", 40);
+        assert_eq!(res.messages, vec![]);
+
+        // 60 -> 40 and 40 -> 60
+        let res = transfer(&mut deps, "addr0000", "addr1111", 20);
+        assert_eq!(
+            res.messages,
+            vec![
+                hook_msg("addr0000", 40, false),
+                hook_msg("addr1111", 60, true),
+            ]
+        );
+
+        let remove_msg = HandleMsg::RemoveBalanceHook {};
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 450, 550);
+        handle(&mut deps, env, remove_msg).unwrap();
+        let res = transfer(&mut deps, "addr1111", "addr0000", 20);
+        assert_eq!(res.messages, vec![hook_msg("addr0000", 60, true)]);
+
+        let remove_msg = HandleMsg::RemoveBalanceHook {};
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 450, 550);
+        match handle(&mut deps, env, remove_msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "No balance hook registered"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
+
+mod snapshots {
+    use super::*;
+    use crate::msg::{BalanceAtSnapshotResponse, CheckpointResponse};
+    use cosmwasm_std::{from_binary, Querier, StdResult};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: Some(vec![HumanAddr("governance".to_string())]),
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn transfer<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        amount: u128,
+        height: u64,
+    ) {
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(amount),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), height, 550);
+        handle(deps, env, transfer_msg).unwrap();
+    }
+
+    fn balance_at_snapshot<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        id: u64,
+        address: &str,
+    ) -> StdResult<BalanceAtSnapshotResponse> {
+        let query_msg = QueryMsg::BalanceAtSnapshot {
+            id,
+            address: HumanAddr(address.to_string()),
+        };
+        query(deps, query_msg).and_then(|data| from_binary(&data))
+    }
+
+    #[test]
+    fn snapshot_freezes_balances_of_previous_block() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        transfer(&mut deps, 10, 460);
+        transfer(&mut deps, 20, 461);
+
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 461, 550);
+        match handle(&mut deps, env, HandleMsg::Checkpoint {}) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(&HumanAddr("governance".to_string()), 461, 550);
+        let res = handle(&mut deps, env, HandleMsg::Checkpoint {}).unwrap();
+        let checkpoint: CheckpointResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(checkpoint.id, 1);
+        assert_eq!(checkpoint.height, 460);
+        assert_eq!(checkpoint.event.action, "checkpoint");
+        assert_eq!(checkpoint.event.height, 461);
+
+        transfer(&mut deps, 30, 470);
+        assert_eq!(
+            balance_at_snapshot(&deps, 1, "addr0000").unwrap(),
+            BalanceAtSnapshotResponse {
+                id: 1,
+                height: 460,
+                balance: Uint128::from(90u128),
+            }
+        );
+        assert_eq!(
+            balance_at_snapshot(&deps, 1, "addr1111").unwrap().balance,
+            Uint128::from(10u128)
+        );
+
+        let env = mock_env_height(&HumanAddr("governance".to_string()), 480, 550);
+        let res = handle(&mut deps, env, HandleMsg::Checkpoint {}).unwrap();
+        let checkpoint: CheckpointResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(checkpoint.id, 2);
+        assert_eq!(
+            balance_at_snapshot(&deps, 2, "addr1111").unwrap().balance,
+            Uint128::from(60u128)
+        );
+
+        match balance_at_snapshot(&deps, 3, "addr1111") {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Snapshot does not exist"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
+
+mod session_keys {
+    use super::*;
+    use crate::msg::SessionKeyResponse;
+    use cosmwasm_std::{from_binary, HandleResponse, Querier, StdResult};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn register<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) -> HandleResponse {
+        let register_msg = HandleMsg::RegisterSessionKey {
+            key: HumanAddr("session".to_string()),
+            spend_limit: Uint128::from(10u128),
+            expires_at_height: 500,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(deps, env, register_msg).unwrap()
+    }
+
+    fn transfer_from<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        amount: u128,
+        height: u64,
+    ) -> StdResult<HandleResponse> {
+        let transfer_from_msg = HandleMsg::TransferFrom {
+            owner: HumanAddr("addr0000".to_string()),
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(amount),
+        };
+        let env = mock_env_height(&HumanAddr("session".to_string()), height, 550);
+        handle(deps, env, transfer_from_msg)
+    }
+
+    fn query_session_key<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+    ) -> StdResult<SessionKeyResponse> {
+        let query_msg = QueryMsg::SessionKey {
+            owner: HumanAddr("addr0000".to_string()),
+            key: HumanAddr("session".to_string()),
+        };
+        query(deps, query_msg).and_then(|data| from_binary(&data))
+    }
+
+    #[test]
+    fn session_key_spends_up_to_limit() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let res = register(&mut deps);
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "register_session_key"),
+                log("owner", "addr0000"),
+                log("key", "session"),
+                log("spend_limit", "10"),
+                log("expires_at_height", "500"),
+            ]
+        );
+
+        transfer_from(&mut deps, 6, 460).unwrap();
+        assert_eq!(
+            query_session_key(&deps).unwrap(),
+            SessionKeyResponse {
+                remaining: Uint128::from(4u128),
+                expires_at_height: 500,
+            }
+        );
+        match transfer_from(&mut deps, 5, 460) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(
+                    msg,
+                    "Session key spend limit exceeded: remaining=4, required=5"
+                )
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            6
+        );
+    }
+
+    #[test]
+    fn expired_or_revoked_key_needs_allowance() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+        register(&mut deps);
+
+        match transfer_from(&mut deps, 1, 500) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Insufficient allowance: allowance=0, required=1")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+
+        let revoke_msg = || HandleMsg::RevokeSessionKey {
+            key: HumanAddr("session".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(&mut deps, env.clone(), revoke_msg()).unwrap();
+        assert!(transfer_from(&mut deps, 1, 460).is_err());
+        match query_session_key(&deps) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Session key not registered"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        match handle(&mut deps, env, revoke_msg()) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Session key not registered"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn register_fails_for_past_expiration() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env1, make_init_msg()).unwrap();
+
+        let register_msg = HandleMsg::RegisterSessionKey {
+            key: HumanAddr("session".to_string()),
+            spend_limit: Uint128::from(10u128),
+            expires_at_height: 450,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        match handle(&mut deps, env, register_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Session key must expire after the current height")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
+
+mod burn {
+    use super::*;
+    use crate::msg::{BurnResponse, Event};
+    use cosmwasm_std::from_binary;
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![
+                InitialBalance {
+                    address: HumanAddr("addr0000".to_string()),
+                    amount: Uint128::from(11u128),
+                },
+                InitialBalance {
+                    address: HumanAddr("addr1111".to_string()),
+                    amount: Uint128::from(22u128),
+                },
+            ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    #[test]
+    fn can_burn_from_existing_account() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let res = init(&mut deps, env1, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // Initial state
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            11
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            22
+        );
+        assert_eq!(get_total_supply(&deps.storage), 33);
+
+        // Burn
+        let burn_msg = HandleMsg::Burn {
+            amount: Uint128::from(1u128),
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let burn_result = handle(&mut deps, env2, burn_msg).unwrap();
+        assert_eq!(burn_result.messages.len(), 0);
+        assert_eq!(
+            burn_result.log,
+            vec![
+                log("action", "burn"),
+                log("account", "addr0000"),
+                log("amount", "1")
+            ]
+        );
+        let burn_data: BurnResponse = from_binary(&burn_result.data.unwrap()).unwrap();
+        assert_eq!(
+            burn_data,
+            BurnResponse {
+                total_supply_before: Uint128::from(33u128),
+                total_supply_after: Uint128::from(32u128),
+                event: Event {
+                    action: "burn".to_string(),
+                    actor: HumanAddr("addr0000".to_string()),
+                    counterparty: None,
+                    amount: Some(Uint128::from(1u128)),
+                    height: 450,
+                },
+            }
+        );
+
+        // New state
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            10
+        ); // -1
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            22
+        );
+        assert_eq!(get_total_supply(&deps.storage), 32);
+    }
+
+    #[test]
+    fn can_burn_zero_amount() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let res = init(&mut deps, env1, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // Initial state
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            11
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            22
+        );
+        assert_eq!(get_total_supply(&deps.storage), 33);
+
+        // Burn
+        let burn_msg = HandleMsg::Burn {
+            amount: Uint128::from(0u128),
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let burn_result = handle(&mut deps, env2, burn_msg).unwrap();
+        assert_eq!(burn_result.messages.len(), 0);
+        assert_eq!(
+            burn_result.log,
+            vec![
+                log("action", "burn"),
+                log("account", "addr0000"),
+                log("amount", "0"),
+            ]
+        );
+
+        // New state (unchanged)
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            11
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            22
+        );
+        assert_eq!(get_total_supply(&deps.storage), 33);
+    }
+
+    #[test]
+    fn fails_on_insufficient_balance() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        let res = init(&mut deps, env1, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // Initial state
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            11
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            22
+        );
+        assert_eq!(get_total_supply(&deps.storage), 33);
+
+        // Burn
+        let burn_msg = HandleMsg::Burn {
+            amount: Uint128::from(12u128),
+        };
+        let env2 = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let burn_result = handle(&mut deps, env2, burn_msg);
+        match burn_result {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "insufficient funds to burn: balance=11, required=12")
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+
+        // New state (unchanged)
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr0000".to_string())),
+            11
+        );
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
+            22
+        );
+        assert_eq!(get_total_supply(&deps.storage), 33);
+    }
+}
+
+mod burner {
+    use super::*;
+    use crate::msg::{BurnResponse, BurnerResponse, BurnerRole};
+    use cosmwasm_std::{from_binary, StdError};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![
+                InitialBalance {
+                    address: HumanAddr("reserve".to_string()),
+                    amount: Uint128::from(100u128),
+                },
+                InitialBalance {
+                    address: HumanAddr("addr1111".to_string()),
+                    amount: Uint128::from(22u128),
+                },
+            ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: Some(BurnerRole {
+                burner: HumanAddr("burner".to_string()),
+                reserve: HumanAddr("reserve".to_string()),
+            }),
+            hashed_references: None,
+        }
+    }
+
+    #[test]
+    fn burner_can_burn_from_reserve() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let burn_msg = HandleMsg::BurnFromReserve {
+            amount: Uint128::from(40u128),
+        };
+        let env = mock_env_height(&HumanAddr("burner".to_string()), 451, 551);
+        let res = handle(&mut deps, env, burn_msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "burn_from_reserve"),
+                log("burner", "burner"),
+                log("reserve", "reserve"),
+                log("amount", "40"),
+            ]
+        );
+        let data: BurnResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(data.total_supply_before, Uint128::from(122u128));
+        assert_eq!(data.total_supply_after, Uint128::from(82u128));
+
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("reserve".to_string())),
+            60
         );
         assert_eq!(
             get_balance(&deps.api, &deps.storage, &HumanAddr("addr1111".to_string())),
             22
         );
-        assert_eq!(get_total_supply(&deps.storage), 33);
+        assert_eq!(get_total_supply(&deps.storage), 82);
+    }
+
+    #[test]
+    fn only_burner_can_burn_from_reserve() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        for sender in &["reserve", "addr1111", "creator"] {
+            let burn_msg = HandleMsg::BurnFromReserve {
+                amount: Uint128::from(1u128),
+            };
+            let env = mock_env_height(&HumanAddr(sender.to_string()), 451, 551);
+            match handle(&mut deps, env, burn_msg) {
+                Err(StdError::Unauthorized { .. }) => {}
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+        assert_eq!(get_total_supply(&deps.storage), 122);
+    }
+
+    #[test]
+    fn burner_cannot_exceed_reserve_balance() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let burn_msg = HandleMsg::BurnFromReserve {
+            amount: Uint128::from(101u128),
+        };
+        let env = mock_env_height(&HumanAddr("burner".to_string()), 451, 551);
+        assert!(handle(&mut deps, env, burn_msg).is_err());
+        assert_eq!(
+            get_balance(&deps.api, &deps.storage, &HumanAddr("reserve".to_string())),
+            100
+        );
+    }
+
+    #[test]
+    fn burn_from_reserve_requires_a_burner() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let mut init_msg = make_init_msg();
+        init_msg.burner = None;
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, init_msg).unwrap();
+
+        let burn_msg = HandleMsg::BurnFromReserve {
+            amount: Uint128::from(1u128),
+        };
+        let env = mock_env_height(&HumanAddr("burner".to_string()), 451, 551);
+        assert!(handle(&mut deps, env, burn_msg).is_err());
+
+        let res = query(&deps, QueryMsg::Burner {}).unwrap();
+        let burner: BurnerResponse = from_binary(&res).unwrap();
+        assert_eq!(burner.reserve, None);
+    }
+
+    #[test]
+    fn can_query_burner() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let res = query(&deps, QueryMsg::Burner {}).unwrap();
+        let burner: BurnerResponse = from_binary(&res).unwrap();
+        assert_eq!(burner.reserve, Some(HumanAddr("reserve".to_string())));
+    }
+}
+
+mod account_metadata {
+    use super::*;
+    use crate::msg::AccountMetadataResponse;
+    use cosmwasm_std::{from_binary, Querier};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(11u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn query_metadata<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        address: &str,
+    ) -> AccountMetadataResponse {
+        let query_msg = QueryMsg::AccountMetadata {
+            address: HumanAddr(address.to_string()),
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn can_set_and_clear_metadata() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let metadata = query_metadata(&deps, "addr0000");
+        assert_eq!(metadata.name, None);
+        assert_eq!(metadata.avatar_hash, None);
+
+        let msg = HandleMsg::SetAccountMetadata {
+            name: Some("Alice".to_string()),
+            avatar_hash: Some("ab".repeat(32)),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "set_account_metadata"),
+                log("account", "addr0000"),
+            ]
+        );
+        let metadata = query_metadata(&deps, "addr0000");
+        assert_eq!(metadata.name, Some("Alice".to_string()));
+        assert_eq!(metadata.avatar_hash, Some("ab".repeat(32)));
+
+        let msg = HandleMsg::SetAccountMetadata {
+            name: None,
+            avatar_hash: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        handle(&mut deps, env, msg).unwrap();
+        let metadata = query_metadata(&deps, "addr0000");
+        assert_eq!(metadata.name, None);
+        assert_eq!(metadata.avatar_hash, None);
+    }
+
+    #[test]
+    fn rejects_oversized_metadata() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let msg = HandleMsg::SetAccountMetadata {
+            name: Some("a".repeat(33)),
+            avatar_hash: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        assert!(handle(&mut deps, env, msg).is_err());
+
+        let msg = HandleMsg::SetAccountMetadata {
+            name: None,
+            avatar_hash: Some("a".repeat(65)),
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        assert!(handle(&mut deps, env, msg).is_err());
+        assert_eq!(query_metadata(&deps, "addr0000").name, None);
+    }
+
+    #[test]
+    fn transfer_logs_include_display_names() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let transfer_msg = || HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let res = handle(&mut deps, env, transfer_msg()).unwrap();
+        assert_eq!(res.log.len(), 3);
+
+        let msg = HandleMsg::SetAccountMetadata {
+            name: Some("Bob".to_string()),
+            avatar_hash: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 450, 550);
+        handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 450, 550);
+        let res = handle(&mut deps, env, transfer_msg()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "transfer"),
+                log("sender", "addr0000"),
+                log("recipient", "addr1111"),
+                log("recipient_name", "Bob"),
+            ]
+        );
+    }
+}
+
+mod roles {
+    use super::*;
+    use crate::msg::{HasRoleResponse, Role, RoleMembersResponse};
+    use cosmwasm_std::{from_binary, Binary, HandleResponse, Querier, StdError, StdResult};
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    fn has_role<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        role: Role,
+        address: &str,
+    ) -> bool {
+        let query_msg = QueryMsg::HasRole {
+            role,
+            address: HumanAddr(address.to_string()),
+        };
+        let res: HasRoleResponse = from_binary(&query(deps, query_msg).unwrap()).unwrap();
+        res.has_role
+    }
+
+    fn role_members<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        role: Role,
+        start_after: Option<Binary>,
+        limit: Option<u32>,
+    ) -> RoleMembersResponse {
+        let query_msg = QueryMsg::RoleMembers {
+            role,
+            start_after,
+            limit,
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    fn grant_role<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        sender: &str,
+        role: Role,
+        address: &str,
+    ) -> StdResult<HandleResponse> {
+        let msg = HandleMsg::GrantRole {
+            role,
+            address: HumanAddr(address.to_string()),
+        };
+        let env = mock_env_height(&HumanAddr(sender.to_string()), 450, 550);
+        handle(deps, env, msg)
+    }
+
+    #[test]
+    fn creator_is_admin_and_can_grant_roles() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+        assert!(has_role(&deps, Role::Admin, "creator"));
+        assert!(!has_role(&deps, Role::Burner, "creator"));
+
+        let res = grant_role(&mut deps, "creator", Role::Admin, "addr0000").unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "grant_role"),
+                log("role", "admin"),
+                log("address", "addr0000"),
+            ]
+        );
+        // the new admin can grant roles too
+        grant_role(&mut deps, "addr0000", Role::Governance, "addr1111").unwrap();
+        assert!(has_role(&deps, Role::Governance, "addr1111"));
+
+        match grant_role(&mut deps, "creator", Role::Admin, "addr0000") {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address already has the role")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert_eq!(
+            role_members(&deps, Role::Admin, None, None).members,
+            vec![
+                HumanAddr("addr0000".to_string()),
+                HumanAddr("creator".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_admins_manage_roles() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        match grant_role(&mut deps, "addr0000", Role::Admin, "addr0000") {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        let msg = HandleMsg::RevokeRole {
+            role: Role::Burner,
+            address: HumanAddr("addr0000".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address does not have the role")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn revoked_and_renounced_roles_lose_access() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        grant_role(&mut deps, "creator", Role::Governance, "governance").unwrap();
+        let env = mock_env_height(&HumanAddr("governance".to_string()), 451, 551);
+        handle(&mut deps, env, HandleMsg::Checkpoint {}).unwrap();
+
+        let msg = HandleMsg::RevokeRole {
+            role: Role::Governance,
+            address: HumanAddr("governance".to_string()),
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 451, 551);
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(res.log[0], log("action", "revoke_role"));
+        let env = mock_env_height(&HumanAddr("governance".to_string()), 452, 552);
+        match handle(&mut deps, env, HandleMsg::Checkpoint {}) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let msg = HandleMsg::RenounceRole { role: Role::Admin };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 452, 552);
+        let res = handle(&mut deps, env.clone(), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "renounce_role"),
+                log("role", "admin"),
+                log("address", "creator"),
+            ]
+        );
+        assert!(!has_role(&deps, Role::Admin, "creator"));
+        let msg = HandleMsg::RenounceRole { role: Role::Admin };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+        match grant_role(&mut deps, "creator", Role::Admin, "creator") {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn role_members_are_paginated() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = InitMsg {
+            governance_contracts: Some(vec![
+                HumanAddr("gov1".to_string()),
+                HumanAddr("gov2".to_string()),
+                HumanAddr("gov3".to_string()),
+            ]),
+            ..make_init_msg()
+        };
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, init_msg).unwrap();
+
+        let page = role_members(&deps, Role::Governance, None, Some(2));
+        assert_eq!(
+            page.members,
+            vec![HumanAddr("gov1".to_string()), HumanAddr("gov2".to_string()),]
+        );
+        let page = role_members(&deps, Role::Governance, page.next_cursor, Some(2));
+        assert_eq!(page.members, vec![HumanAddr("gov3".to_string())]);
+        assert_eq!(page.next_cursor, None);
+
+        // a cursor only works for the role it came from
+        let page = role_members(&deps, Role::Governance, None, Some(1));
+        let query_msg = QueryMsg::RoleMembers {
+            role: Role::Admin,
+            start_after: page.next_cursor,
+            limit: None,
+        };
+        assert!(query(&deps, query_msg).is_err());
+    }
+}
+
+mod events {
+    use super::*;
+    use crate::msg::Event;
+    use cosmwasm_std::from_binary;
+
+    fn make_init_msg() -> InitMsg {
+        InitMsg {
+            name: "Cash Token".to_string(),
+            symbol: "CASH".to_string(),
+            decimals: 9,
+            initial_balances: vec![InitialBalance {
+                address: HumanAddr("addr0000".to_string()),
+                amount: Uint128::from(11u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        }
+    }
+
+    #[test]
+    fn handlers_return_event_data() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let env = mock_env_height(&HumanAddr("creator".to_string()), 450, 550);
+        init(&mut deps, env, make_init_msg()).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: HumanAddr("addr1111".to_string()),
+            amount: Uint128::from(3u128),
+            tx_key: None,
+        };
+        let env = mock_env_height(&HumanAddr("addr0000".to_string()), 451, 550);
+        let res = handle(&mut deps, env, transfer_msg).unwrap();
+        let event: Event = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(
+            event,
+            Event {
+                action: "transfer".to_string(),
+                actor: HumanAddr("addr0000".to_string()),
+                counterparty: Some(HumanAddr("addr1111".to_string())),
+                amount: Some(Uint128::from(3u128)),
+                height: 451,
+            }
+        );
+
+        let approve_msg = HandleMsg::Approve {
+            spender: HumanAddr("addr2222".to_string()),
+            amount: Uint128::from(5u128),
+        };
+        let env = mock_env_height(&HumanAddr("addr1111".to_string()), 452, 550);
+        let res = handle(&mut deps, env, approve_msg).unwrap();
+        let event: Event = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(
+            event,
+            Event {
+                action: "approve".to_string(),
+                actor: HumanAddr("addr1111".to_string()),
+                counterparty: Some(HumanAddr("addr2222".to_string())),
+                amount: Some(Uint128::from(5u128)),
+                height: 452,
+            }
+        );
     }
 }
 
 mod query {
     use super::*;
+    use crate::msg::{
+        AccountBalance, AllAccountsResponse, AllAllowancesResponse, BalanceResult,
+        BalancesResponse, HoldersAtResponse, SpenderAllowance,
+    };
+    use cosmwasm_std::{from_binary, Binary};
 
     fn address(index: u8) -> HumanAddr {
         match index {
@@ -1121,6 +4259,15 @@ mod query {
                     amount: Uint128::from(33u128),
                 },
             ],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
         }
     }
 
@@ -1154,6 +4301,39 @@ mod query {
         assert_eq!(query_result.as_slice(), b"{\"balance\":\"0\"}");
     }
 
+    #[test]
+    fn can_query_balances_in_batch() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&address(0), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let query_msg = QueryMsg::Balances {
+            addresses: vec![address(1), HumanAddr("x".to_string()), address(4)],
+        };
+        let res: BalancesResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(res.balances.len(), 3);
+        assert_eq!(
+            res.balances[0],
+            BalanceResult {
+                address: address(1),
+                balance: Some(Uint128::from(11u128)),
+                error: None,
+            }
+        );
+        assert_eq!(res.balances[1].balance, None);
+        assert!(res.balances[1].error.is_some());
+        assert_eq!(res.balances[2].balance, Some(Uint128::zero()));
+
+        let query_msg = QueryMsg::Balances {
+            addresses: vec![address(1); 31],
+        };
+        match query(&deps, query_msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Too many addresses: max 30"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
     #[test]
     fn can_query_allowance_of_existing_addresses() {
         let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
@@ -1233,4 +4413,226 @@ mod query {
         let query_result = query(&deps, query_msg).unwrap();
         assert_eq!(query_result.as_slice(), b"{\"allowance\":\"0\"}");
     }
+
+    #[test]
+    fn can_page_through_all_accounts() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&address(0), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let query_msg = QueryMsg::AllAccounts {
+            start_after: None,
+            limit: Some(2),
+        };
+        let page: AllAccountsResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            page.accounts,
+            vec![
+                AccountBalance {
+                    address: address(1),
+                    balance: Uint128::from(11u128),
+                },
+                AccountBalance {
+                    address: address(2),
+                    balance: Uint128::from(22u128),
+                },
+            ]
+        );
+        assert!(page.next_cursor.is_some());
+
+        let query_msg = QueryMsg::AllAccounts {
+            start_after: page.next_cursor,
+            limit: Some(2),
+        };
+        let page: AllAccountsResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            page.accounts,
+            vec![AccountBalance {
+                address: address(3),
+                balance: Uint128::from(33u128),
+            }]
+        );
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn can_page_through_all_allowances() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&address(0), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        for (spender, amount) in &[(address(2), 5u128), (address(3), 6u128)] {
+            let approve_msg = HandleMsg::Approve {
+                spender: spender.clone(),
+                amount: Uint128::from(*amount),
+            };
+            let env = mock_env_height(&address(1), 450, 550);
+            handle(&mut deps, env, approve_msg).unwrap();
+        }
+
+        let query_msg = QueryMsg::AllAllowances {
+            owner: address(1),
+            start_after: None,
+            limit: Some(1),
+        };
+        let page: AllAllowancesResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            page.allowances,
+            vec![SpenderAllowance {
+                spender: address(2),
+                allowance: Uint128::from(5u128),
+            }]
+        );
+
+        let query_msg = QueryMsg::AllAllowances {
+            owner: address(1),
+            start_after: page.next_cursor,
+            limit: Some(1),
+        };
+        let page: AllAllowancesResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            page.allowances,
+            vec![SpenderAllowance {
+                spender: address(3),
+                allowance: Uint128::from(6u128),
+            }]
+        );
+    }
+
+    #[test]
+    fn can_query_holders_at_height() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&address(0), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        // addr1111 sends everything to addr4321 at height 500
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: address(2),
+            amount: Uint128::from(11u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&address(1), 500, 600);
+        handle(&mut deps, env2, transfer_msg).unwrap();
+        // and gets some back at height 600
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: address(1),
+            amount: Uint128::from(3u128),
+            tx_key: None,
+        };
+        let env3 = mock_env_height(&address(3), 600, 700);
+        handle(&mut deps, env3, transfer_msg).unwrap();
+
+        let holders_at = |height: u64| {
+            let query_msg = QueryMsg::HoldersAt {
+                height,
+                start_after: None,
+                limit: None,
+            };
+            let res: HoldersAtResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+            assert_eq!(res.height, height);
+            assert_eq!(res.next_cursor, None);
+            res.holders
+                .into_iter()
+                .map(|holder| (holder.address, holder.balance.u128()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(holders_at(449), vec![]);
+        assert_eq!(
+            holders_at(499),
+            vec![(address(1), 11), (address(2), 22), (address(3), 33)]
+        );
+        assert_eq!(holders_at(500), vec![(address(2), 33), (address(3), 33)]);
+        assert_eq!(holders_at(599), vec![(address(2), 33), (address(3), 33)]);
+        assert_eq!(
+            holders_at(600),
+            vec![(address(1), 3), (address(2), 33), (address(3), 30)]
+        );
+        assert_eq!(
+            holders_at(u64::MAX),
+            vec![(address(1), 3), (address(2), 33), (address(3), 30)]
+        );
+    }
+
+    #[test]
+    fn can_page_through_holders_at_height() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&address(0), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        let transfer_msg = HandleMsg::Transfer {
+            recipient: address(2),
+            amount: Uint128::from(11u128),
+            tx_key: None,
+        };
+        let env2 = mock_env_height(&address(1), 500, 600);
+        handle(&mut deps, env2, transfer_msg).unwrap();
+
+        // the empty account still counts towards the page
+        let query_msg = QueryMsg::HoldersAt {
+            height: 500,
+            start_after: None,
+            limit: Some(2),
+        };
+        let page: HoldersAtResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            page.holders,
+            vec![AccountBalance {
+                address: address(2),
+                balance: Uint128::from(33u128),
+            }]
+        );
+        assert!(page.next_cursor.is_some());
+
+        let query_msg = QueryMsg::HoldersAt {
+            height: 500,
+            start_after: page.next_cursor,
+            limit: Some(2),
+        };
+        let page: HoldersAtResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(
+            page.holders,
+            vec![AccountBalance {
+                address: address(3),
+                balance: Uint128::from(33u128),
+            }]
+        );
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn fails_for_foreign_cursor() {
+        let mut deps = mock_dependencies(CANONICAL_LENGTH, &[]);
+        let init_msg = make_init_msg();
+        let env1 = mock_env_height(&address(0), 450, 550);
+        init(&mut deps, env1, init_msg).unwrap();
+
+        // a raw address is not a cursor
+        let query_msg = QueryMsg::AllAccounts {
+            start_after: Some(Binary::from(address(1).as_str().as_bytes())),
+            limit: None,
+        };
+        match query(&deps, query_msg) {
+            Ok(_) => panic!("expected error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Invalid pagination cursor"),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+
+        // neither is a cursor from another list
+        let query_msg = QueryMsg::AllAccounts {
+            start_after: None,
+            limit: Some(1),
+        };
+        let page: AllAccountsResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        let query_msg = QueryMsg::AllAllowances {
+            owner: address(1),
+            start_after: page.next_cursor,
+            limit: None,
+        };
+        assert!(query(&deps, query_msg).is_err());
+    }
 }
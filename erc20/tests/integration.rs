@@ -23,10 +23,11 @@ use cosmwasm_vm::testing::{handle, init, mock_env, mock_instance, query};
 use cosmwasm_vm::{Api, Storage};
 
 use cw_erc20::contract::{
-    bytes_to_u128, Constants, KEY_CONSTANTS, KEY_TOTAL_SUPPLY, PREFIX_ALLOWANCES, PREFIX_BALANCES,
+    decode_balance, Constants, KEY_CONSTANTS, KEY_TOTAL_SUPPLY, PREFIX_ALLOWANCES, PREFIX_BALANCES,
     PREFIX_CONFIG,
 };
 use cw_erc20::msg::{HandleMsg, InitMsg, InitialBalance, QueryMsg};
+use cw_math::bytes_to_u128;
 
 static WASM: &[u8] = include_bytes!("../target/wasm32-unknown-unknown/release/cw_erc20.wasm");
 
@@ -67,7 +68,10 @@ fn get_balance<S: Storage, A: Api>(api: &A, storage: &S, address: &HumanAddr) ->
         address_key.as_slice(),
     ]
     .concat();
-    read_u128(storage, &key)
+    match storage.get(&key).0.unwrap() {
+        Some(data) => decode_balance(&data).unwrap(),
+        None => 0,
+    }
 }
 
 fn get_allowance<S: Storage, A: Api>(
@@ -132,6 +136,15 @@ fn init_msg() -> InitMsg {
             },
         ]
         .to_vec(),
+        compliance: None,
+        blocked_recipients: None,
+        restricted_transfers: None,
+        bootstrap_grant: None,
+        bootstrap_min_transfer: None,
+        daily_outflow_cap: None,
+        governance_contracts: None,
+        burner: None,
+        hashed_references: None,
     }
 }
 
@@ -187,6 +200,7 @@ fn transfer_works() {
     let transfer_msg = HandleMsg::Transfer {
         recipient: recipient.clone(),
         amount: Uint128::from(1u128),
+        tx_key: None,
     };
     let env2 = mock_env_height(&sender, 877, 0);
     let transfer_response: HandleResponse = handle(&mut deps, env2, transfer_msg).unwrap();
@@ -0,0 +1,101 @@
+//! Measures what balances cost in storage size and gas through the VM, for the layout the Wasm
+//! was built with. Run it once per layout and compare the output:
+//!
+//!   cargo wasm && cargo test --test storage_benchmark -- --nocapture
+//!   cargo wasm --features json-balances &&
+//!     cargo test --features json-balances --test storage_benchmark -- --nocapture
+
+use cosmwasm_std::{Env, HandleResponse, HumanAddr, InitResponse, Uint128};
+use cosmwasm_storage::to_length_prefixed;
+use cosmwasm_vm::testing::{handle, init, mock_env, mock_instance_with_gas_limit};
+use cosmwasm_vm::{Api, Storage};
+
+use cw_erc20::contract::PREFIX_BALANCES;
+use cw_erc20::msg::{HandleMsg, InitMsg, InitialBalance};
+
+static WASM: &[u8] = include_bytes!("../target/wasm32-unknown-unknown/release/cw_erc20.wasm");
+
+const LAYOUT: &str = if cfg!(feature = "json-balances") {
+    "json"
+} else {
+    "be-bytes"
+};
+const GAS_LIMIT: u64 = 10_000_000_000;
+const TRANSFERS: u64 = 20;
+
+// Balances of increasing length in decimal
+const BALANCES: [u128; 3] = [1, 1_000_000, 1_000_000_000_000_000_000_000_000_000];
+
+fn holder(index: usize) -> HumanAddr {
+    HumanAddr(format!("holder{:04}", index))
+}
+
+fn mock_env_height(signer: &HumanAddr, height: u64) -> Env {
+    let mut env = mock_env(signer, &[]);
+    env.block.height = height;
+    env
+}
+
+fn init_msg() -> InitMsg {
+    InitMsg {
+        decimals: 6,
+        name: "Bench token".to_string(),
+        symbol: "BENCH".to_string(),
+        initial_balances: BALANCES
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| InitialBalance {
+                address: holder(index),
+                amount: Uint128::from(*amount),
+            })
+            .collect(),
+        compliance: None,
+        blocked_recipients: None,
+        restricted_transfers: None,
+        bootstrap_grant: None,
+        bootstrap_min_transfer: None,
+        daily_outflow_cap: None,
+        governance_contracts: None,
+        burner: None,
+        hashed_references: None,
+    }
+}
+
+#[test]
+fn balance_layout_size_and_gas() {
+    let mut deps = mock_instance_with_gas_limit(WASM, &[], GAS_LIMIT);
+    let _res: InitResponse = init(&mut deps, mock_env_height(&holder(0), 876), init_msg()).unwrap();
+
+    let api = deps.api;
+    deps.with_storage(|storage| {
+        for (index, amount) in BALANCES.iter().enumerate() {
+            let address = api.canonical_address(&holder(index)).0.unwrap();
+            let key = [&to_length_prefixed(PREFIX_BALANCES), address.as_slice()].concat();
+            let size = storage.get(&key).0.unwrap().unwrap().len();
+            println!("{}: a balance of {} takes {} bytes", LAYOUT, amount, size);
+            let expected = if cfg!(feature = "json-balances") {
+                amount.to_string().len() + 2
+            } else {
+                16
+            };
+            assert_eq!(size, expected);
+        }
+        Ok(())
+    })
+    .unwrap();
+
+    // each transfer reads and writes the balances of both parties
+    let gas_before = deps.get_gas_left();
+    for height in 0..TRANSFERS {
+        let msg = HandleMsg::Transfer {
+            recipient: holder(1),
+            amount: Uint128::from(1u128),
+            tx_key: None,
+        };
+        let _res: HandleResponse =
+            handle(&mut deps, mock_env_height(&holder(2), 877 + height), msg).unwrap();
+    }
+    let gas_used = gas_before - deps.get_gas_left();
+    println!("{}: a transfer uses {} gas", LAYOUT, gas_used / TRANSFERS);
+    assert!(gas_used > 0);
+}
@@ -0,0 +1,8 @@
+mod secp256k1;
+mod sign_doc;
+
+#[cfg(test)]
+mod tests;
+
+pub use crate::secp256k1::verify_secp256k1;
+pub use crate::sign_doc::{sign_bytes, SignDoc};
@@ -0,0 +1,16 @@
+use cosmwasm_std::{StdError, StdResult};
+use k256::ecdsa::signature::Verifier;
+use k256::ecdsa::{Signature, VerifyingKey};
+use std::convert::TryFrom;
+
+/// Checks a 64 byte `r || s` signature over the SHA-256 hash of `message`, as produced by Cosmos
+/// SDK wallets. The public key may be compressed (33 bytes) or uncompressed (65 bytes).
+/// Signatures with a high `s` are rejected so that each message has a single valid signature.
+/// Returns false for a signature that does not match and an error for malformed input.
+pub fn verify_secp256k1(message: &[u8], signature: &[u8], public_key: &[u8]) -> StdResult<bool> {
+    let signature = Signature::try_from(signature)
+        .map_err(|_| StdError::generic_err("Invalid signature format"))?;
+    let public_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|_| StdError::generic_err("Invalid public key format"))?;
+    Ok(public_key.verify(message, &signature).is_ok())
+}
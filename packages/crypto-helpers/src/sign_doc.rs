@@ -0,0 +1,29 @@
+use cosmwasm_std::{to_vec, HumanAddr, StdResult};
+use serde::Serialize;
+
+/// What a signer signs to authorise `msg` on a contract. The chain and contract stop the
+/// signature from being replayed elsewhere, and the nonce stops it being replayed on the same
+/// contract.
+#[derive(Serialize)]
+pub struct SignDoc<'a, M: Serialize> {
+    pub chain_id: &'a str,
+    pub contract: &'a HumanAddr,
+    pub nonce: u64,
+    pub msg: &'a M,
+}
+
+/// The JSON bytes of a SignDoc. Fields are written in declaration order without whitespace, so a
+/// client serializing the same values produces the same bytes.
+pub fn sign_bytes<M: Serialize>(
+    chain_id: &str,
+    contract: &HumanAddr,
+    nonce: u64,
+    msg: &M,
+) -> StdResult<Vec<u8>> {
+    to_vec(&SignDoc {
+        chain_id,
+        contract,
+        nonce,
+        msg,
+    })
+}
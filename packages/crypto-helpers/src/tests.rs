@@ -0,0 +1,76 @@
+use crate::{sign_bytes, verify_secp256k1};
+use cosmwasm_std::{HumanAddr, StdError, Uint128};
+use serde::Serialize;
+
+// Generated with k256 from the private key [0x11; 32]
+const PUBLIC_KEY: &str = "034f355bdcb7cc0af728ef3cceb9615d90684bb5b2ca5f859ab0f0b704075871aa";
+const PUBLIC_KEY_UNCOMPRESSED: &str = "044f355bdcb7cc0af728ef3cceb9615d90684bb5b2ca5f859ab0f0b704075871aa385b6b1b8ead809ca67454d9683fcf2ba03456d6fe2c4abe2b07f0fbdbb2f1c1";
+const SIGNATURE: &str = "26efe15da25fa0c7a5a7e06926e45a614993481f28d236fb9a3b62e470c074cc3e1bf0a68757c41e26343e568ff2ad311dcdd3d932f2dd9a3cc25cc265562b62";
+// The same signature with s replaced by n - s
+const SIGNATURE_HIGH_S: &str = "26efe15da25fa0c7a5a7e06926e45a614993481f28d236fb9a3b62e470c074ccc1e40f5978a83be1d9cbc1a9700d52cd9ce1090d7c55c2a1831001ca6ae015df";
+// From the private key [0x22; 32]
+const OTHER_PUBLIC_KEY: &str = "02466d7fcae563e5cb09a0d1870bb580344804617879a14949cf22285f1bae3f27";
+
+#[derive(Serialize)]
+struct Transfer {
+    recipient: HumanAddr,
+    amount: Uint128,
+}
+
+fn signed_message() -> Vec<u8> {
+    let msg = Transfer {
+        recipient: HumanAddr::from("addr0002"),
+        amount: Uint128(1),
+    };
+    sign_bytes("testing", &HumanAddr::from("cosmos2contract"), 1, &msg).unwrap()
+}
+
+#[test]
+fn sign_bytes_are_canonical_json() {
+    assert_eq!(
+        String::from_utf8(signed_message()).unwrap(),
+        r#"{"chain_id":"testing","contract":"cosmos2contract","nonce":1,"msg":{"recipient":"addr0002","amount":"1"}}"#
+    );
+}
+
+#[test]
+fn verify_secp256k1_vectors() {
+    let message = signed_message();
+    let signature = hex::decode(SIGNATURE).unwrap();
+    let public_key = hex::decode(PUBLIC_KEY).unwrap();
+    assert!(verify_secp256k1(&message, &signature, &public_key).unwrap());
+    let uncompressed = hex::decode(PUBLIC_KEY_UNCOMPRESSED).unwrap();
+    assert!(verify_secp256k1(&message, &signature, &uncompressed).unwrap());
+
+    // a different nonce, signer or malleated signature does not verify
+    let replayed = sign_bytes(
+        "testing",
+        &HumanAddr::from("cosmos2contract"),
+        2,
+        &Transfer {
+            recipient: HumanAddr::from("addr0002"),
+            amount: Uint128(1),
+        },
+    )
+    .unwrap();
+    assert!(!verify_secp256k1(&replayed, &signature, &public_key).unwrap());
+    let other_key = hex::decode(OTHER_PUBLIC_KEY).unwrap();
+    assert!(!verify_secp256k1(&message, &signature, &other_key).unwrap());
+    let high_s = hex::decode(SIGNATURE_HIGH_S).unwrap();
+    assert!(!verify_secp256k1(&message, &high_s, &public_key).unwrap());
+}
+
+#[test]
+fn verify_secp256k1_rejects_malformed_input() {
+    let message = signed_message();
+    let signature = hex::decode(SIGNATURE).unwrap();
+    let public_key = hex::decode(PUBLIC_KEY).unwrap();
+    match verify_secp256k1(&message, &signature[..63], &public_key) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Invalid signature format"),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    match verify_secp256k1(&message, &signature, &public_key[1..]) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Invalid public key format"),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
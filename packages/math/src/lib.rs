@@ -0,0 +1,8 @@
+mod bytes;
+mod ratio;
+
+#[cfg(test)]
+mod tests;
+
+pub use crate::bytes::{bytes_to_u128, bytes_to_u64, read_u128};
+pub use crate::ratio::{fixed_div, fixed_from_ratio, fixed_mul, mul_ratio, Rounding, FRACTIONAL};
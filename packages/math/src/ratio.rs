@@ -0,0 +1,59 @@
+use cosmwasm_std::{StdError, StdResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One in fixed-point, the same 18 decimals cosmwasm_std::Decimal uses
+pub const FRACTIONAL: u128 = 1_000_000_000_000_000_000;
+
+/// How to round the result of a division
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    Floor,
+    /// Half up
+    Round,
+    Ceil,
+}
+
+/// amount * numerator / denominator, rounded as requested. Done in integers so that equal
+/// inputs always round the same way.
+pub fn mul_ratio(
+    amount: u128,
+    numerator: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> StdResult<u128> {
+    if denominator == 0 {
+        return Err(StdError::generic_err("Division by zero"));
+    }
+    let product = amount
+        .checked_mul(numerator)
+        .ok_or_else(|| StdError::generic_err(format!("Overflow: {} * {}", amount, numerator)))?;
+    let floor = product / denominator;
+    let remainder = product % denominator;
+    let round_up = match rounding {
+        Rounding::Floor => false,
+        Rounding::Round => remainder >= denominator - remainder,
+        Rounding::Ceil => remainder > 0,
+    };
+    if round_up {
+        Ok(floor + 1)
+    } else {
+        Ok(floor)
+    }
+}
+
+/// amount times a fixed-point rate
+pub fn fixed_mul(amount: u128, rate: u128, rounding: Rounding) -> StdResult<u128> {
+    mul_ratio(amount, rate, FRACTIONAL, rounding)
+}
+
+/// amount divided by a fixed-point rate
+pub fn fixed_div(amount: u128, rate: u128, rounding: Rounding) -> StdResult<u128> {
+    mul_ratio(amount, FRACTIONAL, rate, rounding)
+}
+
+/// numerator / denominator as a fixed-point rate
+pub fn fixed_from_ratio(numerator: u128, denominator: u128, rounding: Rounding) -> StdResult<u128> {
+    mul_ratio(numerator, FRACTIONAL, denominator, rounding)
+}
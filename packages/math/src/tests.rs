@@ -0,0 +1,60 @@
+use crate::{
+    bytes_to_u128, bytes_to_u64, fixed_div, fixed_from_ratio, fixed_mul, mul_ratio, read_u128,
+    Rounding, FRACTIONAL,
+};
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{StdError, Storage};
+
+#[test]
+fn bytes_round_trip() {
+    assert_eq!(bytes_to_u64(&7u64.to_be_bytes()).unwrap(), 7);
+    assert_eq!(bytes_to_u128(&9u128.to_be_bytes()).unwrap(), 9);
+    match bytes_to_u64(&[0u8; 4]) {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Corrupted data found. 8 byte expected.")
+        }
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn read_u128_defaults_to_zero() {
+    let mut store = MockStorage::new();
+    assert_eq!(read_u128(&store, b"missing").unwrap(), 0);
+    store.set(b"amount", &1234u128.to_be_bytes());
+    assert_eq!(read_u128(&store, b"amount").unwrap(), 1234);
+}
+
+#[test]
+fn mul_ratio_rounds_as_requested() {
+    assert_eq!(mul_ratio(10, 1, 4, Rounding::Floor).unwrap(), 2);
+    assert_eq!(mul_ratio(10, 1, 4, Rounding::Round).unwrap(), 3);
+    assert_eq!(mul_ratio(10, 1, 4, Rounding::Ceil).unwrap(), 3);
+    assert_eq!(mul_ratio(9, 1, 4, Rounding::Round).unwrap(), 2);
+    // exact results are never rounded
+    assert_eq!(mul_ratio(12, 1, 4, Rounding::Ceil).unwrap(), 3);
+
+    match mul_ratio(1, 1, 0, Rounding::Floor) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Division by zero"),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    match mul_ratio(u128::MAX, 2, 3, Rounding::Floor) {
+        Err(StdError::GenericErr { msg, .. }) => assert!(msg.starts_with("Overflow")),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn fixed_point_rates() {
+    // a 0.3% fee
+    let fee_rate = 3 * FRACTIONAL / 1000;
+    assert_eq!(fixed_mul(1000, fee_rate, Rounding::Floor).unwrap(), 3);
+    assert_eq!(fixed_mul(999, fee_rate, Rounding::Floor).unwrap(), 2);
+    assert_eq!(fixed_mul(999, fee_rate, Rounding::Ceil).unwrap(), 3);
+
+    let price = fixed_from_ratio(3, 2, Rounding::Floor).unwrap();
+    assert_eq!(price, 1_500_000_000_000_000_000);
+    assert_eq!(fixed_div(300, price, Rounding::Floor).unwrap(), 200);
+    assert_eq!(fixed_div(100, price, Rounding::Floor).unwrap(), 66);
+    assert_eq!(fixed_div(100, price, Rounding::Round).unwrap(), 67);
+}
@@ -0,0 +1,6 @@
+mod querier;
+
+#[cfg(test)]
+mod tests;
+
+pub use crate::querier::{ContractQuerier, MockDeps};
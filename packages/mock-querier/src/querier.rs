@@ -0,0 +1,98 @@
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    from_binary, from_slice, Binary, Empty, Extern, HumanAddr, Querier, QuerierResult,
+    QueryRequest, ReadonlyStorage, StdResult, SystemError, WasmQuery,
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// What `mock_dependencies` returns, the dependencies of an in-memory contract instance
+pub type MockDeps = Extern<MockStorage, MockApi, MockQuerier>;
+
+type QueryFn = Box<dyn Fn(&MockDeps, &Binary) -> StdResult<Binary>>;
+
+struct Instance {
+    deps: MockDeps,
+    query: QueryFn,
+}
+
+/// Answers smart and raw queries to registered addresses from in-memory instances of other
+/// contracts, and everything else from `base`. The instances query their own MockQuerier, so
+/// they cannot query each other.
+pub struct ContractQuerier {
+    pub base: MockQuerier,
+    instances: HashMap<HumanAddr, Instance>,
+}
+
+impl ContractQuerier {
+    pub fn new(base: MockQuerier) -> Self {
+        ContractQuerier {
+            base,
+            instances: HashMap::new(),
+        }
+    }
+
+    /// Routes queries to `address` to `query`, usually the contract's own `query` entry point,
+    /// run against `deps`
+    pub fn add_contract<M: DeserializeOwned + 'static>(
+        &mut self,
+        address: HumanAddr,
+        deps: MockDeps,
+        query: fn(&MockDeps, M) -> StdResult<Binary>,
+    ) {
+        let instance = Instance {
+            deps,
+            query: Box::new(move |deps, msg| query(deps, from_binary(msg)?)),
+        };
+        self.instances.insert(address, instance);
+    }
+
+    /// The dependencies of the instance at `address`. Panics if there is none.
+    pub fn contract(&self, address: &HumanAddr) -> &MockDeps {
+        match self.instances.get(address) {
+            Some(instance) => &instance.deps,
+            None => panic!("No contract instance at {}", address),
+        }
+    }
+
+    /// Mutable dependencies of the instance at `address`, to call its `handle` with. Panics if
+    /// there is none.
+    pub fn contract_mut(&mut self, address: &HumanAddr) -> &mut MockDeps {
+        match self.instances.get_mut(address) {
+            Some(instance) => &mut instance.deps,
+            None => panic!("No contract instance at {}", address),
+        }
+    }
+}
+
+impl Querier for ContractQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                match self.instances.get(contract_addr) {
+                    Some(instance) => Ok((instance.query)(&instance.deps, msg)),
+                    None => self.base.raw_query(bin_request),
+                }
+            }
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
+                match self.instances.get(contract_addr) {
+                    Some(instance) => {
+                        let value = instance.deps.storage.get(key.as_slice());
+                        Ok(Ok(Binary::from(value.unwrap_or_default())))
+                    }
+                    None => self.base.raw_query(bin_request),
+                }
+            }
+            _ => self.base.raw_query(bin_request),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{ContractQuerier, MockDeps};
+use cosmwasm_std::testing::{mock_dependencies, MockQuerier};
+use cosmwasm_std::{
+    coins, to_binary, to_vec, BalanceResponse, BankQuery, Binary, Empty, HumanAddr, Querier,
+    QueryRequest, ReadonlyStorage, StdError, StdResult, Storage, SystemError, WasmQuery,
+};
+use serde::{Deserialize, Serialize};
+
+const COUNTER: &str = "counter";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CounterQueryMsg {
+    Count {},
+    Fail {},
+}
+
+// The query entry point of a contract keeping a counter under b"count"
+fn counter_query(deps: &MockDeps, msg: CounterQueryMsg) -> StdResult<Binary> {
+    match msg {
+        CounterQueryMsg::Count {} => {
+            let count = deps.storage.get(b"count").map_or(0, |data| data[0]);
+            to_binary(&count)
+        }
+        CounterQueryMsg::Fail {} => Err(StdError::generic_err("Counter failed")),
+    }
+}
+
+fn mock_querier() -> ContractQuerier {
+    let mut querier = ContractQuerier::new(MockQuerier::new(&[(
+        &HumanAddr::from("holder"),
+        &coins(5, "ucosm"),
+    )]));
+    querier.add_contract(
+        HumanAddr::from(COUNTER),
+        mock_dependencies(20, &[]),
+        counter_query,
+    );
+    querier
+}
+
+fn smart(contract_addr: &str, msg: CounterQueryMsg) -> QueryRequest<Empty> {
+    QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: HumanAddr::from(contract_addr),
+        msg: to_binary(&msg).unwrap(),
+    })
+}
+
+#[test]
+fn smart_queries_reach_contract_instances() {
+    let mut querier = mock_querier();
+    let count: u8 = querier
+        .query(&smart(COUNTER, CounterQueryMsg::Count {}))
+        .unwrap();
+    assert_eq!(count, 0);
+
+    querier
+        .contract_mut(&HumanAddr::from(COUNTER))
+        .storage
+        .set(b"count", &[3]);
+    let count: u8 = querier
+        .query(&smart(COUNTER, CounterQueryMsg::Count {}))
+        .unwrap();
+    assert_eq!(count, 3);
+
+    match querier.query::<u8>(&smart(COUNTER, CounterQueryMsg::Fail {})) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Counter failed"),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn raw_queries_read_instance_storage() {
+    let mut querier = mock_querier();
+    querier
+        .contract_mut(&HumanAddr::from(COUNTER))
+        .storage
+        .set(b"count", &[7]);
+    let request: QueryRequest<Empty> = QueryRequest::Wasm(WasmQuery::Raw {
+        contract_addr: HumanAddr::from(COUNTER),
+        key: Binary::from(b"count".to_vec()),
+    });
+    let raw = to_vec(&request).unwrap();
+    assert_eq!(querier.raw_query(&raw), Ok(Ok(Binary::from(vec![7]))));
+}
+
+#[test]
+fn other_queries_go_to_base() {
+    let querier = mock_querier();
+    let request = QueryRequest::Bank(BankQuery::Balance {
+        address: HumanAddr::from("holder"),
+        denom: "ucosm".to_string(),
+    });
+    let res: BalanceResponse = querier.query(&request).unwrap();
+    assert_eq!(res.amount.amount.u128(), 5);
+
+    let raw = to_vec(&smart("unknown", CounterQueryMsg::Count {})).unwrap();
+    match querier.raw_query(&raw) {
+        Err(SystemError::NoSuchContract { addr }) => assert_eq!(addr, HumanAddr::from("unknown")),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
@@ -0,0 +1,187 @@
+use cosmwasm_std::{from_slice, to_vec, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+// Positions of the first item and one past the last, as two big-endian u64. Items are keyed by
+// their position in big-endian. Positions wrap around, so pushing to the front never runs out.
+const ENDS_KEY: &[u8] = b"ends";
+
+/// A double-ended queue kept in storage under `namespace`. Pushing and popping at either end
+/// reads and writes a fixed number of keys however long the queue is.
+pub struct Deque<'a, S: Storage, T> {
+    storage: PrefixedStorage<'a, S>,
+    item: PhantomData<T>,
+}
+
+impl<'a, S: Storage, T: Serialize + DeserializeOwned> Deque<'a, S, T> {
+    pub fn new(storage: &'a mut S, namespace: &[u8]) -> Self {
+        Deque {
+            storage: PrefixedStorage::new(namespace, storage),
+            item: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> StdResult<u64> {
+        let (head, tail) = ends(&self.storage)?;
+        Ok(tail.wrapping_sub(head))
+    }
+
+    pub fn is_empty(&self) -> StdResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The item `index` places from the front
+    pub fn get(&self, index: u64) -> StdResult<Option<T>> {
+        get(&self.storage, index)
+    }
+
+    pub fn front(&self) -> StdResult<Option<T>> {
+        get(&self.storage, 0)
+    }
+
+    pub fn back(&self) -> StdResult<Option<T>> {
+        back(&self.storage)
+    }
+
+    pub fn push_back(&mut self, item: &T) -> StdResult<()> {
+        let (head, tail) = ends(&self.storage)?;
+        self.storage.set(&tail.to_be_bytes(), &to_vec(item)?);
+        set_ends(&mut self.storage, head, tail.wrapping_add(1));
+        Ok(())
+    }
+
+    pub fn push_front(&mut self, item: &T) -> StdResult<()> {
+        let (head, tail) = ends(&self.storage)?;
+        let head = head.wrapping_sub(1);
+        self.storage.set(&head.to_be_bytes(), &to_vec(item)?);
+        set_ends(&mut self.storage, head, tail);
+        Ok(())
+    }
+
+    pub fn pop_front(&mut self) -> StdResult<Option<T>> {
+        let (head, tail) = ends(&self.storage)?;
+        if head == tail {
+            return Ok(None);
+        }
+        let item = load(&self.storage, head)?;
+        self.storage.remove(&head.to_be_bytes());
+        set_ends(&mut self.storage, head.wrapping_add(1), tail);
+        Ok(Some(item))
+    }
+
+    pub fn pop_back(&mut self) -> StdResult<Option<T>> {
+        let (head, tail) = ends(&self.storage)?;
+        if head == tail {
+            return Ok(None);
+        }
+        let tail = tail.wrapping_sub(1);
+        let item = load(&self.storage, tail)?;
+        self.storage.remove(&tail.to_be_bytes());
+        set_ends(&mut self.storage, head, tail);
+        Ok(Some(item))
+    }
+
+    /// Pops up to `limit` items off the front for as long as `expired` holds for them, and
+    /// returns them oldest first. Items pushed to the back in expiry order are pruned exactly
+    /// once they expire.
+    pub fn prune_front<F: Fn(&T) -> bool>(
+        &mut self,
+        expired: F,
+        limit: usize,
+    ) -> StdResult<Vec<T>> {
+        let mut pruned = vec![];
+        while pruned.len() < limit {
+            match self.front()? {
+                Some(item) if expired(&item) => {
+                    self.pop_front()?;
+                    pruned.push(item);
+                }
+                _ => break,
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+/// Read-only view of a Deque
+pub struct ReadonlyDeque<'a, S: ReadonlyStorage, T> {
+    storage: ReadonlyPrefixedStorage<'a, S>,
+    item: PhantomData<T>,
+}
+
+impl<'a, S: ReadonlyStorage, T: DeserializeOwned> ReadonlyDeque<'a, S, T> {
+    pub fn new(storage: &'a S, namespace: &[u8]) -> Self {
+        ReadonlyDeque {
+            storage: ReadonlyPrefixedStorage::new(namespace, storage),
+            item: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> StdResult<u64> {
+        let (head, tail) = ends(&self.storage)?;
+        Ok(tail.wrapping_sub(head))
+    }
+
+    pub fn is_empty(&self) -> StdResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    pub fn get(&self, index: u64) -> StdResult<Option<T>> {
+        get(&self.storage, index)
+    }
+
+    pub fn front(&self) -> StdResult<Option<T>> {
+        get(&self.storage, 0)
+    }
+
+    pub fn back(&self) -> StdResult<Option<T>> {
+        back(&self.storage)
+    }
+}
+
+fn ends<S: ReadonlyStorage>(storage: &S) -> StdResult<(u64, u64)> {
+    match storage.get(ENDS_KEY) {
+        Some(data) => match (data.get(0..8), data.get(8..16)) {
+            (Some(head), Some(tail)) => Ok((
+                u64::from_be_bytes(head.try_into().unwrap()),
+                u64::from_be_bytes(tail.try_into().unwrap()),
+            )),
+            _ => Err(StdError::generic_err(
+                "Corrupted data found. 16 byte expected.",
+            )),
+        },
+        None => Ok((0, 0)),
+    }
+}
+
+fn set_ends<S: Storage>(storage: &mut S, head: u64, tail: u64) {
+    let mut data = head.to_be_bytes().to_vec();
+    data.extend_from_slice(&tail.to_be_bytes());
+    storage.set(ENDS_KEY, &data);
+}
+
+fn get<S: ReadonlyStorage, T: DeserializeOwned>(storage: &S, index: u64) -> StdResult<Option<T>> {
+    let (head, tail) = ends(storage)?;
+    if index >= tail.wrapping_sub(head) {
+        return Ok(None);
+    }
+    load(storage, head.wrapping_add(index)).map(Some)
+}
+
+fn back<S: ReadonlyStorage, T: DeserializeOwned>(storage: &S) -> StdResult<Option<T>> {
+    let (head, tail) = ends(storage)?;
+    if head == tail {
+        return Ok(None);
+    }
+    load(storage, tail.wrapping_sub(1)).map(Some)
+}
+
+fn load<S: ReadonlyStorage, T: DeserializeOwned>(storage: &S, position: u64) -> StdResult<T> {
+    match storage.get(&position.to_be_bytes()) {
+        Some(data) => from_slice(&data),
+        None => Err(StdError::not_found("deque item")),
+    }
+}
@@ -0,0 +1,6 @@
+mod deque;
+
+#[cfg(test)]
+mod tests;
+
+pub use crate::deque::{Deque, ReadonlyDeque};
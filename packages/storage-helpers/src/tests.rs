@@ -0,0 +1,61 @@
+use crate::{Deque, ReadonlyDeque};
+use cosmwasm_std::testing::MockStorage;
+
+#[test]
+fn push_and_pop_at_both_ends() {
+    let mut store = MockStorage::new();
+    let mut deque = Deque::<_, u32>::new(&mut store, b"queue");
+    assert!(deque.is_empty().unwrap());
+    assert_eq!(deque.pop_front().unwrap(), None);
+    assert_eq!(deque.pop_back().unwrap(), None);
+
+    deque.push_back(&2).unwrap();
+    deque.push_back(&3).unwrap();
+    // the front wraps below position 0
+    deque.push_front(&1).unwrap();
+    assert_eq!(deque.len().unwrap(), 3);
+    assert_eq!(deque.front().unwrap(), Some(1));
+    assert_eq!(deque.back().unwrap(), Some(3));
+    assert_eq!(deque.get(1).unwrap(), Some(2));
+    assert_eq!(deque.get(3).unwrap(), None);
+
+    assert_eq!(deque.pop_front().unwrap(), Some(1));
+    assert_eq!(deque.pop_back().unwrap(), Some(3));
+    assert_eq!(deque.pop_back().unwrap(), Some(2));
+    assert!(deque.is_empty().unwrap());
+}
+
+#[test]
+fn namespaces_are_separate() {
+    let mut store = MockStorage::new();
+    Deque::new(&mut store, b"a").push_back(&1u32).unwrap();
+    Deque::new(&mut store, b"b").push_back(&2u32).unwrap();
+
+    let a = ReadonlyDeque::<_, u32>::new(&store, b"a");
+    assert_eq!(a.len().unwrap(), 1);
+    assert_eq!(a.front().unwrap(), Some(1));
+    let b = ReadonlyDeque::<_, u32>::new(&store, b"b");
+    assert_eq!(b.back().unwrap(), Some(2));
+}
+
+#[test]
+fn prune_front_stops_at_first_unexpired() {
+    let mut store = MockStorage::new();
+    let mut deque = Deque::new(&mut store, b"expiring");
+    // (expires_at, value) pushed in expiry order
+    for item in &[(10u64, 'a'), (20, 'b'), (30, 'c'), (40, 'd')] {
+        deque.push_back(item).unwrap();
+    }
+
+    let now = 30;
+    let pruned = deque
+        .prune_front(|(expires_at, _)| *expires_at <= now, 2)
+        .unwrap();
+    assert_eq!(pruned, vec![(10, 'a'), (20, 'b')]);
+    let pruned = deque
+        .prune_front(|(expires_at, _)| *expires_at <= now, 10)
+        .unwrap();
+    assert_eq!(pruned, vec![(30, 'c')]);
+    assert_eq!(deque.len().unwrap(), 1);
+    assert_eq!(deque.front().unwrap(), Some((40, 'd')));
+}
@@ -0,0 +1,150 @@
+use crate::msg::{BadgesResponse, ConfigResponse, HandleMsg, InitMsg, QueryMsg, TokenResponse};
+use crate::state::{badges, badges_read, config, config_read, token, token_read, State, Token};
+use cosmwasm_std::{
+    log, to_binary, Api, Binary, Env, Extern, HandleResponse, HumanAddr, InitResponse, Order,
+    Querier, StdError, StdResult, Storage,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    let state = State {
+        voting: deps.api.canonical_address(&msg.voting)?,
+        token_count: 0,
+    };
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        HandleMsg::PollTallied { poll_id, voters } => poll_tallied(deps, env, poll_id, voters),
+    }
+}
+
+/// Mints one badge per voter and poll. Voters that already have the poll's badge are skipped.
+fn poll_tallied<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+    voters: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let mut state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.voting {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut minted = 0u64;
+    for voter in &voters {
+        let owner = deps.api.canonical_address(voter)?;
+        if badges_read(&deps.storage, &owner)
+            .may_load(&poll_id.to_be_bytes())?
+            .is_some()
+        {
+            continue;
+        }
+        state.token_count += 1;
+        let token_id = state.token_count;
+        badges(&mut deps.storage, &owner).save(&poll_id.to_be_bytes(), &token_id)?;
+        token(&mut deps.storage).save(
+            &token_id.to_be_bytes(),
+            &Token {
+                owner,
+                poll_id,
+                minted_at_height: env.block.height,
+            },
+        )?;
+        minted += 1;
+    }
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "mint_badges"),
+            log("poll_id", poll_id),
+            log("minted", minted),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Token { token_id } => to_binary(&query_token(deps, token_id)?),
+        QueryMsg::Badges {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_badges(deps, owner, start_after, limit)?),
+    }
+}
+
+fn query_config<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ConfigResponse> {
+    let state = config_read(&deps.storage).load()?;
+    Ok(ConfigResponse {
+        voting: deps.api.human_address(&state.voting)?,
+        token_count: state.token_count,
+    })
+}
+
+fn query_token<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: u64,
+) -> StdResult<TokenResponse> {
+    match token_read(&deps.storage).may_load(&token_id.to_be_bytes())? {
+        Some(a_token) => to_token_response(&deps.api, token_id, a_token),
+        None => Err(StdError::generic_err("Token does not exist")),
+    }
+}
+
+fn query_badges<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: HumanAddr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<BadgesResponse> {
+    let owner = deps.api.canonical_address(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|poll_id| {
+        let mut start = poll_id.to_be_bytes().to_vec();
+        start.push(0);
+        start
+    });
+    let badges = badges_read(&deps.storage, &owner)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, token_id) = item?;
+            let a_token = token_read(&deps.storage).load(&token_id.to_be_bytes())?;
+            to_token_response(&deps.api, token_id, a_token)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BadgesResponse { badges })
+}
+
+fn to_token_response<A: Api>(api: &A, token_id: u64, a_token: Token) -> StdResult<TokenResponse> {
+    Ok(TokenResponse {
+        token_id,
+        owner: api.human_address(&a_token.owner)?,
+        poll_id: a_token.poll_id,
+        minted_at_height: a_token.minted_at_height,
+    })
+}
@@ -0,0 +1,54 @@
+use cosmwasm_std::HumanAddr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitMsg {
+    /// Voting contract whose tally hook this contract is
+    pub voting: HumanAddr,
+}
+
+/// Badges can't be transferred, so the only message is the voting contract's tally hook
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    /// Voting contract only. Mints a badge for `poll_id` to each voter that doesn't have one.
+    PollTallied {
+        poll_id: u64,
+        voters: Vec<HumanAddr>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    Token {
+        token_id: u64,
+    },
+    /// An owner's badges in poll order
+    Badges {
+        owner: HumanAddr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub voting: HumanAddr,
+    pub token_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenResponse {
+    pub token_id: u64,
+    pub owner: HumanAddr,
+    pub poll_id: u64,
+    pub minted_at_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BadgesResponse {
+    pub badges: Vec<TokenResponse>,
+}
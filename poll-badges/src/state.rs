@@ -0,0 +1,53 @@
+use cosmwasm_std::{CanonicalAddr, Storage};
+use cosmwasm_storage::{
+    bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
+    Singleton,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+static CONFIG_KEY: &[u8] = b"config";
+static TOKEN_KEY: &[u8] = b"tokens";
+static BADGE_KEY: &[u8] = b"badges";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    pub voting: CanonicalAddr,
+    pub token_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Token {
+    pub owner: CanonicalAddr,
+    pub poll_id: u64,
+    pub minted_at_height: u64,
+}
+
+pub fn config<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, State> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn config_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+/// Tokens keyed by id in big-endian
+pub fn token<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, Token> {
+    bucket(TOKEN_KEY, storage)
+}
+
+pub fn token_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, Token> {
+    bucket_read(TOKEN_KEY, storage)
+}
+
+/// An owner's token ids keyed by poll_id in big-endian
+pub fn badges<'a, S: Storage>(storage: &'a mut S, owner: &CanonicalAddr) -> Bucket<'a, S, u64> {
+    Bucket::multilevel(&[BADGE_KEY, owner.as_slice()], storage)
+}
+
+pub fn badges_read<'a, S: Storage>(
+    storage: &'a S,
+    owner: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, u64> {
+    ReadonlyBucket::multilevel(&[BADGE_KEY, owner.as_slice()], storage)
+}
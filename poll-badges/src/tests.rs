@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use crate::contract::{handle, init, query};
+    use crate::msg::{BadgesResponse, ConfigResponse, HandleMsg, InitMsg, QueryMsg, TokenResponse};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, log, Extern, HumanAddr, StdError};
+
+    const VOTING: &str = "voting";
+    const TEST_VOTER: &str = "voter1";
+    const TEST_VOTER_2: &str = "voter2";
+
+    fn mock_init(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let msg = InitMsg {
+            voting: HumanAddr::from(VOTING),
+        };
+        init(deps, mock_env("creator", &[]), msg).unwrap();
+    }
+
+    fn tallied(poll_id: u64, voters: &[&str]) -> HandleMsg {
+        HandleMsg::PollTallied {
+            poll_id,
+            voters: voters.iter().map(|voter| HumanAddr::from(*voter)).collect(),
+        }
+    }
+
+    fn badges(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        owner: &str,
+        start_after: Option<u64>,
+    ) -> Vec<(u64, u64)> {
+        let msg = QueryMsg::Badges {
+            owner: HumanAddr::from(owner),
+            start_after,
+            limit: None,
+        };
+        let value: BadgesResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+        value
+            .badges
+            .iter()
+            .map(|badge| (badge.poll_id, badge.token_id))
+            .collect()
+    }
+
+    #[test]
+    fn tally_hook_mints_badges() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(VOTING, &[]);
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            tallied(1, &[TEST_VOTER, TEST_VOTER_2]),
+        )
+        .unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "mint_badges"),
+                log("poll_id", 1),
+                log("minted", 2),
+            ]
+        );
+        // a voter reported twice for the same poll gets one badge
+        let res = handle(&mut deps, env.clone(), tallied(1, &[TEST_VOTER])).unwrap();
+        assert_eq!(res.log[2], log("minted", 0));
+        handle(&mut deps, env, tallied(3, &[TEST_VOTER])).unwrap();
+
+        assert_eq!(badges(&deps, TEST_VOTER, None), vec![(1, 1), (3, 3)]);
+        assert_eq!(badges(&deps, TEST_VOTER, Some(1)), vec![(3, 3)]);
+        assert!(badges(&deps, TEST_VOTER, Some(u64::MAX)).is_empty());
+        assert_eq!(badges(&deps, TEST_VOTER_2, None), vec![(1, 2)]);
+
+        let value: TokenResponse =
+            from_binary(&query(&deps, QueryMsg::Token { token_id: 2 }).unwrap()).unwrap();
+        assert_eq!(
+            value,
+            TokenResponse {
+                token_id: 2,
+                owner: HumanAddr::from(TEST_VOTER_2),
+                poll_id: 1,
+                minted_at_height: 12345,
+            }
+        );
+        let value: ConfigResponse =
+            from_binary(&query(&deps, QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(value.token_count, 3);
+    }
+
+    #[test]
+    fn only_voting_contract_mints() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(TEST_VOTER, &[]);
+        match handle(&mut deps, env, tallied(1, &[TEST_VOTER])) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert!(badges(&deps, TEST_VOTER, None).is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
+use std::env::current_dir;
+use std::fs::create_dir_all;
+
+use cw_savings::msg::{HandleMsg, InitMsg, PotResponse, PotsResponse, QueryMsg, StatsResponse};
+use cw_savings::state::{Pot, State};
+
+fn main() {
+    let mut out_dir = current_dir().unwrap();
+    out_dir.push("schema");
+    create_dir_all(&out_dir).unwrap();
+    remove_schemas(&out_dir).unwrap();
+
+    export_schema(&schema_for!(InitMsg), &out_dir);
+    export_schema(&schema_for!(HandleMsg), &out_dir);
+    export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(Pot), &out_dir);
+    export_schema(&schema_for!(PotResponse), &out_dir);
+    export_schema(&schema_for!(PotsResponse), &out_dir);
+    export_schema(&schema_for!(StatsResponse), &out_dir);
+}
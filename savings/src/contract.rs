@@ -0,0 +1,313 @@
+use crate::msg::{
+    HandleMsg, InitMsg, PenaltyInfo, PotResponse, PotsResponse, QueryMsg, StatsResponse,
+};
+use crate::state::{config, config_read, pots, pots_read, Penalty, Pot, State};
+use cosmwasm_std::{
+    coin, log, to_binary, Api, BankMsg, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
+    InitResponse, Order, Querier, StdError, StdResult, Storage, Uint128,
+};
+
+const MIN_NAME_LENGTH: usize = 3;
+const MAX_NAME_LENGTH: usize = 32;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    let state = State {
+        denom: msg.denom,
+        pot_count: 0,
+        total_locked: Uint128::zero(),
+        total_penalties: Uint128::zero(),
+    };
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        HandleMsg::CreatePot {
+            name,
+            unlock_height,
+            penalty,
+        } => create_pot(deps, env, name, unlock_height, penalty),
+        HandleMsg::Deposit { name } => deposit(deps, env, name),
+        HandleMsg::Withdraw { name } => withdraw(deps, env, name),
+    }
+}
+
+fn create_pot<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+    unlock_height: u64,
+    penalty: Option<PenaltyInfo>,
+) -> StdResult<HandleResponse> {
+    validate_name(&name)?;
+    if unlock_height <= env.block.height {
+        return Err(StdError::generic_err("Unlock height must be in the future"));
+    }
+    let penalty = match penalty {
+        Some(penalty) => {
+            if penalty.percentage == 0 || penalty.percentage > 100 {
+                return Err(StdError::generic_err("Penalty percentage must be 1 to 100"));
+            }
+            Some(Penalty {
+                percentage: penalty.percentage,
+                beneficiary: deps.api.canonical_address(&penalty.beneficiary)?,
+            })
+        }
+        None => None,
+    };
+    let mut state = config_read(&deps.storage).load()?;
+    let amount = sent_amount(&env, &state.denom)?;
+
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    if pots_read(&deps.storage, &owner)
+        .may_load(name.as_bytes())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Pot already exists"));
+    }
+    let a_pot = Pot {
+        amount,
+        unlock_height,
+        penalty,
+        created_at_height: env.block.height,
+    };
+    pots(&mut deps.storage, &owner).save(name.as_bytes(), &a_pot)?;
+
+    state.pot_count += 1;
+    state.total_locked += amount;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "create_pot"),
+            log("owner", env.message.sender),
+            log("name", name),
+            log("amount", amount),
+            log("unlock_height", unlock_height),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+fn deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+) -> StdResult<HandleResponse> {
+    let mut state = config_read(&deps.storage).load()?;
+    let amount = sent_amount(&env, &state.denom)?;
+
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let mut a_pot = match pots_read(&deps.storage, &owner).may_load(name.as_bytes())? {
+        Some(a_pot) => a_pot,
+        None => return Err(StdError::generic_err("Pot does not exist")),
+    };
+    a_pot.amount += amount;
+    pots(&mut deps.storage, &owner).save(name.as_bytes(), &a_pot)?;
+
+    state.total_locked += amount;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "deposit"),
+            log("name", name),
+            log("amount", amount),
+            log("pot_amount", a_pot.amount),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// Pays out the whole pot. Before the unlock height the penalty is taken out for the
+/// beneficiary, and pots without one can't be withdrawn.
+fn withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+) -> StdResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let a_pot = match pots_read(&deps.storage, &owner).may_load(name.as_bytes())? {
+        Some(a_pot) => a_pot,
+        None => return Err(StdError::generic_err("Pot does not exist")),
+    };
+
+    let mut state = config_read(&deps.storage).load()?;
+    let mut messages = vec![];
+    let mut penalty_amount = Uint128::zero();
+    if env.block.height < a_pot.unlock_height {
+        let penalty = match &a_pot.penalty {
+            Some(penalty) => penalty,
+            None => {
+                return Err(StdError::generic_err(format!(
+                    "Pot is locked until height {}",
+                    a_pot.unlock_height
+                )))
+            }
+        };
+        penalty_amount = a_pot
+            .amount
+            .multiply_ratio(u128::from(penalty.percentage), 100u128);
+        if !penalty_amount.is_zero() {
+            messages.push(send_msg(
+                &env,
+                deps.api.human_address(&penalty.beneficiary)?,
+                penalty_amount,
+                &state.denom,
+            ));
+        }
+    }
+    let payout = (a_pot.amount - penalty_amount)?;
+    if !payout.is_zero() {
+        messages.push(send_msg(
+            &env,
+            env.message.sender.clone(),
+            payout,
+            &state.denom,
+        ));
+    }
+    pots(&mut deps.storage, &owner).remove(name.as_bytes());
+
+    state.pot_count -= 1;
+    state.total_locked = (state.total_locked - a_pot.amount)?;
+    state.total_penalties += penalty_amount;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "withdraw"),
+            log("name", name),
+            log("amount", payout),
+            log("penalty", penalty_amount),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// amount of the savings denom sent, which must be the only coin sent
+fn sent_amount(env: &Env, denom: &str) -> StdResult<Uint128> {
+    match env.message.sent_funds.as_slice() {
+        [sent] if sent.denom == denom && !sent.amount.is_zero() => Ok(sent.amount),
+        _ => Err(StdError::generic_err(format!(
+            "Send {} and no other coins",
+            denom
+        ))),
+    }
+}
+
+fn send_msg(env: &Env, to_address: HumanAddr, amount: Uint128, denom: &str) -> CosmosMsg {
+    CosmosMsg::Bank(BankMsg::Send {
+        from_address: env.contract.address.clone(),
+        to_address,
+        amount: vec![coin(amount.u128(), denom)],
+    })
+}
+
+fn validate_name(name: &str) -> StdResult<()> {
+    if name.len() < MIN_NAME_LENGTH {
+        Err(StdError::generic_err("Name too short"))
+    } else if name.len() > MAX_NAME_LENGTH {
+        Err(StdError::generic_err("Name too long"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Pot { owner, name } => to_binary(&query_pot(deps, owner, name)?),
+        QueryMsg::Pots {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_pots(deps, owner, start_after, limit)?),
+        QueryMsg::Stats {} => to_binary(&query_stats(deps)?),
+    }
+}
+
+fn query_pot<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: HumanAddr,
+    name: String,
+) -> StdResult<PotResponse> {
+    let owner = deps.api.canonical_address(&owner)?;
+    match pots_read(&deps.storage, &owner).may_load(name.as_bytes())? {
+        Some(a_pot) => to_pot_response(&deps.api, name, a_pot),
+        None => Err(StdError::generic_err("Pot does not exist")),
+    }
+}
+
+fn query_pots<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: HumanAddr,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PotsResponse> {
+    let owner = deps.api.canonical_address(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|name| {
+        let mut start = name.into_bytes();
+        start.push(0);
+        start
+    });
+    let pots = pots_read(&deps.storage, &owner)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, a_pot) = item?;
+            let name = String::from_utf8(key)
+                .map_err(|_| StdError::generic_err("Pot name is not UTF-8"))?;
+            to_pot_response(&deps.api, name, a_pot)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PotsResponse { pots })
+}
+
+fn to_pot_response<A: Api>(api: &A, name: String, a_pot: Pot) -> StdResult<PotResponse> {
+    Ok(PotResponse {
+        name,
+        amount: a_pot.amount,
+        unlock_height: a_pot.unlock_height,
+        penalty: a_pot
+            .penalty
+            .map(|penalty| -> StdResult<PenaltyInfo> {
+                Ok(PenaltyInfo {
+                    percentage: penalty.percentage,
+                    beneficiary: api.human_address(&penalty.beneficiary)?,
+                })
+            })
+            .transpose()?,
+        created_at_height: a_pot.created_at_height,
+    })
+}
+
+fn query_stats<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<StatsResponse> {
+    let state = config_read(&deps.storage).load()?;
+    Ok(StatsResponse {
+        denom: state.denom,
+        pot_count: state.pot_count,
+        total_locked: state.total_locked,
+        total_penalties: state.total_penalties,
+    })
+}
@@ -0,0 +1,71 @@
+use cosmwasm_std::{HumanAddr, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitMsg {
+    /// Native denom pots are saved in
+    pub denom: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    /// Opens a pot named `name`, 3 to 32 bytes, with the coins sent. Without a penalty the pot
+    /// can't be withdrawn before `unlock_height`.
+    CreatePot {
+        name: String,
+        unlock_height: u64,
+        penalty: Option<PenaltyInfo>,
+    },
+    /// Adds the coins sent to one of the signer's pots
+    Deposit { name: String },
+    /// Empties one of the signer's pots, paying the penalty if it is still locked
+    Withdraw { name: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PenaltyInfo {
+    /// 1 to 100
+    pub percentage: u8,
+    pub beneficiary: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Pot {
+        owner: HumanAddr,
+        name: String,
+    },
+    /// An owner's pots in name order
+    Pots {
+        owner: HumanAddr,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Stats {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PotResponse {
+    pub name: String,
+    pub amount: Uint128,
+    pub unlock_height: u64,
+    pub penalty: Option<PenaltyInfo>,
+    pub created_at_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PotsResponse {
+    pub pots: Vec<PotResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub denom: String,
+    /// Pots not yet withdrawn
+    pub pot_count: u64,
+    pub total_locked: Uint128,
+    pub total_penalties: Uint128,
+}
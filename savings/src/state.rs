@@ -0,0 +1,54 @@
+use cosmwasm_std::{CanonicalAddr, Storage, Uint128};
+use cosmwasm_storage::{
+    singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton, Singleton,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+static CONFIG_KEY: &[u8] = b"config";
+static POT_KEY: &[u8] = b"pots";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    pub denom: String,
+    /// Pots not yet withdrawn
+    pub pot_count: u64,
+    pub total_locked: Uint128,
+    /// Penalties paid to beneficiaries so far
+    pub total_penalties: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Pot {
+    pub amount: Uint128,
+    pub unlock_height: u64,
+    pub penalty: Option<Penalty>,
+    pub created_at_height: u64,
+}
+
+/// Percentage of a pot withdrawn before its unlock height that goes to the beneficiary
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Penalty {
+    pub percentage: u8,
+    pub beneficiary: CanonicalAddr,
+}
+
+pub fn config<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, State> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn config_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+/// A saver's pots keyed by name
+pub fn pots<'a, S: Storage>(storage: &'a mut S, owner: &CanonicalAddr) -> Bucket<'a, S, Pot> {
+    Bucket::multilevel(&[POT_KEY, owner.as_slice()], storage)
+}
+
+pub fn pots_read<'a, S: Storage>(
+    storage: &'a S,
+    owner: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, Pot> {
+    ReadonlyBucket::multilevel(&[POT_KEY, owner.as_slice()], storage)
+}
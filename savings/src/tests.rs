@@ -0,0 +1,241 @@
+#[cfg(test)]
+mod tests {
+    use crate::contract::{handle, init, query};
+    use crate::msg::{
+        HandleMsg, InitMsg, PenaltyInfo, PotResponse, PotsResponse, QueryMsg, StatsResponse,
+    };
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        coin, coins, from_binary, log, BankMsg, Coin, CosmosMsg, Env, Extern, HumanAddr, StdError,
+        Uint128,
+    };
+
+    const DENOM: &str = "ucosm";
+    const SAVER: &str = "saver";
+    const BENEFICIARY: &str = "charity";
+
+    fn mock_init(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let msg = InitMsg {
+            denom: DENOM.to_string(),
+        };
+        init(deps, mock_env("creator", &[]), msg).unwrap();
+    }
+
+    fn mock_env_height(sender: &str, sent: &[Coin], height: u64) -> Env {
+        let mut env = mock_env(sender, sent);
+        env.block.height = height;
+        env
+    }
+
+    fn create_pot_msg(name: &str, unlock_height: u64, penalty: Option<u8>) -> HandleMsg {
+        HandleMsg::CreatePot {
+            name: name.to_string(),
+            unlock_height,
+            penalty: penalty.map(|percentage| PenaltyInfo {
+                percentage,
+                beneficiary: HumanAddr::from(BENEFICIARY),
+            }),
+        }
+    }
+
+    fn send(to_address: &str, amount: u128) -> CosmosMsg {
+        CosmosMsg::Bank(BankMsg::Send {
+            from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+            to_address: HumanAddr::from(to_address),
+            amount: coins(amount, DENOM),
+        })
+    }
+
+    fn stats(deps: &Extern<MockStorage, MockApi, MockQuerier>) -> StatsResponse {
+        from_binary(&query(deps, QueryMsg::Stats {}).unwrap()).unwrap()
+    }
+
+    fn assert_generic_err<T: std::fmt::Debug>(res: Result<T, StdError>, expected: &str) {
+        match res {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, expected),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn withdraw_after_unlock() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env_height(SAVER, &coins(100, DENOM), 1000);
+        let res = handle(&mut deps, env, create_pot_msg("house", 2000, None)).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "create_pot"),
+                log("owner", SAVER),
+                log("name", "house"),
+                log("amount", 100),
+                log("unlock_height", 2000),
+            ]
+        );
+        let env = mock_env_height(SAVER, &coins(50, DENOM), 1500);
+        let msg = HandleMsg::Deposit {
+            name: "house".to_string(),
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::Pot {
+            owner: HumanAddr::from(SAVER),
+            name: "house".to_string(),
+        };
+        let value: PotResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            value,
+            PotResponse {
+                name: "house".to_string(),
+                amount: Uint128::from(150u128),
+                unlock_height: 2000,
+                penalty: None,
+                created_at_height: 1000,
+            }
+        );
+
+        let msg = HandleMsg::Withdraw {
+            name: "house".to_string(),
+        };
+        let env = mock_env_height(SAVER, &[], 1999);
+        assert_generic_err(
+            handle(&mut deps, env, msg.clone()),
+            "Pot is locked until height 2000",
+        );
+
+        let env = mock_env_height(SAVER, &[], 2000);
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_eq!(res.messages, vec![send(SAVER, 150)]);
+        assert_eq!(
+            stats(&deps),
+            StatsResponse {
+                denom: DENOM.to_string(),
+                pot_count: 0,
+                total_locked: Uint128::zero(),
+                total_penalties: Uint128::zero(),
+            }
+        );
+
+        let env = mock_env_height(SAVER, &[], 2000);
+        assert_generic_err(handle(&mut deps, env, msg), "Pot does not exist");
+    }
+
+    #[test]
+    fn early_withdrawal_pays_penalty() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env_height(SAVER, &coins(1000, DENOM), 1000);
+        handle(&mut deps, env, create_pot_msg("car", 2000, Some(10))).unwrap();
+        let env = mock_env_height(SAVER, &coins(300, DENOM), 1000);
+        handle(&mut deps, env, create_pot_msg("bike", 1500, Some(5))).unwrap();
+
+        let msg = HandleMsg::Withdraw {
+            name: "car".to_string(),
+        };
+        let res = handle(&mut deps, mock_env_height(SAVER, &[], 1200), msg).unwrap();
+        assert_eq!(res.messages, vec![send(BENEFICIARY, 100), send(SAVER, 900)]);
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "withdraw"),
+                log("name", "car"),
+                log("amount", 900),
+                log("penalty", 100),
+            ]
+        );
+
+        // no penalty once unlocked
+        let msg = HandleMsg::Withdraw {
+            name: "bike".to_string(),
+        };
+        let res = handle(&mut deps, mock_env_height(SAVER, &[], 1500), msg).unwrap();
+        assert_eq!(res.messages, vec![send(SAVER, 300)]);
+
+        let value = stats(&deps);
+        assert_eq!(value.pot_count, 0);
+        assert_eq!(value.total_penalties, Uint128::from(100u128));
+    }
+
+    #[test]
+    fn create_pot_validation() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env_height(SAVER, &coins(100, "uatom"), 1000);
+        assert_generic_err(
+            handle(&mut deps, env, create_pot_msg("house", 2000, None)),
+            "Send ucosm and no other coins",
+        );
+        let env = mock_env_height(SAVER, &[coin(100, DENOM), coin(1, "uatom")], 1000);
+        assert_generic_err(
+            handle(&mut deps, env, create_pot_msg("house", 2000, None)),
+            "Send ucosm and no other coins",
+        );
+
+        let env = mock_env_height(SAVER, &coins(100, DENOM), 1000);
+        assert_generic_err(
+            handle(&mut deps, env.clone(), create_pot_msg("house", 1000, None)),
+            "Unlock height must be in the future",
+        );
+        assert_generic_err(
+            handle(
+                &mut deps,
+                env.clone(),
+                create_pot_msg("house", 2000, Some(0)),
+            ),
+            "Penalty percentage must be 1 to 100",
+        );
+        assert_generic_err(
+            handle(&mut deps, env.clone(), create_pot_msg("h", 2000, None)),
+            "Name too short",
+        );
+
+        handle(&mut deps, env.clone(), create_pot_msg("house", 2000, None)).unwrap();
+        assert_generic_err(
+            handle(&mut deps, env, create_pot_msg("house", 3000, None)),
+            "Pot already exists",
+        );
+
+        // names are per saver
+        let env = mock_env_height("other", &coins(100, DENOM), 1000);
+        handle(&mut deps, env, create_pot_msg("house", 2000, None)).unwrap();
+        let value = stats(&deps);
+        assert_eq!(value.pot_count, 2);
+        assert_eq!(value.total_locked, Uint128::from(200u128));
+    }
+
+    #[test]
+    fn pots_in_name_order() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(SAVER, &coins(100, DENOM), 1000);
+        for name in &["ccc", "aaa", "bbb"] {
+            handle(&mut deps, env.clone(), create_pot_msg(name, 2000, None)).unwrap();
+        }
+        let env = mock_env_height("other", &coins(100, DENOM), 1000);
+        handle(&mut deps, env, create_pot_msg("abc", 2000, None)).unwrap();
+
+        let msg = QueryMsg::Pots {
+            owner: HumanAddr::from(SAVER),
+            start_after: None,
+            limit: Some(2),
+        };
+        let value: PotsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        let names: Vec<&str> = value.pots.iter().map(|pot| pot.name.as_str()).collect();
+        assert_eq!(names, vec!["aaa", "bbb"]);
+
+        let msg = QueryMsg::Pots {
+            owner: HumanAddr::from(SAVER),
+            start_after: Some("bbb".to_string()),
+            limit: None,
+        };
+        let value: PotsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        let names: Vec<&str> = value.pots.iter().map(|pot| pot.name.as_str()).collect();
+        assert_eq!(names, vec!["ccc"]);
+    }
+}
@@ -0,0 +1,525 @@
+use crate::msg::{
+    ChallengeResponse, ConfigResponse, Cw20ReceiveMsg, HandleMsg, InitMsg, ListingResponse,
+    ListingsResponse, PollStatus, QueryMsg, ReceiveMsg, TokenHandleMsg, VoteOption, VoteResponse,
+    VotingConfigResponse, VotingHandleMsg, VotingPollResponse, VotingQueryMsg,
+};
+use crate::state::{
+    challenge, challenge_read, claims, claims_read, config, config_read, listing, listing_read,
+    Challenge, Listing, State,
+};
+use cosmwasm_std::{
+    from_binary, log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
+    InitResponse, Order, Querier, QueryRequest, StdError, StdResult, Storage, Uint128, WasmMsg,
+    WasmQuery,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const MIN_NAME_LENGTH: usize = 3;
+const MAX_NAME_LENGTH: usize = 32;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    if msg.dispensation_percentage > 100 {
+        return Err(StdError::generic_err(
+            "dispensation_percentage must be 0 to 100",
+        ));
+    }
+    let state = State {
+        token: deps.api.canonical_address(&msg.token)?,
+        voting: deps.api.canonical_address(&msg.voting)?,
+        min_deposit: msg.min_deposit,
+        apply_stage_blocks: msg.apply_stage_blocks,
+        voting_period_blocks: msg.voting_period_blocks,
+        quorum_percentage: msg.quorum_percentage,
+        dispensation_percentage: msg.dispensation_percentage,
+        challenge_count: 0,
+    };
+    config(&mut deps.storage).save(&state)?;
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        HandleMsg::Receive(msg) => receive(deps, env, msg),
+        HandleMsg::Exit { name } => exit(deps, env, name),
+        HandleMsg::ResolveChallenge { name } => resolve_challenge(deps, env, name),
+        HandleMsg::ClaimReward { challenge_id } => claim_reward(deps, env, challenge_id),
+    }
+}
+
+fn receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: Cw20ReceiveMsg,
+) -> StdResult<HandleResponse> {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.token {
+        return Err(StdError::unauthorized());
+    }
+    let receive_msg: ReceiveMsg = match msg.msg {
+        Some(data) => from_binary(&data)?,
+        None => return Err(StdError::generic_err("Deposit needs a message")),
+    };
+    match receive_msg {
+        ReceiveMsg::Apply { name, data } => {
+            apply(deps, env, &state, msg.sender, msg.amount, name, data)
+        }
+        ReceiveMsg::Challenge { name, reason } => {
+            challenge_listing(deps, env, state, msg.sender, msg.amount, name, reason)
+        }
+    }
+}
+
+fn apply<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    state: &State,
+    applicant: HumanAddr,
+    deposit: Uint128,
+    name: String,
+    data: String,
+) -> StdResult<HandleResponse> {
+    validate_name(&name)?;
+    if deposit < state.min_deposit {
+        return Err(StdError::generic_err(format!(
+            "Deposit must be at least {}",
+            state.min_deposit
+        )));
+    }
+    if listing_read(&deps.storage)
+        .may_load(name.as_bytes())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Listing already exists"));
+    }
+
+    let a_listing = Listing {
+        owner: deps.api.canonical_address(&applicant)?,
+        data,
+        deposit,
+        application_expiry: env.block.height + state.apply_stage_blocks,
+        challenge_id: None,
+    };
+    listing(&mut deps.storage).save(name.as_bytes(), &a_listing)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "apply"),
+            log("name", name),
+            log("applicant", applicant),
+            log("deposit", deposit),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// Opens a poll asking the voting contract's stakers whether to remove the listing. The
+/// poll's id is the voting contract's poll count plus one, as the poll is created right after.
+fn challenge_listing<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    mut state: State,
+    challenger: HumanAddr,
+    stake: Uint128,
+    name: String,
+    reason: String,
+) -> StdResult<HandleResponse> {
+    let mut a_listing = match listing_read(&deps.storage).may_load(name.as_bytes())? {
+        Some(a_listing) => a_listing,
+        None => return Err(StdError::generic_err("Listing does not exist")),
+    };
+    if a_listing.challenge_id.is_some() {
+        return Err(StdError::generic_err("Listing is already challenged"));
+    }
+    if stake != a_listing.deposit {
+        return Err(StdError::generic_err(format!(
+            "Challenge must match the listing's deposit of {}",
+            a_listing.deposit
+        )));
+    }
+
+    let voting = deps.api.human_address(&state.voting)?;
+    let voting_config: VotingConfigResponse =
+        query_contract(deps, &voting, &VotingQueryMsg::Config {})?;
+    let poll_id = voting_config.poll_count + 1;
+
+    state.challenge_count += 1;
+    let challenge_id = state.challenge_count;
+    config(&mut deps.storage).save(&state)?;
+    let a_challenge = Challenge {
+        listing: name.clone(),
+        challenger: deps.api.canonical_address(&challenger)?,
+        stake,
+        poll_id,
+        succeeded: None,
+        reward_pool: Uint128::zero(),
+        winning_weight: Uint128::zero(),
+    };
+    challenge(&mut deps.storage).save(&challenge_id.to_be_bytes(), &a_challenge)?;
+    a_listing.challenge_id = Some(challenge_id);
+    listing(&mut deps.storage).save(name.as_bytes(), &a_listing)?;
+
+    let create_poll = VotingHandleMsg::CreatePoll {
+        quorum_percentage: state.quorum_percentage,
+        title: format!("Remove {}", name),
+        description: reason,
+        end_height: Some(env.block.height + state.voting_period_blocks),
+    };
+    let r = HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: voting,
+            msg: to_binary(&create_poll)?,
+            send: vec![],
+        })],
+        log: vec![
+            log("action", "challenge"),
+            log("name", name),
+            log("challenger", challenger),
+            log("challenge_id", challenge_id),
+            log("poll_id", poll_id),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+fn exit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+) -> StdResult<HandleResponse> {
+    let a_listing = match listing_read(&deps.storage).may_load(name.as_bytes())? {
+        Some(a_listing) => a_listing,
+        None => return Err(StdError::generic_err("Listing does not exist")),
+    };
+    if deps.api.canonical_address(&env.message.sender)? != a_listing.owner {
+        return Err(StdError::unauthorized());
+    }
+    if a_listing.challenge_id.is_some() {
+        return Err(StdError::generic_err("Listing is being challenged"));
+    }
+    listing(&mut deps.storage).remove(name.as_bytes());
+
+    let state = config_read(&deps.storage).load()?;
+    let r = HandleResponse {
+        messages: vec![transfer_msg(
+            &deps.api,
+            &state,
+            env.message.sender,
+            a_listing.deposit,
+        )?],
+        log: vec![log("action", "exit"), log("name", name)],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// The winner gets its deposit back and dispensation_percentage of the loser's. The rest is
+/// the reward pool of the voters on the winning side, or goes to the winner too if there are
+/// none.
+fn resolve_challenge<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    name: String,
+) -> StdResult<HandleResponse> {
+    let mut a_listing = match listing_read(&deps.storage).may_load(name.as_bytes())? {
+        Some(a_listing) => a_listing,
+        None => return Err(StdError::generic_err("Listing does not exist")),
+    };
+    let challenge_id = match a_listing.challenge_id {
+        Some(challenge_id) => challenge_id,
+        None => return Err(StdError::generic_err("Listing is not challenged")),
+    };
+    let key = challenge_id.to_be_bytes();
+    let mut a_challenge = challenge_read(&deps.storage).load(&key)?;
+
+    let state = config_read(&deps.storage).load()?;
+    let voting = deps.api.human_address(&state.voting)?;
+    let poll: VotingPollResponse = query_contract(
+        deps,
+        &voting,
+        &VotingQueryMsg::Poll {
+            poll_id: a_challenge.poll_id,
+        },
+    )?;
+    let succeeded = match poll.status {
+        PollStatus::InProgress | PollStatus::Tally => {
+            return Err(StdError::generic_err("Challenge poll has not ended"))
+        }
+        PollStatus::Passed => true,
+        _ => false,
+    };
+    // the poll's running totals, where no votes include vetoes
+    let winning_weight = if succeeded {
+        poll.yes_votes
+    } else {
+        poll.no_votes
+    };
+
+    let (winner, winner_stake, pot) = if succeeded {
+        listing(&mut deps.storage).remove(name.as_bytes());
+        (
+            a_challenge.challenger.clone(),
+            a_challenge.stake,
+            a_listing.deposit,
+        )
+    } else {
+        a_listing.challenge_id = None;
+        listing(&mut deps.storage).save(name.as_bytes(), &a_listing)?;
+        // the listing keeps its deposit
+        (a_listing.owner, Uint128::zero(), a_challenge.stake)
+    };
+    let dispensation = pot.multiply_ratio(state.dispensation_percentage as u128, 100u128);
+    let mut reward_pool = (pot - dispensation)?;
+    let mut payout = winner_stake + dispensation;
+    if winning_weight.is_zero() {
+        payout += reward_pool;
+        reward_pool = Uint128::zero();
+    }
+
+    a_challenge.succeeded = Some(succeeded);
+    a_challenge.reward_pool = reward_pool;
+    a_challenge.winning_weight = winning_weight;
+    challenge(&mut deps.storage).save(&key, &a_challenge)?;
+
+    let winner = deps.api.human_address(&winner)?;
+    let mut messages = vec![];
+    if !payout.is_zero() {
+        messages.push(transfer_msg(&deps.api, &state, winner.clone(), payout)?);
+    }
+    let r = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "resolve_challenge"),
+            log("challenge_id", challenge_id),
+            log("succeeded", succeeded),
+            log("winner", winner),
+            log("reward_pool", reward_pool),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+fn claim_reward<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    challenge_id: u64,
+) -> StdResult<HandleResponse> {
+    let a_challenge = match challenge_read(&deps.storage).may_load(&challenge_id.to_be_bytes())? {
+        Some(a_challenge) => a_challenge,
+        None => return Err(StdError::generic_err("Challenge does not exist")),
+    };
+    let succeeded = match a_challenge.succeeded {
+        Some(succeeded) => succeeded,
+        None => return Err(StdError::generic_err("Challenge is not resolved")),
+    };
+    let voter = deps.api.canonical_address(&env.message.sender)?;
+    if claims_read(&deps.storage, challenge_id)
+        .may_load(voter.as_slice())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Reward already claimed"));
+    }
+
+    let state = config_read(&deps.storage).load()?;
+    let voting = deps.api.human_address(&state.voting)?;
+    let vote: VoteResponse = query_contract(
+        deps,
+        &voting,
+        &VotingQueryMsg::Vote {
+            poll_id: a_challenge.poll_id,
+            voter: env.message.sender.clone(),
+        },
+    )?;
+    let weight = match vote.ballot {
+        Some(ballot) if voted_for_winner(ballot.vote, succeeded) => ballot.weight,
+        _ => return Err(StdError::generic_err("Voter is not on the winning side")),
+    };
+    claims(&mut deps.storage, challenge_id).save(voter.as_slice(), &true)?;
+
+    let reward = a_challenge
+        .reward_pool
+        .multiply_ratio(weight, a_challenge.winning_weight);
+    let mut messages = vec![];
+    if !reward.is_zero() {
+        messages.push(transfer_msg(
+            &deps.api,
+            &state,
+            env.message.sender.clone(),
+            reward,
+        )?);
+    }
+    let r = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "claim_reward"),
+            log("challenge_id", challenge_id),
+            log("voter", env.message.sender),
+            log("reward", reward),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// yes removes the listing, no and no_with_veto keep it
+fn voted_for_winner(vote: VoteOption, succeeded: bool) -> bool {
+    match vote {
+        VoteOption::Yes => succeeded,
+        VoteOption::No | VoteOption::NoWithVeto => !succeeded,
+        VoteOption::Abstain => false,
+    }
+}
+
+fn transfer_msg<A: Api>(
+    api: &A,
+    state: &State,
+    recipient: HumanAddr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: api.human_address(&state.token)?,
+        msg: to_binary(&TokenHandleMsg::Transfer {
+            recipient,
+            amount,
+            tx_key: None,
+        })?,
+        send: vec![],
+    }))
+}
+
+fn query_contract<S: Storage, A: Api, Q: Querier, M: Serialize, T: DeserializeOwned>(
+    deps: &Extern<S, A, Q>,
+    contract_addr: &HumanAddr,
+    msg: &M,
+) -> StdResult<T> {
+    let request = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: contract_addr.clone(),
+        msg: to_binary(msg)?,
+    });
+    deps.querier.query(&request)
+}
+
+/// validate_name returns an error if the listing name is too short or too long
+fn validate_name(name: &str) -> StdResult<()> {
+    if name.len() < MIN_NAME_LENGTH {
+        Err(StdError::generic_err("Name too short"))
+    } else if name.len() > MAX_NAME_LENGTH {
+        Err(StdError::generic_err("Name too long"))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::Listing { name } => to_binary(&query_listing(deps, name)?),
+        QueryMsg::Listings { start_after, limit } => {
+            to_binary(&query_listings(deps, start_after, limit)?)
+        }
+        QueryMsg::Challenge { challenge_id } => to_binary(&query_challenge(deps, challenge_id)?),
+    }
+}
+
+fn query_config<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ConfigResponse> {
+    let state = config_read(&deps.storage).load()?;
+    Ok(ConfigResponse {
+        token: deps.api.human_address(&state.token)?,
+        voting: deps.api.human_address(&state.voting)?,
+        min_deposit: state.min_deposit,
+        apply_stage_blocks: state.apply_stage_blocks,
+        voting_period_blocks: state.voting_period_blocks,
+        quorum_percentage: state.quorum_percentage,
+        dispensation_percentage: state.dispensation_percentage,
+        challenge_count: state.challenge_count,
+    })
+}
+
+fn query_listing<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    name: String,
+) -> StdResult<ListingResponse> {
+    match listing_read(&deps.storage).may_load(name.as_bytes())? {
+        Some(a_listing) => to_listing_response(&deps.api, name, a_listing),
+        None => Err(StdError::generic_err("Listing does not exist")),
+    }
+}
+
+fn query_listings<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListingsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|name| {
+        let mut start = name.into_bytes();
+        start.push(0);
+        start
+    });
+    let listings = listing_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, a_listing) = item?;
+            let name = String::from_utf8(key)
+                .map_err(|_| StdError::generic_err("Listing name is not UTF-8"))?;
+            to_listing_response(&deps.api, name, a_listing)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ListingsResponse { listings })
+}
+
+fn to_listing_response<A: Api>(
+    api: &A,
+    name: String,
+    a_listing: Listing,
+) -> StdResult<ListingResponse> {
+    Ok(ListingResponse {
+        name,
+        owner: api.human_address(&a_listing.owner)?,
+        data: a_listing.data,
+        deposit: a_listing.deposit,
+        application_expiry: a_listing.application_expiry,
+        challenge_id: a_listing.challenge_id,
+    })
+}
+
+fn query_challenge<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    challenge_id: u64,
+) -> StdResult<ChallengeResponse> {
+    let a_challenge = match challenge_read(&deps.storage).may_load(&challenge_id.to_be_bytes())? {
+        Some(a_challenge) => a_challenge,
+        None => return Err(StdError::generic_err("Challenge does not exist")),
+    };
+    Ok(ChallengeResponse {
+        challenge_id,
+        listing: a_challenge.listing,
+        challenger: deps.api.human_address(&a_challenge.challenger)?,
+        stake: a_challenge.stake,
+        poll_id: a_challenge.poll_id,
+        succeeded: a_challenge.succeeded,
+        reward_pool: a_challenge.reward_pool,
+        winning_weight: a_challenge.winning_weight,
+    })
+}
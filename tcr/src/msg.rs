@@ -0,0 +1,194 @@
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitMsg {
+    /// erc20 contract deposits are made in
+    pub token: HumanAddr,
+    /// Voting contract challenges are decided by
+    pub voting: HumanAddr,
+    pub min_deposit: Uint128,
+    /// Blocks an application can be challenged before it is listed
+    pub apply_stage_blocks: u64,
+    /// Blocks a challenge poll is open for
+    pub voting_period_blocks: u64,
+    pub quorum_percentage: Option<u8>,
+    /// Percentage of the loser's deposit going to the winner. Voters on the winning side split
+    /// the rest.
+    pub dispensation_percentage: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    /// Deposits sent by the token contract, carrying a ReceiveMsg
+    Receive(Cw20ReceiveMsg),
+    /// Listing owner only. Removes an unchallenged listing and returns its deposit.
+    Exit { name: String },
+    /// Anyone can settle a challenge once its poll has ended
+    ResolveChallenge { name: String },
+    /// Pays the signer's share of a resolved challenge's reward pool, if they voted on the
+    /// winning side
+    ClaimReward { challenge_id: u64 },
+}
+
+/// The `msg` of the token's Send
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Applies for `name`, 3 to 32 bytes, with a deposit of at least min_deposit
+    Apply { name: String, data: String },
+    /// Challenges a listing with a deposit matching the listing's. `reason`, 3 to 64 bytes,
+    /// becomes the description of the challenge poll.
+    Challenge { name: String, reason: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    Listing {
+        name: String,
+    },
+    /// Listings in name order
+    Listings {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    Challenge {
+        challenge_id: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub token: HumanAddr,
+    pub voting: HumanAddr,
+    pub min_deposit: Uint128,
+    pub apply_stage_blocks: u64,
+    pub voting_period_blocks: u64,
+    pub quorum_percentage: Option<u8>,
+    pub dispensation_percentage: u8,
+    pub challenge_count: u64,
+}
+
+/// A listing is in the registry once `application_expiry` has passed, unless it is being
+/// challenged
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListingResponse {
+    pub name: String,
+    pub owner: HumanAddr,
+    pub data: String,
+    pub deposit: Uint128,
+    pub application_expiry: u64,
+    /// The challenge still to be resolved, if any
+    pub challenge_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListingsResponse {
+    pub listings: Vec<ListingResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ChallengeResponse {
+    pub challenge_id: u64,
+    pub listing: String,
+    pub challenger: HumanAddr,
+    pub stake: Uint128,
+    pub poll_id: u64,
+    /// None until resolved
+    pub succeeded: Option<bool>,
+    /// Split between the winning voters by weight
+    pub reward_pool: Uint128,
+    pub winning_weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20ReceiveMsg {
+    pub sender: HumanAddr,
+    pub amount: Uint128,
+    pub msg: Option<Binary>,
+}
+
+/// The token messages the registry sends
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenHandleMsg {
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+        tx_key: Option<String>,
+    },
+}
+
+/// The voting messages the registry sends
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingHandleMsg {
+    CreatePoll {
+        quorum_percentage: Option<u8>,
+        title: String,
+        description: String,
+        end_height: Option<u64>,
+    },
+}
+
+/// The voting queries the registry makes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingQueryMsg {
+    Config {},
+    Poll { poll_id: u64 },
+    Vote { poll_id: u64, voter: HumanAddr },
+}
+
+/// Subset of the voting contract's State
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingConfigResponse {
+    pub poll_count: u64,
+}
+
+/// Unlike the messages, the voting contract's PollStatus is not renamed to snake_case
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+pub enum PollStatus {
+    InProgress,
+    Tally,
+    Passed,
+    Rejected,
+    Queued,
+    Executed,
+    Vetoed,
+    RejectedWithVeto,
+    Cancelled,
+}
+
+/// Subset of the voting contract's PollResponse
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingPollResponse {
+    pub status: PollStatus,
+    pub yes_votes: Uint128,
+    /// Vetoes are included in no_votes too
+    pub no_votes: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+    NoWithVeto,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Ballot {
+    pub vote: VoteOption,
+    pub weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteResponse {
+    pub ballot: Option<Ballot>,
+}
@@ -0,0 +1,82 @@
+use cosmwasm_std::{CanonicalAddr, Storage, Uint128};
+use cosmwasm_storage::{
+    bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
+    Singleton,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+static CONFIG_KEY: &[u8] = b"config";
+static LISTING_KEY: &[u8] = b"listings";
+static CHALLENGE_KEY: &[u8] = b"challenges";
+static CLAIM_KEY: &[u8] = b"claims";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    pub token: CanonicalAddr,
+    pub voting: CanonicalAddr,
+    pub min_deposit: Uint128,
+    pub apply_stage_blocks: u64,
+    pub voting_period_blocks: u64,
+    pub quorum_percentage: Option<u8>,
+    pub dispensation_percentage: u8,
+    pub challenge_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Listing {
+    pub owner: CanonicalAddr,
+    pub data: String,
+    pub deposit: Uint128,
+    pub application_expiry: u64,
+    pub challenge_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Challenge {
+    pub listing: String,
+    pub challenger: CanonicalAddr,
+    pub stake: Uint128,
+    pub poll_id: u64,
+    pub succeeded: Option<bool>,
+    pub reward_pool: Uint128,
+    pub winning_weight: Uint128,
+}
+
+pub fn config<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, State> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn config_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+/// Listings keyed by name
+pub fn listing<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, Listing> {
+    bucket(LISTING_KEY, storage)
+}
+
+pub fn listing_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, Listing> {
+    bucket_read(LISTING_KEY, storage)
+}
+
+/// Challenges keyed by id in big-endian
+pub fn challenge<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, Challenge> {
+    bucket(CHALLENGE_KEY, storage)
+}
+
+pub fn challenge_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, Challenge> {
+    bucket_read(CHALLENGE_KEY, storage)
+}
+
+/// Voters who claimed their reward of a challenge, keyed by voter
+pub fn claims<'a, S: Storage>(storage: &'a mut S, challenge_id: u64) -> Bucket<'a, S, bool> {
+    Bucket::multilevel(&[CLAIM_KEY, &challenge_id.to_be_bytes()], storage)
+}
+
+pub fn claims_read<'a, S: Storage>(
+    storage: &'a S,
+    challenge_id: u64,
+) -> ReadonlyBucket<'a, S, bool> {
+    ReadonlyBucket::multilevel(&[CLAIM_KEY, &challenge_id.to_be_bytes()], storage)
+}
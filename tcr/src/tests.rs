@@ -0,0 +1,480 @@
+#[cfg(test)]
+mod tests {
+    use crate::contract::{handle, init, query};
+    use crate::msg::{
+        Ballot, ChallengeResponse, Cw20ReceiveMsg, HandleMsg, InitMsg, ListingResponse,
+        ListingsResponse, PollStatus, QueryMsg, ReceiveMsg, TokenHandleMsg, VoteOption,
+        VoteResponse, VotingConfigResponse, VotingHandleMsg, VotingPollResponse, VotingQueryMsg,
+    };
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        coins, from_binary, from_slice, log, to_binary, CosmosMsg, Empty, Extern, HandleResponse,
+        HumanAddr, Querier, QuerierResult, QueryRequest, StdError, StdResult, Uint128, WasmMsg,
+        WasmQuery,
+    };
+    use cw_mock_querier::ContractQuerier;
+
+    const TOKEN: &str = "token";
+    const VOTING: &str = "voting";
+    const APPLICANT: &str = "applicant";
+    const CHALLENGER: &str = "challenger";
+    const TEST_VOTER: &str = "voter1";
+    const TEST_VOTER_2: &str = "voter2";
+
+    // Answers the queries the registry makes to VOTING
+    struct VotingQuerier {
+        base: MockQuerier,
+        poll_count: u64,
+        status: PollStatus,
+        ballots: Vec<(HumanAddr, Ballot)>,
+    }
+
+    impl VotingQuerier {
+        fn weight(&self, options: &[VoteOption]) -> Uint128 {
+            self.ballots
+                .iter()
+                .filter(|(_, ballot)| options.contains(&ballot.vote))
+                .fold(Uint128::zero(), |total, (_, ballot)| total + ballot.weight)
+        }
+    }
+
+    impl Querier for VotingQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                    if contract_addr.as_str() == VOTING =>
+                {
+                    Ok(match from_binary(msg).unwrap() {
+                        VotingQueryMsg::Config {} => to_binary(&VotingConfigResponse {
+                            poll_count: self.poll_count,
+                        }),
+                        VotingQueryMsg::Poll { .. } => to_binary(&VotingPollResponse {
+                            status: self.status,
+                            yes_votes: self.weight(&[VoteOption::Yes]),
+                            no_votes: self.weight(&[VoteOption::No, VoteOption::NoWithVeto]),
+                        }),
+                        VotingQueryMsg::Vote { voter, .. } => to_binary(&VoteResponse {
+                            ballot: self
+                                .ballots
+                                .iter()
+                                .find(|(address, _)| *address == voter)
+                                .map(|(_, ballot)| ballot.clone()),
+                        }),
+                    })
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    fn mock_init() -> Extern<MockStorage, MockApi, VotingQuerier> {
+        let mut deps = Extern {
+            storage: MockStorage::default(),
+            api: MockApi::new(20),
+            querier: VotingQuerier {
+                base: MockQuerier::new(&[]),
+                poll_count: 4,
+                status: PollStatus::InProgress,
+                ballots: vec![],
+            },
+        };
+        init_registry(&mut deps);
+        deps
+    }
+
+    fn init_registry<Q: Querier>(deps: &mut Extern<MockStorage, MockApi, Q>) {
+        let msg = InitMsg {
+            token: HumanAddr::from(TOKEN),
+            voting: HumanAddr::from(VOTING),
+            min_deposit: Uint128::from(100u128),
+            apply_stage_blocks: 100,
+            voting_period_blocks: 50,
+            quorum_percentage: Some(30),
+            dispensation_percentage: 50,
+        };
+        init(deps, mock_env("creator", &[]), msg).unwrap();
+    }
+
+    /// What the token sends after `sender` sent it `amount` with `msg`
+    fn deposit<Q: Querier>(
+        deps: &mut Extern<MockStorage, MockApi, Q>,
+        sender: &str,
+        amount: u128,
+        msg: ReceiveMsg,
+    ) -> StdResult<HandleResponse> {
+        let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: HumanAddr::from(sender),
+            amount: Uint128::from(amount),
+            msg: Some(to_binary(&msg).unwrap()),
+        });
+        handle(deps, mock_env(TOKEN, &[]), receive_msg)
+    }
+
+    fn apply_msg(name: &str) -> ReceiveMsg {
+        ReceiveMsg::Apply {
+            name: name.to_string(),
+            data: "https://example.com".to_string(),
+        }
+    }
+
+    fn challenge_msg(name: &str) -> ReceiveMsg {
+        ReceiveMsg::Challenge {
+            name: name.to_string(),
+            reason: "Not a real project".to_string(),
+        }
+    }
+
+    fn transfer(recipient: &str, amount: u128) -> CosmosMsg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: HumanAddr::from(TOKEN),
+            msg: to_binary(&TokenHandleMsg::Transfer {
+                recipient: HumanAddr::from(recipient),
+                amount: Uint128::from(amount),
+                tx_key: None,
+            })
+            .unwrap(),
+            send: vec![],
+        })
+    }
+
+    fn query_challenge<Q: Querier>(
+        deps: &Extern<MockStorage, MockApi, Q>,
+        challenge_id: u64,
+    ) -> ChallengeResponse {
+        from_binary(&query(deps, QueryMsg::Challenge { challenge_id }).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn apply_and_exit() {
+        let mut deps = mock_init();
+
+        let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: HumanAddr::from(APPLICANT),
+            amount: Uint128::from(100u128),
+            msg: Some(to_binary(&apply_msg("project")).unwrap()),
+        });
+        match handle(&mut deps, mock_env("other_token", &[]), receive_msg) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match deposit(&mut deps, APPLICANT, 99, apply_msg("project")) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Deposit must be at least 100")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let res = deposit(&mut deps, APPLICANT, 120, apply_msg("project")).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "apply"),
+                log("name", "project"),
+                log("applicant", APPLICANT),
+                log("deposit", "120"),
+            ]
+        );
+        let value: ListingResponse = from_binary(
+            &query(
+                &deps,
+                QueryMsg::Listing {
+                    name: "project".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            ListingResponse {
+                name: "project".to_string(),
+                owner: HumanAddr::from(APPLICANT),
+                data: "https://example.com".to_string(),
+                deposit: Uint128::from(120u128),
+                application_expiry: 12_345 + 100,
+                challenge_id: None,
+            }
+        );
+        match deposit(&mut deps, CHALLENGER, 100, apply_msg("project")) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Listing already exists"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let exit_msg = HandleMsg::Exit {
+            name: "project".to_string(),
+        };
+        match handle(&mut deps, mock_env(CHALLENGER, &[]), exit_msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, mock_env(APPLICANT, &[]), exit_msg).unwrap();
+        assert_eq!(res.messages, vec![transfer(APPLICANT, 120)]);
+        let res: ListingsResponse = from_binary(
+            &query(
+                &deps,
+                QueryMsg::Listings {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(res.listings.is_empty());
+    }
+
+    #[test]
+    fn successful_challenge_removes_listing_and_rewards_voters() {
+        let mut deps = mock_init();
+        deposit(&mut deps, APPLICANT, 100, apply_msg("project")).unwrap();
+
+        match deposit(&mut deps, CHALLENGER, 50, challenge_msg("project")) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Challenge must match the listing's deposit of 100")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = deposit(&mut deps, CHALLENGER, 100, challenge_msg("project")).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr::from(VOTING),
+                msg: to_binary(&VotingHandleMsg::CreatePoll {
+                    quorum_percentage: Some(30),
+                    title: "Remove project".to_string(),
+                    description: "Not a real project".to_string(),
+                    end_height: Some(12_345 + 50),
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+        assert_eq!(query_challenge(&deps, 1).poll_id, 5);
+
+        let exit_msg = HandleMsg::Exit {
+            name: "project".to_string(),
+        };
+        match handle(&mut deps, mock_env(APPLICANT, &[]), exit_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Listing is being challenged")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let resolve_msg = HandleMsg::ResolveChallenge {
+            name: "project".to_string(),
+        };
+        match handle(&mut deps, mock_env("anyone", &[]), resolve_msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Challenge poll has not ended")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        deps.querier.status = PollStatus::Passed;
+        deps.querier.ballots = vec![
+            (
+                HumanAddr::from(TEST_VOTER),
+                Ballot {
+                    vote: VoteOption::Yes,
+                    weight: Uint128::from(200u128),
+                },
+            ),
+            (
+                HumanAddr::from(TEST_VOTER_2),
+                Ballot {
+                    vote: VoteOption::Yes,
+                    weight: Uint128::from(100u128),
+                },
+            ),
+            (
+                HumanAddr::from("voter3"),
+                Ballot {
+                    vote: VoteOption::No,
+                    weight: Uint128::from(100u128),
+                },
+            ),
+        ];
+        let res = handle(&mut deps, mock_env("anyone", &[]), resolve_msg).unwrap();
+        // the challenger's stake and half of the listing's deposit
+        assert_eq!(res.messages, vec![transfer(CHALLENGER, 150)]);
+        let value = query_challenge(&deps, 1);
+        assert_eq!(value.succeeded, Some(true));
+        assert_eq!(value.reward_pool, Uint128::from(50u128));
+        assert_eq!(value.winning_weight, Uint128::from(300u128));
+        match query(
+            &deps,
+            QueryMsg::Listing {
+                name: "project".to_string(),
+            },
+        ) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Listing does not exist"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let claim_msg = HandleMsg::ClaimReward { challenge_id: 1 };
+        let res = handle(&mut deps, mock_env(TEST_VOTER, &[]), claim_msg.clone()).unwrap();
+        assert_eq!(res.messages, vec![transfer(TEST_VOTER, 33)]);
+        let res = handle(&mut deps, mock_env(TEST_VOTER_2, &[]), claim_msg.clone()).unwrap();
+        assert_eq!(res.messages, vec![transfer(TEST_VOTER_2, 16)]);
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), claim_msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Reward already claimed"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match handle(&mut deps, mock_env("voter3", &[]), claim_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Voter is not on the winning side")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn failed_challenge_without_winning_voters_pays_listing_owner() {
+        let mut deps = mock_init();
+        deposit(&mut deps, APPLICANT, 100, apply_msg("project")).unwrap();
+        deposit(&mut deps, CHALLENGER, 100, challenge_msg("project")).unwrap();
+
+        deps.querier.status = PollStatus::Rejected;
+        let resolve_msg = HandleMsg::ResolveChallenge {
+            name: "project".to_string(),
+        };
+        let res = handle(&mut deps, mock_env("anyone", &[]), resolve_msg).unwrap();
+        assert_eq!(res.messages, vec![transfer(APPLICANT, 100)]);
+        assert_eq!(query_challenge(&deps, 1).reward_pool, Uint128::zero());
+
+        // the listing stays, keeps its deposit and can be challenged again
+        let value: ListingResponse = from_binary(
+            &query(
+                &deps,
+                QueryMsg::Listing {
+                    name: "project".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(value.deposit, Uint128::from(100u128));
+        assert_eq!(value.challenge_id, None);
+        deps.querier.poll_count = 5;
+        deposit(&mut deps, CHALLENGER, 100, challenge_msg("project")).unwrap();
+        assert_eq!(query_challenge(&deps, 2).poll_id, 6);
+    }
+
+    #[test]
+    fn challenge_decided_by_voting_contract() {
+        let voting = HumanAddr::from(VOTING);
+        let mut voting_deps = mock_dependencies(20, &coins(300, "vote"));
+        let voting_init = cw_voting::msg::InitMsg {
+            denom: "vote".to_string(),
+            proposer_requirement: None,
+            reward_denom: None,
+            rate_source: None,
+            initial_polls: None,
+            tally_executor: None,
+            weight_rounding: None,
+            bootstrap: None,
+            owner: None,
+            init_hook: None,
+            proposal_deposit: None,
+            tally_hook: None,
+            reward_token: None,
+            min_voting_period: None,
+            max_voting_period: None,
+            unbonding_period: None,
+            poll_creation_fee: None,
+            token: None,
+            extra_denoms: None,
+            restrict_poll_creation: None,
+        };
+        cw_voting::contract::init(&mut voting_deps, mock_env("creator", &[]), voting_init).unwrap();
+        let mut querier = ContractQuerier::new(MockQuerier::new(&[]));
+        querier.add_contract(voting.clone(), voting_deps, cw_voting::contract::query);
+        let mut deps = Extern {
+            storage: MockStorage::default(),
+            api: MockApi::new(20),
+            querier,
+        };
+        init_registry(&mut deps);
+
+        deposit(&mut deps, APPLICANT, 100, apply_msg("project")).unwrap();
+        let res = deposit(&mut deps, CHALLENGER, 100, challenge_msg("project")).unwrap();
+        assert_eq!(query_challenge(&deps, 1).poll_id, 1);
+
+        // deliver the registry's CreatePoll, then vote and tally in the voting contract
+        let voting_deps = deps.querier.contract_mut(&voting);
+        let create_poll = match &res.messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => from_binary(msg).unwrap(),
+            msg => panic!("Unexpected message: {:?}", msg),
+        };
+        cw_voting::contract::handle(voting_deps, mock_env(MOCK_CONTRACT_ADDR, &[]), create_poll)
+            .unwrap();
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, cw_voting::msg::VoteOption::Yes, 200),
+            (TEST_VOTER_2, cw_voting::msg::VoteOption::No, 100),
+        ] {
+            let env = mock_env(*voter, &coins(*weight, "vote"));
+            let msg = cw_voting::msg::HandleMsg::StakeVotingTokens {};
+            cw_voting::contract::handle(voting_deps, env.clone(), msg).unwrap();
+            let msg = cw_voting::msg::HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            cw_voting::contract::handle(voting_deps, env, msg).unwrap();
+        }
+        let mut env = mock_env("anyone", &[]);
+        env.block.height = 12_345 + 50;
+        let msg = cw_voting::msg::HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        cw_voting::contract::handle(voting_deps, env, msg).unwrap();
+
+        let resolve_msg = HandleMsg::ResolveChallenge {
+            name: "project".to_string(),
+        };
+        let res = handle(&mut deps, mock_env("anyone", &[]), resolve_msg).unwrap();
+        assert_eq!(res.messages, vec![transfer(CHALLENGER, 150)]);
+        assert_eq!(
+            query_challenge(&deps, 1).winning_weight,
+            Uint128::from(200u128)
+        );
+
+        let claim_msg = HandleMsg::ClaimReward { challenge_id: 1 };
+        let res = handle(&mut deps, mock_env(TEST_VOTER, &[]), claim_msg.clone()).unwrap();
+        assert_eq!(res.messages, vec![transfer(TEST_VOTER, 50)]);
+        match handle(&mut deps, mock_env(TEST_VOTER_2, &[]), claim_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Voter is not on the winning side")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn query_listings_paginates() {
+        let mut deps = mock_init();
+        for name in &["gamma", "alpha", "beta"] {
+            deposit(&mut deps, APPLICANT, 100, apply_msg(name)).unwrap();
+        }
+
+        let names = |start_after: Option<&str>, limit: Option<u32>| {
+            let msg = QueryMsg::Listings {
+                start_after: start_after.map(String::from),
+                limit,
+            };
+            let res: ListingsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+            res.listings
+                .into_iter()
+                .map(|listing| listing.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None, None), vec!["alpha", "beta", "gamma"]);
+        assert_eq!(names(None, Some(2)), vec!["alpha", "beta"]);
+        assert_eq!(names(Some("beta"), None), vec!["gamma"]);
+    }
+}
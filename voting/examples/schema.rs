@@ -2,8 +2,16 @@ use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 use std::env::current_dir;
 use std::fs::create_dir_all;
 
-use cw_voting::msg::{HandleMsg, InitMsg, PollResponse, QueryMsg, TokenStakeResponse};
-use cw_voting::state::State;
+use cw_voting::msg::{
+    BatchResponse, ClaimsResponse, FundingRoundResponse, HandleMsg, InitMsg, InvariantReport,
+    InvestmentResponse, MigrateMsg, NonVotingAddressesResponse, OwnershipResponse,
+    ParticipatedPollsResponse, PollAuditResponse, PollCreatorsResponse, PollResponse,
+    PollVoteTotalsResponse, ProposerEligibilityResponse, QueryMsg, RateSourceQueryMsg, ReceiveMsg,
+    ResultProofResponse, ResultsResponse, RewardRateResponse, RewardsResponse, StakingInfoResponse,
+    TallyHookMsg, TallyResponse, TokenHandleMsg, TokenStakeResponse, VoteBackfillResponse,
+    VoteResponse, VoterHistoryResponse, VotersResponse,
+};
+use cw_voting::state::{ContractVersion, PollArchive, State};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -13,8 +21,37 @@ fn main() {
 
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
+    export_schema(&schema_for!(BatchResponse), &out_dir);
+    export_schema(&schema_for!(ClaimsResponse), &out_dir);
+    export_schema(&schema_for!(ContractVersion), &out_dir);
+    export_schema(&schema_for!(FundingRoundResponse), &out_dir);
+    export_schema(&schema_for!(InvariantReport), &out_dir);
+    export_schema(&schema_for!(InvestmentResponse), &out_dir);
+    export_schema(&schema_for!(NonVotingAddressesResponse), &out_dir);
+    export_schema(&schema_for!(OwnershipResponse), &out_dir);
+    export_schema(&schema_for!(PollArchive), &out_dir);
+    export_schema(&schema_for!(PollAuditResponse), &out_dir);
+    export_schema(&schema_for!(PollCreatorsResponse), &out_dir);
     export_schema(&schema_for!(PollResponse), &out_dir);
+    export_schema(&schema_for!(ProposerEligibilityResponse), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(RateSourceQueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(TallyHookMsg), &out_dir);
+    export_schema(&schema_for!(TokenHandleMsg), &out_dir);
+    export_schema(&schema_for!(ResultsResponse), &out_dir);
+    export_schema(&schema_for!(StakingInfoResponse), &out_dir);
+    export_schema(&schema_for!(RewardRateResponse), &out_dir);
+    export_schema(&schema_for!(RewardsResponse), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(TallyResponse), &out_dir);
     export_schema(&schema_for!(TokenStakeResponse), &out_dir);
+    export_schema(&schema_for!(VoterHistoryResponse), &out_dir);
+    export_schema(&schema_for!(ParticipatedPollsResponse), &out_dir);
+    export_schema(&schema_for!(VoteResponse), &out_dir);
+    export_schema(&schema_for!(ResultProofResponse), &out_dir);
+    export_schema(&schema_for!(VotersResponse), &out_dir);
+    export_schema(&schema_for!(VoteBackfillResponse), &out_dir);
+    export_schema(&schema_for!(PollVoteTotalsResponse), &out_dir);
 }
@@ -1,37 +1,238 @@
-use crate::coin_helpers::assert_sent_sufficient_coin;
+use crate::merkle::{append_leaf, edge_nodes, frontier_root, leaf_hash, proof};
 use crate::msg::{
-    CreatePollResponse, HandleMsg, InitMsg, PollResponse, QueryMsg, TokenStakeResponse,
+    Allocation, Ballot, BatchResponse, ClaimsResponse, CreatePollResponse, Cw20ReceiveMsg,
+    FundingRoundResponse, HandleMsg, InitMsg, InvariantReport, InvestmentResponse, MigrateMsg,
+    NonVotingAddressesResponse, OwnershipResponse, ParticipatedPoll, ParticipatedPollsResponse,
+    PollAuditResponse, PollCreatorsResponse, PollResponse, PollResult, PollType,
+    PollVoteTotalsResponse, Project, ProjectResult, ProposerEligibilityResponse, QueryMsg,
+    RateSourceQueryMsg, ReceiveMsg, ResultProofResponse, ResultsResponse, RewardRateResponse,
+    RewardsResponse, StakingInfoResponse, TallyHookMsg, TallyResponse, TokenHandleMsg,
+    TokenStakeResponse, VoteBackfillResponse, VoteOption, VoteRecord, VoteResponse, VoteTotals,
+    VoterHistoryEntry, VoterHistoryResponse, VoterInfo, VotersResponse,
 };
+use crate::percentage;
 use crate::state::{
-    bank, bank_read, config, config_read, poll, poll_read, Poll, PollStatus, State, Voter,
+    bank, bank_read, claims, claims_read, commitments, commitments_read, config, config_read,
+    contract_version, contract_version_read, deposit_reserve, deposit_reserve_read,
+    emergency_unlock, emergency_unlock_read, funding_reserve, funding_reserve_read,
+    invariant_check, invariant_check_read, legacy_locks_read, legacy_votes_read, locks, locks_read,
+    non_voting, non_voting_read, pending_owner, pending_owner_read, poll, poll_archive,
+    poll_archive_read, poll_creators, poll_creators_read, poll_index, poll_index_read, poll_read,
+    poll_tags, poll_tags_read, poll_tally, poll_tally_read, rewards, rewards_read, stake_sizes,
+    stake_sizes_read, unbonding_reserve, unbonding_reserve_read, vote_backfill, vote_backfill_read,
+    vote_leaves, vote_leaves_read, vote_tree, vote_tree_key, vote_tree_read, voter_history,
+    voter_history_read, votes, votes_read, Claim, Commitment, ContractVersion, Duration,
+    Expiration, FundingProject, FundingRound, InvariantCheck, Poll, PollExecution, PollStatus,
+    ProposerRequirement, RewardState, State, TokenManager, VoteHistory, VoteWeightCap, Voter,
 };
 use cosmwasm_std::{
-    coin, log, to_binary, Api, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Env, Extern,
-    HandleResponse, HandleResult, HumanAddr, InitResponse, InitResult, Querier, StdError,
-    StdResult, Storage, Uint128,
+    coin, from_binary, log, to_binary, Api, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg,
+    Decimal, Env, Extern, HandleResponse, HandleResult, HumanAddr, InitResponse, InitResult,
+    LogAttribute, MigrateResponse, MigrateResult, Order, Querier, QueryRequest, StdError,
+    StdResult, Storage, Uint128, WasmMsg, WasmQuery,
 };
+use cw_math::{mul_ratio, Rounding};
+use sha2::{Digest, Sha256};
 
 pub const VOTING_TOKEN: &str = "voting_token";
+pub const CONTRACT_NAME: &str = "crates.io:cw-voting";
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const DEFAULT_END_HEIGHT_BLOCKS: &u64 = &100_800_u64;
 const MIN_STAKE_AMOUNT: u128 = 1;
 const MIN_DESC_LENGTH: usize = 3;
 const MAX_DESC_LENGTH: usize = 64;
+const MIN_TITLE_LENGTH: usize = 3;
+const MAX_TITLE_LENGTH: usize = 64;
+const MAX_LINK_LENGTH: usize = 256;
+const MAX_RATIONALE_LENGTH: usize = 256;
+const MAX_POLL_TAGS: usize = 5;
+const MAX_TAG_LENGTH: usize = 16;
+// Bytes of the description kept in the search index
+const POLL_INDEX_PREFIX_LENGTH: usize = 16;
+const MAX_FUNDING_PROJECTS: usize = 32;
+// Scale of the reward-per-staked-token index
+const REWARD_INDEX_PRECISION: u128 = 1_000_000_000_000;
+const SECONDS_PER_YEAR: u64 = 31_536_000;
+// Stakers checked per CheckInvariants message
+const DEFAULT_INVARIANT_LIMIT: u32 = 30;
+const MAX_INVARIANT_LIMIT: u32 = 100;
+// Stakers processed per EmergencyUnlockAll message
+const DEFAULT_UNLOCK_LIMIT: u32 = 30;
+const MAX_UNLOCK_LIMIT: u32 = 100;
+// Polls processed per BackfillVotes message
+const DEFAULT_BACKFILL_LIMIT: u32 = 10;
+const MAX_BACKFILL_LIMIT: u32 = 30;
+// Stakes whose locks are moved per migrate or BackfillVotes message
+const LOCK_MIGRATION_LIMIT: usize = 30;
+// Polls checked per PrunePolls message
+const DEFAULT_PRUNE_LIMIT: u32 = 10;
+const MAX_PRUNE_LIMIT: u32 = 30;
+// Votes, commitments and vote tree nodes deleted per PrunePolls message
+const PRUNE_ENTRY_BUDGET: usize = 300;
+// Blocks after its end height that an ended poll is kept before it can be pruned
+pub const POLL_RETENTION_BLOCKS: u64 = 100_800;
+// Pagination settings for list queries
+const DEFAULT_TALLY_LIMIT: u32 = 100;
+const MAX_TALLY_LIMIT: u32 = 300;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+// Polls looked at per Results query, whether they match its status or not
+const MAX_RESULTS_SCAN: u64 = 100;
+// Blocks after a poll's end_height during which only the tally executor can end it
+pub const TALLY_GRACE_PERIOD_BLOCKS: u64 = 14_400;
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     msg: InitMsg,
 ) -> InitResult {
-    let state = State {
+    validate_proposer_requirement(&msg.proposer_requirement)?;
+    validate_voting_period_bounds(msg.min_voting_period, msg.max_voting_period)?;
+    let mut state = State {
         denom: msg.denom,
-        owner: deps.api.canonical_address(&env.message.sender)?,
+        token: msg
+            .token
+            .map(|token| deps.api.canonical_address(&token))
+            .transpose()?,
+        extra_denoms: msg.extra_denoms.unwrap_or_default(),
+        owner: deps
+            .api
+            .canonical_address(msg.owner.as_ref().unwrap_or(&env.message.sender))?,
         poll_count: 0,
         staked_tokens: Uint128::zero(),
+        proposer_requirement: msg.proposer_requirement,
+        rate_source: msg
+            .rate_source
+            .map(|rate_source| deps.api.canonical_address(&rate_source))
+            .transpose()?,
+        tally_executor: msg
+            .tally_executor
+            .map(|tally_executor| deps.api.canonical_address(&tally_executor))
+            .transpose()?,
+        weight_rounding: msg.weight_rounding.unwrap_or(Rounding::Floor),
+        bootstrap: msg.bootstrap,
+        proposal_deposit: msg.proposal_deposit,
+        tally_hook: msg
+            .tally_hook
+            .map(|tally_hook| deps.api.canonical_address(&tally_hook))
+            .transpose()?,
+        min_voting_period: msg.min_voting_period,
+        max_voting_period: msg.max_voting_period,
+        min_description_length: None,
+        max_description_length: None,
+        unbonding_period: msg.unbonding_period,
+        poll_creation_fee: msg.poll_creation_fee.filter(|fee| !fee.is_zero()),
+        restrict_poll_creation: msg.restrict_poll_creation.unwrap_or_default(),
+        staker_count: 0,
+        active_polls: 0,
     };
 
+    let initial_polls = msg.initial_polls.unwrap_or_default();
+    for initial_poll in &initial_polls {
+        validate_quorum_percentage(initial_poll.quorum_percentage)?;
+        validate_quorum_percentage(initial_poll.approval_quorum_percentage)?;
+        validate_end_height(initial_poll.end_height, env.clone())?;
+        validate_description(&state, &initial_poll.description)?;
+        validate_title(&initial_poll.title)?;
+        validate_link(&initial_poll.link)?;
+    }
+
+    let reward_token = msg
+        .reward_token
+        .map(|reward_token| deps.api.canonical_address(&reward_token))
+        .transpose()?;
+    if msg.reward_denom.is_some() && reward_token.is_some() {
+        return Err(StdError::generic_err(
+            "Rewards are paid in either reward_denom or reward_token",
+        ));
+    }
+    if reward_token.is_some() && reward_token == state.token {
+        return Err(StdError::generic_err(
+            "Reward token must differ from the staking token",
+        ));
+    }
+    validate_extra_denoms(&state)?;
+    if state.token.is_some() && !state.extra_denoms.is_empty() {
+        return Err(StdError::generic_err(
+            "Extra staking denoms need a native staking denom",
+        ));
+    }
+    if let Some(reward_denom) = &msg.reward_denom {
+        if extra_denom_weight(&state, reward_denom).is_some() {
+            return Err(StdError::generic_err(
+                "Reward denom must differ from the staking denom",
+            ));
+        }
+    }
+    if state.token.is_some() && state.proposal_deposit.is_some() {
+        return Err(StdError::generic_err(
+            "Proposal deposits need a native staking denom",
+        ));
+    }
+    if msg.reward_denom.as_ref() == Some(&state.denom) {
+        return Err(StdError::generic_err(
+            "Reward denom must differ from the staking denom",
+        ));
+    }
+    if state.poll_creation_fee.is_some() && msg.reward_denom.is_none() {
+        return Err(StdError::generic_err(
+            "Poll creation fees are paid in reward_denom",
+        ));
+    }
+    if msg.reward_denom.is_some() || reward_token.is_some() {
+        rewards(&mut deps.storage).save(&RewardState {
+            denom: msg.reward_denom.unwrap_or_default(),
+            token: reward_token,
+            index: Uint128::zero(),
+            total_funded: Uint128::zero(),
+            first_funded_at: None,
+            last_funded_at: None,
+            accrued_fees: Uint128::zero(),
+        })?;
+    }
+
+    let creator = state.owner.clone();
+    for initial_poll in initial_polls {
+        state.poll_count += 1;
+        state.active_polls += 1;
+        let mut a_poll = new_poll(
+            creator.clone(),
+            initial_poll.quorum_percentage,
+            initial_poll.approval_quorum_percentage,
+            initial_poll.description,
+            initial_poll.start_height,
+            initial_poll.end_height,
+            &env,
+        );
+        a_poll.title = initial_poll.title;
+        a_poll.link = initial_poll.link;
+        let key = state.poll_count.to_string();
+        poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+        poll_index(&mut deps.storage).save(
+            &poll_index_key(&a_poll.description, state.poll_count),
+            &state.poll_count,
+        )?;
+    }
+
     config(&mut deps.storage).save(&state)?;
+    contract_version(&mut deps.storage).save(&ContractVersion {
+        contract: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION.to_string(),
+    })?;
 
-    Ok(InitResponse::default())
+    let messages = match msg.init_hook {
+        Some(init_hook) => vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: init_hook.contract_addr,
+            msg: init_hook.msg,
+            send: vec![],
+        })],
+        None => vec![],
+    };
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -39,29 +240,216 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
+    // stakes still holding their locks would lose them when saved
+    if !matches!(msg, HandleMsg::BackfillVotes { .. }) && locks_pending(&deps.storage)? {
+        return Err(StdError::generic_err(
+            "Locks are still being migrated, the owner must send BackfillVotes",
+        ));
+    }
     match msg {
         HandleMsg::StakeVotingTokens {} => stake_voting_tokens(deps, env),
-        HandleMsg::WithdrawVotingTokens { amount } => withdraw_voting_tokens(deps, env, amount),
+        HandleMsg::WithdrawVotingTokens { amount, denom } => {
+            withdraw_voting_tokens(deps, env, amount, denom)
+        }
+        HandleMsg::ClaimUnbonded {} => claim_unbonded(deps, env),
         HandleMsg::CastVote {
             poll_id,
             vote,
             weight,
-        } => cast_vote(deps, env, poll_id, vote, weight),
-        HandleMsg::EndPoll { poll_id } => end_poll(deps, env, poll_id),
+            rationale,
+        } => cast_vote(deps, env, poll_id, vote, weight, rationale),
+        HandleMsg::UpdateVote {
+            poll_id,
+            vote,
+            weight,
+            rationale,
+        } => update_vote(deps, env, poll_id, vote, weight, rationale),
+        HandleMsg::RetractVote { poll_id } => retract_vote(deps, env, poll_id),
+        HandleMsg::CommitVote {
+            poll_id,
+            commitment,
+            weight,
+        } => commit_vote(deps, env, poll_id, commitment, weight),
+        HandleMsg::RevealVote {
+            poll_id,
+            vote,
+            salt,
+        } => reveal_vote(deps, env, poll_id, vote, salt),
+        HandleMsg::EndPoll { poll_id, limit } => end_poll(deps, env, poll_id, limit),
+        HandleMsg::EditPoll {
+            poll_id,
+            description,
+        } => edit_poll(deps, env, poll_id, description),
+        HandleMsg::AddCoCreator {
+            poll_id,
+            co_creator,
+        } => set_co_creator(deps, env, poll_id, co_creator, true),
+        HandleMsg::RemoveCoCreator {
+            poll_id,
+            co_creator,
+        } => set_co_creator(deps, env, poll_id, co_creator, false),
+        HandleMsg::Execute { poll_id } => execute_poll(deps, env, poll_id),
+        HandleMsg::VetoPoll { poll_id } => veto_poll(deps, env, poll_id),
+        HandleMsg::CancelPoll { poll_id } => cancel_poll(deps, env, poll_id),
         HandleMsg::CreatePoll {
             quorum_percentage,
+            approval_quorum_percentage,
+            title,
+            link,
             description,
             start_height,
             end_height,
+            execution,
+            poll_type,
+            veto_threshold,
+            threshold_percentage,
+            reveal_blocks,
+            tags,
+            max_vote_weight,
         } => create_poll(
             deps,
             env,
             quorum_percentage,
+            approval_quorum_percentage,
+            title,
+            link,
             description,
             start_height,
             end_height,
+            execution,
+            poll_type,
+            veto_threshold,
+            threshold_percentage,
+            reveal_blocks,
+            tags.unwrap_or_default(),
+            max_vote_weight,
+        ),
+        HandleMsg::Contribute {
+            poll_id,
+            allocations,
+        } => contribute(deps, env, poll_id, allocations),
+        HandleMsg::SetProposerRequirement { requirement } => {
+            set_proposer_requirement(deps, env, requirement)
+        }
+        HandleMsg::SetProposalDeposit { amount } => set_proposal_deposit(deps, env, amount),
+        HandleMsg::SetPollCreationFee { amount } => set_poll_creation_fee(deps, env, amount),
+        HandleMsg::TransferOwnership { new_owner } => transfer_ownership(deps, env, new_owner),
+        HandleMsg::AcceptOwnership {} => accept_ownership(deps, env),
+        HandleMsg::UpdateConfig {
+            denom,
+            min_description_length,
+            max_description_length,
+            weight_rounding,
+            unbonding_period,
+            restrict_poll_creation,
+        } => update_config(
+            deps,
+            env,
+            denom,
+            min_description_length,
+            max_description_length,
+            weight_rounding,
+            unbonding_period,
+            restrict_poll_creation,
         ),
+        HandleMsg::SetVotingPeriod {
+            min_voting_period,
+            max_voting_period,
+        } => set_voting_period(deps, env, min_voting_period, max_voting_period),
+        HandleMsg::SetRateSource { rate_source } => set_rate_source(deps, env, rate_source),
+        HandleMsg::SetTallyExecutor { tally_executor } => {
+            set_tally_executor(deps, env, tally_executor)
+        }
+        HandleMsg::SetTallyHook { tally_hook } => set_tally_hook(deps, env, tally_hook),
+        HandleMsg::EndBootstrap {} => end_bootstrap(deps, env),
+        HandleMsg::AddNonVotingAddress { address } => {
+            set_non_voting_address(deps, env, address, true)
+        }
+        HandleMsg::RemoveNonVotingAddress { address } => {
+            set_non_voting_address(deps, env, address, false)
+        }
+        HandleMsg::AddPollCreator { address } => set_poll_creator(deps, env, address, true),
+        HandleMsg::RemovePollCreator { address } => set_poll_creator(deps, env, address, false),
+        HandleMsg::FundRewards {} => fund_rewards(deps, env),
+        HandleMsg::Receive(msg) => receive(deps, env, msg),
+        HandleMsg::ClaimRewards {} => claim_rewards(deps, env),
+        HandleMsg::DistributeRewards {} => distribute_rewards(deps, env),
+        HandleMsg::CheckInvariants { limit } => check_invariants(deps, env, limit),
+        HandleMsg::EmergencyUnlockAll { limit } => emergency_unlock_all(deps, env, limit),
+        HandleMsg::BackfillVotes { limit } => backfill_votes(deps, env, limit),
+        HandleMsg::PrunePolls {
+            up_to_poll_id,
+            limit,
+        } => prune_polls(deps, env, up_to_poll_id, limit),
+    }
+}
+
+/// Upgrades the stored state to this version of the contract. Contracts from before versions
+/// were stored keep locks inside stakes and votes inside their polls: the migration moves the
+/// locks of the first stakes into the lock bucket and the votes of the first polls into the vote
+/// bucket, and the owner moves the rest with BackfillVotes. Other messages are rejected until
+/// every lock has moved.
+pub fn migrate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: MigrateMsg,
+) -> MigrateResult {
+    let mut logs = vec![log("action", "migrate")];
+    match contract_version_read(&deps.storage).may_load()? {
+        Some(stored) => {
+            if stored.contract != CONTRACT_NAME {
+                return Err(StdError::generic_err(format!(
+                    "Cannot migrate from {}",
+                    stored.contract
+                )));
+            }
+            if parse_version(&stored.version)? > parse_version(CONTRACT_VERSION)? {
+                return Err(StdError::generic_err(format!(
+                    "Cannot migrate from version {} to the older {}",
+                    stored.version, CONTRACT_VERSION
+                )));
+            }
+            logs.push(log("from_version", stored.version));
+            logs.push(log("to_version", CONTRACT_VERSION));
+        }
+        None => {
+            let poll_count = config_read(&deps.storage).load()?.poll_count;
+            let mut progress = vote_backfill_read(&deps.storage)
+                .may_load()?
+                .unwrap_or_default();
+            progress.until_poll_id = progress.until_poll_id.max(poll_count);
+            progress.locks_pending = true;
+            vote_backfill(&mut deps.storage).save(&progress)?;
+            logs.push(log("to_version", CONTRACT_VERSION));
+            logs.extend(migrate_locks(&mut deps.storage)?);
+            logs.extend(backfill_poll_votes(&mut deps.storage, msg.limit)?);
+        }
     }
+    contract_version(&mut deps.storage).save(&ContractVersion {
+        contract: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION.to_string(),
+    })?;
+
+    let r = MigrateResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    };
+    Ok(r)
+}
+
+// the numeric parts of a version such as "0.2.1", ignoring any pre-release suffix
+fn parse_version(version: &str) -> StdResult<Vec<u64>> {
+    version
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| {
+            part.parse()
+                .map_err(|_| StdError::generic_err(format!("Invalid version {}", version)))
+        })
+        .collect()
 }
 
 pub fn stake_voting_tokens<S: Storage, A: Api, Q: Querier>(
@@ -69,26 +457,74 @@ pub fn stake_voting_tokens<S: Storage, A: Api, Q: Querier>(
     env: Env,
 ) -> HandleResult {
     let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
-    let key = &sender_address_raw.as_slice();
+    let state = config_read(&deps.storage).load()?;
+    if state.token.is_some() {
+        return Err(StdError::generic_err(
+            "Tokens are staked by sending the staking token",
+        ));
+    }
+
+    let mut votes = 0;
+    let mut extra_funds = vec![];
+    for sent in env
+        .message
+        .sent_funds
+        .iter()
+        .filter(|coin| !coin.amount.is_zero())
+    {
+        if sent.denom == state.denom {
+            votes = checked_add(votes, sent.amount.u128())?;
+        } else if let Some(weight) = extra_denom_weight(&state, &sent.denom) {
+            votes = checked_add(votes, checked_mul(sent.amount.u128(), weight)?)?;
+            extra_funds.push(sent.clone());
+        }
+    }
+    if votes < MIN_STAKE_AMOUNT {
+        return Err(StdError::generic_err("Insufficient funds sent"));
+    }
+
+    stake(deps, &sender_address_raw, votes, extra_funds)
+}
+
+/// stake adds `amount` votes to the staker's balance, of which `extra_funds` were paid in the
+/// extra denoms
+fn stake<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    staker: &CanonicalAddr,
+    amount: u128,
+    extra_funds: Vec<Coin>,
+) -> HandleResult {
+    let key = staker.as_slice();
 
     let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+    settle_rewards(&deps.storage, &mut token_manager)?;
 
     let mut state = config(&mut deps.storage).load()?;
 
-    assert_sent_sufficient_coin(
-        &env.message.sent_funds,
-        Some(coin(MIN_STAKE_AMOUNT, &state.denom)),
+    let balance = checked_add(token_manager.token_balance.u128(), amount)?;
+    update_stake_size(
+        &mut deps.storage,
+        &mut state,
+        staker,
+        token_manager.token_balance.u128(),
+        balance,
     )?;
-    let sent_funds = env
-        .message
-        .sent_funds
-        .iter()
-        .find(|coin| coin.denom.eq(&state.denom))
-        .unwrap();
-
-    token_manager.token_balance += sent_funds.amount;
+    token_manager.token_balance = Uint128::from(balance);
+    for funds in extra_funds {
+        match token_manager
+            .extra_balances
+            .iter_mut()
+            .find(|balance| balance.denom == funds.denom)
+        {
+            Some(balance) => {
+                balance.amount =
+                    Uint128::from(checked_add(balance.amount.u128(), funds.amount.u128())?)
+            }
+            None => token_manager.extra_balances.push(funds),
+        }
+    }
 
-    let staked_tokens = state.staked_tokens.u128() + sent_funds.amount.u128();
+    let staked_tokens = checked_add(state.staked_tokens.u128(), amount)?;
     state.staked_tokens = Uint128::from(staked_tokens);
     config(&mut deps.storage).save(&state)?;
 
@@ -97,202 +533,2317 @@ pub fn stake_voting_tokens<S: Storage, A: Api, Q: Querier>(
     Ok(HandleResponse::default())
 }
 
+// moves a staker in the index of stakes by size and keeps the staker count, as their balance
+// changes from `before` to `after`
+fn update_stake_size<S: Storage>(
+    storage: &mut S,
+    state: &mut State,
+    staker: &CanonicalAddr,
+    before: u128,
+    after: u128,
+) -> StdResult<()> {
+    if before > 0 {
+        stake_sizes(storage).remove(&stake_size_key(before, staker));
+    }
+    if after > 0 {
+        stake_sizes(storage).save(&stake_size_key(after, staker), &true)?;
+    }
+    if before == 0 && after > 0 {
+        state.staker_count += 1;
+    } else if before > 0 && after == 0 {
+        // stakers from before the count was introduced aren't in it
+        state.staker_count = state.staker_count.saturating_sub(1);
+    }
+    Ok(())
+}
+
+fn stake_size_key(balance: u128, staker: &CanonicalAddr) -> Vec<u8> {
+    let mut key = balance.to_be_bytes().to_vec();
+    key.extend_from_slice(staker.as_slice());
+    key
+}
+
+// counts a poll out of the active polls once it has ended or been cancelled
+fn close_poll<S: Storage>(storage: &mut S) -> StdResult<()> {
+    let mut state = config(storage).load()?;
+    state.active_polls = state.active_polls.saturating_sub(1);
+    config(storage).save(&state)
+}
+
 // Withdraw amount if not staked. By default all funds will be withdrawn.
 pub fn withdraw_voting_tokens<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     amount: Option<Uint128>,
+    denom: Option<String>,
 ) -> HandleResult {
     let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
     let contract_address_raw = deps.api.canonical_address(&env.contract.address)?;
     let key = sender_address_raw.as_slice();
 
     if let Some(mut token_manager) = bank_read(&deps.storage).may_load(key)? {
-        let largest_staked = locked_amount(&sender_address_raw, deps);
-        let withdraw_amount = match amount {
-            Some(amount) => Some(amount.u128()),
-            None => Some(token_manager.token_balance.u128()),
-        }
-        .unwrap();
-        if largest_staked + withdraw_amount > token_manager.token_balance.u128() {
+        let mut state = config(&mut deps.storage).load()?;
+        let largest_staked = release_ended_locks(&mut deps.storage, &sender_address_raw)?;
+        // the extra denom withdrawn and its weight, None for the staking denom
+        let extra = match denom.filter(|denom| *denom != state.denom) {
+            Some(denom) => match extra_denom_weight(&state, &denom) {
+                Some(weight) => Some((denom, weight)),
+                None => return Err(StdError::generic_err("Not a staking denom")),
+            },
+            None => None,
+        };
+        let denom_balance = match &extra {
+            Some((denom, _)) => extra_balance(&token_manager, denom),
+            None => checked_sub(
+                token_manager.token_balance.u128(),
+                extra_votes(&state, &token_manager)?,
+            )?,
+        };
+        let withdraw_amount = amount.map_or(denom_balance, |amount| amount.u128());
+        let withdraw_votes = match &extra {
+            Some((_, weight)) => checked_mul(withdraw_amount, *weight)?,
+            None => withdraw_amount,
+        };
+        let withdrawable = token_manager
+            .token_balance
+            .u128()
+            .saturating_sub(largest_staked);
+        if withdraw_amount > denom_balance || withdraw_votes > withdrawable {
             Err(StdError::generic_err(
                 "User is trying to withdraw too many tokens.",
             ))
         } else {
-            let balance = token_manager.token_balance.u128() - withdraw_amount;
+            settle_rewards(&deps.storage, &mut token_manager)?;
+            let balance = checked_sub(token_manager.token_balance.u128(), withdraw_votes)?;
             token_manager.token_balance = Uint128::from(balance);
+            if let Some((denom, _)) = &extra {
+                for balance in token_manager.extra_balances.iter_mut() {
+                    if balance.denom == *denom {
+                        balance.amount =
+                            Uint128::from(checked_sub(balance.amount.u128(), withdraw_amount)?);
+                    }
+                }
+                token_manager
+                    .extra_balances
+                    .retain(|balance| !balance.amount.is_zero());
+            }
 
             bank(&mut deps.storage).save(key, &token_manager)?;
 
-            let mut state = config(&mut deps.storage).load()?;
-            let staked_tokens = state.staked_tokens.u128() - withdraw_amount;
+            let staked_tokens = checked_sub(state.staked_tokens.u128(), withdraw_votes)?;
             state.staked_tokens = Uint128::from(staked_tokens);
+            update_stake_size(
+                &mut deps.storage,
+                &mut state,
+                &sender_address_raw,
+                balance + withdraw_votes,
+                balance,
+            )?;
             config(&mut deps.storage).save(&state)?;
 
-            send_tokens(
-                &deps.api,
-                &contract_address_raw,
-                &sender_address_raw,
-                vec![coin(withdraw_amount, &state.denom)],
-                "approve",
-            )
+            let denom = extra.map(|(denom, _)| denom);
+            match state.unbonding_period {
+                Some(unbonding_period) => {
+                    let claim = Claim {
+                        amount: Uint128::from(withdraw_amount),
+                        denom: denom.clone(),
+                        release_at: expiration_after(unbonding_period, &env),
+                    };
+                    let mut sender_claims = claims_read(&deps.storage)
+                        .may_load(key)?
+                        .unwrap_or_default();
+                    sender_claims.push(claim.clone());
+                    claims(&mut deps.storage).save(key, &sender_claims)?;
+                    if denom.is_none() {
+                        let reserve = unbonding_reserve_read(&deps.storage)
+                            .may_load()?
+                            .unwrap_or_default();
+                        unbonding_reserve(&mut deps.storage).save(&Uint128::from(checked_add(
+                            reserve.u128(),
+                            withdraw_amount,
+                        )?))?;
+                    }
+
+                    let r = HandleResponse {
+                        messages: vec![],
+                        log: vec![
+                            log("action", "unbond"),
+                            log("amount", withdraw_amount),
+                            log("denom", denom.as_ref().unwrap_or(&state.denom)),
+                            log("release_at", describe_expiration(claim.release_at)),
+                        ],
+                        data: None,
+                    };
+                    Ok(r)
+                }
+                None => match denom {
+                    Some(denom) => send_tokens(
+                        &deps.api,
+                        &contract_address_raw,
+                        &sender_address_raw,
+                        vec![coin(withdraw_amount, &denom)],
+                        "approve",
+                    ),
+                    None => send_stake(
+                        &deps.api,
+                        &state,
+                        &contract_address_raw,
+                        &sender_address_raw,
+                        withdraw_amount,
+                        "approve",
+                    ),
+                },
+            }
         }
     } else {
         Err(StdError::generic_err("Nothing staked"))
     }
 }
 
-/// validate_description returns an error if the description is invalid
-fn validate_description(description: &str) -> StdResult<()> {
-    if description.len() < MIN_DESC_LENGTH {
-        Err(StdError::generic_err("Description too short"))
-    } else if description.len() > MAX_DESC_LENGTH {
-        Err(StdError::generic_err("Description too long"))
+/// claim_unbonded sends the sender the claims whose unbonding period is over
+pub fn claim_unbonded<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let contract_address_raw = deps.api.canonical_address(&env.contract.address)?;
+    let key = sender_address_raw.as_slice();
+
+    let (released, pending): (Vec<Claim>, Vec<Claim>) = claims_read(&deps.storage)
+        .may_load(key)?
+        .unwrap_or_default()
+        .into_iter()
+        .partition(|claim| is_expired(claim.release_at, &env));
+    if released.is_empty() {
+        return Err(StdError::generic_err("Nothing to claim"));
+    }
+    if pending.is_empty() {
+        claims(&mut deps.storage).remove(key);
     } else {
-        Ok(())
+        claims(&mut deps.storage).save(key, &pending)?;
+    }
+    let mut amount = 0;
+    let mut extra_amounts: Vec<Coin> = vec![];
+    for claim in released {
+        match claim.denom {
+            Some(denom) => match extra_amounts.iter_mut().find(|c| c.denom == denom) {
+                Some(extra_amount) => {
+                    extra_amount.amount = Uint128::from(checked_add(
+                        extra_amount.amount.u128(),
+                        claim.amount.u128(),
+                    )?)
+                }
+                None => extra_amounts.push(coin(claim.amount.u128(), &denom)),
+            },
+            None => amount = checked_add(amount, claim.amount.u128())?,
+        }
+    }
+    if amount > 0 {
+        let reserve = unbonding_reserve_read(&deps.storage).load()?;
+        unbonding_reserve(&mut deps.storage)
+            .save(&Uint128::from(checked_sub(reserve.u128(), amount)?))?;
+    }
+
+    let state = config_read(&deps.storage).load()?;
+    if extra_amounts.is_empty() {
+        return send_stake(
+            &deps.api,
+            &state,
+            &contract_address_raw,
+            &sender_address_raw,
+            amount,
+            "claim_unbonded",
+        );
     }
+    // extra denoms are only staked with a native staking denom
+    let mut amounts = extra_amounts;
+    if amount > 0 {
+        amounts.insert(0, coin(amount, &state.denom));
+    }
+    send_tokens(
+        &deps.api,
+        &contract_address_raw,
+        &sender_address_raw,
+        amounts,
+        "claim_unbonded",
+    )
 }
 
-/// validate_quorum_percentage returns an error if the quorum_percentage is invalid
-/// (we require 0-100)
-fn validate_quorum_percentage(quorum_percentage: Option<u8>) -> StdResult<()> {
-    if quorum_percentage.is_some() && quorum_percentage.unwrap() > 100 {
-        Err(StdError::generic_err("quorum_percentage must be 0 to 100"))
-    } else {
-        Ok(())
+/// Votes per token of `denom` if it is one of the extra staking denoms
+fn extra_denom_weight(state: &State, denom: &str) -> Option<u128> {
+    state
+        .extra_denoms
+        .iter()
+        .find(|extra| extra.denom == denom)
+        .map(|extra| u128::from(extra.weight))
+}
+
+fn extra_balance(token_manager: &TokenManager, denom: &str) -> u128 {
+    token_manager
+        .extra_balances
+        .iter()
+        .find(|balance| balance.denom == denom)
+        .map_or(0, |balance| balance.amount.u128())
+}
+
+/// The votes a staker holds through the extra denoms
+fn extra_votes(state: &State, token_manager: &TokenManager) -> StdResult<u128> {
+    token_manager
+        .extra_balances
+        .iter()
+        .try_fold(0u128, |votes, balance| {
+            let weight = extra_denom_weight(state, &balance.denom).unwrap_or_default();
+            checked_add(votes, checked_mul(balance.amount.u128(), weight)?)
+        })
+}
+
+fn validate_extra_denoms(state: &State) -> StdResult<()> {
+    for (i, extra) in state.extra_denoms.iter().enumerate() {
+        if extra.weight == 0 {
+            return Err(StdError::generic_err("Denom weights must be positive"));
+        }
+        if extra.denom == state.denom
+            || state.extra_denoms[..i]
+                .iter()
+                .any(|other| other.denom == extra.denom)
+        {
+            return Err(StdError::generic_err("Staking denoms must be unique"));
+        }
     }
+    Ok(())
 }
 
-/// validate_end_height returns an error if the poll ends in the past
-fn validate_end_height(end_height: Option<u64>, env: Env) -> StdResult<()> {
-    if end_height.is_some() && env.block.height >= end_height.unwrap() {
-        Err(StdError::generic_err("Poll cannot end in the past"))
-    } else {
-        Ok(())
+fn expiration_after(duration: Duration, env: &Env) -> Expiration {
+    match duration {
+        Duration::Blocks(blocks) => Expiration::AtHeight(env.block.height.saturating_add(blocks)),
+        Duration::Seconds(seconds) => Expiration::AtTime(env.block.time.saturating_add(seconds)),
     }
 }
 
-/// create a new poll
-pub fn create_poll<S: Storage, A: Api, Q: Querier>(
+fn is_expired(expiration: Expiration, env: &Env) -> bool {
+    match expiration {
+        Expiration::AtHeight(height) => env.block.height >= height,
+        Expiration::AtTime(time) => env.block.time >= time,
+    }
+}
+
+fn describe_expiration(expiration: Expiration) -> String {
+    match expiration {
+        Expiration::AtHeight(height) => format!("height {}", height),
+        Expiration::AtTime(time) => format!("time {}", time),
+    }
+}
+
+/// settle_rewards credits the rewards earned by the current stake since the last settlement.
+/// It must run before every change to token_balance.
+fn settle_rewards<S: Storage>(storage: &S, token_manager: &mut TokenManager) -> StdResult<()> {
+    if let Some(reward_state) = rewards_read(storage).may_load()? {
+        let index_change =
+            checked_sub(reward_state.index.u128(), token_manager.reward_index.u128())?;
+        let earned =
+            checked_mul(token_manager.token_balance.u128(), index_change)? / REWARD_INDEX_PRECISION;
+        let pending_rewards = checked_add(token_manager.pending_rewards.u128(), earned)?;
+        token_manager.pending_rewards = Uint128::from(pending_rewards);
+        token_manager.reward_index = reward_state.index;
+    }
+    Ok(())
+}
+
+/// Distributes the sent reward coins over all currently staked tokens
+pub fn fund_rewards<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    quorum_percentage: Option<u8>,
-    description: String,
-    start_height: Option<u64>,
-    end_height: Option<u64>,
-) -> StdResult<HandleResponse> {
-    validate_quorum_percentage(quorum_percentage)?;
-    validate_end_height(end_height, env.clone())?;
-    validate_description(&description)?;
+) -> HandleResult {
+    let reward_state = match rewards_read(&deps.storage).may_load()? {
+        Some(reward_state) => reward_state,
+        None => return Err(StdError::generic_err("Rewards are not enabled")),
+    };
+    if reward_state.token.is_some() {
+        return Err(StdError::generic_err(
+            "Rewards are funded by sending the reward token",
+        ));
+    }
+    let amount = env
+        .message
+        .sent_funds
+        .iter()
+        .find(|coin| coin.denom == reward_state.denom)
+        .map(|coin| coin.amount.u128())
+        .unwrap_or_default();
+    add_rewards(deps, env, reward_state, amount, "fund_rewards")
+}
 
-    let mut state = config(&mut deps.storage).load()?;
-    let poll_count = state.poll_count;
-    let poll_id = poll_count + 1;
-    state.poll_count = poll_id;
+/// Stakes the tokens Sent by the staking token for their sender, or distributes the reward
+/// tokens Sent by the reward token over all currently staked tokens
+pub fn receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: Cw20ReceiveMsg,
+) -> HandleResult {
+    let token = deps.api.canonical_address(&env.message.sender)?;
+    let state = config_read(&deps.storage).load()?;
+    let receive_msg: Option<ReceiveMsg> = match &msg.msg {
+        Some(receive_msg) => Some(from_binary(receive_msg)?),
+        None => None,
+    };
+    let is_staking_token = state.token.as_ref() == Some(&token);
+    match receive_msg {
+        Some(ReceiveMsg::StakeVotingTokens {}) | None if is_staking_token => {
+            if msg.amount.u128() < MIN_STAKE_AMOUNT {
+                return Err(StdError::generic_err("Insufficient funds sent"));
+            }
+            let staker = deps.api.canonical_address(&msg.sender)?;
+            return stake(deps, &staker, msg.amount.u128(), vec![]);
+        }
+        Some(ReceiveMsg::StakeVotingTokens {}) => {
+            return Err(StdError::generic_err(
+                "Only the staking token can be staked",
+            ));
+        }
+        Some(ReceiveMsg::FundRewards {}) | None => {}
+    }
 
-    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
-    let new_poll = Poll {
-        creator: sender_address_raw,
-        status: PollStatus::InProgress,
-        quorum_percentage,
-        yes_votes: Uint128::zero(),
-        no_votes: Uint128::zero(),
-        voters: vec![],
-        voter_info: vec![],
-        end_height: end_height.unwrap_or(env.block.height + DEFAULT_END_HEIGHT_BLOCKS),
-        start_height,
-        description,
+    let reward_state = match rewards_read(&deps.storage).may_load()? {
+        Some(reward_state) => reward_state,
+        None => return Err(StdError::generic_err("Rewards are not enabled")),
     };
-    let key = state.poll_count.to_string();
-    poll(&mut deps.storage).save(key.as_bytes(), &new_poll)?;
+    if reward_state.token != Some(token) {
+        return Err(StdError::unauthorized());
+    }
+    add_rewards(deps, env, reward_state, msg.amount.u128(), "fund_rewards")
+}
 
-    config(&mut deps.storage).save(&state)?;
+/// Distributes the poll creation fees collected so far over all currently staked tokens
+pub fn distribute_rewards<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut reward_state = match rewards_read(&deps.storage).may_load()? {
+        Some(reward_state) => reward_state,
+        None => return Err(StdError::generic_err("Rewards are not enabled")),
+    };
+    let amount = reward_state.accrued_fees.u128();
+    if amount == 0 {
+        return Err(StdError::generic_err("No fees to distribute"));
+    }
+    reward_state.accrued_fees = Uint128::zero();
+    add_rewards(deps, env, reward_state, amount, "distribute_rewards")
+}
+
+fn add_rewards<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    mut reward_state: RewardState,
+    amount: u128,
+    action: &str,
+) -> HandleResult {
+    if amount == 0 {
+        return Err(StdError::generic_err("No reward funds sent"));
+    }
+
+    let state = config_read(&deps.storage).load()?;
+    if state.staked_tokens.is_zero() {
+        return Err(StdError::generic_err("Nothing staked"));
+    }
+
+    let index_increase = checked_mul(amount, REWARD_INDEX_PRECISION)? / state.staked_tokens.u128();
+    reward_state.index = Uint128::from(checked_add(reward_state.index.u128(), index_increase)?);
+    reward_state.total_funded =
+        Uint128::from(checked_add(reward_state.total_funded.u128(), amount)?);
+    reward_state.first_funded_at = reward_state.first_funded_at.or(Some(env.block.time));
+    reward_state.last_funded_at = Some(env.block.time);
+    rewards(&mut deps.storage).save(&reward_state)?;
 
     let r = HandleResponse {
         messages: vec![],
         log: vec![
-            log("action", "create_poll"),
-            log(
-                "creator",
-                deps.api.human_address(&new_poll.creator)?.as_str(),
-            ),
-            log("poll_id", &poll_id.to_string()),
-            log("quorum_percentage", quorum_percentage.unwrap_or(0)),
-            log("end_height", new_poll.end_height),
-            log("start_height", start_height.unwrap_or(0)),
+            log("action", action),
+            log("amount", amount),
+            log("index", reward_state.index),
         ],
-        data: Some(to_binary(&CreatePollResponse { poll_id })?),
+        data: None,
     };
     Ok(r)
 }
 
-/*
- * Ends a poll. Only the creator of a given poll can end that poll.
- */
-pub fn end_poll<S: Storage, A: Api, Q: Querier>(
+/// Sends all of the sender's rewards to them
+pub fn claim_rewards<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    poll_id: u64,
 ) -> HandleResult {
-    let key = &poll_id.to_string();
-    let mut a_poll = poll(&mut deps.storage).load(key.as_bytes())?;
-
+    let reward_state = match rewards_read(&deps.storage).may_load()? {
+        Some(reward_state) => reward_state,
+        None => return Err(StdError::generic_err("Rewards are not enabled")),
+    };
     let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
-    if a_poll.creator != sender_address_raw {
-        return Err(StdError::generic_err(
-            "User is not the creator of the poll.",
-        ));
-    }
+    let contract_address_raw = deps.api.canonical_address(&env.contract.address)?;
+    let key = sender_address_raw.as_slice();
 
-    if a_poll.status != PollStatus::InProgress {
-        return Err(StdError::generic_err("Poll is not in progress"));
+    let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+    settle_rewards(&deps.storage, &mut token_manager)?;
+    let claim_amount = token_manager.pending_rewards.u128();
+    if claim_amount == 0 {
+        return Err(StdError::generic_err("No rewards to claim"));
     }
+    token_manager.pending_rewards = Uint128::zero();
+    bank(&mut deps.storage).save(key, &token_manager)?;
 
-    if a_poll.start_height.is_some() && a_poll.start_height.unwrap() > env.block.height {
-        return Err(StdError::generic_err("Voting period has not started."));
+    if let Some(token) = reward_state.token {
+        return transfer_tokens(
+            &deps.api,
+            &token,
+            &sender_address_raw,
+            claim_amount,
+            "claim_rewards",
+        );
     }
+    send_tokens(
+        &deps.api,
+        &contract_address_raw,
+        &sender_address_raw,
+        vec![coin(claim_amount, &reward_state.denom)],
+        "claim_rewards",
+    )
+}
 
-    if a_poll.end_height > env.block.height {
-        return Err(StdError::generic_err("Voting period has not expired."));
+/// Verifies that the stakers' balances add up to staked_tokens and that tokens are only locked
+/// in existing polls that are in progress. A large number of stakers is checked over several
+/// messages; stake changes made in between can show up as a balance mismatch.
+pub fn check_invariants<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limit: Option<u32>,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
     }
+    let limit = limit
+        .unwrap_or(DEFAULT_INVARIANT_LIMIT)
+        .min(MAX_INVARIANT_LIMIT) as usize;
 
-    let mut no = 0u128;
-    let mut yes = 0u128;
+    let mut progress = invariant_check_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let start = progress.last_key.as_ref().map(|key| {
+        let mut start = key.as_slice().to_vec();
+        start.push(0);
+        start
+    });
 
-    for voter in &a_poll.voter_info {
-        if voter.vote == "yes" {
-            yes += voter.weight.u128();
-        } else {
-            no += voter.weight.u128();
+    let token_managers = bank_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (key, token_manager) in &token_managers {
+        let voter = deps
+            .api
+            .human_address(&CanonicalAddr::from(key.as_slice()))?;
+        progress.balance_sum = Uint128::from(checked_add(
+            progress.balance_sum.u128(),
+            token_manager.token_balance.u128(),
+        )?);
+        let voter_raw = CanonicalAddr::from(key.as_slice());
+        let locked_polls = locks_read(&deps.storage, &voter_raw)
+            .range(None, None, Order::Ascending)
+            .map(|item| item.map(|(poll_key, _)| poll_id_from_key(&poll_key)))
+            .collect::<StdResult<Vec<_>>>()?;
+        // locks in ended polls are left until the voter withdraws, but PrunePolls clears them
+        for poll_id in locked_polls {
+            if poll_read(&deps.storage)
+                .may_load(poll_id.to_string().as_bytes())?
+                .is_none()
+            {
+                progress.violations.push(format!(
+                    "{} has tokens locked in poll {} which does not exist",
+                    voter, poll_id
+                ));
+            }
         }
     }
-    let tallied_weight = yes + no;
-
-    let mut rejected_reason = "";
-    let mut passed = false;
-
-    if tallied_weight > 0 {
-        let state = config_read(&deps.storage).load()?;
-
-        let staked_weight = deps
-            .querier
-            .query_balance(&env.contract.address, &state.denom)
-            .unwrap()
-            .amount
-            .u128();
+    progress.accounts_checked += token_managers.len() as u64;
 
-        if staked_weight == 0 {
-            return Err(StdError::generic_err("Nothing staked"));
+    let complete = token_managers.len() < limit;
+    if complete {
+        if progress.balance_sum != state.staked_tokens {
+            progress.violations.push(format!(
+                "Staked balances add up to {} but staked_tokens is {}",
+                progress.balance_sum, state.staked_tokens
+            ));
         }
+        invariant_check(&mut deps.storage).save(&InvariantCheck::default())?;
+    } else {
+        progress.last_key = token_managers
+            .last()
+            .map(|(key, _)| Binary::from(key.as_slice()));
+        invariant_check(&mut deps.storage).save(&progress)?;
+    }
+
+    let report = InvariantReport {
+        complete,
+        accounts_checked: progress.accounts_checked,
+        violations: progress.violations,
+    };
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "check_invariants"),
+            log("complete", complete),
+            log("violations", report.violations.len()),
+        ],
+        data: Some(to_binary(&report)?),
+    };
+    Ok(r)
+}
+
+/// Cleanup tool: removes the locks stakers hold in polls that are no longer in progress, which
+/// are otherwise left until the staker next withdraws
+pub fn emergency_unlock_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limit: Option<u32>,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    let limit = limit.unwrap_or(DEFAULT_UNLOCK_LIMIT).min(MAX_UNLOCK_LIMIT) as usize;
+
+    let start = emergency_unlock_read(&deps.storage)
+        .may_load()?
+        .map(|last_key| {
+            let mut start = last_key.as_slice().to_vec();
+            start.push(0);
+            start
+        });
+    let voters = bank_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(key, _)| CanonicalAddr::from(key.as_slice())))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut unlocked = 0u64;
+    for voter in &voters {
+        let locked_polls = locks_read(&deps.storage, voter)
+            .range(None, None, Order::Ascending)
+            .map(|item| item.map(|(poll_key, _)| poll_id_from_key(&poll_key)))
+            .collect::<StdResult<Vec<_>>>()?;
+        for poll_id in locked_polls {
+            if !poll_in_progress(&deps.storage, poll_id)? {
+                unlock_tokens(deps, voter, poll_id)?;
+                unlocked += 1;
+            }
+        }
+    }
+
+    let complete = voters.len() < limit;
+    match voters.last() {
+        Some(last) if !complete => {
+            emergency_unlock(&mut deps.storage).save(&Binary::from(last.as_slice()))?
+        }
+        _ => emergency_unlock(&mut deps.storage).remove(),
+    }
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "emergency_unlock_all"),
+            log("complete", complete),
+            log("accounts", voters.len()),
+            log("unlocked", unlocked),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+pub fn backfill_votes<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limit: Option<u32>,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    let mut logs = vec![log("action", "backfill_votes")];
+    logs.extend(migrate_locks(&mut deps.storage)?);
+    logs.extend(backfill_poll_votes(&mut deps.storage, limit)?);
+    let r = HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    };
+    Ok(r)
+}
+
+pub fn prune_polls<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    up_to_poll_id: u64,
+    limit: Option<u32>,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    let limit = limit.unwrap_or(DEFAULT_PRUNE_LIMIT).min(MAX_PRUNE_LIMIT) as u64;
+    let mut archive = poll_archive_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let last_poll_id = archive
+        .pruned_through
+        .saturating_add(limit)
+        .min(up_to_poll_id)
+        .min(state.poll_count);
+
+    let mut pruned = 0u64;
+    let mut budget = PRUNE_ENTRY_BUDGET;
+    for poll_id in archive.pruned_through + 1..=last_poll_id {
+        let key = poll_id.to_string();
+        if let Some(a_poll) = poll_read(&deps.storage).may_load(key.as_bytes())? {
+            let retained =
+                env.block.height < a_poll.end_height.saturating_add(POLL_RETENTION_BLOCKS);
+            match a_poll.status {
+                PollStatus::Passed | PollStatus::Rejected if !retained => {
+                    // out of budget, the next message carries on with this poll
+                    if !prune_poll(&mut deps.storage, poll_id, &a_poll, &mut budget)? {
+                        break;
+                    }
+                    pruned += 1;
+                }
+                PollStatus::Executed
+                | PollStatus::Vetoed
+                | PollStatus::RejectedWithVeto
+                | PollStatus::Cancelled => {}
+                _ => break,
+            }
+        }
+        archive.pruned_through = poll_id;
+    }
+    archive.polls_pruned += pruned;
+    poll_archive(&mut deps.storage).save(&archive)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "prune_polls"),
+            log("pruned_through", archive.pruned_through),
+            log("polls_pruned", pruned),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// deletes an ended poll along with its votes and its entries in the voters' histories, spending
+// one unit of `budget` per vote, commitment or tree node. Entries are deleted as they are
+// found, so a poll that runs out of budget is resumed from where it stopped by pruning it
+// again. Returns whether the poll itself was deleted.
+fn prune_poll<S: Storage>(
+    storage: &mut S,
+    poll_id: u64,
+    a_poll: &Poll,
+    budget: &mut usize,
+) -> StdResult<bool> {
+    let voters = votes_read(storage, poll_id)
+        .range(None, None, Order::Ascending)
+        .take(*budget)
+        .map(|item| item.map(|(key, _)| CanonicalAddr::from(key)))
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in &voters {
+        votes(storage, poll_id).remove(voter.as_slice());
+        vote_leaves(storage, poll_id).remove(voter.as_slice());
+        voter_history(storage, voter).remove(&poll_id.to_be_bytes());
+        locks(storage, voter).remove(&poll_id.to_be_bytes());
+    }
+    *budget -= voters.len();
+    let unrevealed = commitments_read(storage, poll_id)
+        .range(None, None, Order::Ascending)
+        .take(*budget)
+        .map(|item| item.map(|(key, _)| CanonicalAddr::from(key)))
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in &unrevealed {
+        commitments(storage, poll_id).remove(voter.as_slice());
+        locks(storage, voter).remove(&poll_id.to_be_bytes());
+    }
+    *budget -= unrevealed.len();
+    let tree_keys = vote_tree_read(storage, poll_id)
+        .range(None, None, Order::Ascending)
+        .take(*budget)
+        .map(|item| item.map(|(key, _)| key))
+        .collect::<StdResult<Vec<_>>>()?;
+    for key in &tree_keys {
+        vote_tree(storage, poll_id).remove(key);
+    }
+    *budget -= tree_keys.len();
+    // with none left, entries may remain
+    if *budget == 0 {
+        return Ok(false);
+    }
+    poll_tally(storage).remove(&poll_id.to_be_bytes());
+    poll_index(storage).remove(&poll_index_key(&a_poll.description, poll_id));
+    for tag in &a_poll.tags {
+        poll_tags(storage, tag).remove(&poll_id.to_be_bytes());
+    }
+    poll(storage).remove(poll_id.to_string().as_bytes());
+    Ok(true)
+}
+
+// the error for a poll that isn't stored, which says so when PrunePolls deleted it
+fn missing_poll<S: Storage>(storage: &S, poll_id: u64) -> StdError {
+    match poll_archive_read(storage).may_load() {
+        Ok(Some(archive)) if poll_id > 0 && poll_id <= archive.pruned_through => {
+            StdError::generic_err(format!("Poll {} is archived", poll_id))
+        }
+        _ => StdError::generic_err("Poll does not exist"),
+    }
+}
+
+// migrates the votes of up to `limit` polls after the last one backfilled
+fn backfill_poll_votes<S: Storage>(
+    storage: &mut S,
+    limit: Option<u32>,
+) -> StdResult<Vec<LogAttribute>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_BACKFILL_LIMIT)
+        .min(MAX_BACKFILL_LIMIT) as u64;
+    let progress = vote_backfill_read(storage).may_load()?.unwrap_or_default();
+    let last_poll_id = progress
+        .last_poll_id
+        .saturating_add(limit)
+        .min(progress.until_poll_id)
+        .max(progress.last_poll_id);
+
+    let mut moved = 0u64;
+    for poll_id in progress.last_poll_id + 1..=last_poll_id {
+        let poll_moved = migrate_poll_votes(storage, poll_id)?;
+        // polls without votes are saved again too, in the current layout
+        if poll_moved == 0 {
+            let key = poll_id.to_string();
+            if let Some(a_poll) = poll_read(storage).may_load(key.as_bytes())? {
+                poll(storage).save(key.as_bytes(), &a_poll)?;
+            }
+        }
+        moved += poll_moved;
+    }
+    // migrate_poll_votes counts what it moved
+    let mut progress = vote_backfill_read(storage).load()?;
+    progress.last_poll_id = last_poll_id;
+    vote_backfill(storage).save(&progress)?;
+
+    Ok(vec![
+        log("complete", last_poll_id >= progress.until_poll_id),
+        log("last_poll_id", last_poll_id),
+        log("votes", moved),
+    ])
+}
+
+// moves the votes a poll stored inline into the vote bucket, returning how many it moved
+fn migrate_poll_votes<S: Storage>(storage: &mut S, poll_id: u64) -> StdResult<u64> {
+    let key = poll_id.to_string();
+    let legacy = match legacy_votes_read(storage).may_load(key.as_bytes())? {
+        Some(legacy) if !legacy.voters.is_empty() => legacy,
+        _ => return Ok(0),
+    };
+    if legacy.voters.len() != legacy.voter_info.len() {
+        return Err(StdError::generic_err(format!(
+            "Poll {} has mismatched voter records",
+            poll_id
+        )));
+    }
+    for (voter, info) in legacy.voters.iter().zip(legacy.voter_info.iter()) {
+        let vote = Voter {
+            vote: info.vote_option(),
+            weight: info.weight,
+            voted_at_height: 0,
+            rationale: None,
+        };
+        votes(storage, poll_id).save(voter.as_slice(), &vote)?;
+    }
+    // saving the poll again drops the inline votes. Open polls start their running totals from
+    // the votes moved.
+    let mut a_poll = poll_read(storage).load(key.as_bytes())?;
+    if a_poll.status == PollStatus::InProgress {
+        for info in &legacy.voter_info {
+            count_vote(&mut a_poll, info.vote_option(), info.weight)?;
+        }
+    }
+    poll(storage).save(key.as_bytes(), &a_poll)?;
+
+    let moved = legacy.voters.len() as u64;
+    let mut progress = vote_backfill_read(storage).may_load()?.unwrap_or_default();
+    progress.polls_migrated += 1;
+    progress.votes_migrated += moved;
+    vote_backfill(storage).save(&progress)?;
+    Ok(moved)
+}
+
+fn locks_pending<S: Storage>(storage: &S) -> StdResult<bool> {
+    let progress = vote_backfill_read(storage).may_load()?;
+    Ok(matches!(progress, Some(progress) if progress.locks_pending))
+}
+
+// moves the locks that the next LOCK_MIGRATION_LIMIT stakes after the last one migrated stored
+// inline into the lock bucket. Saving the stake again drops them. Locks in polls that have ended
+// are released on withdraw.
+fn migrate_locks<S: Storage>(storage: &mut S) -> StdResult<Vec<LogAttribute>> {
+    let mut progress = vote_backfill_read(storage).may_load()?.unwrap_or_default();
+    if !progress.locks_pending {
+        return Ok(vec![log("locks", 0), log("locks_complete", true)]);
+    }
+    let start = progress.last_staker.as_ref().map(|staker| {
+        let mut start = staker.as_slice().to_vec();
+        start.push(0);
+        start
+    });
+    let stakes = legacy_locks_read(storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(LOCK_MIGRATION_LIMIT)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut moved = 0u64;
+    for (key, legacy) in &stakes {
+        let voter = CanonicalAddr::from(key.as_slice());
+        for (poll_id, weight) in &legacy.locked_tokens {
+            locks(storage, &voter).save(&poll_id.to_be_bytes(), weight)?;
+            moved += 1;
+        }
+        let token_manager = bank_read(storage).load(voter.as_slice())?;
+        bank(storage).save(voter.as_slice(), &token_manager)?;
+    }
+    progress.locks_migrated += moved;
+    progress.last_staker = stakes
+        .last()
+        .map(|(key, _)| CanonicalAddr::from(key.as_slice()));
+    // a short page means no stakes are left
+    if stakes.len() < LOCK_MIGRATION_LIMIT {
+        progress.locks_pending = false;
+        progress.last_staker = None;
+    }
+    vote_backfill(storage).save(&progress)?;
+
+    Ok(vec![
+        log("locks", moved),
+        log("locks_complete", !progress.locks_pending),
+    ])
+}
+
+fn description_length_bounds(state: &State) -> (usize, usize) {
+    (
+        state
+            .min_description_length
+            .map_or(MIN_DESC_LENGTH, |length| length as usize),
+        state
+            .max_description_length
+            .map_or(MAX_DESC_LENGTH, |length| length as usize),
+    )
+}
+
+/// validate_description returns an error if the description is invalid
+fn validate_description(state: &State, description: &str) -> StdResult<()> {
+    let (min_length, max_length) = description_length_bounds(state);
+    if description.len() < min_length {
+        Err(StdError::generic_err("Description too short"))
+    } else if description.len() > max_length {
+        Err(StdError::generic_err("Description too long"))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_title returns an error if the title is too short or too long
+fn validate_title(title: &str) -> StdResult<()> {
+    if title.len() < MIN_TITLE_LENGTH {
+        Err(StdError::generic_err("Title too short"))
+    } else if title.len() > MAX_TITLE_LENGTH {
+        Err(StdError::generic_err("Title too long"))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_link returns an error unless the link is a http(s) URL of up to MAX_LINK_LENGTH bytes
+fn validate_link(link: &Option<String>) -> StdResult<()> {
+    match link {
+        Some(link) if link.len() > MAX_LINK_LENGTH => Err(StdError::generic_err("Link too long")),
+        Some(link) if !link.starts_with("https://") && !link.starts_with("http://") => {
+            Err(StdError::generic_err("Link must be an http(s) URL"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_vote_weight_cap(max_vote_weight: Option<VoteWeightCap>) -> StdResult<()> {
+    match max_vote_weight {
+        Some(VoteWeightCap::Absolute(cap)) if cap.is_zero() => {
+            Err(StdError::generic_err("max_vote_weight must be positive"))
+        }
+        Some(VoteWeightCap::Percentage(cap)) if cap.is_zero() || cap > percentage::hundred() => {
+            Err(StdError::generic_err(
+                "max_vote_weight percentage must be above 0 and at most 100",
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// check_vote_weight rejects votes weighing more than the poll's max_vote_weight
+fn check_vote_weight(a_poll: &Poll, state: &State, weight: Uint128) -> StdResult<()> {
+    match a_poll.max_vote_weight {
+        Some(VoteWeightCap::Absolute(cap)) if weight > cap => Err(StdError::generic_err(format!(
+            "Vote weight exceeds the poll's cap of {}",
+            cap
+        ))),
+        Some(VoteWeightCap::Percentage(cap))
+            if !state.staked_tokens.is_zero()
+                && percentage::of(weight.u128(), state.staked_tokens.u128())? > cap =>
+        {
+            Err(StdError::generic_err(format!(
+                "Vote weight exceeds the poll's cap of {}% of the total stake",
+                cap
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_tags(tags: &[String]) -> StdResult<()> {
+    if tags.len() > MAX_POLL_TAGS {
+        return Err(StdError::generic_err(format!(
+            "A poll can have at most {} tags",
+            MAX_POLL_TAGS
+        )));
+    }
+    for (index, tag) in tags.iter().enumerate() {
+        let valid = !tag.is_empty()
+            && tag.len() <= MAX_TAG_LENGTH
+            && tag
+                .bytes()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-');
+        if !valid {
+            return Err(StdError::generic_err(format!(
+                "Tags must be 1 to {} lowercase letters, digits or dashes",
+                MAX_TAG_LENGTH
+            )));
+        }
+        if tags[..index].contains(tag) {
+            return Err(StdError::generic_err("Duplicate tag"));
+        }
+    }
+    Ok(())
+}
+
+/// validate_quorum_percentage returns an error if the quorum_percentage is invalid
+/// (we require 0-100)
+fn validate_quorum_percentage(quorum_percentage: Option<Decimal>) -> StdResult<()> {
+    if quorum_percentage.is_some() && quorum_percentage.unwrap() > percentage::hundred() {
+        Err(StdError::generic_err("quorum_percentage must be 0 to 100"))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_end_height returns an error if the poll ends in the past
+fn validate_end_height(end_height: Option<u64>, env: Env) -> StdResult<()> {
+    if end_height.is_some() && env.block.height >= end_height.unwrap() {
+        Err(StdError::generic_err("Poll cannot end in the past"))
+    } else {
+        Ok(())
+    }
+}
+
+/// validate_voting_period_bounds returns an error if no voting period fits the bounds
+fn validate_voting_period_bounds(min: Option<u64>, max: Option<u64>) -> StdResult<()> {
+    match (min, max) {
+        (_, Some(0)) => Err(StdError::generic_err("max_voting_period must be positive")),
+        (Some(min), Some(max)) if min > max => Err(StdError::generic_err(
+            "min_voting_period must not exceed max_voting_period",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// validate_voting_period returns an error if a poll open for `voting_period` blocks is outside
+/// the configured bounds
+fn validate_voting_period(state: &State, voting_period: u64) -> StdResult<()> {
+    if let Some(min) = state.min_voting_period {
+        if voting_period < min {
+            return Err(StdError::generic_err(format!(
+                "Voting period must be at least {} blocks",
+                min
+            )));
+        }
+    }
+    if let Some(max) = state.max_voting_period {
+        if voting_period > max {
+            return Err(StdError::generic_err(format!(
+                "Voting period must be at most {} blocks",
+                max
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// validate_proposer_requirement returns an error if the requirement can never be met
+fn validate_proposer_requirement(requirement: &Option<ProposerRequirement>) -> StdResult<()> {
+    match requirement {
+        Some(requirement) if requirement.last_polls == 0 => {
+            Err(StdError::generic_err("last_polls must be greater than 0"))
+        }
+        Some(requirement) if requirement.min_polls > requirement.last_polls => Err(
+            StdError::generic_err("min_polls cannot be greater than last_polls"),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// proposer_participation returns how many of the last `last_polls` polls the voter took part in,
+/// and how many they need. While fewer polls exist than required, only the existing ones count.
+fn proposer_participation(
+    requirement: &ProposerRequirement,
+    poll_count: u64,
+    token_manager: &TokenManager,
+) -> (u64, u64) {
+    let window_start = poll_count.saturating_sub(requirement.last_polls);
+    let participated = token_manager
+        .participated_polls
+        .iter()
+        .filter(|poll_id| **poll_id > window_start && **poll_id <= poll_count)
+        .count() as u64;
+    let required = requirement.min_polls.min(poll_count - window_start);
+    (participated, required)
+}
+
+/// set or clear the participation requirement for poll creators. Only the owner can do this.
+pub fn set_proposer_requirement<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    requirement: Option<ProposerRequirement>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    validate_proposer_requirement(&requirement)?;
+
+    state.proposer_requirement = requirement;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_proposer_requirement"),
+            log("enabled", state.proposer_requirement.is_some()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set or clear the deposit CreatePoll must send. Only the owner can do this.
+pub fn set_proposal_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Option<Uint128>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    state.proposal_deposit = amount.filter(|amount| !amount.is_zero());
+    if state.token.is_some() && state.proposal_deposit.is_some() {
+        return Err(StdError::generic_err(
+            "Proposal deposits need a native staking denom",
+        ));
+    }
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_proposal_deposit"),
+            log("amount", state.proposal_deposit.unwrap_or_default()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set or clear the fee CreatePoll must pay. Only the owner can do this.
+pub fn set_poll_creation_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Option<Uint128>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    state.poll_creation_fee = amount.filter(|amount| !amount.is_zero());
+    let native_rewards = matches!(
+        rewards_read(&deps.storage).may_load()?,
+        Some(reward_state) if reward_state.token.is_none()
+    );
+    if state.poll_creation_fee.is_some() && !native_rewards {
+        return Err(StdError::generic_err(
+            "Poll creation fees are paid in reward_denom",
+        ));
+    }
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_poll_creation_fee"),
+            log("amount", state.poll_creation_fee.unwrap_or_default()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// transfer_ownership offers ownership to new_owner. Only the owner can do this.
+pub fn transfer_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    new_owner: HumanAddr,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    pending_owner(&mut deps.storage).save(&deps.api.canonical_address(&new_owner)?)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "transfer_ownership"),
+            log("new_owner", new_owner),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// accept_ownership makes the sender the owner if ownership was offered to it
+pub fn accept_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    if pending_owner_read(&deps.storage).may_load()? != Some(sender_address_raw.clone()) {
+        return Err(StdError::unauthorized());
+    }
+    let mut state = config(&mut deps.storage).load()?;
+    state.owner = sender_address_raw;
+    config(&mut deps.storage).save(&state)?;
+    pending_owner(&mut deps.storage).remove();
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "accept_ownership"),
+            log("owner", env.message.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// update_config changes the settings given. Only the owner can do this.
+#[allow(clippy::too_many_arguments)]
+pub fn update_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    denom: Option<String>,
+    min_description_length: Option<u32>,
+    max_description_length: Option<u32>,
+    weight_rounding: Option<Rounding>,
+    unbonding_period: Option<Duration>,
+    restrict_poll_creation: Option<bool>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut logs = vec![log("action", "config_updated")];
+    if let Some(denom) = denom {
+        // deposits, matching pools and unbonding stake are held in the staking denom too
+        let unbonding_reserve = unbonding_reserve_read(&deps.storage)
+            .may_load()?
+            .unwrap_or_default();
+        let deposit_reserve = deposit_reserve_read(&deps.storage)
+            .may_load()?
+            .unwrap_or_default();
+        let funding_reserve = funding_reserve_read(&deps.storage)
+            .may_load()?
+            .unwrap_or_default();
+        if !state.staked_tokens.is_zero()
+            || !deposit_reserve.is_zero()
+            || !funding_reserve.is_zero()
+            || !unbonding_reserve.is_zero()
+        {
+            return Err(StdError::generic_err(
+                "Denom can only change while nothing is staked or held for polls",
+            ));
+        }
+        if let Some(reward_state) = rewards_read(&deps.storage).may_load()? {
+            if reward_state.denom == denom {
+                return Err(StdError::generic_err(
+                    "Reward denom must differ from the staking denom",
+                ));
+            }
+        }
+        if extra_denom_weight(&state, &denom).is_some() {
+            return Err(StdError::generic_err("Staking denoms must be unique"));
+        }
+        logs.push(log("denom", &denom));
+        state.denom = denom;
+    }
+    if let Some(length) = min_description_length {
+        state.min_description_length = Some(length);
+        logs.push(log("min_description_length", length));
+    }
+    if let Some(length) = max_description_length {
+        state.max_description_length = Some(length);
+        logs.push(log("max_description_length", length));
+    }
+    let (min_length, max_length) = description_length_bounds(&state);
+    if min_length > max_length {
+        return Err(StdError::generic_err(
+            "min_description_length must not exceed max_description_length",
+        ));
+    }
+    if let Some(weight_rounding) = weight_rounding {
+        state.weight_rounding = weight_rounding;
+        logs.push(log(
+            "weight_rounding",
+            format!("{:?}", weight_rounding).to_lowercase(),
+        ));
+    }
+    if let Some(unbonding_period) = unbonding_period {
+        state.unbonding_period = Some(unbonding_period);
+        let unbonding_period = match unbonding_period {
+            Duration::Blocks(blocks) => format!("{} blocks", blocks),
+            Duration::Seconds(seconds) => format!("{} seconds", seconds),
+        };
+        logs.push(log("unbonding_period", unbonding_period));
+    }
+    if let Some(restrict_poll_creation) = restrict_poll_creation {
+        state.restrict_poll_creation = restrict_poll_creation;
+        logs.push(log("restrict_poll_creation", restrict_poll_creation));
+    }
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set_voting_period sets the bounds on how long new polls are open for. Only the owner can do this.
+pub fn set_voting_period<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    min_voting_period: Option<u64>,
+    max_voting_period: Option<u64>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    validate_voting_period_bounds(min_voting_period, max_voting_period)?;
+
+    state.min_voting_period = min_voting_period;
+    state.max_voting_period = max_voting_period;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_voting_period"),
+            log("min_voting_period", min_voting_period.unwrap_or_default()),
+            log(
+                "max_voting_period",
+                max_voting_period.map_or_else(|| "none".to_string(), |max| max.to_string()),
+            ),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set or clear the contract votes are converted to underlying tokens with. Only the owner can do this.
+pub fn set_rate_source<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rate_source: Option<HumanAddr>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    state.rate_source = rate_source
+        .map(|rate_source| deps.api.canonical_address(&rate_source))
+        .transpose()?;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_rate_source"),
+            log("enabled", state.rate_source.is_some()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set or clear the address that gets to end polls first. Only the owner can do this.
+pub fn set_tally_executor<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    tally_executor: Option<HumanAddr>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    state.tally_executor = tally_executor
+        .map(|tally_executor| deps.api.canonical_address(&tally_executor))
+        .transpose()?;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_tally_executor"),
+            log("enabled", state.tally_executor.is_some()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set or clear the contract told about the voters of tallied polls. Only the owner can do this.
+pub fn set_tally_hook<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    tally_hook: Option<HumanAddr>,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    state.tally_hook = tally_hook
+        .map(|tally_hook| deps.api.canonical_address(&tally_hook))
+        .transpose()?;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "set_tally_hook"),
+            log("enabled", state.tally_hook.is_some()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// tally_hook_msg tells the tally hook, if one is set, which voters were counted
+fn tally_hook_msg<A: Api>(
+    api: &A,
+    state: &State,
+    poll_id: u64,
+    voters: &[CanonicalAddr],
+) -> StdResult<Option<CosmosMsg>> {
+    let tally_hook = match &state.tally_hook {
+        Some(tally_hook) if !voters.is_empty() => tally_hook,
+        _ => return Ok(None),
+    };
+    let msg = TallyHookMsg::PollTallied {
+        poll_id,
+        voters: voters
+            .iter()
+            .map(|voter| api.human_address(voter))
+            .collect::<StdResult<Vec<HumanAddr>>>()?,
+    };
+    Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: api.human_address(tally_hook)?,
+        msg: to_binary(&msg)?,
+        send: vec![],
+    })))
+}
+
+/// set_non_voting_address adds or removes a non-voting address. Only the owner can do this.
+pub fn set_non_voting_address<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+    add: bool,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let address_raw = deps.api.canonical_address(&address)?;
+    let is_non_voting = non_voting_read(&deps.storage)
+        .may_load(address_raw.as_slice())?
+        .is_some();
+    let action = if add {
+        if is_non_voting {
+            return Err(StdError::generic_err("Address is already non-voting"));
+        }
+        non_voting(&mut deps.storage).save(address_raw.as_slice(), &true)?;
+        "add_non_voting_address"
+    } else {
+        if !is_non_voting {
+            return Err(StdError::generic_err("Address is not non-voting"));
+        }
+        non_voting(&mut deps.storage).remove(address_raw.as_slice());
+        "remove_non_voting_address"
+    };
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", action), log("address", address.as_str())],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set_poll_creator adds or removes an address on the poll creator allowlist. Only the owner can
+/// do this.
+pub fn set_poll_creator<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+    add: bool,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+
+    let address_raw = deps.api.canonical_address(&address)?;
+    let is_creator = poll_creators_read(&deps.storage)
+        .may_load(address_raw.as_slice())?
+        .is_some();
+    let action = if add {
+        if is_creator {
+            return Err(StdError::generic_err("Address is already a poll creator"));
+        }
+        poll_creators(&mut deps.storage).save(address_raw.as_slice(), &true)?;
+        "add_poll_creator"
+    } else {
+        if !is_creator {
+            return Err(StdError::generic_err("Address is not a poll creator"));
+        }
+        poll_creators(&mut deps.storage).remove(address_raw.as_slice());
+        "remove_poll_creator"
+    };
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", action), log("address", address.as_str())],
+        data: None,
+    };
+    Ok(r)
+}
+
+// total stake of the non-voting addresses
+fn excluded_stake<S: Storage>(storage: &S) -> StdResult<u128> {
+    let bank = bank_read(storage);
+    let mut total = 0u128;
+    for item in non_voting_read(storage).range(None, None, Order::Ascending) {
+        let (key, _) = item?;
+        let token_manager = bank.may_load(&key)?.unwrap_or_default();
+        total = checked_add(total, token_manager.token_balance.u128())?;
+    }
+    Ok(total)
+}
+
+/// end_bootstrap allows anyone to create polls again. Only the owner can do this.
+pub fn end_bootstrap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state = config(&mut deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    if state.bootstrap.is_none() {
+        return Err(StdError::generic_err("Not bootstrapping"));
+    }
+
+    state.bootstrap = None;
+    config(&mut deps.storage).save(&state)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "end_bootstrap")],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// query_exchange_rate reads the current exchange rate from the rate source, if one is set
+fn query_exchange_rate<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    state: &State,
+) -> StdResult<Option<InvestmentResponse>> {
+    match &state.rate_source {
+        Some(rate_source) => {
+            let request = QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: deps.api.human_address(rate_source)?,
+                msg: to_binary(&RateSourceQueryMsg::Investment {})?,
+            });
+            Ok(Some(deps.querier.query(&request)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// to_underlying converts derivative units into underlying tokens. Like in the staking contract,
+/// units are worth one token each while no derivative tokens have been issued.
+fn to_underlying(
+    amount: u128,
+    exchange_rate: &Option<InvestmentResponse>,
+    rounding: Rounding,
+) -> StdResult<u128> {
+    match exchange_rate {
+        Some(rate) if !rate.token_supply.is_zero() => mul_ratio(
+            amount,
+            rate.staked_tokens.amount.u128(),
+            rate.token_supply.u128(),
+            rounding,
+        ),
+        _ => Ok(amount),
+    }
+}
+
+/// checked_add adds token amounts, failing instead of wrapping around
+fn checked_add(a: u128, b: u128) -> StdResult<u128> {
+    a.checked_add(b)
+        .ok_or_else(|| StdError::generic_err(format!("Overflow: {} + {}", a, b)))
+}
+
+/// checked_sub subtracts token amounts, failing with an Underflow error below zero
+fn checked_sub(a: u128, b: u128) -> StdResult<u128> {
+    a.checked_sub(b).ok_or_else(|| StdError::underflow(a, b))
+}
+
+/// checked_mul multiplies token amounts, failing instead of wrapping around
+fn checked_mul(a: u128, b: u128) -> StdResult<u128> {
+    a.checked_mul(b)
+        .ok_or_else(|| StdError::generic_err(format!("Overflow: {} * {}", a, b)))
+}
+
+/// create a new poll
+#[allow(clippy::too_many_arguments)]
+pub fn create_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    quorum_percentage: Option<Decimal>,
+    approval_quorum_percentage: Option<Decimal>,
+    title: String,
+    link: Option<String>,
+    description: String,
+    start_height: Option<u64>,
+    end_height: Option<u64>,
+    execution: Option<PollExecution>,
+    poll_type: Option<PollType>,
+    veto_threshold: Option<Decimal>,
+    threshold_percentage: Option<Decimal>,
+    reveal_blocks: Option<u64>,
+    tags: Vec<String>,
+    max_vote_weight: Option<VoteWeightCap>,
+) -> StdResult<HandleResponse> {
+    validate_quorum_percentage(quorum_percentage)?;
+    validate_quorum_percentage(approval_quorum_percentage)?;
+    if matches!(veto_threshold, Some(veto_threshold) if veto_threshold > percentage::hundred()) {
+        return Err(StdError::generic_err("veto_threshold must be 0 to 100"));
+    }
+    if matches!(threshold_percentage, Some(threshold) if threshold.is_zero() || threshold > percentage::hundred())
+    {
+        return Err(StdError::generic_err(
+            "threshold_percentage must be 1 to 100",
+        ));
+    }
+    validate_end_height(end_height, env.clone())?;
+    validate_link(&link)?;
+    if matches!(&execution, Some(execution) if execution.msgs.is_empty()) {
+        return Err(StdError::generic_err(
+            "Execution needs at least one message",
+        ));
+    }
+    if reveal_blocks == Some(0) {
+        return Err(StdError::generic_err("reveal_blocks must be positive"));
+    }
+    validate_tags(&tags)?;
+    validate_vote_weight_cap(max_vote_weight)?;
+
+    let mut state = config(&mut deps.storage).load()?;
+    validate_description(&state, &description)?;
+    validate_title(&title)?;
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+
+    // polls are open from their start height, or from creation, until their end height
+    let opens_at = start_height
+        .unwrap_or(env.block.height)
+        .max(env.block.height);
+    let closes_at = end_height.unwrap_or(env.block.height + DEFAULT_END_HEIGHT_BLOCKS);
+    validate_voting_period(&state, closes_at.saturating_sub(opens_at))?;
+
+    if let Some(bootstrap) = &state.bootstrap {
+        let ended = match bootstrap.until_height {
+            Some(until_height) => env.block.height >= until_height,
+            None => false,
+        };
+        if !ended {
+            return Err(StdError::generic_err(
+                "Poll creation is disabled while bootstrapping",
+            ));
+        }
+    }
+
+    if state.restrict_poll_creation
+        && poll_creators_read(&deps.storage)
+            .may_load(sender_address_raw.as_slice())?
+            .is_none()
+    {
+        return Err(StdError::generic_err(
+            "Only allowlisted addresses can create polls",
+        ));
+    }
+
+    if let Some(requirement) = &state.proposer_requirement {
+        let token_manager = bank_read(&deps.storage)
+            .may_load(sender_address_raw.as_slice())?
+            .unwrap_or_default();
+        let (participated, required) =
+            proposer_participation(requirement, state.poll_count, &token_manager);
+        if participated < required {
+            return Err(StdError::generic_err(format!(
+                "Proposer must have voted in {} of the last {} polls",
+                required, requirement.last_polls
+            )));
+        }
+    }
+
+    let deposit = state.proposal_deposit.unwrap_or_default().u128();
+    if sent_amount(&env, &state.denom) < deposit {
+        return Err(StdError::generic_err(format!(
+            "Proposal deposit of {}{} required",
+            deposit, state.denom
+        )));
+    }
+    if deposit > 0 {
+        let reserve = deposit_reserve_read(&deps.storage)
+            .may_load()?
+            .unwrap_or_default();
+        deposit_reserve(&mut deps.storage)
+            .save(&Uint128::from(checked_add(reserve.u128(), deposit)?))?;
+    }
+    if let Some(fee) = state.poll_creation_fee {
+        let mut reward_state = rewards_read(&deps.storage).load()?;
+        if sent_amount(&env, &reward_state.denom) < fee.u128() {
+            return Err(StdError::generic_err(format!(
+                "Poll creation fee of {}{} required",
+                fee, reward_state.denom
+            )));
+        }
+        reward_state.accrued_fees =
+            Uint128::from(checked_add(reward_state.accrued_fees.u128(), fee.u128())?);
+        rewards(&mut deps.storage).save(&reward_state)?;
+    }
+
+    let funding_round = match poll_type {
+        Some(PollType::FundingRound { projects }) => {
+            if state.token.is_some() {
+                return Err(StdError::generic_err(
+                    "Funding rounds need a native staking denom",
+                ));
+            }
+            if execution.is_some() {
+                return Err(StdError::generic_err(
+                    "Funding rounds cannot carry execution",
+                ));
+            }
+            if reveal_blocks.is_some() {
+                return Err(StdError::generic_err(
+                    "Funding rounds cannot be commit-reveal",
+                ));
+            }
+            Some(new_funding_round(
+                deps,
+                &env,
+                &state.denom,
+                deposit,
+                projects,
+            )?)
+        }
+        Some(PollType::Standard {}) | None => None,
+    };
+
+    let poll_count = state.poll_count;
+    let poll_id = poll_count + 1;
+    state.poll_count = poll_id;
+    state.active_polls += 1;
+
+    let mut new_poll = new_poll(
+        sender_address_raw,
+        quorum_percentage,
+        approval_quorum_percentage,
+        description,
+        start_height,
+        end_height,
+        &env,
+    );
+    new_poll.title = title;
+    new_poll.link = link;
+    new_poll.execution = execution;
+    new_poll.funding_round = funding_round;
+    new_poll.veto_threshold = veto_threshold;
+    new_poll.threshold_percentage = threshold_percentage;
+    new_poll.deposit = Uint128::from(deposit);
+    new_poll.reveal_end_height =
+        reveal_blocks.map(|reveal_blocks| new_poll.end_height.saturating_add(reveal_blocks));
+    new_poll.tags = tags;
+    new_poll.max_vote_weight = max_vote_weight;
+    let key = state.poll_count.to_string();
+    poll(&mut deps.storage).save(key.as_bytes(), &new_poll)?;
+    poll_index(&mut deps.storage)
+        .save(&poll_index_key(&new_poll.description, poll_id), &poll_id)?;
+    for tag in &new_poll.tags {
+        poll_tags(&mut deps.storage, tag).save(&poll_id.to_be_bytes(), &poll_id)?;
+    }
+
+    config(&mut deps.storage).save(&state)?;
+
+    let mut logs = vec![
+        log("action", "create_poll"),
+        log(
+            "creator",
+            deps.api.human_address(&new_poll.creator)?.as_str(),
+        ),
+        log("poll_id", &poll_id.to_string()),
+        log(
+            "quorum_percentage",
+            quorum_percentage.unwrap_or_else(Decimal::zero),
+        ),
+        log("end_height", new_poll.end_height),
+        log("start_height", start_height.unwrap_or(0)),
+    ];
+    if let Some(funding_round) = &new_poll.funding_round {
+        logs.push(log("matching_pool", funding_round.matching_pool));
+    }
+    if deposit > 0 {
+        logs.push(log("deposit", deposit));
+    }
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: logs,
+        data: Some(to_binary(&CreatePollResponse { poll_id })?),
+    };
+    Ok(r)
+}
+
+// amount of `denom` sent with the message
+fn sent_amount(env: &Env, denom: &str) -> u128 {
+    env.message
+        .sent_funds
+        .iter()
+        .find(|coin| coin.denom == denom)
+        .map_or(0, |coin| coin.amount.u128())
+}
+
+// release_deposit returns the poll's proposal deposit to its creator, or forfeits it to the owner
+fn release_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    state: &State,
+    a_poll: &mut Poll,
+    refund: bool,
+) -> StdResult<Option<CosmosMsg>> {
+    if a_poll.deposit.is_zero() {
+        return Ok(None);
+    }
+    let reserve = deposit_reserve_read(&deps.storage).load()?;
+    deposit_reserve(&mut deps.storage).save(&Uint128::from(checked_sub(
+        reserve.u128(),
+        a_poll.deposit.u128(),
+    )?))?;
+    let recipient = if refund {
+        &a_poll.creator
+    } else {
+        &state.owner
+    };
+    let msg = CosmosMsg::Bank(BankMsg::Send {
+        from_address: env.contract.address.clone(),
+        to_address: deps.api.human_address(recipient)?,
+        amount: vec![coin(a_poll.deposit.u128(), &state.denom)],
+    });
+    a_poll.deposit = Uint128::zero();
+    Ok(Some(msg))
+}
+
+/// new_funding_round takes the staking tokens sent with CreatePoll, less the proposal deposit, as
+/// the matching pool
+fn new_funding_round<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    denom: &str,
+    deposit: u128,
+    projects: Vec<Project>,
+) -> StdResult<FundingRound> {
+    if projects.is_empty() {
+        return Err(StdError::generic_err(
+            "Funding round needs at least one project",
+        ));
+    }
+    if projects.len() > MAX_FUNDING_PROJECTS {
+        return Err(StdError::generic_err("Too many projects"));
+    }
+    let matching_pool = sent_amount(env, denom) - deposit;
+    if matching_pool == 0 {
+        return Err(StdError::generic_err("Funding round needs a matching pool"));
+    }
+
+    let reserve = funding_reserve_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    funding_reserve(&mut deps.storage)
+        .save(&Uint128::from(checked_add(reserve.u128(), matching_pool)?))?;
+
+    Ok(FundingRound {
+        matching_pool: Uint128::from(matching_pool),
+        projects: projects
+            .into_iter()
+            .map(|project| FundingProject {
+                name: project.name,
+                recipient: project.recipient,
+                contributed: Uint128::zero(),
+                sqrt_sum: Uint128::zero(),
+                matched: Uint128::zero(),
+            })
+            .collect(),
+        contributors: vec![],
+    })
+}
+
+/// contribute allocates the sender's stake between the projects of a funding round
+pub fn contribute<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+    allocations: Vec<Allocation>,
+) -> HandleResult {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let poll_key = poll_id.to_string();
+    let mut a_poll = match poll_read(&deps.storage).may_load(poll_key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+    if a_poll.end_height <= env.block.height {
+        return Err(StdError::generic_err("Voting period has expired."));
+    }
+    if non_voting_read(&deps.storage)
+        .may_load(sender_address_raw.as_slice())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Non-voting addresses cannot vote"));
+    }
+    let funding_round = match a_poll.funding_round.as_mut() {
+        Some(funding_round) => funding_round,
+        None => return Err(StdError::generic_err("Poll is not a funding round")),
+    };
+    if funding_round.contributors.contains(&sender_address_raw) {
+        return Err(StdError::generic_err("User has already contributed."));
+    }
+
+    let mut total = 0u128;
+    let mut allocated = vec![false; funding_round.projects.len()];
+    for allocation in &allocations {
+        let index = allocation.project as usize;
+        let project = match funding_round.projects.get_mut(index) {
+            Some(project) => project,
+            None => return Err(StdError::generic_err("Project does not exist")),
+        };
+        // splitting one contribution would raise the sum of square roots
+        if allocated[index] {
+            return Err(StdError::generic_err("Project allocated more than once"));
+        }
+        allocated[index] = true;
+        let weight = allocation.weight.u128();
+        project.contributed = Uint128::from(checked_add(project.contributed.u128(), weight)?);
+        project.sqrt_sum = Uint128::from(checked_add(project.sqrt_sum.u128(), isqrt(weight))?);
+        total = checked_add(total, weight)?;
+    }
+    if total == 0 {
+        return Err(StdError::generic_err("Nothing allocated"));
+    }
+
+    let key = sender_address_raw.as_slice();
+    let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+    if token_manager.token_balance.u128() < total {
+        return Err(StdError::generic_err(
+            "User does not have enough staked tokens.",
+        ));
+    }
+    token_manager.participated_polls.push(poll_id);
+    bank(&mut deps.storage).save(key, &token_manager)?;
+    locks(&mut deps.storage, &sender_address_raw)
+        .save(&poll_id.to_be_bytes(), &Uint128::from(total))?;
+
+    funding_round.contributors.push(sender_address_raw);
+    poll(&mut deps.storage).save(poll_key.as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "contribute"),
+            log("poll_id", poll_id),
+            log("weight", total),
+            log("contributor", env.message.sender.as_str()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// integer square root, rounded down
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = n / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// poll_index_key orders the search index by description, then poll_id
+fn poll_index_key(description: &str, poll_id: u64) -> Vec<u8> {
+    let description = description.as_bytes();
+    let mut key = description[..description.len().min(POLL_INDEX_PREFIX_LENGTH)].to_vec();
+    key.extend_from_slice(&poll_id.to_be_bytes());
+    key
+}
+
+/// new_poll builds a poll that hasn't received any votes yet
+fn new_poll(
+    creator: CanonicalAddr,
+    quorum_percentage: Option<Decimal>,
+    approval_quorum_percentage: Option<Decimal>,
+    description: String,
+    start_height: Option<u64>,
+    end_height: Option<u64>,
+    env: &Env,
+) -> Poll {
+    Poll {
+        creator,
+        status: PollStatus::InProgress,
+        quorum_percentage,
+        approval_quorum_percentage,
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::zero(),
+        abstain_votes: Uint128::zero(),
+        veto_votes: Uint128::zero(),
+        veto_threshold: None,
+        threshold_percentage: None,
+        staked_weight: None,
+        end_height: end_height.unwrap_or(env.block.height + DEFAULT_END_HEIGHT_BLOCKS),
+        start_height,
+        title: String::new(),
+        link: None,
+        description,
+        execution: None,
+        executable_at: None,
+        votes_root: None,
+        co_creators: vec![],
+        funding_round: None,
+        deposit: Uint128::zero(),
+        reveal_end_height: None,
+        tags: vec![],
+        max_vote_weight: None,
+    }
+}
+
+/// edit_poll replaces the description of a poll before its voting period starts
+pub fn edit_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+    description: String,
+) -> HandleResult {
+    validate_description(&config_read(&deps.storage).load()?, &description)?;
+    let key = poll_id.to_string();
+    let mut a_poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if !is_editor(&deps.api.canonical_address(&env.message.sender)?, &a_poll) {
+        return Err(StdError::unauthorized());
+    }
+    match a_poll.start_height {
+        Some(start_height) if start_height > env.block.height => {}
+        _ => {
+            return Err(StdError::generic_err(
+                "Poll can only be edited before voting starts",
+            ))
+        }
+    }
+
+    let mut index = poll_index(&mut deps.storage);
+    index.remove(&poll_index_key(&a_poll.description, poll_id));
+    index.save(&poll_index_key(&description, poll_id), &poll_id)?;
+    a_poll.description = description;
+    poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "edit_poll"),
+            log("poll_id", poll_id),
+            log("editor", env.message.sender.as_str()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// set_co_creator adds or removes a co-creator of a poll. Only the poll's creator can do this.
+pub fn set_co_creator<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+    co_creator: HumanAddr,
+    add: bool,
+) -> HandleResult {
+    let key = poll_id.to_string();
+    let mut a_poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if deps.api.canonical_address(&env.message.sender)? != a_poll.creator {
+        return Err(StdError::unauthorized());
+    }
+    let co_creator_raw = deps.api.canonical_address(&co_creator)?;
+    let action = if add {
+        if is_editor(&co_creator_raw, &a_poll) {
+            return Err(StdError::generic_err("Address can already edit the poll"));
+        }
+        a_poll.co_creators.push(co_creator_raw);
+        "add_co_creator"
+    } else {
+        if !a_poll.co_creators.contains(&co_creator_raw) {
+            return Err(StdError::generic_err("Address is not a co-creator"));
+        }
+        a_poll.co_creators.retain(|c| *c != co_creator_raw);
+        "remove_co_creator"
+    };
+    poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", action),
+            log("poll_id", poll_id),
+            log("co_creator", co_creator.as_str()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/*
+ * Ends a poll. While a tally executor is set, only the executor and the poll's creator and
+ * co-creators can end a poll until TALLY_GRACE_PERIOD_BLOCKS after its end height; after that,
+ * or without an executor, anyone can.
+ *
+ * The result is decided from the poll's running totals, so ending a poll costs the same however
+ * many voted, and locks in an ended poll no longer hold any stake. The votes are then committed
+ * to the poll's Merkle root and told to the tally hook in batches of `limit`: the message that
+ * ends the poll commits the first batch, and further EndPoll messages commit the rest.
+ */
+pub fn end_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+    limit: Option<u32>,
+) -> HandleResult {
+    let key = &poll_id.to_string();
+    let mut a_poll = poll(&mut deps.storage).load(key.as_bytes())?;
+    let limit = limit.unwrap_or(DEFAULT_TALLY_LIMIT).min(MAX_TALLY_LIMIT) as usize;
+
+    if a_poll.status != PollStatus::InProgress {
+        // an ended poll takes EndPoll until all of its votes are committed
+        if poll_tally_read(&deps.storage)
+            .may_load(&poll_id.to_be_bytes())?
+            .is_none()
+        {
+            return Err(StdError::generic_err("Poll is not in progress"));
+        }
+        let state = config_read(&deps.storage).load()?;
+        let (messages, complete, votes_tallied) =
+            commit_votes(deps, &state, poll_id, &mut a_poll, limit)?;
+        poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+        let r = HandleResponse {
+            messages,
+            log: vec![
+                log("action", "end_poll"),
+                log("poll_id", poll_id),
+                log("complete", complete),
+                log("votes_tallied", votes_tallied),
+            ],
+            data: None,
+        };
+        return Ok(r);
+    }
+
+    if a_poll.start_height.is_some() && a_poll.start_height.unwrap() > env.block.height {
+        return Err(StdError::generic_err("Voting period has not started."));
+    }
+
+    if a_poll.end_height > env.block.height {
+        return Err(StdError::generic_err("Voting period has not expired."));
+    }
+    // commit-reveal polls close once their reveal period is over
+    let closing_height = a_poll.reveal_end_height.unwrap_or(a_poll.end_height);
+    if closing_height > env.block.height {
+        return Err(StdError::generic_err("Reveal period has not expired."));
+    }
+
+    let state = config_read(&deps.storage).load()?;
+    if let Some(tally_executor) = &state.tally_executor {
+        let sender = deps.api.canonical_address(&env.message.sender)?;
+        if env.block.height < closing_height + TALLY_GRACE_PERIOD_BLOCKS
+            && sender != *tally_executor
+            && !is_editor(&sender, &a_poll)
+        {
+            return Err(StdError::generic_err(
+                "Only the tally executor or the poll's editors can end the poll during the grace period",
+            ));
+        }
+    }
+    if let Some(funding_round) = a_poll.funding_round.take() {
+        return end_funding_round(deps, env, &state, poll_id, a_poll, funding_round);
+    }
+
+    // votes still stored inline join the running totals first
+    migrate_poll_votes(&mut deps.storage, poll_id)?;
+    let mut a_poll = poll_read(&deps.storage).load(key.as_bytes())?;
+
+    // votes are cast in staked units, which are counted at the rate at tally time. The running no
+    // votes include vetoes, which are converted on their own.
+    let exchange_rate = query_exchange_rate(deps, &state)?;
+    let yes = to_underlying(
+        a_poll.yes_votes.u128(),
+        &exchange_rate,
+        state.weight_rounding,
+    )?;
+    let veto = to_underlying(
+        a_poll.veto_votes.u128(),
+        &exchange_rate,
+        state.weight_rounding,
+    )?;
+    let no = checked_add(
+        to_underlying(
+            a_poll
+                .no_votes
+                .u128()
+                .saturating_sub(a_poll.veto_votes.u128()),
+            &exchange_rate,
+            state.weight_rounding,
+        )?,
+        veto,
+    )?;
+    let abstain = to_underlying(
+        a_poll.abstain_votes.u128(),
+        &exchange_rate,
+        state.weight_rounding,
+    )?;
+    let tallied_weight = checked_add(checked_add(yes, no)?, abstain)?;
+    a_poll.yes_votes = Uint128::from(yes);
+    a_poll.no_votes = Uint128::from(no);
+    a_poll.abstain_votes = Uint128::from(abstain);
+    a_poll.veto_votes = Uint128::from(veto);
+    let mut vetoed = false;
+
+    let mut rejected_reason = "";
+    let mut passed = false;
+    let mut quorum_reached = false;
+    let mut participation = Decimal::zero();
+    let mut approval = Decimal::zero();
+
+    if tallied_weight > 0 {
+        // quorum is counted against the tracked stake rather than the contract's balance, which
+        // also holds matching pools, deposits and tokens sent without staking. The stake of
+        // non-voting addresses doesn't count either.
+        let staked_balance = state
+            .staked_tokens
+            .u128()
+            .saturating_sub(excluded_stake(&deps.storage)?);
+        let staked_weight = to_underlying(staked_balance, &exchange_rate, state.weight_rounding)?;
 
-        let quorum = ((tallied_weight / staked_weight) * 100) as u8;
-        if a_poll.quorum_percentage.is_some() && quorum < a_poll.quorum_percentage.unwrap() {
+        if staked_weight == 0 {
+            return Err(StdError::generic_err("Nothing staked"));
+        }
+        a_poll.staked_weight = Some(Uint128::from(staked_weight));
+
+        participation = percentage::of(tallied_weight, staked_weight)?;
+        approval = percentage::of(yes, staked_weight)?;
+        let threshold_reached = match a_poll.threshold_percentage {
+            Some(threshold) => yes > 0 && percentage::of(yes, checked_add(yes, no)?)? >= threshold,
+            None => yes > checked_add(yes, no)? / 2,
+        };
+        let veto_exceeded = match a_poll.veto_threshold {
+            Some(veto_threshold) => {
+                tallied_weight > 0 && percentage::of(veto, tallied_weight)? > veto_threshold
+            }
+            None => false,
+        };
+        quorum_reached = match a_poll.quorum_percentage {
+            Some(quorum_percentage) => participation >= quorum_percentage,
+            None => true,
+        };
+        if !quorum_reached {
             // Quorum: More than quorum_percentage of the total staked tokens at the end of the voting
             // period need to have participated in the vote.
             rejected_reason = "Quorum not reached";
-        } else if yes > tallied_weight / 2 {
-            //Threshold: More than 50% of the tokens that participated in the vote
-            // (after excluding “Abstain” votes) need to have voted in favor of the proposal (“Yes”).
+        } else if veto_exceeded {
+            // Veto: more than veto_threshold percent of the votes cast were NoWithVeto
+            rejected_reason = "Veto threshold exceeded";
+            vetoed = true;
+        } else if a_poll.approval_quorum_percentage.is_some()
+            && approval < a_poll.approval_quorum_percentage.unwrap()
+        {
+            // Approval quorum: approval_quorum_percentage of the total staked tokens need to have
+            // voted in favor of the proposal, however many voted against it.
+            rejected_reason = "Approval quorum not reached";
+        } else if threshold_reached {
+            //Threshold: More than 50% of the tokens that participated in the vote, or
+            // threshold_percentage of them if set, (after excluding “Abstain” votes) need to have
+            // voted in favor of the proposal (“Yes”).
             a_poll.status = PollStatus::Passed;
             passed = true;
         } else {
@@ -301,20 +2852,494 @@ pub fn end_poll<S: Storage, A: Api, Q: Querier>(
     } else {
         rejected_reason = "Quorum not reached";
     }
-    if !passed {
+    if vetoed {
+        a_poll.status = PollStatus::RejectedWithVeto
+    } else if !passed {
         a_poll.status = PollStatus::Rejected
     }
+    if let (true, Some(execution)) = (passed, &a_poll.execution) {
+        a_poll.status = PollStatus::Queued;
+        a_poll.executable_at = Some(env.block.height.saturating_add(execution.delay_blocks));
+    }
+    close_poll(&mut deps.storage)?;
+    let mut messages: Vec<CosmosMsg> =
+        release_deposit(deps, &env, &state, &mut a_poll, quorum_reached)?
+            .into_iter()
+            .collect();
+    let (commit_messages, complete, votes_tallied) =
+        commit_votes(deps, &state, poll_id, &mut a_poll, limit)?;
+    messages.extend(commit_messages);
     poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
 
-    for voter in &a_poll.voters {
+    let mut logs = vec![
+        log("action", "end_poll"),
+        log("poll_id", &poll_id.to_string()),
+        log("rejected_reason", rejected_reason),
+        log("passed", &passed.to_string()),
+    ];
+    if let Some(executable_at) = a_poll.executable_at {
+        logs.push(log("executable_at", executable_at));
+    }
+    if !complete {
+        logs.push(log("complete", false));
+        logs.push(log("votes_tallied", votes_tallied));
+    }
+
+    let r = HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&TallyResponse {
+            poll_id,
+            passed,
+            participation_percentage: participation,
+            approval_percentage: approval,
+        })?),
+    };
+    Ok(r)
+}
+
+// commits up to `limit` more votes of an ended poll to its Merkle root, after discarding the
+// commitments left unrevealed, and tells the tally hook who cast them. Returns whether all of
+// the votes are committed, and how many are.
+fn commit_votes<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    state: &State,
+    poll_id: u64,
+    a_poll: &mut Poll,
+    limit: usize,
+) -> StdResult<(Vec<CosmosMsg>, bool, u64)> {
+    let tally_key = poll_id.to_be_bytes();
+    let mut progress = poll_tally_read(&deps.storage)
+        .may_load(&tally_key)?
+        .unwrap_or_default();
+
+    let unrevealed = commitments_read(&deps.storage, poll_id)
+        .range(None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(key, _)| CanonicalAddr::from(key)))
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in &unrevealed {
+        commitments(&mut deps.storage, poll_id).remove(voter.as_slice());
         unlock_tokens(deps, voter, poll_id)?;
     }
+    if unrevealed.len() == limit {
+        poll_tally(&mut deps.storage).save(&tally_key, &progress)?;
+        return Ok((vec![], false, progress.votes_tallied));
+    }
+    let limit = limit - unrevealed.len();
+
+    let start = progress.last_voter.as_ref().map(|key| {
+        let mut start = key.as_slice().to_vec();
+        start.push(0);
+        start
+    });
+    let poll_votes = votes_read(&deps.storage, poll_id)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut voters = vec![];
+    // the tree's nodes are kept so proofs don't rebuild it
+    for (key, voter) in &poll_votes {
+        let voter_raw = CanonicalAddr::from(key.as_slice());
+        let index = progress.votes_tallied;
+        let nodes = append_leaf(
+            &mut progress.frontier,
+            leaf_hash(&voter_raw, voter.vote, voter.weight),
+        );
+        for (level, node) in nodes.iter().enumerate() {
+            vote_tree(&mut deps.storage, poll_id)
+                .save(&vote_tree_key(level as u8, index >> level), node)?;
+        }
+        vote_leaves(&mut deps.storage, poll_id).save(voter_raw.as_slice(), &index)?;
+        progress.votes_tallied += 1;
+        unlock_tokens(deps, &voter_raw, poll_id)?;
+        voters.push(voter_raw);
+    }
+    let messages = tally_hook_msg(&deps.api, state, poll_id, &voters)?
+        .into_iter()
+        .collect();
+
+    let complete = poll_votes.len() < limit;
+    if complete {
+        let last_index = progress.votes_tallied.saturating_sub(1);
+        for (level, node) in edge_nodes(&progress.frontier) {
+            vote_tree(&mut deps.storage, poll_id)
+                .save(&vote_tree_key(level, last_index >> level), &node)?;
+        }
+        a_poll.votes_root = frontier_root(&progress.frontier);
+        poll_tally(&mut deps.storage).remove(&tally_key);
+    } else {
+        progress.last_voter = poll_votes
+            .last()
+            .map(|(key, _)| Binary::from(key.as_slice()));
+        poll_tally(&mut deps.storage).save(&tally_key, &progress)?;
+    }
+    Ok((messages, complete, progress.votes_tallied))
+}
+
+/// end_funding_round pays each project its share of the matching pool, and the rest back to the
+/// poll's creator
+fn end_funding_round<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    state: &State,
+    poll_id: u64,
+    mut a_poll: Poll,
+    mut funding_round: FundingRound,
+) -> HandleResult {
+    let scores = funding_round
+        .projects
+        .iter()
+        .map(|project| checked_mul(project.sqrt_sum.u128(), project.sqrt_sum.u128()))
+        .collect::<StdResult<Vec<u128>>>()?;
+    let total_score = scores
+        .iter()
+        .try_fold(0u128, |total, score| checked_add(total, *score))?;
+
+    let mut messages = vec![];
+    let mut matched = 0u128;
+    if total_score > 0 {
+        for (project, score) in funding_round.projects.iter_mut().zip(scores) {
+            project.matched = funding_round
+                .matching_pool
+                .multiply_ratio(score, total_score);
+            if project.matched.is_zero() {
+                continue;
+            }
+            matched = checked_add(matched, project.matched.u128())?;
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                from_address: env.contract.address.clone(),
+                to_address: project.recipient.clone(),
+                amount: vec![coin(project.matched.u128(), &state.denom)],
+            }));
+        }
+    }
+    // a round without contributions and any rounding dust go back to the creator
+    let refund = checked_sub(funding_round.matching_pool.u128(), matched)?;
+    if refund > 0 {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address.clone(),
+            to_address: deps.api.human_address(&a_poll.creator)?,
+            amount: vec![coin(refund, &state.denom)],
+        }));
+    }
+
+    let reserve = funding_reserve_read(&deps.storage).load()?;
+    funding_reserve(&mut deps.storage).save(&Uint128::from(checked_sub(
+        reserve.u128(),
+        funding_round.matching_pool.u128(),
+    )?))?;
+
+    let passed = total_score > 0;
+    a_poll.status = if passed {
+        PollStatus::Passed
+    } else {
+        PollStatus::Rejected
+    };
+    close_poll(&mut deps.storage)?;
+    for contributor in &funding_round.contributors {
+        unlock_tokens(deps, contributor, poll_id)?;
+    }
+    messages.extend(tally_hook_msg(
+        &deps.api,
+        state,
+        poll_id,
+        &funding_round.contributors,
+    )?);
+    // a round counts as reaching quorum once anyone contributed
+    messages.extend(release_deposit(deps, &env, state, &mut a_poll, passed)?);
+    a_poll.funding_round = Some(funding_round);
+    poll(&mut deps.storage).save(poll_id.to_string().as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages,
+        log: vec![
+            log("action", "end_poll"),
+            log("poll_id", poll_id),
+            log("passed", passed),
+            log("matched", matched),
+        ],
+        data: Some(to_binary(&funding_round_response(poll_id, &a_poll)?)?),
+    };
+    Ok(r)
+}
+
+// unlock voter's tokens in a given poll
+fn unlock_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    voter: &CanonicalAddr,
+    poll_id: u64,
+) -> HandleResult {
+    locks(&mut deps.storage, voter).remove(&poll_id.to_be_bytes());
+    Ok(HandleResponse::default())
+}
+
+/// execute_poll dispatches the messages of a queued poll once its execution delay is over
+pub fn execute_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+) -> HandleResult {
+    let key = poll_id.to_string();
+    let mut a_poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if a_poll.status != PollStatus::Queued {
+        return Err(StdError::generic_err("Poll is not queued for execution"));
+    }
+    if env.block.height < a_poll.executable_at.unwrap_or_default() {
+        return Err(StdError::generic_err("Execution delay has not passed"));
+    }
+
+    a_poll.status = PollStatus::Executed;
+    poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages: a_poll.execution.map(|e| e.msgs).unwrap_or_default(),
+        log: vec![log("action", "execute_poll"), log("poll_id", poll_id)],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// veto_poll cancels a queued poll during its execution delay. Only the owner can do this.
+pub fn veto_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+) -> HandleResult {
+    let state = config_read(&deps.storage).load()?;
+    if deps.api.canonical_address(&env.message.sender)? != state.owner {
+        return Err(StdError::unauthorized());
+    }
+    let key = poll_id.to_string();
+    let mut a_poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if a_poll.status != PollStatus::Queued {
+        return Err(StdError::generic_err("Poll is not queued for execution"));
+    }
+    if env.block.height >= a_poll.executable_at.unwrap_or_default() {
+        return Err(StdError::generic_err("Execution delay has passed"));
+    }
+
+    a_poll.status = PollStatus::Vetoed;
+    poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "veto_poll"), log("poll_id", poll_id)],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// Polls can only be cancelled before their first vote, so no tokens are locked on them
+pub fn cancel_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+) -> HandleResult {
+    let key = poll_id.to_string();
+    let mut a_poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if deps.api.canonical_address(&env.message.sender)? != a_poll.creator {
+        return Err(StdError::unauthorized());
+    }
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+    if has_votes(&deps.storage, poll_id, &a_poll)? {
+        return Err(StdError::generic_err("Poll already has votes"));
+    }
+
+    let state = config_read(&deps.storage).load()?;
+    let mut messages: Vec<CosmosMsg> = release_deposit(deps, &env, &state, &mut a_poll, true)?
+        .into_iter()
+        .collect();
+    if let Some(funding_round) = &a_poll.funding_round {
+        let reserve = funding_reserve_read(&deps.storage).load()?;
+        funding_reserve(&mut deps.storage).save(&Uint128::from(checked_sub(
+            reserve.u128(),
+            funding_round.matching_pool.u128(),
+        )?))?;
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address,
+            to_address: env.message.sender,
+            amount: vec![coin(funding_round.matching_pool.u128(), &state.denom)],
+        }));
+    }
+
+    a_poll.status = PollStatus::Cancelled;
+    close_poll(&mut deps.storage)?;
+    poll(&mut deps.storage).save(key.as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages,
+        log: vec![log("action", "cancel_poll"), log("poll_id", poll_id)],
+        data: None,
+    };
+    Ok(r)
+}
+
+// votes, contributions to a funding round and votes not yet moved out of the poll all count
+fn has_votes<S: Storage>(storage: &S, poll_id: u64, a_poll: &Poll) -> StdResult<bool> {
+    if votes_read(storage, poll_id)
+        .range(None, None, Order::Ascending)
+        .next()
+        .is_some()
+        || commitments_read(storage, poll_id)
+            .range(None, None, Order::Ascending)
+            .next()
+            .is_some()
+    {
+        return Ok(true);
+    }
+    if let Some(funding_round) = &a_poll.funding_round {
+        if !funding_round.contributors.is_empty() {
+            return Ok(true);
+        }
+    }
+    let legacy = legacy_votes_read(storage).may_load(poll_id.to_string().as_bytes())?;
+    Ok(matches!(legacy, Some(legacy) if !legacy.voters.is_empty()))
+}
+
+// finds the largest locked amount in participated polls. Ending a poll leaves its locks in place,
+// so they are released here, one voter at a time.
+fn release_ended_locks<S: Storage>(storage: &mut S, voter: &CanonicalAddr) -> StdResult<u128> {
+    let locked = locks_read(storage, voter)
+        .range(None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut largest = 0u128;
+    for (key, weight) in locked {
+        if poll_in_progress(storage, poll_id_from_key(&key))? {
+            largest = largest.max(weight.u128());
+        } else {
+            locks(storage, voter).remove(&key);
+        }
+    }
+    Ok(largest)
+}
+
+// whether votes in the poll still lock stake
+fn poll_in_progress<S: Storage>(storage: &S, poll_id: u64) -> StdResult<bool> {
+    let a_poll = poll_read(storage).may_load(poll_id.to_string().as_bytes())?;
+    Ok(matches!(a_poll, Some(a_poll) if a_poll.status == PollStatus::InProgress))
+}
+
+// locks are keyed by the poll_id's big-endian bytes
+fn poll_id_from_key(key: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(key);
+    u64::from_be_bytes(bytes)
+}
+
+fn has_voted<S: Storage>(storage: &S, voter: &CanonicalAddr, poll_id: u64) -> StdResult<bool> {
+    Ok(votes_read(storage, poll_id)
+        .may_load(voter.as_slice())?
+        .is_some())
+}
+
+// the creator and co-creators of a poll can edit and end it
+fn is_editor(address: &CanonicalAddr, a_poll: &Poll) -> bool {
+    a_poll.creator == *address || a_poll.co_creators.contains(address)
+}
+
+pub fn cast_vote<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+    vote: VoteOption,
+    weight: Option<Uint128>,
+    rationale: Option<String>,
+) -> HandleResult {
+    if rationale.as_ref().map_or(0, String::len) > MAX_RATIONALE_LENGTH {
+        return Err(StdError::generic_err("Rationale too long"));
+    }
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let poll_key = &poll_id.to_string();
+    let state = config_read(&deps.storage).load()?;
+    if poll_id == 0 || poll_id > state.poll_count {
+        return Err(StdError::generic_err("Poll does not exist"));
+    }
+
+    migrate_poll_votes(&mut deps.storage, poll_id)?;
+    let mut a_poll = match poll_read(&deps.storage).may_load(poll_key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+    if a_poll.end_height <= env.block.height {
+        return Err(StdError::generic_err("Voting period has expired."));
+    }
+
+    if a_poll.funding_round.is_some() {
+        return Err(StdError::generic_err(
+            "Funding rounds take contributions, not votes",
+        ));
+    }
+    if a_poll.reveal_end_height.is_some() {
+        return Err(StdError::generic_err(
+            "Commit-reveal polls take committed votes",
+        ));
+    }
+
+    if has_voted(&deps.storage, &sender_address_raw, poll_id)? {
+        return Err(StdError::generic_err("User has already voted."));
+    }
+
+    if non_voting_read(&deps.storage)
+        .may_load(sender_address_raw.as_slice())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Non-voting addresses cannot vote"));
+    }
+
+    let key = &sender_address_raw.as_slice();
+    let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+
+    // votes with the whole stake unless a weight is given
+    let weight = weight.unwrap_or(token_manager.token_balance);
+    if token_manager.token_balance < weight || token_manager.token_balance.is_zero() {
+        return Err(StdError::generic_err(
+            "User does not have enough staked tokens.",
+        ));
+    }
+    check_vote_weight(&a_poll, &state, weight)?;
+    token_manager.participated_polls.push(poll_id);
+    bank(&mut deps.storage).save(key, &token_manager)?;
+    locks(&mut deps.storage, &sender_address_raw).save(&poll_id.to_be_bytes(), &weight)?;
+
+    let voter_info = Voter {
+        vote,
+        weight,
+        voted_at_height: env.block.height,
+        rationale,
+    };
+
+    voter_history(&mut deps.storage, &sender_address_raw).save(
+        &poll_id.to_be_bytes(),
+        &VoteHistory {
+            vote: voter_info.vote,
+            weight,
+        },
+    )?;
+    votes(&mut deps.storage, poll_id).save(sender_address_raw.as_slice(), &voter_info)?;
+    count_vote(&mut a_poll, vote, weight)?;
+    poll(&mut deps.storage).save(poll_key.as_bytes(), &a_poll)?;
 
     let log = vec![
-        log("action", "end_poll"),
+        log("action", "vote_casted"),
         log("poll_id", &poll_id.to_string()),
-        log("rejected_reason", rejected_reason),
-        log("passed", &passed.to_string()),
+        log("weight", &weight.to_string()),
+        log("voter", &env.message.sender.as_str()),
     ];
 
     let r = HandleResponse {
@@ -325,98 +3350,315 @@ pub fn end_poll<S: Storage, A: Api, Q: Querier>(
     Ok(r)
 }
 
-// unlock voter's tokens in a given poll
-fn unlock_tokens<S: Storage, A: Api, Q: Querier>(
+/// commit_vote locks the weight of a vote on a commit-reveal poll whose option is only
+/// disclosed by reveal_vote
+pub fn commit_vote<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    voter: &CanonicalAddr,
+    env: Env,
     poll_id: u64,
+    commitment: Binary,
+    weight: Uint128,
 ) -> HandleResult {
-    let voter_key = &voter.as_slice();
-    let mut token_manager = bank_read(&deps.storage).load(voter_key).unwrap();
+    if commitment.len() != 32 {
+        return Err(StdError::generic_err("Commitment must be a sha256 hash"));
+    }
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+    if a_poll.reveal_end_height.is_none() {
+        return Err(StdError::generic_err("Poll is not commit-reveal"));
+    }
+    if a_poll.start_height.unwrap_or_default() > env.block.height {
+        return Err(StdError::generic_err("Voting period has not started."));
+    }
+    if a_poll.end_height <= env.block.height {
+        return Err(StdError::generic_err("Voting period has expired."));
+    }
 
-    // unlock entails removing the mapped poll_id, retaining the rest
-    token_manager.locked_tokens.retain(|(k, _)| k != &poll_id);
-    bank(&mut deps.storage).save(voter_key, &token_manager)?;
-    Ok(HandleResponse::default())
+    if commitments_read(&deps.storage, poll_id)
+        .may_load(sender_address_raw.as_slice())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("User has already voted."));
+    }
+    if non_voting_read(&deps.storage)
+        .may_load(sender_address_raw.as_slice())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Non-voting addresses cannot vote"));
+    }
+
+    let key = sender_address_raw.as_slice();
+    let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+    if token_manager.token_balance < weight {
+        return Err(StdError::generic_err(
+            "User does not have enough staked tokens.",
+        ));
+    }
+    check_vote_weight(&a_poll, &config_read(&deps.storage).load()?, weight)?;
+    token_manager.participated_polls.push(poll_id);
+    bank(&mut deps.storage).save(key, &token_manager)?;
+    locks(&mut deps.storage, &sender_address_raw).save(&poll_id.to_be_bytes(), &weight)?;
+    commitments(&mut deps.storage, poll_id).save(
+        key,
+        &Commitment {
+            hash: commitment,
+            weight,
+        },
+    )?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "vote_committed"),
+            log("poll_id", poll_id),
+            log("weight", weight),
+            log("voter", env.message.sender.as_str()),
+        ],
+        data: None,
+    };
+    Ok(r)
 }
 
-// finds the largest locked amount in participated polls.
-fn locked_amount<S: Storage, A: Api, Q: Querier>(
-    voter: &CanonicalAddr,
+/// reveal_vote counts a committed vote whose option and salt match the commitment
+pub fn reveal_vote<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-) -> u128 {
-    let voter_key = &voter.as_slice();
-    let token_manager = bank_read(&deps.storage).load(voter_key).unwrap();
-    token_manager
-        .locked_tokens
-        .iter()
-        .map(|(_, v)| v.u128())
-        .max()
-        .unwrap_or_default()
+    env: Env,
+    poll_id: u64,
+    vote: VoteOption,
+    salt: String,
+) -> HandleResult {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    let mut a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+    let reveal_end_height = match a_poll.reveal_end_height {
+        Some(reveal_end_height) => reveal_end_height,
+        None => return Err(StdError::generic_err("Poll is not commit-reveal")),
+    };
+    if env.block.height < a_poll.end_height || env.block.height >= reveal_end_height {
+        return Err(StdError::generic_err("Poll is not in its reveal period"));
+    }
+
+    let commitment =
+        match commitments_read(&deps.storage, poll_id).may_load(sender_address_raw.as_slice())? {
+            Some(commitment) => commitment,
+            None => return Err(StdError::generic_err("User has no committed vote")),
+        };
+    if vote_commitment(&env.message.sender, vote, &salt) != commitment.hash {
+        return Err(StdError::generic_err("Vote does not match the commitment"));
+    }
+    commitments(&mut deps.storage, poll_id).remove(sender_address_raw.as_slice());
+
+    let voter_info = Voter {
+        vote,
+        weight: commitment.weight,
+        voted_at_height: env.block.height,
+        rationale: None,
+    };
+    voter_history(&mut deps.storage, &sender_address_raw).save(
+        &poll_id.to_be_bytes(),
+        &VoteHistory {
+            vote,
+            weight: commitment.weight,
+        },
+    )?;
+    votes(&mut deps.storage, poll_id).save(sender_address_raw.as_slice(), &voter_info)?;
+    count_vote(&mut a_poll, vote, commitment.weight)?;
+    poll(&mut deps.storage).save(poll_id.to_string().as_bytes(), &a_poll)?;
+
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "vote_revealed"),
+            log("poll_id", poll_id),
+            log("weight", commitment.weight),
+            log("voter", env.message.sender.as_str()),
+        ],
+        data: None,
+    };
+    Ok(r)
 }
 
-fn has_voted(voter: &CanonicalAddr, a_poll: &Poll) -> bool {
-    a_poll.voters.iter().any(|i| i == voter)
+/// sha256(voter || ":" || vote || ":" || salt), the commitment CommitVote takes
+pub fn vote_commitment(voter: &HumanAddr, vote: VoteOption, salt: &str) -> Binary {
+    let option = match vote {
+        VoteOption::Yes => "yes",
+        VoteOption::No => "no",
+        VoteOption::Abstain => "abstain",
+        VoteOption::NoWithVeto => "no_with_veto",
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}:{}:{}", voter, option, salt).as_bytes());
+    Binary(hasher.finalize().to_vec())
 }
 
-pub fn cast_vote<S: Storage, A: Api, Q: Querier>(
+/// update_vote replaces a vote and the weight locked for it until the poll's end height
+pub fn update_vote<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     poll_id: u64,
-    vote: String,
+    vote: VoteOption,
     weight: Uint128,
+    rationale: Option<String>,
 ) -> HandleResult {
-    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
-    let poll_key = &poll_id.to_string();
-    let state = config_read(&deps.storage).load()?;
-    if poll_id == 0 || state.poll_count > poll_id {
-        return Err(StdError::generic_err("Poll does not exist"));
+    if rationale.as_ref().map_or(0, String::len) > MAX_RATIONALE_LENGTH {
+        return Err(StdError::generic_err("Rationale too long"));
     }
-
-    let mut a_poll = poll(&mut deps.storage).load(poll_key.as_bytes())?;
-
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    migrate_poll_votes(&mut deps.storage, poll_id)?;
+    let mut a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
     if a_poll.status != PollStatus::InProgress {
         return Err(StdError::generic_err("Poll is not in progress"));
     }
-
-    if has_voted(&sender_address_raw, &a_poll) {
-        return Err(StdError::generic_err("User has already voted."));
+    if a_poll.end_height <= env.block.height {
+        return Err(StdError::generic_err("Voting period has expired."));
     }
 
-    let key = &sender_address_raw.as_slice();
-    let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+    let previous =
+        match votes_read(&deps.storage, poll_id).may_load(sender_address_raw.as_slice())? {
+            Some(previous) => previous,
+            None => return Err(StdError::generic_err("User has not voted.")),
+        };
+    if non_voting_read(&deps.storage)
+        .may_load(sender_address_raw.as_slice())?
+        .is_some()
+    {
+        return Err(StdError::generic_err("Non-voting addresses cannot vote"));
+    }
 
+    let token_manager = bank_read(&deps.storage)
+        .may_load(sender_address_raw.as_slice())?
+        .unwrap_or_default();
     if token_manager.token_balance < weight {
         return Err(StdError::generic_err(
             "User does not have enough staked tokens.",
         ));
     }
-    token_manager.participated_polls.push(poll_id);
-    token_manager.locked_tokens.push((poll_id, weight));
-    bank(&mut deps.storage).save(key, &token_manager)?;
+    check_vote_weight(&a_poll, &config_read(&deps.storage).load()?, weight)?;
+    locks(&mut deps.storage, &sender_address_raw).save(&poll_id.to_be_bytes(), &weight)?;
+
+    let voter_info = Voter {
+        vote,
+        weight,
+        voted_at_height: env.block.height,
+        rationale: rationale.or(previous.rationale),
+    };
+    voter_history(&mut deps.storage, &sender_address_raw).save(
+        &poll_id.to_be_bytes(),
+        &VoteHistory {
+            vote: voter_info.vote,
+            weight,
+        },
+    )?;
+    votes(&mut deps.storage, poll_id).save(sender_address_raw.as_slice(), &voter_info)?;
+    uncount_vote(&mut a_poll, previous.vote, previous.weight);
+    count_vote(&mut a_poll, vote, weight)?;
+    poll(&mut deps.storage).save(poll_id.to_string().as_bytes(), &a_poll)?;
 
-    a_poll.voters.push(sender_address_raw.clone());
+    let r = HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "vote_updated"),
+            log("poll_id", poll_id),
+            log("weight", weight),
+            log("voter", env.message.sender.as_str()),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
 
-    let voter_info = Voter { vote, weight };
+/// retract_vote removes a vote until the poll's end height, as if it was never cast
+pub fn retract_vote<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll_id: u64,
+) -> HandleResult {
+    let sender_address_raw = deps.api.canonical_address(&env.message.sender)?;
+    migrate_poll_votes(&mut deps.storage, poll_id)?;
+    let mut a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+    if a_poll.end_height <= env.block.height {
+        return Err(StdError::generic_err("Voting period has expired."));
+    }
 
-    a_poll.voter_info.push(voter_info);
-    poll(&mut deps.storage).save(poll_key.as_bytes(), &a_poll)?;
+    let previous =
+        match votes_read(&deps.storage, poll_id).may_load(sender_address_raw.as_slice())? {
+            Some(previous) => previous,
+            None => return Err(StdError::generic_err("User has not voted.")),
+        };
 
-    let log = vec![
-        log("action", "vote_casted"),
-        log("poll_id", &poll_id.to_string()),
-        log("weight", &weight.to_string()),
-        log("voter", &env.message.sender.as_str()),
-    ];
+    let key = sender_address_raw.as_slice();
+    let mut token_manager = bank_read(&deps.storage).may_load(key)?.unwrap_or_default();
+    token_manager
+        .participated_polls
+        .retain(|participated| *participated != poll_id);
+    bank(&mut deps.storage).save(key, &token_manager)?;
+    unlock_tokens(deps, &sender_address_raw, poll_id)?;
+    voter_history(&mut deps.storage, &sender_address_raw).remove(&poll_id.to_be_bytes());
+    votes(&mut deps.storage, poll_id).remove(sender_address_raw.as_slice());
+    uncount_vote(&mut a_poll, previous.vote, previous.weight);
+    poll(&mut deps.storage).save(poll_id.to_string().as_bytes(), &a_poll)?;
 
     let r = HandleResponse {
         messages: vec![],
-        log,
+        log: vec![
+            log("action", "vote_retracted"),
+            log("poll_id", poll_id),
+            log("voter", env.message.sender.as_str()),
+        ],
         data: None,
     };
     Ok(r)
 }
 
+// adds a vote to the running totals of an open poll, which are in staked units. As in the tally,
+// no votes include vetoes.
+fn count_vote(a_poll: &mut Poll, vote: VoteOption, weight: Uint128) -> StdResult<()> {
+    let total = match vote {
+        VoteOption::Yes => &mut a_poll.yes_votes,
+        VoteOption::No | VoteOption::NoWithVeto => &mut a_poll.no_votes,
+        VoteOption::Abstain => &mut a_poll.abstain_votes,
+    };
+    *total = Uint128::from(checked_add(total.u128(), weight.u128())?);
+    if vote == VoteOption::NoWithVeto {
+        a_poll.veto_votes = Uint128::from(checked_add(a_poll.veto_votes.u128(), weight.u128())?);
+    }
+    Ok(())
+}
+
+// takes a vote off the running totals. Votes moved into the vote bucket before the totals were
+// kept aren't in them, so the totals stop at zero.
+fn uncount_vote(a_poll: &mut Poll, vote: VoteOption, weight: Uint128) {
+    let total = match vote {
+        VoteOption::Yes => &mut a_poll.yes_votes,
+        VoteOption::No | VoteOption::NoWithVeto => &mut a_poll.no_votes,
+        VoteOption::Abstain => &mut a_poll.abstain_votes,
+    };
+    *total = Uint128::from(total.u128().saturating_sub(weight.u128()));
+    if vote == VoteOption::NoWithVeto {
+        a_poll.veto_votes = Uint128::from(a_poll.veto_votes.u128().saturating_sub(weight.u128()));
+    }
+}
+
 fn send_tokens<A: Api>(
     api: &A,
     from_address: &CanonicalAddr,
@@ -440,6 +3682,53 @@ fn send_tokens<A: Api>(
     Ok(r)
 }
 
+/// send_stake pays out staked tokens, as a transfer of the staking token when there is one
+fn send_stake<A: Api>(
+    api: &A,
+    state: &State,
+    from_address: &CanonicalAddr,
+    to_address: &CanonicalAddr,
+    amount: u128,
+    action: &str,
+) -> HandleResult {
+    match &state.token {
+        Some(token) => transfer_tokens(api, token, to_address, amount, action),
+        None => send_tokens(
+            api,
+            from_address,
+            to_address,
+            vec![coin(amount, &state.denom)],
+            action,
+        ),
+    }
+}
+
+fn transfer_tokens<A: Api>(
+    api: &A,
+    token: &CanonicalAddr,
+    to_address: &CanonicalAddr,
+    amount: u128,
+    action: &str,
+) -> HandleResult {
+    let to_human = api.human_address(to_address)?;
+    let log = vec![log("action", action), log("to", to_human.as_str())];
+
+    let r = HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: api.human_address(token)?,
+            msg: to_binary(&TokenHandleMsg::Transfer {
+                recipient: to_human,
+                amount: Uint128::from(amount),
+                tx_key: None,
+            })?,
+            send: vec![],
+        })],
+        log,
+        data: None,
+    };
+    Ok(r)
+}
+
 pub fn query<S: Storage, A: Api, Q: Querier>(
     _deps: &Extern<S, A, Q>,
     msg: QueryMsg,
@@ -449,7 +3738,666 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
 
         QueryMsg::TokenStake { address } => token_balance(_deps, address),
         QueryMsg::Poll { poll_id } => query_poll(_deps, poll_id),
+        QueryMsg::ProposerEligibility { address } => proposer_eligibility(_deps, address),
+        QueryMsg::Batch { queries } => query_batch(_deps, queries),
+        QueryMsg::PollAudit {
+            poll_id,
+            start_after,
+            limit,
+        } => to_binary(&query_poll_audit(_deps, poll_id, start_after, limit)?),
+        QueryMsg::Vote { poll_id, voter } => to_binary(&query_vote(_deps, poll_id, voter)?),
+        QueryMsg::ResultProof { poll_id, voter } => {
+            to_binary(&query_result_proof(_deps, poll_id, voter)?)
+        }
+        QueryMsg::Voters {
+            poll_id,
+            start_after,
+            limit,
+        } => to_binary(&query_voters(_deps, poll_id, start_after, limit)?),
+        QueryMsg::Rewards { address } => query_rewards(_deps, address),
+        QueryMsg::RewardRate {} => query_reward_rate(_deps),
+        QueryMsg::NonVotingAddresses {} => query_non_voting_addresses(_deps),
+        QueryMsg::PollCreators { start_after, limit } => {
+            to_binary(&query_poll_creators(_deps, start_after, limit)?)
+        }
+        QueryMsg::FundingRound { poll_id } => query_funding_round(_deps, poll_id),
+        QueryMsg::VoterHistory {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_voter_history(_deps, address, start_after, limit)?),
+        QueryMsg::ParticipatedPolls {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&query_participated_polls(
+            _deps,
+            address,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::VoteBackfill {} => to_binary(&query_vote_backfill(_deps)?),
+        QueryMsg::PollVoteTotals { poll_id } => to_binary(&query_poll_vote_totals(_deps, poll_id)?),
+        QueryMsg::Results {
+            status,
+            start_after,
+            limit,
+        } => to_binary(&query_results(_deps, status, start_after, limit)?),
+        QueryMsg::SearchPolls {
+            prefix,
+            start_after,
+            limit,
+        } => to_binary(&search_polls(_deps, prefix, start_after, limit)?),
+        QueryMsg::PollsByTag {
+            tag,
+            start_after,
+            limit,
+        } => to_binary(&query_polls_by_tag(_deps, tag, start_after, limit)?),
+        QueryMsg::Ownership {} => to_binary(&query_ownership(_deps)?),
+        QueryMsg::Claims { address } => to_binary(&query_claims(_deps, address)?),
+        QueryMsg::ContractVersion {} => to_binary(&contract_version_read(&_deps.storage).load()?),
+        QueryMsg::StakingInfo {} => to_binary(&query_staking_info(_deps)?),
+        QueryMsg::PollArchive {} => to_binary(
+            &poll_archive_read(&_deps.storage)
+                .may_load()?
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+fn query_staking_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<StakingInfoResponse> {
+    let state = config_read(&deps.storage).load()?;
+    // the key of the largest stake starts with its balance
+    let largest_stake = match stake_sizes_read(&deps.storage)
+        .range(None, None, Order::Descending)
+        .next()
+    {
+        Some(item) => {
+            let (key, _) = item?;
+            let mut balance = [0u8; 16];
+            balance.copy_from_slice(&key[..16]);
+            u128::from_be_bytes(balance)
+        }
+        None => 0,
+    };
+    Ok(StakingInfoResponse {
+        total_staked: state.staked_tokens,
+        stakers: state.staker_count,
+        active_polls: state.active_polls,
+        largest_stake: Uint128::from(largest_stake),
+    })
+}
+
+fn query_claims<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<ClaimsResponse> {
+    let key = deps.api.canonical_address(&address)?;
+    Ok(ClaimsResponse {
+        claims: claims_read(&deps.storage)
+            .may_load(key.as_slice())?
+            .unwrap_or_default(),
+    })
+}
+
+fn query_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<OwnershipResponse> {
+    let state = config_read(&deps.storage).load()?;
+    Ok(OwnershipResponse {
+        owner: deps.api.human_address(&state.owner)?,
+        pending_owner: pending_owner_read(&deps.storage)
+            .may_load()?
+            .map(|pending_owner| deps.api.human_address(&pending_owner))
+            .transpose()?,
+    })
+}
+
+fn query_rewards<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<Binary> {
+    let key = deps.api.canonical_address(&address)?;
+    let mut token_manager = bank_read(&deps.storage)
+        .may_load(key.as_slice())?
+        .unwrap_or_default();
+    settle_rewards(&deps.storage, &mut token_manager)?;
+
+    to_binary(&RewardsResponse {
+        pending_rewards: token_manager.pending_rewards,
+    })
+}
+
+fn query_voter_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VoterHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let voter = deps.api.canonical_address(&address)?;
+    let start = start_after.map(|poll_id| {
+        let mut start = poll_id.to_be_bytes().to_vec();
+        start.push(0);
+        start
+    });
+    let votes = voter_history_read(&deps.storage, &voter)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, entry) = item?;
+            let poll_id = poll_id_from_key(&key);
+            let a_poll = poll_read(&deps.storage).load(poll_id.to_string().as_bytes())?;
+            Ok(VoterHistoryEntry {
+                poll_id,
+                vote: entry.vote,
+                weight: entry.weight,
+                outcome: a_poll.status,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(VoterHistoryResponse { votes })
+}
+
+fn query_participated_polls<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ParticipatedPollsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let voter = deps.api.canonical_address(&address)?;
+    let mut poll_ids = match bank_read(&deps.storage).may_load(voter.as_slice())? {
+        Some(token_manager) => token_manager.participated_polls,
+        None => vec![],
+    };
+    poll_ids.sort_unstable();
+    poll_ids.dedup();
+
+    let locks = locks_read(&deps.storage, &voter);
+    let polls = poll_ids
+        .into_iter()
+        .filter(|poll_id| *poll_id > start_after.unwrap_or_default())
+        .take(limit)
+        .map(|poll_id| {
+            let locked_weight = match locks.may_load(&poll_id.to_be_bytes())? {
+                Some(weight) if poll_in_progress(&deps.storage, poll_id)? => weight,
+                _ => Uint128::zero(),
+            };
+            Ok(ParticipatedPoll {
+                poll_id,
+                locked_weight,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ParticipatedPollsResponse { polls })
+}
+
+fn query_vote_backfill<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<VoteBackfillResponse> {
+    let progress = vote_backfill_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    Ok(VoteBackfillResponse {
+        last_poll_id: progress.last_poll_id,
+        until_poll_id: progress.until_poll_id,
+        polls_migrated: progress.polls_migrated,
+        votes_migrated: progress.votes_migrated,
+        locks_migrated: progress.locks_migrated,
+        locks_complete: !progress.locks_pending,
+        complete: !progress.locks_pending && progress.last_poll_id >= progress.until_poll_id,
+    })
+}
+
+/// Totals the votes of a poll from both places they can be stored, so a migration can be
+/// checked to have kept them
+fn query_poll_vote_totals<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+) -> StdResult<PollVoteTotalsResponse> {
+    let legacy = match legacy_votes_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(legacy) => legacy,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    let mut legacy_totals = VoteTotals::default();
+    for info in &legacy.voter_info {
+        add_vote(&mut legacy_totals, info.vote_option(), info.weight)?;
+    }
+    let mut migrated = VoteTotals::default();
+    for item in votes_read(&deps.storage, poll_id).range(None, None, Order::Ascending) {
+        let (_, info) = item?;
+        add_vote(&mut migrated, info.vote, info.weight)?;
+    }
+    Ok(PollVoteTotalsResponse {
+        poll_id,
+        legacy: legacy_totals,
+        migrated,
+    })
+}
+
+fn add_vote(totals: &mut VoteTotals, vote: VoteOption, weight: Uint128) -> StdResult<()> {
+    let total = match vote {
+        VoteOption::Yes => &mut totals.yes,
+        VoteOption::No => &mut totals.no,
+        VoteOption::Abstain => &mut totals.abstain,
+        VoteOption::NoWithVeto => &mut totals.no_with_veto,
+    };
+    *total = Uint128::from(checked_add(total.u128(), weight.u128())?);
+    totals.votes += 1;
+    Ok(())
+}
+
+fn query_funding_round<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+) -> StdResult<Binary> {
+    let a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    to_binary(&funding_round_response(poll_id, &a_poll)?)
+}
+
+fn funding_round_response(poll_id: u64, a_poll: &Poll) -> StdResult<FundingRoundResponse> {
+    let funding_round = match &a_poll.funding_round {
+        Some(funding_round) => funding_round,
+        None => return Err(StdError::generic_err("Poll is not a funding round")),
+    };
+    Ok(FundingRoundResponse {
+        poll_id,
+        matching_pool: funding_round.matching_pool,
+        projects: funding_round
+            .projects
+            .iter()
+            .map(|project| ProjectResult {
+                name: project.name.clone(),
+                recipient: project.recipient.clone(),
+                contributed: project.contributed,
+                matched: project.matched,
+            })
+            .collect(),
+    })
+}
+
+fn query_non_voting_addresses<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<Binary> {
+    let addresses = non_voting_read(&deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (key, _) = item?;
+            deps.api.human_address(&CanonicalAddr::from(key))
+        })
+        .collect::<StdResult<Vec<HumanAddr>>>()?;
+    to_binary(&NonVotingAddressesResponse {
+        addresses,
+        excluded_stake: Uint128::from(excluded_stake(&deps.storage)?),
+    })
+}
+
+fn query_poll_creators<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> StdResult<PollCreatorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(address) => {
+            let mut start = deps.api.canonical_address(&address)?.as_slice().to_vec();
+            start.push(0);
+            Some(start)
+        }
+        None => None,
+    };
+    let creators = poll_creators_read(&deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, _) = item?;
+            deps.api.human_address(&CanonicalAddr::from(key))
+        })
+        .collect::<StdResult<Vec<HumanAddr>>>()?;
+    Ok(PollCreatorsResponse { creators })
+}
+
+fn query_reward_rate<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
+    let reward_state = match rewards_read(&deps.storage).may_load()? {
+        Some(reward_state) => reward_state,
+        None => return Err(StdError::generic_err("Rewards are not enabled")),
+    };
+    let state = config_read(&deps.storage).load()?;
+
+    let apr = match (reward_state.first_funded_at, reward_state.last_funded_at) {
+        (Some(first), Some(last)) if last > first && !state.staked_tokens.is_zero() => {
+            Some(Decimal::from_ratio(
+                checked_mul(
+                    reward_state.total_funded.u128(),
+                    u128::from(SECONDS_PER_YEAR),
+                )?,
+                checked_mul(state.staked_tokens.u128(), u128::from(last - first))?,
+            ))
+        }
+        _ => None,
+    };
+
+    to_binary(&RewardRateResponse {
+        denom: reward_state.denom,
+        token: reward_state
+            .token
+            .map(|token| deps.api.human_address(&token))
+            .transpose()?,
+        total_funded: reward_state.total_funded,
+        accrued_fees: reward_state.accrued_fees,
+        staked_tokens: state.staked_tokens,
+        apr,
+    })
+}
+
+/// Lists the individual votes of a poll, in voter address order, together with the totals
+/// the tally used, so anyone can recount a finished poll.
+fn query_poll_audit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> StdResult<PollAuditResponse> {
+    let key = &poll_id.to_string();
+    let a_poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+
+    let votes = poll_votes(deps, poll_id, start_after, limit)?
+        .into_iter()
+        .map(|(voter, info)| VoteRecord {
+            voter,
+            vote: info.vote,
+            weight: info.weight,
+            voted_at_height: info.voted_at_height,
+            rationale: info.rationale,
+        })
+        .collect();
+
+    Ok(PollAuditResponse {
+        poll_id,
+        status: a_poll.status,
+        yes_votes: a_poll.yes_votes,
+        no_votes: a_poll.no_votes,
+        abstain_votes: a_poll.abstain_votes,
+        staked_weight: a_poll.staked_weight,
+        votes,
+    })
+}
+
+/// Looks in the vote bucket first, then in the votes of a poll not migrated yet
+fn query_vote<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+    voter: HumanAddr,
+) -> StdResult<VoteResponse> {
+    let legacy = match legacy_votes_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(legacy) => legacy,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    let voter_raw = deps.api.canonical_address(&voter)?;
+    let ballot = match votes_read(&deps.storage, poll_id).may_load(voter_raw.as_slice())? {
+        Some(info) => Some(Ballot {
+            vote: info.vote,
+            weight: info.weight,
+        }),
+        None => legacy
+            .voters
+            .iter()
+            .position(|address| *address == voter_raw)
+            .and_then(|i| legacy.voter_info.get(i))
+            .map(|info| Ballot {
+                vote: info.vote_option(),
+                weight: info.weight,
+            }),
+    };
+    Ok(VoteResponse { ballot })
+}
+
+/// The path of one vote through the Merkle tree the tally kept
+fn query_result_proof<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+    voter: HumanAddr,
+) -> StdResult<ResultProofResponse> {
+    let a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(missing_poll(&deps.storage, poll_id)),
+    };
+    let voter_raw = deps.api.canonical_address(&voter)?;
+    let info = match votes_read(&deps.storage, poll_id).may_load(voter_raw.as_slice())? {
+        Some(info) => info,
+        None => return Err(StdError::generic_err("Voter did not vote")),
+    };
+    let root = match a_poll.votes_root {
+        Some(root) => root,
+        None => return Err(StdError::generic_err("Poll votes are not committed")),
+    };
+
+    let index = match vote_leaves_read(&deps.storage, poll_id).may_load(voter_raw.as_slice())? {
+        Some(index) => index,
+        None => return Err(StdError::generic_err("Poll votes are not committed")),
+    };
+    let tree = vote_tree_read(&deps.storage, poll_id);
+    Ok(ResultProofResponse {
+        root,
+        leaf: leaf_hash(&voter_raw, info.vote, info.weight),
+        vote: info.vote,
+        weight: info.weight,
+        proof: proof(index, |level, index| {
+            tree.may_load(&vote_tree_key(level, index))
+        })?,
+    })
+}
+
+fn query_voters<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> StdResult<VotersResponse> {
+    if poll_read(&deps.storage)
+        .may_load(poll_id.to_string().as_bytes())?
+        .is_none()
+    {
+        return Err(missing_poll(&deps.storage, poll_id));
+    }
+    let voters = poll_votes(deps, poll_id, start_after, limit)?
+        .into_iter()
+        .map(|(voter, info)| VoterInfo {
+            voter,
+            vote: info.vote,
+            weight: info.weight,
+            rationale: info.rationale,
+        })
+        .collect();
+    Ok(VotersResponse { voters })
+}
+
+// a page of the votes cast in a poll, in voter address order
+fn poll_votes<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    poll_id: u64,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(HumanAddr, Voter)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(address) => {
+            let mut start = deps.api.canonical_address(&address)?.as_slice().to_vec();
+            start.push(0);
+            Some(start)
+        }
+        None => None,
+    };
+    votes_read(&deps.storage, poll_id)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, info) = item?;
+            Ok((deps.api.human_address(&CanonicalAddr::from(key))?, info))
+        })
+        .collect()
+}
+
+/// Summarises the polls after `start_after`. Polls are stored under their decimal id, so they are
+/// looked up one by one to keep them in numeric order.
+fn query_results<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    status: Option<PollStatus>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ResultsResponse> {
+    let state = config_read(&deps.storage).load()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let first = start_after.unwrap_or_default() + 1;
+    let last = state
+        .poll_count
+        .min(first.saturating_add(MAX_RESULTS_SCAN - 1));
+
+    let mut results = vec![];
+    let mut last_scanned = None;
+    for poll_id in first..=last {
+        if results.len() == limit {
+            break;
+        }
+        last_scanned = Some(poll_id);
+        // pruned polls are skipped
+        let a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+            Some(a_poll) => a_poll,
+            None => continue,
+        };
+        if matches!(&status, Some(status) if *status != a_poll.status) {
+            continue;
+        }
+        results.push(poll_result(poll_id, a_poll));
+    }
+
+    Ok(ResultsResponse {
+        results,
+        last_scanned: last_scanned.filter(|poll_id| *poll_id < state.poll_count),
+    })
+}
+
+fn search_polls<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    prefix: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ResultsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let prefix = prefix.as_bytes();
+    // longer prefixes are narrowed down with the index and then checked against the description
+    let indexed_prefix = &prefix[..prefix.len().min(POLL_INDEX_PREFIX_LENGTH)];
+    let start = match start_after {
+        Some(poll_id) => {
+            let a_poll = match poll_read(&deps.storage).may_load(poll_id.to_string().as_bytes())? {
+                Some(a_poll) => a_poll,
+                None => return Err(missing_poll(&deps.storage, poll_id)),
+            };
+            let mut start = poll_index_key(&a_poll.description, poll_id);
+            start.push(0);
+            start
+        }
+        None => indexed_prefix.to_vec(),
+    };
+
+    let mut results = vec![];
+    let mut last_scanned = None;
+    let mut exhausted = true;
+    let index = poll_index_read(&deps.storage);
+    for item in index.range(Some(&start), None, Order::Ascending) {
+        let (key, poll_id) = item?;
+        if !key.starts_with(indexed_prefix) {
+            break;
+        }
+        if results.len() == limit {
+            exhausted = false;
+            break;
+        }
+        last_scanned = Some(poll_id);
+        let a_poll = poll_read(&deps.storage).load(poll_id.to_string().as_bytes())?;
+        if a_poll.description.as_bytes().starts_with(prefix) {
+            results.push(poll_result(poll_id, a_poll));
+        }
+    }
+
+    Ok(ResultsResponse {
+        results,
+        last_scanned: last_scanned.filter(|_| !exhausted),
+    })
+}
+
+fn query_polls_by_tag<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    tag: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ResultsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|poll_id| {
+        let mut start = poll_id.to_be_bytes().to_vec();
+        start.push(0);
+        start
+    });
+    let results = poll_tags_read(&deps.storage, &tag)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, poll_id) = item?;
+            let a_poll = poll_read(&deps.storage).load(poll_id.to_string().as_bytes())?;
+            Ok(poll_result(poll_id, a_poll))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let last_scanned = match results.last() {
+        Some(result) if results.len() == limit => Some(result.poll_id),
+        _ => None,
+    };
+    Ok(ResultsResponse {
+        results,
+        last_scanned,
+    })
+}
+
+fn poll_result(poll_id: u64, a_poll: Poll) -> PollResult {
+    PollResult {
+        poll_id,
+        title: a_poll.title,
+        passed: matches!(
+            a_poll.status,
+            PollStatus::Passed | PollStatus::Queued | PollStatus::Executed
+        ),
+        status: a_poll.status,
+        yes: a_poll.yes_votes,
+        no: a_poll.no_votes,
+        abstain: a_poll.abstain_votes,
+        veto: a_poll.veto_votes,
+        quorum_pct: a_poll.quorum_percentage,
+        end_height: a_poll.end_height,
+    }
+}
+
+fn query_batch<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    queries: Vec<QueryMsg>,
+) -> StdResult<Binary> {
+    if queries.iter().any(|q| matches!(q, QueryMsg::Batch { .. })) {
+        return Err(StdError::generic_err("Batch queries cannot be nested"));
     }
+
+    let results = queries
+        .into_iter()
+        .map(|q| query(deps, q))
+        .collect::<StdResult<Vec<Binary>>>()?;
+
+    to_binary(&BatchResponse { results })
 }
 
 fn query_poll<S: Storage, A: Api, Q: Querier>(
@@ -460,7 +4408,7 @@ fn query_poll<S: Storage, A: Api, Q: Querier>(
 
     let poll = match poll_read(&deps.storage).may_load(key.as_bytes())? {
         Some(poll) => Some(poll),
-        None => return Err(StdError::generic_err("Poll does not exist")),
+        None => return Err(missing_poll(&deps.storage, poll_id)),
     }
     .unwrap();
 
@@ -468,9 +4416,30 @@ fn query_poll<S: Storage, A: Api, Q: Querier>(
         creator: deps.api.human_address(&poll.creator).unwrap(),
         status: poll.status,
         quorum_percentage: poll.quorum_percentage,
+        approval_quorum_percentage: poll.approval_quorum_percentage,
         end_height: Some(poll.end_height),
         start_height: poll.start_height,
+        title: poll.title,
+        link: poll.link,
         description: poll.description,
+        execution: poll.execution,
+        executable_at: poll.executable_at,
+        co_creators: poll
+            .co_creators
+            .iter()
+            .map(|c| deps.api.human_address(c))
+            .collect::<StdResult<Vec<HumanAddr>>>()?,
+        yes_votes: poll.yes_votes,
+        no_votes: poll.no_votes,
+        abstain_votes: poll.abstain_votes,
+        veto_threshold: poll.veto_threshold,
+        threshold_percentage: poll.threshold_percentage,
+        veto_votes: poll.veto_votes,
+        votes_root: poll.votes_root,
+        deposit: poll.deposit,
+        reveal_end_height: poll.reveal_end_height,
+        tags: poll.tags,
+        max_vote_weight: poll.max_vote_weight,
     };
     to_binary(&resp)
 }
@@ -487,6 +4456,37 @@ fn token_balance<S: Storage, A: Api, Q: Querier>(
 
     let resp = TokenStakeResponse {
         token_balance: token_manager.token_balance,
+        extra_balances: token_manager.extra_balances,
+    };
+
+    to_binary(&resp)
+}
+
+fn proposer_eligibility<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<Binary> {
+    let state = config_read(&deps.storage).load()?;
+    let key = deps.api.canonical_address(&address)?;
+
+    let resp = match &state.proposer_requirement {
+        Some(requirement) => {
+            let token_manager = bank_read(&deps.storage)
+                .may_load(key.as_slice())?
+                .unwrap_or_default();
+            let (participated, required) =
+                proposer_participation(requirement, state.poll_count, &token_manager);
+            ProposerEligibilityResponse {
+                eligible: participated >= required,
+                participated_polls: participated,
+                required_polls: required,
+            }
+        }
+        None => ProposerEligibilityResponse {
+            eligible: true,
+            participated_polls: 0,
+            required_polls: 0,
+        },
     };
 
     to_binary(&resp)
@@ -1,10 +1,12 @@
 pub mod coin_helpers;
 pub mod contract;
+pub mod merkle;
 pub mod msg;
+pub mod percentage;
 pub mod state;
 
 #[cfg(test)]
 mod tests;
 
 #[cfg(target_arch = "wasm32")]
-cosmwasm_std::create_entry_points!(contract);
+cosmwasm_std::create_entry_points_with_migration!(contract);
@@ -0,0 +1,169 @@
+use crate::msg::{ProofStep, VoteOption};
+use cosmwasm_std::{Binary, CanonicalAddr, StdResult, Uint128};
+use sha2::{Digest, Sha256};
+
+// Votes are committed to in a binary Merkle tree whose leaves are in voter address order.
+// Each level pairs nodes from the left and moves an odd last node up unchanged. Leaves and
+// inner nodes are hashed with different prefixes so one can't pass for the other.
+// Node `index` of level `level` covers leaves `index << level` up to the next node's.
+
+/// sha256(0x00 || voter || option || weight as 16 big-endian bytes)
+pub fn leaf_hash(voter: &CanonicalAddr, vote: VoteOption, weight: Uint128) -> Binary {
+    let option: u8 = match vote {
+        VoteOption::Yes => 0,
+        VoteOption::No => 1,
+        VoteOption::Abstain => 2,
+        VoteOption::NoWithVeto => 3,
+    };
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(voter.as_slice());
+    hasher.update([option]);
+    hasher.update(weight.u128().to_be_bytes());
+    Binary(hasher.finalize().to_vec())
+}
+
+/// sha256(0x01 || left || right)
+pub fn node_hash(left: &Binary, right: &Binary) -> Binary {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left.as_slice());
+    hasher.update(right.as_slice());
+    Binary(hasher.finalize().to_vec())
+}
+
+/// Adds the next leaf to a tree built incrementally. `frontier` holds, per level, the root of
+/// the complete subtree still waiting for its right sibling. Returns the nodes the leaf
+/// completed, lowest level first: for leaf `index`, the one at level `level` is node
+/// `index >> level`.
+pub fn append_leaf(frontier: &mut Vec<Option<Binary>>, leaf: Binary) -> Vec<Binary> {
+    let mut nodes = vec![leaf.clone()];
+    let mut node = leaf;
+    for slot in frontier.iter_mut() {
+        match slot.take() {
+            Some(left) => {
+                node = node_hash(&left, &node);
+                nodes.push(node.clone());
+            }
+            None => {
+                *slot = Some(node);
+                return nodes;
+            }
+        }
+    }
+    frontier.push(Some(node));
+    nodes
+}
+
+/// The root of the leaves appended to `frontier`, None if there were none
+pub fn frontier_root(frontier: &[Option<Binary>]) -> Option<Binary> {
+    frontier
+        .iter()
+        .fold(None, |right, slot| match (slot, right) {
+            (Some(left), Some(right)) => Some(node_hash(left, &right)),
+            (Some(left), None) => Some(left.clone()),
+            (None, right) => right,
+        })
+}
+
+/// The nodes on the right edge of a finished tree that no leaf completed, as (level, node):
+/// each covers the leaves left over after the complete subtrees below that level. With the
+/// nodes append_leaf returned, every node of the tree is known.
+pub fn edge_nodes(frontier: &[Option<Binary>]) -> Vec<(u8, Binary)> {
+    let mut nodes = vec![];
+    let mut right: Option<Binary> = None;
+    for (level, slot) in frontier.iter().enumerate() {
+        right = match (slot, right) {
+            (Some(left), Some(right)) => Some(node_hash(left, &right)),
+            (Some(left), None) => Some(left.clone()),
+            (None, right) => right,
+        };
+        if let Some(node) = &right {
+            nodes.push((level as u8 + 1, node.clone()));
+        }
+    }
+    nodes
+}
+
+/// The siblings on the path from leaf `index` to the root, lowest first. `node` looks up a
+/// node by level and index, and finds none past the end of its level.
+pub fn proof<F>(mut index: u64, node: F) -> StdResult<Vec<ProofStep>>
+where
+    F: Fn(u8, u64) -> StdResult<Option<Binary>>,
+{
+    let mut steps = vec![];
+    let mut level = 0u8;
+    loop {
+        let sibling = index ^ 1;
+        match node(level, sibling)? {
+            Some(hash) => steps.push(ProofStep {
+                hash,
+                left: sibling < index,
+            }),
+            // the only node of its level is the root
+            None if index == 0 => return Ok(steps),
+            // an odd last node moves up unchanged
+            None => {}
+        }
+        index /= 2;
+        level += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn leaves(count: u8) -> Vec<Binary> {
+        (0..count)
+            .map(|i| {
+                let voter = CanonicalAddr(Binary(vec![i; 20]));
+                leaf_hash(&voter, VoteOption::Yes, Uint128::from(u128::from(i)))
+            })
+            .collect()
+    }
+
+    fn verify(leaf: &Binary, steps: &[ProofStep]) -> Binary {
+        steps.iter().fold(leaf.clone(), |node, step| {
+            if step.left {
+                node_hash(&step.hash, &node)
+            } else {
+                node_hash(&node, &step.hash)
+            }
+        })
+    }
+
+    #[test]
+    fn proofs_match_incremental_root() {
+        for count in 1..12 {
+            let leaves = leaves(count);
+            let mut frontier = vec![];
+            let mut nodes = HashMap::new();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let appended = append_leaf(&mut frontier, leaf.clone());
+                for (level, node) in appended.into_iter().enumerate() {
+                    nodes.insert((level as u8, index as u64 >> level), node);
+                }
+            }
+            for (level, node) in edge_nodes(&frontier) {
+                nodes.insert((level, (count as u64 - 1) >> level), node);
+            }
+            let root = frontier_root(&frontier).unwrap();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let steps = proof(index as u64, |level, index| {
+                    Ok(nodes.get(&(level, index)).cloned())
+                })
+                .unwrap();
+                assert_eq!(
+                    verify(leaf, &steps),
+                    root,
+                    "{} leaves, leaf {}",
+                    count,
+                    index
+                );
+            }
+        }
+        assert_eq!(frontier_root(&[]), None);
+    }
+}
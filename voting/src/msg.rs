@@ -1,33 +1,344 @@
-use crate::state::PollStatus;
-use cosmwasm_std::{HumanAddr, Uint128};
+use crate::state::{
+    Bootstrap, Claim, DenomWeight, Duration, PollExecution, PollStatus, ProposerRequirement,
+    VoteWeightCap,
+};
+use cosmwasm_std::{Binary, Coin, Decimal, HumanAddr, Uint128};
+use cw_math::Rounding;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub denom: String,
+    /// erc20 contract staked instead of `denom`. Stake is Sent to this contract and withdrawn
+    /// as transfers of the token. Proposal deposits and funding rounds need a native denom.
+    pub token: Option<HumanAddr>,
+    /// Other native denoms that can be staked, each worth `weight` votes per token where a
+    /// token of `denom` is worth one. They are fixed at instantiation.
+    pub extra_denoms: Option<Vec<DenomWeight>>,
+    pub proposer_requirement: Option<ProposerRequirement>,
+    /// Native denom stakers are rewarded in. Rewards are disabled when not set.
+    pub reward_denom: Option<String>,
+    /// erc20 contract stakers are rewarded in instead of a native denom. Rewards are funded by
+    /// Sending its tokens to this contract.
+    pub reward_token: Option<HumanAddr>,
+    /// Staking-derivative contract whose exchange rate converts the staked derivative units
+    /// into underlying tokens when polls are tallied. Votes count derivative units when not set.
+    pub rate_source: Option<HumanAddr>,
+    /// Rounding of votes converted with the rate source's exchange rate. Defaults to floor.
+    pub weight_rounding: Option<Rounding>,
+    /// Only address allowed to end polls until TALLY_GRACE_PERIOD_BLOCKS after they close.
+    /// Anyone can end polls when not set.
+    pub tally_executor: Option<HumanAddr>,
+    /// Polls created by the contract creator at instantiation, numbered from 1 in order
+    pub initial_polls: Option<Vec<InitialPoll>>,
+    /// Starts the contract with poll creation disabled, so only the initial polls can be voted on
+    pub bootstrap: Option<Bootstrap>,
+    /// Owns the contract and creates the initial polls. Defaults to the sender.
+    pub owner: Option<HumanAddr>,
+    /// Called once the contract is instantiated, e.g. so a factory can learn its address
+    pub init_hook: Option<InitHook>,
+    /// Staking tokens CreatePoll must send. Refunded to the creator when the poll reaches
+    /// quorum and forfeited to the owner when it doesn't.
+    pub proposal_deposit: Option<Uint128>,
+    /// Contract sent a TallyHookMsg with the voters counted by every EndPoll
+    pub tally_hook: Option<HumanAddr>,
+    /// Bounds in blocks on how long polls created with CreatePoll are open for, from their
+    /// start height or creation to their end height
+    pub min_voting_period: Option<u64>,
+    pub max_voting_period: Option<u64>,
+    /// How long WithdrawVotingTokens holds the withdrawn tokens before ClaimUnbonded can release
+    /// them. They are sent at once when not set.
+    pub unbonding_period: Option<Duration>,
+    /// Paid in reward_denom by CreatePoll and shared out among stakers by DistributeRewards
+    pub poll_creation_fee: Option<Uint128>,
+    /// Only addresses the owner adds with AddPollCreator can CreatePoll when true
+    pub restrict_poll_creation: Option<bool>,
 }
 
+/// Sent to the tally hook by each EndPoll that counts votes, and by the EndPoll of a funding
+/// round with its contributors
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+pub enum TallyHookMsg {
+    PollTallied {
+        poll_id: u64,
+        voters: Vec<HumanAddr>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20ReceiveMsg {
+    pub sender: HumanAddr,
+    pub amount: Uint128,
+    /// A ReceiveMsg, or nothing to stake the staking token and fund rewards with the reward token
+    pub msg: Option<Binary>,
+}
+
+/// What tokens `Send` to this contract are for, carried in the Cw20ReceiveMsg
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Stakes the tokens for the sender. Only the staking token can be staked.
+    StakeVotingTokens {},
+    /// Adds the tokens to the rewards. Only the reward token funds rewards.
+    FundRewards {},
+}
+
+/// The messages sent to the staking and reward tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenHandleMsg {
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+        tx_key: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitHook {
+    pub contract_addr: HumanAddr,
+    pub msg: Binary,
+}
+
+/// When migrating a contract instantiated before versions were stored, moves the votes of up to
+/// `limit` polls out of the polls themselves. BackfillVotes moves the rest.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitialPoll {
+    #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+    pub quorum_percentage: Option<Decimal>,
+    #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+    pub approval_quorum_percentage: Option<Decimal>,
+    pub title: String,
+    pub link: Option<String>,
+    pub description: String,
+    pub start_height: Option<u64>,
+    pub end_height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+// Messages are only deserialized once per call, so CreatePoll is not boxed
+#[allow(clippy::large_enum_variant)]
 pub enum HandleMsg {
     CastVote {
         poll_id: u64,
-        vote: String,
+        vote: VoteOption,
+        /// The signer's whole stake when not set
+        weight: Option<Uint128>,
+        /// Why the voter voted this way, up to 256 bytes
+        rationale: Option<String>,
+    },
+    /// Replaces the signer's vote on a poll until voting closes
+    UpdateVote {
+        poll_id: u64,
+        vote: VoteOption,
+        weight: Uint128,
+        /// Replaces the rationale of the old vote, which is kept when not set
+        rationale: Option<String>,
+    },
+    /// Removes the signer's vote from a poll and unlocks its weight, until voting closes
+    RetractVote {
+        poll_id: u64,
+    },
+    /// Votes on a commit-reveal poll without disclosing the option until the reveal period.
+    /// `commitment` is sha256(voter || ":" || vote || ":" || salt), with the vote in snake_case.
+    /// The weight is locked straight away.
+    CommitVote {
+        poll_id: u64,
+        commitment: Binary,
         weight: Uint128,
     },
+    /// Counts a committed vote. Only accepted from end_height until the poll's reveal period
+    /// is over, and votes left unrevealed are discarded when the poll ends.
+    RevealVote {
+        poll_id: u64,
+        vote: VoteOption,
+        salt: String,
+    },
     StakeVotingTokens {},
+    /// Sends the tokens, or holds them as a claim for the unbonding period if there is one.
+    /// Withdraws `denom` by default, and all of it when `amount` is not set.
     WithdrawVotingTokens {
         amount: Option<Uint128>,
+        denom: Option<String>,
     },
+    /// Releases the sender's claims whose unbonding period is over
+    ClaimUnbonded {},
+    /// `quorum_percentage` of the total stake must vote and, if set,
+    /// `approval_quorum_percentage` of it must vote yes for the poll to pass
     CreatePoll {
-        quorum_percentage: Option<u8>,
+        #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+        quorum_percentage: Option<Decimal>,
+        #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+        approval_quorum_percentage: Option<Decimal>,
+        /// 3 to 64 bytes
+        title: String,
+        /// An http(s) URL of up to 256 bytes, e.g. the full proposal text
+        link: Option<String>,
         description: String,
         start_height: Option<u64>,
         end_height: Option<u64>,
+        /// A passing poll with execution is queued instead of being enacted right away
+        execution: Option<PollExecution>,
+        /// Standard when not set
+        poll_type: Option<PollType>,
+        /// Percentage of the votes cast that must be NoWithVeto to veto the poll.
+        /// Vetoes only count as no votes when not set.
+        #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+        veto_threshold: Option<Decimal>,
+        /// Percentage of the yes and no votes that must be yes for the poll to pass, 1 to 100.
+        /// More than 50% when not set.
+        #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+        threshold_percentage: Option<Decimal>,
+        /// Makes the poll commit-reveal, with a reveal period of this many blocks after
+        /// end_height
+        reveal_blocks: Option<u64>,
+        /// Up to 5 tags of 1 to 16 lowercase letters, digits or dashes, e.g. "treasury"
+        tags: Option<Vec<String>>,
+        /// Votes weighing more than this are rejected
+        max_vote_weight: Option<VoteWeightCap>,
+    },
+    /// Allocates the signer's stake between the projects of a funding round. The allocated
+    /// stake is locked until the round ends.
+    Contribute {
+        poll_id: u64,
+        allocations: Vec<Allocation>,
     },
+    /// Anyone can end a poll once voting has closed, unless a tally executor is set: then only
+    /// the executor and the poll's creator and co-creators can until the grace period after
+    /// end_height has passed. Tallies up to `limit` votes per message, continuing where the
+    /// last one stopped, and ends the poll once all votes are counted.
     EndPoll {
         poll_id: u64,
+        limit: Option<u32>,
+    },
+    /// Creator or co-creators only. Replaces the description of a poll that hasn't started yet.
+    EditPoll {
+        poll_id: u64,
+        description: String,
+    },
+    /// Creator only
+    AddCoCreator {
+        poll_id: u64,
+        co_creator: HumanAddr,
+    },
+    /// Creator only
+    RemoveCoCreator {
+        poll_id: u64,
+        co_creator: HumanAddr,
+    },
+    /// Anyone can dispatch the messages of a queued poll once its execution delay is over
+    Execute {
+        poll_id: u64,
+    },
+    /// Owner only. Cancels a queued poll before its execution delay is over.
+    VetoPoll {
+        poll_id: u64,
+    },
+    /// Creator only. Cancels a poll before anyone votes on it, refunding its deposit and a
+    /// funding round's matching pool.
+    CancelPoll {
+        poll_id: u64,
+    },
+    SetProposerRequirement {
+        requirement: Option<ProposerRequirement>,
+    },
+    /// Owner only. Applies to polls created afterwards.
+    SetProposalDeposit {
+        amount: Option<Uint128>,
+    },
+    /// Owner only. Applies to polls created afterwards.
+    SetPollCreationFee {
+        amount: Option<Uint128>,
+    },
+    /// Owner only. Offers ownership to `new_owner`, which takes over once it sends
+    /// AcceptOwnership. A later offer replaces this one.
+    TransferOwnership {
+        new_owner: HumanAddr,
+    },
+    AcceptOwnership {},
+    /// Owner only. Changes the settings given and leaves the rest. The staking denom can only
+    /// change while nothing is staked or held for polls.
+    UpdateConfig {
+        denom: Option<String>,
+        /// Applies to descriptions set afterwards
+        min_description_length: Option<u32>,
+        max_description_length: Option<u32>,
+        weight_rounding: Option<Rounding>,
+        /// Applies to withdrawals made afterwards
+        unbonding_period: Option<Duration>,
+        restrict_poll_creation: Option<bool>,
+    },
+    /// Owner only. Applies to polls created afterwards.
+    SetVotingPeriod {
+        min_voting_period: Option<u64>,
+        max_voting_period: Option<u64>,
+    },
+    SetRateSource {
+        rate_source: Option<HumanAddr>,
+    },
+    SetTallyExecutor {
+        tally_executor: Option<HumanAddr>,
+    },
+    /// Owner only. The hook can fail EndPoll, so it should only be set to a trusted contract.
+    SetTallyHook {
+        tally_hook: Option<HumanAddr>,
+    },
+    /// Owner only. Enables poll creation before the bootstrap height is reached.
+    EndBootstrap {},
+    /// Owner only. The stake of non-voting addresses, such as a treasury, can't vote and is
+    /// left out of the total stake that quorums are calculated against.
+    AddNonVotingAddress {
+        address: HumanAddr,
+    },
+    /// Owner only
+    RemoveNonVotingAddress {
+        address: HumanAddr,
+    },
+    /// Owner only. Allows the address to create polls while poll creation is restricted.
+    AddPollCreator {
+        address: HumanAddr,
+    },
+    /// Owner only
+    RemovePollCreator {
+        address: HumanAddr,
+    },
+    FundRewards {},
+    /// Sent by the staking token to stake the tokens Sent for their sender, or by the reward
+    /// token to fund rewards with them
+    Receive(Cw20ReceiveMsg),
+    ClaimRewards {},
+    /// Shares the poll creation fees collected so far among the current stakers
+    DistributeRewards {},
+    /// Owner only. Checks up to `limit` stakers per message, continuing where the last one
+    /// stopped, and returns an InvariantReport in the response data.
+    CheckInvariants {
+        limit: Option<u32>,
+    },
+    /// Owner only. Releases tokens locked in polls that have ended or don't exist, for up to
+    /// `limit` stakers per message, continuing where the last one stopped.
+    EmergencyUnlockAll {
+        limit: Option<u32>,
+    },
+    /// Owner only. Moves the votes that polls stored inline before the upgrade into the vote
+    /// bucket, for up to `limit` polls per message, continuing where the last one stopped.
+    BackfillVotes {
+        limit: Option<u32>,
+    },
+    /// Owner only. Deletes the Passed and Rejected polls up to `up_to_poll_id` that ended at
+    /// least a retention window ago, checking up to `limit` polls after the last one checked.
+    /// Stops at the first poll that is still open or within the window. A poll with many votes
+    /// can take several messages, each deleting a fixed number of its entries.
+    PrunePolls {
+        up_to_poll_id: u64,
+        limit: Option<u32>,
     },
 }
 
@@ -35,18 +346,170 @@ pub enum HandleMsg {
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     Config {},
-    TokenStake { address: HumanAddr },
-    Poll { poll_id: u64 },
+    TokenStake {
+        address: HumanAddr,
+    },
+    Poll {
+        poll_id: u64,
+    },
+    ProposerEligibility {
+        address: HumanAddr,
+    },
+    /// Run several queries in one round-trip. Batches cannot be nested.
+    Batch {
+        queries: Vec<QueryMsg>,
+    },
+    /// The votes of a poll in voter address order, with the totals the tally used
+    PollAudit {
+        poll_id: u64,
+        start_after: Option<HumanAddr>,
+        limit: Option<u32>,
+    },
+    /// How `voter` voted in a poll, if at all
+    Vote {
+        poll_id: u64,
+        voter: HumanAddr,
+    },
+    /// Proof that `voter`'s vote is part of the poll's committed votes_root
+    ResultProof {
+        poll_id: u64,
+        voter: HumanAddr,
+    },
+    /// The voters of a poll in address order
+    Voters {
+        poll_id: u64,
+        start_after: Option<HumanAddr>,
+        limit: Option<u32>,
+    },
+    Rewards {
+        address: HumanAddr,
+    },
+    RewardRate {},
+    NonVotingAddresses {},
+    /// The poll creator allowlist in address order
+    PollCreators {
+        start_after: Option<HumanAddr>,
+        limit: Option<u32>,
+    },
+    FundingRound {
+        poll_id: u64,
+    },
+    /// The polls `address` voted in, in poll_id order
+    VoterHistory {
+        address: HumanAddr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The polls `address` took part in, in poll_id order, with the weight still locked in each
+    ParticipatedPolls {
+        address: HumanAddr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// How far the vote backfill has got
+    VoteBackfill {},
+    /// Vote totals of a poll, from the votes still stored inline and from the vote bucket
+    PollVoteTotals {
+        poll_id: u64,
+    },
+    /// One row per poll, in poll_id order, optionally only polls with the given status. Each
+    /// page looks at a bounded number of polls, so a filtered page can come back short or empty
+    /// before the last poll; carry on from its last_scanned.
+    Results {
+        status: Option<PollStatus>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Polls whose description starts with `prefix`, ordered by the first 16 bytes of the
+    /// description, then poll_id. `start_after` is the last poll_id of the previous page.
+    SearchPolls {
+        prefix: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Polls with the tag, in poll_id order
+    PollsByTag {
+        tag: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The owner and any ownership transfer waiting to be accepted
+    Ownership {},
+    /// Withdrawals of `address` that haven't been claimed yet, oldest first
+    Claims {
+        address: HumanAddr,
+    },
+    /// The contract name and version the stored state was last written by
+    ContractVersion {},
+    /// How far PrunePolls got. Queries for polls it pruned fail as archived.
+    PollArchive {},
+    StakingInfo {},
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct PollResponse {
     pub creator: HumanAddr,
     pub status: PollStatus,
-    pub quorum_percentage: Option<u8>,
+    pub quorum_percentage: Option<Decimal>,
+    pub approval_quorum_percentage: Option<Decimal>,
     pub end_height: Option<u64>,
     pub start_height: Option<u64>,
+    pub title: String,
+    pub link: Option<String>,
     pub description: String,
+    pub execution: Option<PollExecution>,
+    pub executable_at: Option<u64>,
+    pub co_creators: Vec<HumanAddr>,
+    /// The votes cast so far in staked units, until the tally converts them at the exchange
+    /// rate. Commit-reveal votes count once revealed.
+    pub yes_votes: Uint128,
+    /// Vetoes are included in no_votes too
+    pub no_votes: Uint128,
+    pub abstain_votes: Uint128,
+    pub veto_threshold: Option<Decimal>,
+    pub threshold_percentage: Option<Decimal>,
+    pub veto_votes: Uint128,
+    /// Merkle root over the votes, set when the poll is tallied
+    pub votes_root: Option<Binary>,
+    /// Proposal deposit still held, zero once the poll has ended
+    pub deposit: Uint128,
+    /// Set on commit-reveal polls
+    pub reveal_end_height: Option<u64>,
+    pub tags: Vec<String>,
+    pub max_vote_weight: Option<VoteWeightCap>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NonVotingAddressesResponse {
+    pub addresses: Vec<HumanAddr>,
+    /// Stake currently left out of quorum calculations
+    pub excluded_stake: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollCreatorsResponse {
+    pub creators: Vec<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingInfoResponse {
+    pub total_staked: Uint128,
+    /// Addresses with a stake
+    pub stakers: u64,
+    /// Polls that haven't ended or been cancelled
+    pub active_polls: u64,
+    pub largest_stake: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimsResponse {
+    pub claims: Vec<Claim>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnershipResponse {
+    pub owner: HumanAddr,
+    pub pending_owner: Option<HumanAddr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
@@ -54,12 +517,276 @@ pub struct CreatePollResponse {
     pub poll_id: u64,
 }
 
+/// Returned by EndPoll. Percentages are of the total stake at tally time.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TallyResponse {
+    pub poll_id: u64,
+    pub passed: bool,
+    pub participation_percentage: Decimal,
+    pub approval_percentage: Decimal,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct PollCountResponse {
     pub poll_count: u64,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenStakeResponse {
+    /// In votes
     pub token_balance: Uint128,
+    pub extra_balances: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposerEligibilityResponse {
+    pub eligible: bool,
+    pub participated_polls: u64,
+    pub required_polls: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BatchResponse {
+    pub results: Vec<Binary>,
+}
+
+/// Standard polls are decided by votes. In a funding round voters allocate their stake between
+/// projects instead, and the funds sent with CreatePoll are the matching pool, shared between the
+/// projects in proportion to the square of the sum of the square roots of their contributions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PollType {
+    Standard {},
+    FundingRound { projects: Vec<Project> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Project {
+    pub name: String,
+    /// Receives the project's share of the matching pool
+    pub recipient: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Allocation {
+    /// Index of the project in the funding round
+    pub project: u32,
+    pub weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FundingRoundResponse {
+    pub poll_id: u64,
+    pub matching_pool: Uint128,
+    pub projects: Vec<ProjectResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProjectResult {
+    pub name: String,
+    pub recipient: HumanAddr,
+    pub contributed: Uint128,
+    /// Zero until the round ends
+    pub matched: Uint128,
+}
+
+/// Abstain votes count towards quorum, but not towards the yes/no threshold
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+    /// Counts as no, and rejects the poll outright once vetoes exceed its veto threshold
+    NoWithVeto,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoterHistoryResponse {
+    pub votes: Vec<VoterHistoryEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoterHistoryEntry {
+    pub poll_id: u64,
+    pub vote: VoteOption,
+    pub weight: Uint128,
+    /// InProgress until the poll is tallied
+    pub outcome: PollStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ParticipatedPollsResponse {
+    pub polls: Vec<ParticipatedPoll>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ParticipatedPoll {
+    pub poll_id: u64,
+    /// Zero once the poll has ended
+    pub locked_weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteRecord {
+    pub voter: HumanAddr,
+    pub vote: VoteOption,
+    pub weight: Uint128,
+    /// Zero for votes cast before heights were recorded
+    pub voted_at_height: u64,
+    pub rationale: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteBackfillResponse {
+    pub last_poll_id: u64,
+    /// The last poll created before the migration
+    pub until_poll_id: u64,
+    pub polls_migrated: u64,
+    pub votes_migrated: u64,
+    pub locks_migrated: u64,
+    /// False until the locks of every stake have moved
+    pub locks_complete: bool,
+    pub complete: bool,
+}
+
+/// Summed weights per vote option, in staked units
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteTotals {
+    pub votes: u64,
+    pub yes: Uint128,
+    pub no: Uint128,
+    pub abstain: Uint128,
+    pub no_with_veto: Uint128,
+}
+
+/// Once a poll is migrated `legacy` is empty and `migrated` holds what `legacy` did
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollVoteTotalsResponse {
+    pub poll_id: u64,
+    pub legacy: VoteTotals,
+    pub migrated: VoteTotals,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoterInfo {
+    pub voter: HumanAddr,
+    pub vote: VoteOption,
+    pub weight: Uint128,
+    pub rationale: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Ballot {
+    pub vote: VoteOption,
+    pub weight: Uint128,
+}
+
+/// `ballot` is None if the address did not vote
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteResponse {
+    pub ballot: Option<Ballot>,
+}
+
+/// Hashing the leaf with each step in turn gives the root: sha256(0x01 || hash || node) when
+/// `left` is set, sha256(0x01 || node || hash) otherwise. Leaves are sha256(0x00 || canonical
+/// voter address || option || weight), with the option as one byte (yes 0, no 1, abstain 2,
+/// no_with_veto 3) and the weight as 16 big-endian bytes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ResultProofResponse {
+    pub root: Binary,
+    pub leaf: Binary,
+    pub vote: VoteOption,
+    pub weight: Uint128,
+    pub proof: Vec<ProofStep>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProofStep {
+    pub hash: Binary,
+    /// The sibling is on the left
+    pub left: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotersResponse {
+    pub voters: Vec<VoterInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollAuditResponse {
+    pub poll_id: u64,
+    pub status: PollStatus,
+    pub yes_votes: Uint128,
+    pub no_votes: Uint128,
+    pub abstain_votes: Uint128,
+    /// None until the poll is tallied
+    pub staked_weight: Option<Uint128>,
+    pub votes: Vec<VoteRecord>,
+}
+
+/// Flat summary of a poll. Votes are running totals until the poll is tallied.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollResult {
+    pub poll_id: u64,
+    pub title: String,
+    pub status: PollStatus,
+    pub yes: Uint128,
+    /// Vetoes are included in no too
+    pub no: Uint128,
+    pub abstain: Uint128,
+    pub veto: Uint128,
+    pub quorum_pct: Option<Decimal>,
+    pub passed: bool,
+    pub end_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ResultsResponse {
+    pub results: Vec<PollResult>,
+    /// The last poll this page looked at, whether it was listed or not. Pass it as start_after
+    /// for the next page. None once there are no more polls to look at.
+    pub last_scanned: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardsResponse {
+    pub pending_rewards: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardRateResponse {
+    /// Empty when rewards are paid in `token`
+    pub denom: String,
+    pub token: Option<HumanAddr>,
+    pub total_funded: Uint128,
+    /// Poll creation fees waiting for DistributeRewards
+    pub accrued_fees: Uint128,
+    pub staked_tokens: Uint128,
+    /// Annualised rate of the rewards funded so far against the current stake.
+    /// None until rewards have been funded over more than one point in time.
+    pub apr: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvariantReport {
+    /// true once all stakers have been checked; the check starts over on the next message
+    pub complete: bool,
+    pub accounts_checked: u64,
+    pub violations: Vec<String>,
+}
+
+/// The part of the staking-derivative query interface used to read its exchange rate
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RateSourceQueryMsg {
+    Investment {},
+}
+
+/// Subset of the staking-derivative InvestmentResponse. One derivative unit is worth
+/// staked_tokens / token_supply underlying tokens.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvestmentResponse {
+    pub token_supply: Uint128,
+    pub staked_tokens: Coin,
 }
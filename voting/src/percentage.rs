@@ -0,0 +1,54 @@
+use cosmwasm_std::{Decimal, StdError, StdResult};
+use serde::de::{self, Deserializer, Visitor};
+use std::fmt;
+
+/// 100 percent
+pub fn hundred() -> Decimal {
+    Decimal::from_ratio(100u128, 1u128)
+}
+
+/// `part` as a percentage of `total`, which must not be zero
+pub fn of(part: u128, total: u128) -> StdResult<Decimal> {
+    let scaled = part
+        .checked_mul(100)
+        .ok_or_else(|| StdError::generic_err(format!("Overflow: {} * 100", part)))?;
+    Ok(Decimal::from_ratio(scaled, total))
+}
+
+/// Reads an optional percentage given either as a decimal string such as "33.4" or as a whole
+/// number, which is how percentages were sent and stored before they became Decimal.
+pub fn deserialize_option<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Decimal>, D::Error> {
+    deserializer.deserialize_option(PercentageVisitor)
+}
+
+struct PercentageVisitor;
+
+impl<'de> Visitor<'de> for PercentageVisitor {
+    type Value = Option<Decimal>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a percentage as a decimal string or a whole number")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Some(Decimal::from_ratio(value, 1u64)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        value.parse().map(Some).map_err(E::custom)
+    }
+}
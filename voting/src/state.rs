@@ -1,33 +1,200 @@
-use cosmwasm_std::{CanonicalAddr, Storage, Uint128};
+use crate::msg::VoteOption;
+use cosmwasm_std::{Binary, CanonicalAddr, Coin, CosmosMsg, Decimal, HumanAddr, Storage, Uint128};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
 };
+use cw_math::Rounding;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 static CONFIG_KEY: &[u8] = b"config";
 static POLL_KEY: &[u8] = b"polls";
 static BANK_KEY: &[u8] = b"bank";
+static LOCKS_KEY: &[u8] = b"locks";
+static REWARDS_KEY: &[u8] = b"rewards";
+static INVARIANT_CHECK_KEY: &[u8] = b"invariant_check";
+static EMERGENCY_UNLOCK_KEY: &[u8] = b"emergency_unlock";
+static POLL_INDEX_KEY: &[u8] = b"poll_index";
+static NON_VOTING_KEY: &[u8] = b"non_voting";
+static FUNDING_RESERVE_KEY: &[u8] = b"funding_reserve";
+static DEPOSIT_RESERVE_KEY: &[u8] = b"deposit_reserve";
+static VOTER_HISTORY_KEY: &[u8] = b"voter_history";
+static VOTES_KEY: &[u8] = b"votes";
+static VOTE_BACKFILL_KEY: &[u8] = b"vote_backfill";
+static POLL_TALLY_KEY: &[u8] = b"poll_tally";
+static COMMITMENTS_KEY: &[u8] = b"commitments";
+static PENDING_OWNER_KEY: &[u8] = b"pending_owner";
+static CLAIMS_KEY: &[u8] = b"claims";
+static UNBONDING_RESERVE_KEY: &[u8] = b"unbonding_reserve";
+static CONTRACT_VERSION_KEY: &[u8] = b"contract_version";
+static POLL_ARCHIVE_KEY: &[u8] = b"poll_archive";
+static POLL_CREATORS_KEY: &[u8] = b"poll_creators";
+static STAKE_SIZES_KEY: &[u8] = b"stake_sizes";
+static POLL_TAGS_KEY: &[u8] = b"poll_tags";
+static VOTE_TREE_KEY: &[u8] = b"vote_tree";
+static VOTE_LEAVES_KEY: &[u8] = b"vote_leaves";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub denom: String,
+    /// erc20 contract staked instead of denom when set
+    pub token: Option<CanonicalAddr>,
+    /// Other native denoms that can be staked, each worth `weight` votes per token. Stake and
+    /// staked_tokens count votes, so one staked token of `denom` is one vote.
+    #[serde(default)]
+    pub extra_denoms: Vec<DenomWeight>,
     pub owner: CanonicalAddr,
     pub poll_count: u64,
     pub staked_tokens: Uint128,
+    pub proposer_requirement: Option<ProposerRequirement>,
+    pub rate_source: Option<CanonicalAddr>,
+    pub tally_executor: Option<CanonicalAddr>,
+    /// How vote weights converted at the exchange rate are rounded to whole tokens
+    #[serde(default = "default_weight_rounding")]
+    pub weight_rounding: Rounding,
+    pub bootstrap: Option<Bootstrap>,
+    /// Staking tokens CreatePoll must send, refunded once the poll reaches quorum
+    pub proposal_deposit: Option<Uint128>,
+    /// Contract sent the voters of every poll as it is tallied
+    pub tally_hook: Option<CanonicalAddr>,
+    /// Bounds in blocks on how long polls created with CreatePoll are open for
+    pub min_voting_period: Option<u64>,
+    pub max_voting_period: Option<u64>,
+    /// Bounds in bytes on poll descriptions, 3 and 64 when not set
+    pub min_description_length: Option<u32>,
+    pub max_description_length: Option<u32>,
+    /// Withdrawn stake is held this long before ClaimUnbonded releases it. Withdrawals pay out
+    /// at once when not set.
+    pub unbonding_period: Option<Duration>,
+    /// Reward denom CreatePoll must pay, shared out among stakers by DistributeRewards
+    pub poll_creation_fee: Option<Uint128>,
+    /// Only addresses on the poll creator allowlist can CreatePoll when set
+    #[serde(default)]
+    pub restrict_poll_creation: bool,
+    /// Addresses with a stake, counted from when the counter was introduced
+    #[serde(default)]
+    pub staker_count: u64,
+    /// Polls that haven't ended or been cancelled, counted from when the counter was introduced
+    #[serde(default)]
+    pub active_polls: u64,
+}
+
+/// While bootstrapping, staking and voting work but CreatePoll is disabled until the owner
+/// ends it with EndBootstrap or `until_height` is reached
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Bootstrap {
+    pub until_height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Duration {
+    Blocks(u64),
+    Seconds(u64),
+}
+
+/// Most weight a single voter can cast on a poll
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteWeightCap {
+    /// In staked units
+    Absolute(Uint128),
+    /// Of the total stake when the vote is cast, up to 100
+    Percentage(Decimal),
+}
+
+/// Block height or time, in seconds, from which a claim can be released
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Expiration {
+    AtHeight(u64),
+    AtTime(u64),
+}
+
+/// The contract and version that last instantiated or migrated the stored state
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractVersion {
+    pub contract: String,
+    pub version: String,
+}
+
+/// Stake withdrawn during the unbonding period, waiting to be claimed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Claim {
+    pub amount: Uint128,
+    /// Set when the tokens are in one of the extra denoms
+    #[serde(default)]
+    pub denom: Option<String>,
+    pub release_at: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomWeight {
+    pub denom: String,
+    pub weight: u64,
+}
+
+// the rounding of state saved before it could be configured, which is also the init default
+fn default_weight_rounding() -> Rounding {
+    Rounding::Floor
+}
+
+/// Poll creators must have voted in at least `min_polls` of the last `last_polls` polls
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposerRequirement {
+    pub min_polls: u64,
+    pub last_polls: u64,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenManager {
-    pub token_balance: Uint128,             // total staked balance
-    pub locked_tokens: Vec<(u64, Uint128)>, //maps poll_id to weight voted
-    pub participated_polls: Vec<u64>,       // poll_id
+    pub token_balance: Uint128, // total staked balance, in votes
+    #[serde(default)]
+    pub extra_balances: Vec<Coin>, // staked tokens of the extra denoms
+    pub participated_polls: Vec<u64>, // poll_id
+    #[serde(default)]
+    pub reward_index: Uint128, // reward index at the last settlement
+    #[serde(default)]
+    pub pending_rewards: Uint128, // settled but unclaimed rewards
+}
+
+/// The weight stakes locked in each poll before locks moved to the lock bucket. Stakes that
+/// don't carry it read as empty.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyLocks {
+    #[serde(default)]
+    pub locked_tokens: Vec<(u64, Uint128)>, // poll_id and the weight voted
+}
+
+/// Rewards are tracked with a global reward-per-staked-token index, scaled by
+/// REWARD_INDEX_PRECISION. Each staker settles against it whenever their stake changes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardState {
+    pub denom: String,
+    #[serde(default)]
+    pub token: Option<CanonicalAddr>, // erc20 rewards are paid in instead of denom when set
+    pub index: Uint128,
+    pub total_funded: Uint128,
+    pub first_funded_at: Option<u64>,
+    pub last_funded_at: Option<u64>,
+    /// Poll creation fees collected since the last DistributeRewards
+    #[serde(default)]
+    pub accrued_fees: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Voter {
-    pub vote: String,
+    pub vote: VoteOption,
+    pub weight: Uint128,
+    pub voted_at_height: u64, // zero for votes cast before heights were recorded
+    pub rationale: Option<String>,
+}
+
+/// A vote committed to on a commit-reveal poll, which counts once it is revealed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Commitment {
+    pub hash: Binary,
     pub weight: Uint128,
 }
 
@@ -37,20 +204,161 @@ pub enum PollStatus {
     Tally,
     Passed,
     Rejected,
+    /// Passed with messages that can be executed once the execution delay is over
+    Queued,
+    Executed,
+    /// Vetoed by the owner while queued
+    Vetoed,
+    /// NoWithVeto votes exceeded the poll's veto threshold
+    RejectedWithVeto,
+    /// Cancelled by its creator before any votes were cast
+    Cancelled,
+}
+
+/// Messages a poll dispatches with Execute, no sooner than `delay_blocks` after it passed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollExecution {
+    pub msgs: Vec<CosmosMsg>,
+    pub delay_blocks: u64,
+}
+
+/// A vote in the voting history of an address. The outcome is read from the poll.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteHistory {
+    pub vote: VoteOption,
+    pub weight: Uint128,
+}
+
+/// Stake allocated to the projects of a funding round, and the pool matching it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FundingRound {
+    pub matching_pool: Uint128,
+    pub projects: Vec<FundingProject>,
+    pub contributors: Vec<CanonicalAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FundingProject {
+    pub name: String,
+    pub recipient: HumanAddr,
+    pub contributed: Uint128,
+    pub sqrt_sum: Uint128, // sum of the square roots of the contributions
+    pub matched: Uint128,  // share of the matching pool, set at tally
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Poll {
     pub creator: CanonicalAddr,
     pub status: PollStatus,
-    pub quorum_percentage: Option<u8>,
-    pub yes_votes: Uint128,
-    pub no_votes: Uint128,
-    pub voters: Vec<CanonicalAddr>,
-    pub voter_info: Vec<Voter>,
+    #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+    pub quorum_percentage: Option<Decimal>,
+    #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+    pub approval_quorum_percentage: Option<Decimal>,
+    // running totals in staked units until the poll is tallied
+    pub yes_votes: Uint128, // in underlying tokens when a rate source is set
+    pub no_votes: Uint128,  // in underlying tokens when a rate source is set
+    #[serde(default)]
+    pub abstain_votes: Uint128, // in underlying tokens when a rate source is set
+    #[serde(default)]
+    pub veto_votes: Uint128, // included in no_votes
+    #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+    pub veto_threshold: Option<Decimal>,
+    #[serde(default, deserialize_with = "crate::percentage::deserialize_option")]
+    pub threshold_percentage: Option<Decimal>, // more than 50% when not set
+    pub staked_weight: Option<Uint128>, // total staked when the poll was tallied
     pub end_height: u64,
     pub start_height: Option<u64>,
+    #[serde(default)]
+    pub title: String,
+    pub link: Option<String>,
     pub description: String,
+    pub execution: Option<PollExecution>,
+    pub executable_at: Option<u64>, // set when the poll is queued
+    pub votes_root: Option<Binary>, // Merkle root over the votes, set when the poll is tallied
+    /// Can edit the poll before it starts and end it like the creator
+    #[serde(default)]
+    pub co_creators: Vec<CanonicalAddr>,
+    pub funding_round: Option<FundingRound>,
+    /// Proposal deposit held until the poll ends
+    #[serde(default)]
+    pub deposit: Uint128,
+    /// Set on commit-reveal polls, which take CommitVote until end_height and RevealVote from
+    /// then until this height
+    pub reveal_end_height: Option<u64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub max_vote_weight: Option<VoteWeightCap>,
+}
+
+/// The votes polls stored inline before they moved to the per-poll vote bucket. Polls that
+/// don't carry them read as empty.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyVotes {
+    #[serde(default)]
+    pub voters: Vec<CanonicalAddr>,
+    #[serde(default)]
+    pub voter_info: Vec<LegacyVoter>,
+}
+
+/// A vote as polls stored it inline, where `vote` was whatever string CastVote was sent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyVoter {
+    pub vote: String,
+    pub weight: Uint128,
+}
+
+impl LegacyVoter {
+    /// Counted the way polls tallied inline votes: "yes" is yes and anything else is no
+    pub fn vote_option(&self) -> VoteOption {
+        if self.vote == "yes" {
+            VoteOption::Yes
+        } else {
+            VoteOption::No
+        }
+    }
+}
+
+/// Progress of moving legacy votes into the vote bucket, by poll_id
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteBackfill {
+    pub last_poll_id: u64,
+    pub until_poll_id: u64, // the last poll created before the migration
+    pub polls_migrated: u64,
+    pub votes_migrated: u64,
+    /// Set while stakes still have locks to move into the lock bucket
+    #[serde(default)]
+    pub locks_pending: bool,
+    /// The last stake whose locks were moved
+    #[serde(default)]
+    pub last_staker: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub locks_migrated: u64,
+}
+
+/// Progress of PrunePolls. Polls up to `pruned_through` that are no longer stored were pruned.
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollArchive {
+    pub pruned_through: u64,
+    pub polls_pruned: u64,
+}
+
+/// Progress of committing the votes of an ended poll to its Merkle root over several EndPoll
+/// messages
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollTally {
+    pub last_voter: Option<Binary>,
+    pub frontier: Vec<Option<Binary>>, // of the Merkle tree over the votes tallied so far
+    pub votes_tallied: u64,
+}
+
+/// Progress of an invariant check that spans several CheckInvariants messages
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvariantCheck {
+    pub last_key: Option<Binary>,
+    pub accounts_checked: u64,
+    pub balance_sum: Uint128,
+    pub violations: Vec<String>,
 }
 
 pub fn config<S: Storage>(storage: &mut S) -> Singleton<S, State> {
@@ -69,6 +377,11 @@ pub fn poll_read<S: Storage>(storage: &S) -> ReadonlyBucket<S, Poll> {
     bucket_read(POLL_KEY, storage)
 }
 
+/// Reads polls for the inline votes they stored before the vote bucket
+pub fn legacy_votes_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, LegacyVotes> {
+    bucket_read(POLL_KEY, storage)
+}
+
 pub fn bank<S: Storage>(storage: &mut S) -> Bucket<S, TokenManager> {
     bucket(BANK_KEY, storage)
 }
@@ -76,3 +389,243 @@ pub fn bank<S: Storage>(storage: &mut S) -> Bucket<S, TokenManager> {
 pub fn bank_read<S: Storage>(storage: &S) -> ReadonlyBucket<S, TokenManager> {
     bucket_read(BANK_KEY, storage)
 }
+
+/// Reads stakes for the locks they stored before the lock bucket
+pub fn legacy_locks_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, LegacyLocks> {
+    bucket_read(BANK_KEY, storage)
+}
+
+/// Weight the voter has locked in each poll they voted in, keyed by the poll_id's big-endian bytes
+pub fn locks<'a, S: Storage>(storage: &'a mut S, voter: &CanonicalAddr) -> Bucket<'a, S, Uint128> {
+    Bucket::multilevel(&[LOCKS_KEY, voter.as_slice()], storage)
+}
+
+pub fn locks_read<'a, S: Storage>(
+    storage: &'a S,
+    voter: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, Uint128> {
+    ReadonlyBucket::multilevel(&[LOCKS_KEY, voter.as_slice()], storage)
+}
+
+pub fn rewards<S: Storage>(storage: &mut S) -> Singleton<S, RewardState> {
+    singleton(storage, REWARDS_KEY)
+}
+
+pub fn rewards_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, RewardState> {
+    singleton_read(storage, REWARDS_KEY)
+}
+
+/// Tallies in progress, keyed by the poll_id's big-endian bytes
+pub fn poll_tally<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, PollTally> {
+    bucket(POLL_TALLY_KEY, storage)
+}
+
+pub fn poll_tally_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, PollTally> {
+    bucket_read(POLL_TALLY_KEY, storage)
+}
+
+pub fn invariant_check<S: Storage>(storage: &mut S) -> Singleton<S, InvariantCheck> {
+    singleton(storage, INVARIANT_CHECK_KEY)
+}
+
+pub fn invariant_check_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, InvariantCheck> {
+    singleton_read(storage, INVARIANT_CHECK_KEY)
+}
+
+/// Poll ids keyed by the start of their description followed by the poll id
+pub fn poll_index<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, u64> {
+    bucket(POLL_INDEX_KEY, storage)
+}
+
+pub fn poll_index_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, u64> {
+    bucket_read(POLL_INDEX_KEY, storage)
+}
+
+/// The polls with a tag, keyed by poll_id in big-endian
+pub fn poll_tags<'a, S: Storage>(storage: &'a mut S, tag: &str) -> Bucket<'a, S, u64> {
+    Bucket::multilevel(&[POLL_TAGS_KEY, tag.as_bytes()], storage)
+}
+
+pub fn poll_tags_read<'a, S: Storage>(storage: &'a S, tag: &str) -> ReadonlyBucket<'a, S, u64> {
+    ReadonlyBucket::multilevel(&[POLL_TAGS_KEY, tag.as_bytes()], storage)
+}
+
+/// Addresses whose stake can't vote and doesn't count towards quorum, keyed by address
+pub fn non_voting<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, bool> {
+    bucket(NON_VOTING_KEY, storage)
+}
+
+pub fn non_voting_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, bool> {
+    bucket_read(NON_VOTING_KEY, storage)
+}
+
+/// Addresses allowed to create polls while poll creation is restricted, keyed by address
+pub fn poll_creators<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, bool> {
+    bucket(POLL_CREATORS_KEY, storage)
+}
+
+pub fn poll_creators_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, bool> {
+    bucket_read(POLL_CREATORS_KEY, storage)
+}
+
+/// Stakes keyed by their token_balance in big-endian followed by the staker, so the largest
+/// sorts last
+pub fn stake_sizes<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, bool> {
+    bucket(STAKE_SIZES_KEY, storage)
+}
+
+pub fn stake_sizes_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, bool> {
+    bucket_read(STAKE_SIZES_KEY, storage)
+}
+
+/// Votes cast in poll `poll_id`, keyed by voter address
+pub fn votes<'a, S: Storage>(storage: &'a mut S, poll_id: u64) -> Bucket<'a, S, Voter> {
+    Bucket::multilevel(&[VOTES_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+pub fn votes_read<'a, S: Storage>(storage: &'a S, poll_id: u64) -> ReadonlyBucket<'a, S, Voter> {
+    ReadonlyBucket::multilevel(&[VOTES_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+/// Nodes of the Merkle tree over a tallied poll's votes, keyed by vote_tree_key
+pub fn vote_tree<'a, S: Storage>(storage: &'a mut S, poll_id: u64) -> Bucket<'a, S, Binary> {
+    Bucket::multilevel(&[VOTE_TREE_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+pub fn vote_tree_read<'a, S: Storage>(
+    storage: &'a S,
+    poll_id: u64,
+) -> ReadonlyBucket<'a, S, Binary> {
+    ReadonlyBucket::multilevel(&[VOTE_TREE_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+/// The level followed by the node's big-endian index in it
+pub fn vote_tree_key(level: u8, index: u64) -> Vec<u8> {
+    let mut key = vec![level];
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+/// The index of each voter's leaf in the Merkle tree over a tallied poll's votes, keyed by voter
+pub fn vote_leaves<'a, S: Storage>(storage: &'a mut S, poll_id: u64) -> Bucket<'a, S, u64> {
+    Bucket::multilevel(&[VOTE_LEAVES_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+pub fn vote_leaves_read<'a, S: Storage>(
+    storage: &'a S,
+    poll_id: u64,
+) -> ReadonlyBucket<'a, S, u64> {
+    ReadonlyBucket::multilevel(&[VOTE_LEAVES_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+/// Unrevealed commitments of a poll, keyed by voter
+pub fn commitments<'a, S: Storage>(storage: &'a mut S, poll_id: u64) -> Bucket<'a, S, Commitment> {
+    Bucket::multilevel(&[COMMITMENTS_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+pub fn commitments_read<'a, S: Storage>(
+    storage: &'a S,
+    poll_id: u64,
+) -> ReadonlyBucket<'a, S, Commitment> {
+    ReadonlyBucket::multilevel(&[COMMITMENTS_KEY, &poll_id.to_be_bytes()], storage)
+}
+
+pub fn poll_archive<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, PollArchive> {
+    singleton(storage, POLL_ARCHIVE_KEY)
+}
+
+pub fn poll_archive_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, PollArchive> {
+    singleton_read(storage, POLL_ARCHIVE_KEY)
+}
+
+pub fn vote_backfill<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, VoteBackfill> {
+    singleton(storage, VOTE_BACKFILL_KEY)
+}
+
+pub fn vote_backfill_read<'a, S: Storage>(
+    storage: &'a S,
+) -> ReadonlySingleton<'a, S, VoteBackfill> {
+    singleton_read(storage, VOTE_BACKFILL_KEY)
+}
+
+/// Votes cast by `voter`, keyed by the poll_id's big-endian bytes
+pub fn voter_history<'a, S: Storage>(
+    storage: &'a mut S,
+    voter: &CanonicalAddr,
+) -> Bucket<'a, S, VoteHistory> {
+    Bucket::multilevel(&[VOTER_HISTORY_KEY, voter.as_slice()], storage)
+}
+
+pub fn voter_history_read<'a, S: Storage>(
+    storage: &'a S,
+    voter: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, VoteHistory> {
+    ReadonlyBucket::multilevel(&[VOTER_HISTORY_KEY, voter.as_slice()], storage)
+}
+
+/// Matching pools of funding rounds that haven't ended, held in the staking denom
+pub fn funding_reserve<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, Uint128> {
+    singleton(storage, FUNDING_RESERVE_KEY)
+}
+
+pub fn funding_reserve_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, Uint128> {
+    singleton_read(storage, FUNDING_RESERVE_KEY)
+}
+
+/// Proposal deposits of polls that haven't ended, held in the staking denom
+pub fn deposit_reserve<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, Uint128> {
+    singleton(storage, DEPOSIT_RESERVE_KEY)
+}
+
+pub fn deposit_reserve_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, Uint128> {
+    singleton_read(storage, DEPOSIT_RESERVE_KEY)
+}
+
+/// Last staker processed by an EmergencyUnlockAll run that spans several messages
+pub fn emergency_unlock<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, Binary> {
+    singleton(storage, EMERGENCY_UNLOCK_KEY)
+}
+
+pub fn emergency_unlock_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, Binary> {
+    singleton_read(storage, EMERGENCY_UNLOCK_KEY)
+}
+
+/// Claims of each address, in the order they were made
+pub fn claims<'a, S: Storage>(storage: &'a mut S) -> Bucket<'a, S, Vec<Claim>> {
+    bucket(CLAIMS_KEY, storage)
+}
+
+pub fn claims_read<'a, S: Storage>(storage: &'a S) -> ReadonlyBucket<'a, S, Vec<Claim>> {
+    bucket_read(CLAIMS_KEY, storage)
+}
+
+/// Withdrawn stake that hasn't been claimed yet, held in the staking denom
+pub fn unbonding_reserve<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, Uint128> {
+    singleton(storage, UNBONDING_RESERVE_KEY)
+}
+
+pub fn unbonding_reserve_read<'a, S: Storage>(storage: &'a S) -> ReadonlySingleton<'a, S, Uint128> {
+    singleton_read(storage, UNBONDING_RESERVE_KEY)
+}
+
+/// Address the owner has offered ownership to, until it accepts
+pub fn pending_owner<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, CanonicalAddr> {
+    singleton(storage, PENDING_OWNER_KEY)
+}
+
+pub fn pending_owner_read<'a, S: Storage>(
+    storage: &'a S,
+) -> ReadonlySingleton<'a, S, CanonicalAddr> {
+    singleton_read(storage, PENDING_OWNER_KEY)
+}
+
+/// Not set on contracts instantiated before it was introduced
+pub fn contract_version<'a, S: Storage>(storage: &'a mut S) -> Singleton<'a, S, ContractVersion> {
+    singleton(storage, CONTRACT_VERSION_KEY)
+}
+
+pub fn contract_version_read<'a, S: Storage>(
+    storage: &'a S,
+) -> ReadonlySingleton<'a, S, ContractVersion> {
+    singleton_read(storage, CONTRACT_VERSION_KEY)
+}
@@ -1,13 +1,38 @@
 #[cfg(test)]
 mod tests {
-    use crate::contract::{handle, init, query, VOTING_TOKEN};
-    use crate::msg::{HandleMsg, InitMsg, PollResponse, QueryMsg};
-    use crate::state::{config_read, PollStatus, State};
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use crate::contract::{
+        handle, init, migrate, query, vote_commitment, CONTRACT_NAME, CONTRACT_VERSION,
+        POLL_RETENTION_BLOCKS, TALLY_GRACE_PERIOD_BLOCKS, VOTING_TOKEN,
+    };
+    use crate::merkle::{leaf_hash, node_hash};
+    use crate::msg::{
+        Allocation, Ballot, BatchResponse, ClaimsResponse, Cw20ReceiveMsg, FundingRoundResponse,
+        HandleMsg, InitHook, InitMsg, InitialPoll, InvariantReport, InvestmentResponse, MigrateMsg,
+        NonVotingAddressesResponse, OwnershipResponse, ParticipatedPoll, ParticipatedPollsResponse,
+        PollAuditResponse, PollCreatorsResponse, PollResponse, PollResult, PollType,
+        PollVoteTotalsResponse, Project, ProposerEligibilityResponse, QueryMsg, RateSourceQueryMsg,
+        ReceiveMsg, ResultProofResponse, ResultsResponse, RewardRateResponse, RewardsResponse,
+        StakingInfoResponse, TallyHookMsg, TallyResponse, TokenHandleMsg, TokenStakeResponse,
+        VoteBackfillResponse, VoteOption, VoteRecord, VoteResponse, VoteTotals, VoterHistoryEntry,
+        VoterHistoryResponse, VoterInfo, VotersResponse,
+    };
+    use crate::state::{
+        bank, config, config_read, contract_version, locks, poll_read, vote_tree_read, Bootstrap,
+        Claim, ContractVersion, DenomWeight, Duration, Expiration, PollArchive, PollExecution,
+        PollStatus, ProposerRequirement, State, TokenManager, VoteWeightCap,
+    };
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+    };
     use cosmwasm_std::{
-        coins, from_binary, log, Api, BankMsg, Coin, CosmosMsg, Env, Extern, HandleResponse,
-        HumanAddr, StdError, Uint128,
+        coin, coins, from_binary, from_slice, log, to_binary, to_vec, Api, BankMsg, Binary, Coin,
+        CosmosMsg, Decimal, Empty, Env, Extern, HandleResponse, HumanAddr, Order, Querier,
+        QuerierResult, QueryRequest, ReadonlyStorage, StdError, Storage, Uint128, WasmMsg,
+        WasmQuery,
     };
+    use cosmwasm_storage::to_length_prefixed;
+    use cw_math::{mul_ratio, Rounding};
+    use serde::Deserialize;
 
     const DEFAULT_END_HEIGHT: u64 = 100800u64;
     const TEST_CREATOR: &str = "creator";
@@ -17,6 +42,25 @@ mod tests {
     fn mock_init(mut deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
         let msg = InitMsg {
             denom: String::from(VOTING_TOKEN),
+            proposer_requirement: None,
+            reward_denom: None,
+            rate_source: None,
+            initial_polls: None,
+            tally_executor: None,
+            weight_rounding: None,
+            bootstrap: None,
+            owner: None,
+            init_hook: None,
+            proposal_deposit: None,
+            tally_hook: None,
+            reward_token: None,
+            min_voting_period: None,
+            max_voting_period: None,
+            unbonding_period: None,
+            poll_creation_fee: None,
+            token: None,
+            extra_denoms: None,
+            restrict_poll_creation: None,
         };
 
         let env = mock_env(TEST_CREATOR, &coins(2, &msg.denom));
@@ -33,6 +77,25 @@ mod tests {
     fn init_msg() -> InitMsg {
         InitMsg {
             denom: String::from(VOTING_TOKEN),
+            proposer_requirement: None,
+            reward_denom: None,
+            rate_source: None,
+            initial_polls: None,
+            tally_executor: None,
+            weight_rounding: None,
+            bootstrap: None,
+            owner: None,
+            init_hook: None,
+            proposal_deposit: None,
+            tally_hook: None,
+            reward_token: None,
+            min_voting_period: None,
+            max_voting_period: None,
+            unbonding_period: None,
+            poll_creation_fee: None,
+            token: None,
+            extra_denoms: None,
+            restrict_poll_creation: None,
         }
     }
 
@@ -56,10 +119,149 @@ mod tests {
                     .unwrap(),
                 poll_count: 0,
                 staked_tokens: Uint128::zero(),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: 0,
+                active_polls: 0,
             }
         );
     }
 
+    #[test]
+    fn initialization_for_owner_calls_init_hook() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg {
+            owner: Some(HumanAddr::from(TEST_CREATOR)),
+            init_hook: Some(InitHook {
+                contract_addr: HumanAddr::from("factory"),
+                msg: Binary::from(b"{}".to_vec()),
+            }),
+            ..init_msg()
+        };
+        let env = mock_env("factory", &[]);
+        let res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr::from("factory"),
+                msg: Binary::from(b"{}".to_vec()),
+                send: vec![],
+            })]
+        );
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            state.owner,
+            deps.api
+                .canonical_address(&HumanAddr::from(TEST_CREATOR))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn initialization_with_initial_polls() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg {
+            initial_polls: Some(vec![
+                InitialPoll {
+                    quorum_percentage: Some(percent(30)),
+                    description: "first".to_string(),
+                    start_height: None,
+                    end_height: Some(20_000),
+                    approval_quorum_percentage: None,
+                    title: "Initial poll".to_string(),
+                    link: None,
+                },
+                InitialPoll {
+                    quorum_percentage: None,
+                    description: "second".to_string(),
+                    start_height: Some(15_000),
+                    end_height: None,
+                    approval_quorum_percentage: None,
+                    title: "Initial poll".to_string(),
+                    link: None,
+                },
+            ]),
+            ..init_msg()
+        };
+        let env = mock_env(TEST_CREATOR, &[]);
+        init(&mut deps, env.clone(), msg).unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.poll_count, 2);
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.creator, HumanAddr::from(TEST_CREATOR));
+        assert_eq!(value.status, PollStatus::InProgress);
+        assert_eq!(value.quorum_percentage, Some(percent(30)));
+        assert_eq!(value.end_height, Some(20_000));
+        assert_eq!(value.description, "first");
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 2 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.start_height, Some(15_000));
+        assert_eq!(
+            value.end_height,
+            Some(env.block.height + DEFAULT_END_HEIGHT)
+        );
+        assert_eq!(value.description, "second");
+    }
+
+    #[test]
+    fn fails_initialization_with_invalid_initial_poll() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg {
+            initial_polls: Some(vec![
+                InitialPoll {
+                    quorum_percentage: None,
+                    description: "valid".to_string(),
+                    start_height: None,
+                    end_height: None,
+                    approval_quorum_percentage: None,
+                    title: "Initial poll".to_string(),
+                    link: None,
+                },
+                InitialPoll {
+                    quorum_percentage: None,
+                    description: "a".to_string(),
+                    start_height: None,
+                    end_height: None,
+                    approval_quorum_percentage: None,
+                    title: "Initial poll".to_string(),
+                    link: None,
+                },
+            ]),
+            ..init_msg()
+        };
+        let env = mock_env(TEST_CREATOR, &[]);
+        match init(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Description too short"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // nothing was written
+        assert!(config_read(&deps.storage).may_load().unwrap().is_none());
+        assert!(query(&deps, QueryMsg::Poll { poll_id: 1 }).is_err());
+    }
+
     #[test]
     fn poll_not_found() {
         let mut deps = mock_dependencies(20, &[]);
@@ -95,6 +297,7 @@ mod tests {
     #[test]
     fn fails_create_poll_invalid_description() {
         let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
         let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
 
         let msg = create_poll_msg(30, "a".to_string(), None, None);
@@ -119,6 +322,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn create_poll_with_title_and_link() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+
+        let poll_msg = |title: &str, link: Option<&str>| HandleMsg::CreatePoll {
+            quorum_percentage: None,
+            approval_quorum_percentage: None,
+            title: title.to_string(),
+            link: link.map(String::from),
+            description: "test".to_string(),
+            start_height: None,
+            end_height: None,
+            execution: None,
+            poll_type: None,
+            veto_threshold: None,
+            reveal_blocks: None,
+            threshold_percentage: None,
+            tags: None,
+            max_vote_weight: None,
+        };
+        for (title, link, error) in &[
+            ("ab", None, "Title too short"),
+            (&"a".repeat(65)[..], None, "Title too long"),
+            (
+                "Fund the pool",
+                Some("ipfs://proposal"),
+                "Link must be an http(s) URL",
+            ),
+        ] {
+            match handle(&mut deps, env.clone(), poll_msg(title, *link)) {
+                Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, *error),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+
+        let msg = poll_msg("Fund the pool", Some("https://example.com/proposals/1"));
+        handle(&mut deps, env, msg).unwrap();
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.title, "Fund the pool");
+        assert_eq!(
+            value.link,
+            Some("https://example.com/proposals/1".to_string())
+        );
+        assert_eq!(value.description, "test");
+    }
+
+    fn percent(whole: u8) -> Decimal {
+        Decimal::from_ratio(whole, 1u8)
+    }
+
+    /// Stakes `amount` for `staker`, which counts towards quorum without voting
+    fn stake_without_voting(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        staker: &str,
+        amount: u128,
+    ) {
+        let env = mock_env_height(staker, &coins(amount, VOTING_TOKEN), 1000, 10000);
+        handle(deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+    }
+
     fn create_poll_msg(
         quorum_percentage: u8,
         description: String,
@@ -126,10 +392,20 @@ mod tests {
         end_height: Option<u64>,
     ) -> HandleMsg {
         let msg = HandleMsg::CreatePoll {
-            quorum_percentage: Some(quorum_percentage),
+            quorum_percentage: Some(percent(quorum_percentage)),
+            title: description.clone(),
+            link: None,
             description,
             start_height,
             end_height,
+            approval_quorum_percentage: None,
+            execution: None,
+            poll_type: None,
+            veto_threshold: None,
+            reveal_blocks: None,
+            threshold_percentage: None,
+            tags: None,
+            max_vote_weight: None,
         };
         msg
     }
@@ -191,7 +467,10 @@ mod tests {
         let value: PollResponse = from_binary(&res).unwrap();
         assert_eq!(Some(10001), value.end_height);
 
-        let msg = HandleMsg::EndPoll { poll_id: 1 };
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
 
         let handle_res = handle(&mut deps, env.clone(), msg);
 
@@ -239,15 +518,21 @@ mod tests {
         );
 
         let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(stake_amount, VOTING_TOKEN));
+        let env = mock_env_height(
+            TEST_VOTER,
+            &coins(stake_amount, VOTING_TOKEN),
+            POLL_END_HEIGHT,
+            10000,
+        );
 
         let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
-        assert_stake_tokens_result(stake_amount, Some(1), handle_res, &mut deps);
+        assert_stake_tokens_result(stake_amount, 1, Some(1), handle_res, &mut deps);
 
         let msg = HandleMsg::CastVote {
             poll_id: 1,
-            vote: "yes".to_string(),
-            weight: Uint128::from(stake_amount),
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(stake_amount)),
+            rationale: None,
         };
         let handle_res = handle(&mut deps, env.clone(), msg).unwrap();
 
@@ -263,7 +548,10 @@ mod tests {
 
         creator_env.block.height = &creator_env.block.height + 1;
 
-        let msg = HandleMsg::EndPoll { poll_id: 1 };
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
 
         let handle_res = handle(&mut deps, creator_env.clone(), msg).unwrap();
 
@@ -281,6 +569,135 @@ mod tests {
         assert_eq!(PollStatus::Passed, value.status);
     }
 
+    fn payout_msg() -> CosmosMsg {
+        CosmosMsg::Bank(BankMsg::Send {
+            from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+            to_address: HumanAddr::from(TEST_VOTER_2),
+            amount: coins(5, VOTING_TOKEN),
+        })
+    }
+
+    // passes a poll with a payout at height 1010, executable from height 1110
+    fn mock_queued_poll(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let msg = HandleMsg::CreatePoll {
+            quorum_percentage: None,
+            approval_quorum_percentage: None,
+            description: "payout".to_string(),
+            start_height: None,
+            end_height: Some(1010),
+            execution: Some(PollExecution {
+                msgs: vec![payout_msg()],
+                delay_blocks: 100,
+            }),
+            poll_type: None,
+            veto_threshold: None,
+            title: "Test poll".to_string(),
+            link: None,
+            reveal_blocks: None,
+            threshold_percentage: None,
+            tags: None,
+            max_vote_weight: None,
+        };
+        handle(deps, mock_env_height(TEST_CREATOR, &[], 1000, 10000), msg).unwrap();
+
+        let env = mock_env(TEST_VOTER, &coins(1000, VOTING_TOKEN));
+        handle(deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1000u128)),
+            rationale: None,
+        };
+        handle(deps, mock_env_height(TEST_VOTER, &[], 1001, 10000), msg).unwrap();
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(res.log[3], log("passed", "true"));
+        assert_eq!(res.log[4], log("executable_at", "1110"));
+    }
+
+    #[test]
+    fn queued_poll_executes_after_delay() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        mock_queued_poll(&mut deps);
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Queued);
+        assert_eq!(value.executable_at, Some(1110));
+
+        let env = mock_env_height(TEST_VOTER, &[], 1109, 10000);
+        match handle(&mut deps, env, HandleMsg::Execute { poll_id: 1 }) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Execution delay has not passed")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let env = mock_env_height(TEST_VOTER, &[], 1110, 10000);
+        let res = handle(&mut deps, env, HandleMsg::Execute { poll_id: 1 }).unwrap();
+        assert_eq!(res.messages, vec![payout_msg()]);
+        assert_eq!(
+            res.log,
+            vec![log("action", "execute_poll"), log("poll_id", "1")]
+        );
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Executed);
+
+        // messages are only dispatched once
+        let env = mock_env_height(TEST_VOTER, &[], 1111, 10000);
+        assert!(handle(&mut deps, env, HandleMsg::Execute { poll_id: 1 }).is_err());
+    }
+
+    #[test]
+    fn owner_can_veto_queued_poll() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        mock_queued_poll(&mut deps);
+
+        let env = mock_env_height(TEST_VOTER, &[], 1050, 10000);
+        match handle(&mut deps, env, HandleMsg::VetoPoll { poll_id: 1 }) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1050, 10000);
+        let res = handle(&mut deps, env, HandleMsg::VetoPoll { poll_id: 1 }).unwrap();
+        assert_eq!(
+            res.log,
+            vec![log("action", "veto_poll"), log("poll_id", "1")]
+        );
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Vetoed);
+
+        let env = mock_env_height(TEST_VOTER, &[], 1110, 10000);
+        assert!(handle(&mut deps, env, HandleMsg::Execute { poll_id: 1 }).is_err());
+    }
+
+    #[test]
+    fn fails_veto_after_delay() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        mock_queued_poll(&mut deps);
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1110, 10000);
+        match handle(&mut deps, env, HandleMsg::VetoPoll { poll_id: 1 }) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Execution delay has passed"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
     #[test]
     fn end_poll_zero_quorum() {
         let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
@@ -291,7 +708,10 @@ mod tests {
 
         let handle_res = handle(&mut deps, env.clone(), msg).unwrap();
         assert_create_poll_result(1, 0, 1001, 0, TEST_CREATOR, handle_res, &mut deps);
-        let msg = HandleMsg::EndPoll { poll_id: 1 };
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
         env.block.height = &env.block.height + 2;
 
         let handle_res = handle(&mut deps, env.clone(), msg).unwrap();
@@ -342,12 +762,13 @@ mod tests {
         let env = mock_env(TEST_VOTER, &coins(stake_amount, VOTING_TOKEN));
 
         let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
-        assert_stake_tokens_result(stake_amount, Some(1), handle_res, &mut deps);
+        assert_stake_tokens_result(stake_amount, 1, Some(1), handle_res, &mut deps);
 
         let msg = HandleMsg::CastVote {
             poll_id: 1,
-            vote: "yes".to_string(),
-            weight: Uint128::from(10u128),
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(10u128)),
+            rationale: None,
         };
         let handle_res = handle(&mut deps, env.clone(), msg).unwrap();
 
@@ -361,7 +782,10 @@ mod tests {
             ]
         );
 
-        let msg = HandleMsg::EndPoll { poll_id: 1 };
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
 
         creator_env.block.height = &creator_env.block.height + 2;
 
@@ -381,420 +805,4810 @@ mod tests {
         assert_eq!(PollStatus::Rejected, value.status);
     }
 
+    fn funding_round_msg(end_height: u64) -> HandleMsg {
+        HandleMsg::CreatePoll {
+            quorum_percentage: None,
+            approval_quorum_percentage: None,
+            description: "grants".to_string(),
+            start_height: None,
+            end_height: Some(end_height),
+            execution: None,
+            poll_type: Some(PollType::FundingRound {
+                projects: vec![
+                    Project {
+                        name: "alpha".to_string(),
+                        recipient: HumanAddr::from("alpha_team"),
+                    },
+                    Project {
+                        name: "beta".to_string(),
+                        recipient: HumanAddr::from("beta_team"),
+                    },
+                ],
+            }),
+            veto_threshold: None,
+            title: "Test poll".to_string(),
+            link: None,
+            reveal_blocks: None,
+            threshold_percentage: None,
+            tags: None,
+            max_vote_weight: None,
+        }
+    }
+
     #[test]
-    fn end_poll_nay_rejected() {
-        let voter1_stake = 100;
-        let voter2_stake = 1000;
-        let mut deps = mock_dependencies(20, &coins(voter1_stake, VOTING_TOKEN));
+    fn funding_round_matches_quadratically() {
+        let mut deps = mock_dependencies(20, &[]);
         mock_init(&mut deps);
-        let mut creator_env = mock_env(TEST_CREATOR, &coins(2, VOTING_TOKEN));
+        let creator_env = mock_env_height(TEST_CREATOR, &coins(1000, VOTING_TOKEN), 1000, 10000);
+        let res = handle(&mut deps, creator_env, funding_round_msg(1010)).unwrap();
+        assert_eq!(res.log.last(), Some(&log("matching_pool", "1000")));
+
+        // one contributor gives alpha 100, two give beta 25 each
+        for (contributor, project, weight) in &[
+            (TEST_VOTER, 0u32, 100u128),
+            (TEST_VOTER_2, 1, 25),
+            ("voter3", 1, 25),
+        ] {
+            let env = mock_env_height(contributor, &coins(100, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::Contribute {
+                poll_id: 1,
+                allocations: vec![Allocation {
+                    project: *project,
+                    weight: Uint128::from(*weight),
+                }],
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
 
-        let msg = create_poll_msg(
-            10,
-            "test".to_string(),
-            None,
-            Some(creator_env.block.height + 1),
-        );
+        let env = mock_env_height(TEST_VOTER, &[], 1002, 10000);
+        let msg = HandleMsg::Contribute {
+            poll_id: 1,
+            allocations: vec![],
+        };
+        match handle(&mut deps, env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User has already contributed.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1u128)),
+            rationale: None,
+        };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Funding rounds take contributions, not votes")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
 
-        let handle_res = handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
+        // both projects score (sum of square roots)^2 = 100 and split the pool evenly
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
         assert_eq!(
-            handle_res.log,
+            res.messages,
             vec![
-                log("action", "create_poll"),
-                log("creator", TEST_CREATOR),
-                log("poll_id", "1"),
-                log("quorum_percentage", "10"),
-                log("end_height", "12346"),
-                log("start_height", "0"),
+                CosmosMsg::Bank(BankMsg::Send {
+                    from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                    to_address: HumanAddr::from("alpha_team"),
+                    amount: coins(500, VOTING_TOKEN),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                    to_address: HumanAddr::from("beta_team"),
+                    amount: coins(500, VOTING_TOKEN),
+                }),
             ]
         );
 
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(voter1_stake, VOTING_TOKEN));
-
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_stake_tokens_result(voter1_stake, Some(1), handle_res, &mut deps);
-
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER_2, &coins(voter2_stake, VOTING_TOKEN));
+        let res = query(&deps, QueryMsg::FundingRound { poll_id: 1 }).unwrap();
+        let round: FundingRoundResponse = from_binary(&res).unwrap();
+        assert_eq!(round.matching_pool, Uint128::from(1000u128));
+        assert_eq!(round.projects[0].contributed, Uint128::from(100u128));
+        assert_eq!(round.projects[0].matched, Uint128::from(500u128));
+        assert_eq!(round.projects[1].contributed, Uint128::from(50u128));
+        assert_eq!(round.projects[1].matched, Uint128::from(500u128));
 
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_stake_tokens_result(voter1_stake + voter2_stake, Some(1), handle_res, &mut deps);
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Passed);
+    }
 
-        let env = mock_env(TEST_VOTER_2, &[]);
-        let msg = HandleMsg::CastVote {
+    #[test]
+    fn funding_round_without_contributions_refunds_pool() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        match handle(&mut deps, env, funding_round_msg(1010)) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Funding round needs a matching pool")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let creator_env = mock_env_height(TEST_CREATOR, &coins(1000, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, creator_env, funding_round_msg(1010)).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(100, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let allocation = Allocation {
+            project: 0,
+            weight: Uint128::from(50u128),
+        };
+        let msg = HandleMsg::Contribute {
             poll_id: 1,
-            vote: "no".to_string(),
-            weight: Uint128::from(voter2_stake),
+            allocations: vec![allocation.clone(), allocation],
         };
-        let handle_res = handle(&mut deps, env, msg).unwrap();
-        assert_cast_vote_success(TEST_VOTER_2, voter2_stake, 1, handle_res);
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Project allocated more than once")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        // contributions close with the round, before it is ended
+        let env = mock_env_height(TEST_VOTER, &[], 1010, 10000);
+        let msg = HandleMsg::Contribute {
+            poll_id: 1,
+            allocations: vec![Allocation {
+                project: 0,
+                weight: Uint128::from(50u128),
+            }],
+        };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Voting period has expired.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
 
-        let msg = HandleMsg::EndPoll { poll_id: 1 };
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_CREATOR),
+                amount: coins(1000, VOTING_TOKEN),
+            })]
+        );
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Rejected);
+    }
 
-        creator_env.block.height = &creator_env.block.height + 2;
-        let handle_res = handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
+    #[test]
+    fn vetoes_over_threshold_reject_poll() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let msg = HandleMsg::CreatePoll {
+            quorum_percentage: None,
+            approval_quorum_percentage: None,
+            description: "test".to_string(),
+            start_height: None,
+            end_height: Some(1010),
+            execution: None,
+            poll_type: None,
+            veto_threshold: Some(percent(33)),
+            title: "Test poll".to_string(),
+            link: None,
+            reveal_blocks: None,
+            threshold_percentage: None,
+            tags: None,
+            max_vote_weight: None,
+        };
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 60u128),
+            (TEST_VOTER_2, VoteOption::NoWithVeto, 40u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        // yes has a majority, but 40% of the votes vetoed the poll
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
         assert_eq!(
-            handle_res.log,
+            res.log,
             vec![
                 log("action", "end_poll"),
                 log("poll_id", "1"),
-                log("rejected_reason", "Threshold not reached"),
+                log("rejected_reason", "Veto threshold exceeded"),
                 log("passed", "false"),
             ]
         );
 
         let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
         let value: PollResponse = from_binary(&res).unwrap();
-        assert_eq!(PollStatus::Rejected, value.status);
+        assert_eq!(value.status, PollStatus::RejectedWithVeto);
+        assert_eq!(value.veto_threshold, Some(percent(33)));
+        assert_eq!(value.veto_votes, Uint128::from(40u128));
+        let results = query_results(&deps, None, None, None);
+        assert_eq!(results[0].no, Uint128::from(40u128));
+        assert_eq!(results[0].veto, Uint128::from(40u128));
     }
 
     #[test]
-    fn fails_end_poll_before_start_height() {
-        let mut deps = mock_dependencies(20, &[]);
+    fn threshold_percentage_sets_required_yes_share() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
         mock_init(&mut deps);
-        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
-
-        let start_height = 1001;
-        let quorum_percentage = 30;
-        let msg = create_poll_msg(
-            quorum_percentage,
-            "test".to_string(),
-            Some(start_height),
-            None,
-        );
-
-        let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
-        assert_create_poll_result(
-            1,
-            quorum_percentage,
-            DEFAULT_END_HEIGHT,
-            start_height,
-            TEST_CREATOR,
-            handle_res,
-            &mut deps,
-        );
-        let msg = HandleMsg::EndPoll { poll_id: 1 };
-
-        let handle_res = handle(&mut deps, env.clone(), msg);
+        let create_poll = |threshold_percentage| HandleMsg::CreatePoll {
+            quorum_percentage: None,
+            approval_quorum_percentage: None,
+            description: "test".to_string(),
+            start_height: None,
+            end_height: Some(1010),
+            execution: None,
+            poll_type: None,
+            veto_threshold: None,
+            threshold_percentage,
+            title: "Test poll".to_string(),
+            link: None,
+            reveal_blocks: None,
+            tags: None,
+            max_vote_weight: None,
+        };
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        for threshold in &[0, 101] {
+            match handle(
+                &mut deps,
+                creator_env.clone(),
+                create_poll(Some(percent(*threshold))),
+            ) {
+                Err(StdError::GenericErr { msg, .. }) => {
+                    assert_eq!(msg, "threshold_percentage must be 1 to 100")
+                }
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+        for threshold in &[60, 70] {
+            handle(
+                &mut deps,
+                creator_env.clone(),
+                create_poll(Some(percent(*threshold))),
+            )
+            .unwrap();
+        }
 
-        match handle_res {
-            Ok(_) => panic!("Must return error"),
-            Err(StdError::GenericErr { msg, .. }) => {
-                assert_eq!(msg, "Voting period has not started.")
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 60u128),
+            (TEST_VOTER_2, VoteOption::No, 40u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            for poll_id in 1..=2 {
+                let msg = HandleMsg::CastVote {
+                    poll_id,
+                    vote: *vote,
+                    weight: Some(Uint128::from(*weight)),
+                    rationale: None,
+                };
+                handle(&mut deps, env.clone(), msg).unwrap();
             }
-            Err(e) => panic!("Unexpected error: {:?}", e),
         }
+
+        // 60% yes reaches a threshold of 60%, but not one of 70%
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        for (poll_id, rejected_reason, passed) in
+            &[(1, "", "true"), (2, "Threshold not reached", "false")]
+        {
+            let msg = HandleMsg::EndPoll {
+                poll_id: *poll_id,
+                limit: None,
+            };
+            let res = handle(&mut deps, env.clone(), msg).unwrap();
+            assert_eq!(res.log[2], log("rejected_reason", *rejected_reason));
+            assert_eq!(res.log[3], log("passed", *passed));
+        }
+        let res = query(&deps, QueryMsg::Poll { poll_id: 2 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.threshold_percentage, Some(percent(70)));
+    }
+
+    fn query_voter_history(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        address: &str,
+        start_after: Option<u64>,
+    ) -> Vec<VoterHistoryEntry> {
+        let msg = QueryMsg::VoterHistory {
+            address: HumanAddr::from(address),
+            start_after,
+            limit: None,
+        };
+        let res: VoterHistoryResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+        res.votes
     }
 
     #[test]
-    fn fails_cast_vote_not_enough_staked() {
-        let mut deps = mock_dependencies(20, &[]);
+    fn voter_history_tracks_outcomes() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
         mock_init(&mut deps);
-        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        for end_height in &[1010, 1020] {
+            let msg = create_poll_msg(0, "test".to_string(), None, Some(*end_height));
+            handle(&mut deps, creator_env.clone(), msg).unwrap();
+        }
 
-        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        let env = mock_env_height(TEST_VOTER, &coins(50, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        for (poll_id, vote) in &[(1, VoteOption::Yes), (2, VoteOption::No)] {
+            let msg = HandleMsg::CastVote {
+                poll_id: *poll_id,
+                vote: *vote,
+                weight: Some(Uint128::from(50u128)),
+                rationale: None,
+            };
+            handle(&mut deps, env.clone(), msg).unwrap();
+        }
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            query_voter_history(&deps, TEST_VOTER, None),
+            vec![
+                VoterHistoryEntry {
+                    poll_id: 1,
+                    vote: VoteOption::Yes,
+                    weight: Uint128::from(50u128),
+                    outcome: PollStatus::Passed,
+                },
+                VoterHistoryEntry {
+                    poll_id: 2,
+                    vote: VoteOption::No,
+                    weight: Uint128::from(50u128),
+                    outcome: PollStatus::InProgress,
+                },
+            ]
+        );
+        let page = query_voter_history(&deps, TEST_VOTER, Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].poll_id, 2);
+        assert!(query_voter_history(&deps, TEST_VOTER_2, None).is_empty());
+    }
+
+    #[test]
+    fn query_participated_polls_with_locked_weight() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        for end_height in &[1010, 1020] {
+            let msg = create_poll_msg(0, "test".to_string(), None, Some(*end_height));
+            handle(&mut deps, creator_env.clone(), msg).unwrap();
+        }
+
+        let env = mock_env_height(TEST_VOTER, &coins(100, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        for (poll_id, weight) in &[(1, 60u128), (2, 40u128)] {
+            let msg = HandleMsg::CastVote {
+                poll_id: *poll_id,
+                vote: VoteOption::Yes,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env.clone(), msg).unwrap();
+        }
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        let msg = QueryMsg::ParticipatedPolls {
+            address: HumanAddr::from(TEST_VOTER),
+            start_after: None,
+            limit: None,
+        };
+        let res: ParticipatedPollsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.polls,
+            vec![
+                ParticipatedPoll {
+                    poll_id: 1,
+                    locked_weight: Uint128::zero(),
+                },
+                ParticipatedPoll {
+                    poll_id: 2,
+                    locked_weight: Uint128::from(40u128),
+                },
+            ]
+        );
+
+        let msg = QueryMsg::ParticipatedPolls {
+            address: HumanAddr::from(TEST_VOTER),
+            start_after: Some(1),
+            limit: Some(1),
+        };
+        let res: ParticipatedPollsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].poll_id, 2);
+
+        let msg = QueryMsg::ParticipatedPolls {
+            address: HumanAddr::from(TEST_VOTER_2),
+            start_after: None,
+            limit: None,
+        };
+        let res: ParticipatedPollsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert!(res.polls.is_empty());
+    }
+
+    #[test]
+    fn commit_reveal_poll() {
+        let mut deps = mock_dependencies(20, &coins(80, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = HandleMsg::CreatePoll {
+            quorum_percentage: Some(percent(50)),
+            approval_quorum_percentage: None,
+            title: "test".to_string(),
+            link: None,
+            description: "test".to_string(),
+            start_height: None,
+            end_height: Some(1010),
+            execution: None,
+            poll_type: None,
+            veto_threshold: None,
+            reveal_blocks: Some(10),
+            threshold_percentage: None,
+            tags: None,
+            max_vote_weight: None,
+        };
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 40u128),
+            (TEST_VOTER_2, VoteOption::No, 25u128),
+            ("voter3", VoteOption::Yes, 15u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            match handle(&mut deps, env.clone(), msg) {
+                Err(StdError::GenericErr { msg, .. }) => {
+                    assert_eq!(msg, "Commit-reveal polls take committed votes")
+                }
+                res => panic!("Unexpected result: {:?}", res),
+            }
+            let msg = HandleMsg::CommitVote {
+                poll_id: 1,
+                commitment: vote_commitment(&HumanAddr::from(*voter), *vote, "salt"),
+                weight: Uint128::from(*weight),
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        let reveal = |vote: VoteOption, salt: &str| HandleMsg::RevealVote {
+            poll_id: 1,
+            vote,
+            salt: salt.to_string(),
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1009, 10000);
+        match handle(&mut deps, env, reveal(VoteOption::Yes, "salt")) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Poll is not in its reveal period")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(TEST_VOTER, &[], 1010, 10000);
+        match handle(&mut deps, env.clone(), reveal(VoteOption::No, "salt")) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Vote does not match the commitment")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, env, reveal(VoteOption::Yes, "salt")).unwrap();
+        assert_eq!(res.log[0], log("action", "vote_revealed"));
+        let env = mock_env_height(TEST_VOTER_2, &[], 1019, 10000);
+        handle(&mut deps, env, reveal(VoteOption::No, "salt")).unwrap();
+
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        let env = mock_env_height(TEST_CREATOR, &[], 1019, 10000);
+        match handle(&mut deps, env, msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Reveal period has not expired.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        // voter3 never revealed, so their vote is discarded and their stake unlocked
+        let env = mock_env_height(TEST_CREATOR, &[], 1020, 10000);
+        let res = handle(&mut deps, env, msg).unwrap();
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert!(tally.passed);
+        assert_eq!(tally.participation_percentage, "81.25".parse().unwrap());
+        let voter_raw = deps
+            .api
+            .canonical_address(&HumanAddr::from("voter3"))
+            .unwrap();
+        assert_eq!(
+            locks(&mut deps.storage, &voter_raw)
+                .may_load(&1u64.to_be_bytes())
+                .unwrap(),
+            None
+        );
+        let value: PollResponse =
+            from_binary(&query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap()).unwrap();
+        assert_eq!(value.reveal_end_height, Some(1020));
+    }
+
+    #[test]
+    fn tally_hook_told_voters_of_each_batch() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        let mut msg = init_msg();
+        msg.tally_hook = Some(HumanAddr::from("badges"));
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(50, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        for voter in &[TEST_VOTER, TEST_VOTER_2] {
+            let env = mock_env_height(voter, &coins(50, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: VoteOption::Yes,
+                weight: Some(Uint128::from(50u128)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: Some(1),
+        };
+        let mut voters = vec![];
+        for _ in 0..2 {
+            let res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+            match &res.messages[..] {
+                [CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                })] => {
+                    assert_eq!(contract_addr, &HumanAddr::from("badges"));
+                    match from_binary(msg).unwrap() {
+                        TallyHookMsg::PollTallied {
+                            poll_id,
+                            voters: batch,
+                        } => {
+                            assert_eq!(poll_id, 1);
+                            voters.extend(batch);
+                        }
+                    }
+                }
+                messages => panic!("Unexpected messages: {:?}", messages),
+            }
+        }
+        voters.sort();
+        assert_eq!(
+            voters,
+            vec![HumanAddr::from(TEST_VOTER), HumanAddr::from(TEST_VOTER_2)]
+        );
+
+        // the last batch counted nobody, so the hook isn't called
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert!(res.messages.is_empty());
+        let value: PollResponse =
+            from_binary(&query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap()).unwrap();
+        assert_eq!(value.status, PollStatus::Passed);
+    }
+
+    #[test]
+    fn end_poll_decides_at_once_and_commits_votes_in_batches() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(50, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+        stake_without_voting(&mut deps, "staker", 20);
+
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 40u128),
+            (TEST_VOTER_2, VoteOption::No, 25u128),
+            ("voter3", VoteOption::Yes, 15u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        // no more votes once voting has closed
+        let cast_vote = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Some(Uint128::from(20u128)),
+            rationale: None,
+        };
+        let staker_env = mock_env_height("staker", &[], 1010, 10000);
+        match handle(&mut deps, staker_env, cast_vote) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Voting period has expired.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // the result comes from the running totals, the first two votes are committed
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: Some(2),
+        };
+        let res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "end_poll"),
+                log("poll_id", "1"),
+                log("rejected_reason", ""),
+                log("passed", "true"),
+                log("complete", false),
+                log("votes_tallied", 2),
+            ]
+        );
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert!(tally.passed);
+        assert_eq!(tally.participation_percentage, percent(80));
+        let a_poll = poll_read(&deps.storage).load(b"1").unwrap();
+        assert_eq!(a_poll.status, PollStatus::Passed);
+        assert_eq!(a_poll.yes_votes, Uint128::from(55u128));
+        assert_eq!(a_poll.no_votes, Uint128::from(25u128));
+        assert_eq!(a_poll.votes_root, None);
+
+        // every voter can withdraw straight away, whether or not their vote is committed yet
+        for (voter, weight) in &[(TEST_VOTER, 40u128), (TEST_VOTER_2, 25), ("voter3", 15)] {
+            let msg = HandleMsg::WithdrawVotingTokens {
+                amount: Some(Uint128::from(*weight)),
+                denom: None,
+            };
+            handle(&mut deps, mock_env_height(voter, &[], 1010, 10000), msg).unwrap();
+            let voter_raw = deps
+                .api
+                .canonical_address(&HumanAddr::from(*voter))
+                .unwrap();
+            assert_eq!(
+                locks(&mut deps.storage, &voter_raw)
+                    .may_load(&1u64.to_be_bytes())
+                    .unwrap(),
+                None
+            );
+        }
+
+        let res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "end_poll"),
+                log("poll_id", 1),
+                log("complete", true),
+                log("votes_tallied", 3),
+            ]
+        );
+        assert_eq!(res.data, None);
+        let a_poll = poll_read(&deps.storage).load(b"1").unwrap();
+        assert!(a_poll.votes_root.is_some());
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll is not in progress"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn result_proofs_verify_against_votes_root() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let ballots = [
+            (TEST_VOTER, VoteOption::Yes, 40u128),
+            (TEST_VOTER_2, VoteOption::No, 25u128),
+            ("voter3", VoteOption::NoWithVeto, 15u128),
+        ];
+        for (voter, vote, weight) in &ballots {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        let proof_msg = |voter: &str| QueryMsg::ResultProof {
+            poll_id: 1,
+            voter: HumanAddr::from(voter),
+        };
+        match query(&deps, proof_msg(TEST_VOTER)) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Poll votes are not committed")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // the root is built up over several messages
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        for _ in 0..2 {
+            let msg = HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: Some(2),
+            };
+            handle(&mut deps, env.clone(), msg).unwrap();
+        }
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let root = from_binary::<PollResponse>(&res)
+            .unwrap()
+            .votes_root
+            .unwrap();
+
+        for (voter, vote, weight) in &ballots {
+            let res: ResultProofResponse =
+                from_binary(&query(&deps, proof_msg(voter)).unwrap()).unwrap();
+            assert_eq!(res.root, root);
+            assert_eq!(res.vote, *vote);
+            assert_eq!(res.weight, Uint128::from(*weight));
+            let voter_raw = deps
+                .api
+                .canonical_address(&HumanAddr::from(*voter))
+                .unwrap();
+            let leaf = leaf_hash(&voter_raw, *vote, Uint128::from(*weight));
+            assert_eq!(res.leaf, leaf);
+            let computed = res.proof.iter().fold(leaf, |node, step| {
+                if step.left {
+                    node_hash(&step.hash, &node)
+                } else {
+                    node_hash(&node, &step.hash)
+                }
+            });
+            assert_eq!(computed, root);
+        }
+
+        match query(&deps, proof_msg("stranger")) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Voter did not vote"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn abstain_counts_towards_quorum_only() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(50, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+        stake_without_voting(&mut deps, "staker", 30);
+
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 30u128),
+            (TEST_VOTER_2, VoteOption::Abstain, 40u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert!(tally.passed);
+        assert_eq!(tally.participation_percentage, percent(70));
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.abstain_votes, Uint128::from(40u128));
+    }
+
+    #[test]
+    fn stray_transfers_excluded_from_quorum() {
+        // 900 tokens were sent to the contract without staking them
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(30, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+        stake_without_voting(&mut deps, "staker", 60);
+
+        let env = mock_env_height(TEST_VOTER, &coins(40, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(40u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert!(tally.passed);
+        assert_eq!(tally.participation_percentage, percent(40));
+        let a_poll = poll_read(&deps.storage).load(b"1").unwrap();
+        assert_eq!(a_poll.staked_weight, Some(Uint128::from(100u128)));
+    }
+
+    #[test]
+    fn non_voting_stake_excluded_from_quorum() {
+        let mut deps = mock_dependencies(20, &coins(100, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(40, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env.clone(), msg).unwrap();
+
+        let voter_env = mock_env_height(TEST_VOTER, &coins(30, VOTING_TOKEN), 1000, 10000);
+        handle(
+            &mut deps,
+            voter_env.clone(),
+            HandleMsg::StakeVotingTokens {},
+        )
+        .unwrap();
+        let treasury_env = mock_env_height("treasury", &coins(70, VOTING_TOKEN), 1000, 10000);
+        handle(
+            &mut deps,
+            treasury_env.clone(),
+            HandleMsg::StakeVotingTokens {},
+        )
+        .unwrap();
+
+        let msg = HandleMsg::AddNonVotingAddress {
+            address: HumanAddr::from("treasury"),
+        };
+        match handle(&mut deps, voter_env.clone(), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "add_non_voting_address"),
+                log("address", "treasury"),
+            ]
+        );
+        match handle(&mut deps, creator_env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address is already non-voting")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let res = query(&deps, QueryMsg::NonVotingAddresses {}).unwrap();
+        let value: NonVotingAddressesResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            NonVotingAddressesResponse {
+                addresses: vec![HumanAddr::from("treasury")],
+                excluded_stake: Uint128::from(70u128),
+            }
+        );
+
+        let vote = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(30u128)),
+            rationale: None,
+        };
+        match handle(&mut deps, treasury_env, vote.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Non-voting addresses cannot vote")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        handle(&mut deps, voter_env, vote).unwrap();
+
+        // the treasury's 70 tokens are left out of the total stake
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert!(tally.passed);
+        assert_eq!(tally.participation_percentage, percent(100));
+
+        let msg = HandleMsg::RemoveNonVotingAddress {
+            address: HumanAddr::from("treasury"),
+        };
+        handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
+        match handle(&mut deps, creator_env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Address is not non-voting"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = query(&deps, QueryMsg::NonVotingAddresses {}).unwrap();
+        let value: NonVotingAddressesResponse = from_binary(&res).unwrap();
+        assert!(value.addresses.is_empty());
+        assert_eq!(value.excluded_stake, Uint128::zero());
+    }
+
+    #[test]
+    fn end_poll_nay_rejected() {
+        let voter1_stake = 100;
+        let voter2_stake = 1000;
+        let mut deps = mock_dependencies(20, &coins(voter1_stake, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let mut creator_env = mock_env(TEST_CREATOR, &coins(2, VOTING_TOKEN));
+
+        let msg = create_poll_msg(
+            10,
+            "test".to_string(),
+            None,
+            Some(creator_env.block.height + 1),
+        );
+
+        let handle_res = handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            handle_res.log,
+            vec![
+                log("action", "create_poll"),
+                log("creator", TEST_CREATOR),
+                log("poll_id", "1"),
+                log("quorum_percentage", "10"),
+                log("end_height", "12346"),
+                log("start_height", "0"),
+            ]
+        );
+
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER, &coins(voter1_stake, VOTING_TOKEN));
 
         let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_create_poll_result(
-            1,
-            0,
-            DEFAULT_END_HEIGHT,
-            0,
-            TEST_CREATOR,
+        assert_stake_tokens_result(voter1_stake, 1, Some(1), handle_res, &mut deps);
+
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER_2, &coins(voter2_stake, VOTING_TOKEN));
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_stake_tokens_result(
+            voter1_stake + voter2_stake,
+            2,
+            Some(1),
             handle_res,
             &mut deps,
         );
 
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
-        let msg = HandleMsg::CastVote {
+        let env = mock_env(TEST_VOTER_2, &[]);
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Some(Uint128::from(voter2_stake)),
+            rationale: None,
+        };
+        let handle_res = handle(&mut deps, env, msg).unwrap();
+        assert_cast_vote_success(TEST_VOTER_2, voter2_stake, 1, handle_res);
+
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+
+        creator_env.block.height = &creator_env.block.height + 2;
+        let handle_res = handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            handle_res.log,
+            vec![
+                log("action", "end_poll"),
+                log("poll_id", "1"),
+                log("rejected_reason", "Threshold not reached"),
+                log("passed", "false"),
+            ]
+        );
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(PollStatus::Rejected, value.status);
+    }
+
+    #[test]
+    fn end_poll_dual_quorum() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+
+        // 50% of the stake votes, 30% votes yes
+        for approval_quorum in &[35u8, 30u8] {
+            let msg = HandleMsg::CreatePoll {
+                quorum_percentage: Some(percent(40)),
+                approval_quorum_percentage: Some(percent(*approval_quorum)),
+                description: "test".to_string(),
+                start_height: None,
+                end_height: Some(1010),
+                execution: None,
+                poll_type: None,
+                veto_threshold: None,
+                title: "Test poll".to_string(),
+                link: None,
+                reveal_blocks: None,
+                threshold_percentage: None,
+                tags: None,
+                max_vote_weight: None,
+            };
+            let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            handle(&mut deps, creator_env, msg).unwrap();
+        }
+        stake_without_voting(&mut deps, "staker", 500);
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 300u128),
+            (TEST_VOTER_2, VoteOption::No, 200u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            for poll_id in 1..=2 {
+                let msg = HandleMsg::CastVote {
+                    poll_id,
+                    vote: *vote,
+                    weight: Some(Uint128::from(*weight)),
+                    rationale: None,
+                };
+                handle(&mut deps, env.clone(), msg).unwrap();
+            }
+        }
+
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let res = handle(
+            &mut deps,
+            end_env.clone(),
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.log[2],
+            log("rejected_reason", "Approval quorum not reached")
+        );
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(
+            tally,
+            TallyResponse {
+                poll_id: 1,
+                passed: false,
+                participation_percentage: percent(50),
+                approval_percentage: percent(30),
+            }
+        );
+
+        let res = handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 2,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert!(tally.passed);
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.approval_quorum_percentage, Some(percent(35)));
+    }
+
+    #[test]
+    fn end_poll_fractional_quorum() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+
+        // percentages are accepted as decimal strings as well as whole numbers
+        for quorum in &["\"33.5\"", "\"33.4\"", "33"] {
+            let msg: HandleMsg = from_slice(
+                format!(
+                    r#"{{"create_poll":{{"quorum_percentage":{},"title":"Test poll","description":"test","end_height":1010}}}}"#,
+                    quorum
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+            let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            handle(&mut deps, creator_env, msg).unwrap();
+        }
+        stake_without_voting(&mut deps, "staker", 666);
+        let env = mock_env_height(TEST_VOTER, &coins(334, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        for poll_id in 1..=3 {
+            let msg = HandleMsg::CastVote {
+                poll_id,
+                vote: VoteOption::Yes,
+                weight: Some(Uint128::from(334u128)),
+                rationale: None,
+            };
+            handle(&mut deps, env.clone(), msg).unwrap();
+        }
+
+        // 33.4% of the stake voted
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        for (poll_id, passed) in &[(1, false), (2, true), (3, true)] {
+            let msg = HandleMsg::EndPoll {
+                poll_id: *poll_id,
+                limit: None,
+            };
+            let res = handle(&mut deps, end_env.clone(), msg).unwrap();
+            let tally: TallyResponse = from_binary(&res.data.unwrap()).unwrap();
+            assert_eq!(tally.passed, *passed);
+            assert_eq!(tally.participation_percentage, "33.4".parse().unwrap());
+        }
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 3 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.quorum_percentage, Some(percent(33)));
+    }
+
+    #[test]
+    fn fails_end_poll_before_start_height() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
+
+        let start_height = 1001;
+        let quorum_percentage = 30;
+        let msg = create_poll_msg(
+            quorum_percentage,
+            "test".to_string(),
+            Some(start_height),
+            None,
+        );
+
+        let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        assert_create_poll_result(
+            1,
+            quorum_percentage,
+            DEFAULT_END_HEIGHT,
+            start_height,
+            TEST_CREATOR,
+            handle_res,
+            &mut deps,
+        );
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+
+        let handle_res = handle(&mut deps, env.clone(), msg);
+
+        match handle_res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Voting period has not started.")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_cast_vote_not_enough_staked() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
+
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_create_poll_result(
+            1,
+            0,
+            DEFAULT_END_HEIGHT,
+            0,
+            TEST_CREATOR,
+            handle_res,
+            &mut deps,
+        );
+
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1u128)),
+            rationale: None,
+        };
+
+        let res = handle(&mut deps, env, msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User does not have enough staked tokens.")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn happy_days_cast_vote() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
+
+        let quorum_percentage = 30;
+
+        let msg = create_poll_msg(quorum_percentage, "test".to_string(), None, None);
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_create_poll_result(
+            1,
+            quorum_percentage,
+            DEFAULT_END_HEIGHT,
+            0,
+            TEST_CREATOR,
+            handle_res,
+            &mut deps,
+        );
+
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_stake_tokens_result(11, 1, Some(1), handle_res, &mut deps);
+
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let weight = 10u128;
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(weight)),
+            rationale: None,
+        };
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_cast_vote_success(TEST_VOTER, weight, 1, handle_res);
+    }
+
+    #[test]
+    fn cast_vote_defaults_to_whole_stake() {
+        let mut deps = mock_dependencies(20, &coins(600, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_VOTER, &coins(600, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, env, msg).unwrap();
+
+        let cast_vote = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: None,
+            rationale: None,
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1001, 10000);
+        let res = handle(&mut deps, env, cast_vote.clone()).unwrap();
+        assert_cast_vote_success(TEST_VOTER, 600, 1, res);
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.yes_votes, Uint128::from(600u128));
+
+        // the whole stake is locked
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(1u128)),
+            denom: None,
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // without a stake there is nothing to vote with
+        let env = mock_env_height(TEST_VOTER_2, &[], 1001, 10000);
+        match handle(&mut deps, env, cast_vote) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User does not have enough staked tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    fn capped_poll_msg(max_vote_weight: VoteWeightCap) -> HandleMsg {
+        let mut msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        if let HandleMsg::CreatePoll {
+            max_vote_weight: cap,
+            ..
+        } = &mut msg
+        {
+            *cap = Some(max_vote_weight);
+        }
+        msg
+    }
+
+    #[test]
+    fn max_vote_weight_caps_votes() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_VOTER, &coins(600, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let env = mock_env_height(TEST_VOTER_2, &coins(400, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = capped_poll_msg(VoteWeightCap::Absolute(Uint128::zero()));
+        match handle(&mut deps, env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "max_vote_weight must be positive")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = capped_poll_msg(VoteWeightCap::Percentage(percent(101)));
+        match handle(&mut deps, env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "max_vote_weight percentage must be above 0 and at most 100"
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = capped_poll_msg(VoteWeightCap::Percentage(percent(25)));
+        handle(&mut deps, env.clone(), msg).unwrap();
+        let msg = capped_poll_msg(VoteWeightCap::Absolute(Uint128::from(300u128)));
+        handle(&mut deps, env, msg).unwrap();
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value.max_vote_weight,
+            Some(VoteWeightCap::Percentage(percent(25)))
+        );
+
+        // the whole stake is above 25% of the 1000 staked
+        let env = mock_env_height(TEST_VOTER, &[], 1001, 10000);
+        let cast_vote = |poll_id, weight: Option<u128>| HandleMsg::CastVote {
+            poll_id,
+            vote: VoteOption::Yes,
+            weight: weight.map(Uint128::from),
+            rationale: None,
+        };
+        match handle(&mut deps, env.clone(), cast_vote(1, None)) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Vote weight exceeds the poll's cap of 25% of the total stake"
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        handle(&mut deps, env.clone(), cast_vote(1, Some(250))).unwrap();
+        let msg = HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Uint128::from(251u128),
+            rationale: None,
+        };
+        match handle(&mut deps, env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Vote weight exceeds the poll's cap of 25% of the total stake"
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        match handle(&mut deps, env.clone(), cast_vote(2, Some(301))) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Vote weight exceeds the poll's cap of 300")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        handle(&mut deps, env, cast_vote(2, Some(300))).unwrap();
+        let res = query(&deps, QueryMsg::Poll { poll_id: 2 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.yes_votes, Uint128::from(300u128));
+    }
+
+    #[test]
+    fn happy_days_withdraw_voting_tokens() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_stake_tokens_result(11, 1, None, handle_res, &mut deps);
+
+        let state = config_read(&mut deps.storage).load().unwrap();
+        assert_eq!(
+            state,
+            State {
+                denom: String::from(VOTING_TOKEN),
+                owner: deps
+                    .api
+                    .canonical_address(&HumanAddr::from(TEST_CREATOR))
+                    .unwrap(),
+                poll_count: 0,
+                staked_tokens: Uint128::from(11u128),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: 1,
+                active_polls: 0,
+            }
+        );
+
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(11u128)),
+            denom: None,
+        };
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        let msg = handle_res.messages.get(0).expect("no message");
+
+        assert_eq!(
+            msg,
+            &CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from("cosmos2contract"),
+                to_address: HumanAddr::from(TEST_VOTER),
+                amount: coins(11, VOTING_TOKEN),
+            })
+        );
+
+        let state = config_read(&mut deps.storage).load().unwrap();
+        assert_eq!(
+            state,
+            State {
+                denom: String::from(VOTING_TOKEN),
+                owner: deps
+                    .api
+                    .canonical_address(&HumanAddr::from(TEST_CREATOR))
+                    .unwrap(),
+                poll_count: 0,
+                staked_tokens: Uint128::zero(),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: 0,
+                active_polls: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn fails_withdraw_voting_tokens_no_stake() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(11u128)),
+            denom: None,
+        };
+
+        let res = handle(&mut deps, env, msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Nothing staked"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_withdraw_too_many_tokens() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER, &coins(10, VOTING_TOKEN));
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_stake_tokens_result(10, 1, None, handle_res, &mut deps);
+
+        let env = mock_env(TEST_VOTER, &[]);
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(11u128)),
+            denom: None,
+        };
+
+        let res = handle(&mut deps, env, msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn withdraw_drains_stake_completely() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(TEST_VOTER, &coins(10, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: None,
+            denom: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::zero());
+
+        // nothing is left to withdraw from the drained stake
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(1u128)),
+            denom: None,
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn fails_withdraw_amount_overflowing_locked_stake() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let env = mock_env(TEST_VOTER, &coins(10, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(5u128)),
+            rationale: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(u128::MAX)),
+            denom: None,
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn fails_stake_overflowing_total() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(TEST_VOTER, &coins(u128::MAX, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+
+        let env = mock_env(TEST_VOTER_2, &coins(10, VOTING_TOKEN));
+        match handle(&mut deps, env, HandleMsg::StakeVotingTokens {}) {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.starts_with("Overflow")),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(u128::MAX));
+    }
+
+    #[test]
+    fn withdraw_underflowing_staked_tokens_fails() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env(TEST_VOTER, &coins(10, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        // corrupt the total so it no longer covers the voter's stake
+        let mut state = config_read(&deps.storage).load().unwrap();
+        state.staked_tokens = Uint128::from(5u128);
+        config(&mut deps.storage).save(&state).unwrap();
+
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: None,
+            denom: None,
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg) {
+            Err(StdError::Underflow { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn voted_tokens_locked_until_poll_ends() {
+        let mut deps = mock_dependencies(20, &coins(10, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(10, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(8u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        let withdraw_msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(3u128)),
+            denom: None,
+        };
+        match handle(&mut deps, env.clone(), withdraw_msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        let voter = deps
+            .api
+            .canonical_address(&HumanAddr::from(TEST_VOTER))
+            .unwrap();
+        assert_eq!(
+            locks(&mut deps.storage, &voter)
+                .may_load(&1u64.to_be_bytes())
+                .unwrap(),
+            None
+        );
+        handle(&mut deps, env, withdraw_msg).unwrap();
+    }
+
+    #[test]
+    fn fails_cast_vote_twice() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
+
+        let quorum_percentage = 30;
+        let msg = create_poll_msg(quorum_percentage, "test".to_string(), None, None);
+        let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+
+        assert_create_poll_result(
+            1,
+            quorum_percentage,
+            DEFAULT_END_HEIGHT,
+            0,
+            TEST_CREATOR,
+            handle_res,
+            &mut deps,
+        );
+
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let msg = HandleMsg::StakeVotingTokens {};
+
+        let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        assert_stake_tokens_result(11, 1, Some(1), handle_res, &mut deps);
+
+        let weight = 1u128;
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(weight)),
+            rationale: None,
+        };
+        let handle_res = handle(&mut deps, env.clone(), msg).unwrap();
+        assert_cast_vote_success(TEST_VOTER, weight, 1, handle_res);
+
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(weight)),
+            rationale: None,
+        };
+        let res = handle(&mut deps, env.clone(), msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "User has already voted."),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_cast_vote_without_poll() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::CastVote {
+            poll_id: 0,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1u128)),
+            rationale: None,
+        };
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+
+        let res = handle(&mut deps, env, msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll does not exist"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn happy_days_stake_voting_tokens() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::StakeVotingTokens {};
+
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+
+        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_stake_tokens_result(11, 1, None, handle_res, &mut deps);
+    }
+
+    #[test]
+    fn fails_insufficient_funds() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        // initialize the store
+        let msg = init_msg();
+        let env = mock_env(TEST_VOTER, &coins(2, VOTING_TOKEN));
+        let init_res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(0, init_res.messages.len());
+
+        // insufficient token
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER, &coins(0, VOTING_TOKEN));
+
+        let res = handle(&mut deps, env, msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Insufficient funds sent"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn fails_staking_wrong_token() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        // initialize the store
+        let msg = init_msg();
+        let env = mock_env(TEST_VOTER, &coins(2, VOTING_TOKEN));
+        let init_res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(0, init_res.messages.len());
+
+        // wrong token
+        let msg = HandleMsg::StakeVotingTokens {};
+        let env = mock_env(TEST_VOTER, &coins(11, "play money"));
+
+        let res = handle(&mut deps, env, msg);
+
+        match res {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Insufficient funds sent"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    fn query_proposer_eligibility(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        address: &str,
+    ) -> ProposerEligibilityResponse {
+        let res = query(
+            deps,
+            QueryMsg::ProposerEligibility {
+                address: HumanAddr::from(address),
+            },
+        )
+        .unwrap();
+        from_binary(&res).unwrap()
+    }
+
+    #[test]
+    fn proposer_requirement_enforced_on_create_poll() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            denom: String::from(VOTING_TOKEN),
+            proposer_requirement: Some(ProposerRequirement {
+                min_polls: 1,
+                last_polls: 2,
+            }),
+            reward_denom: None,
+            rate_source: None,
+            initial_polls: None,
+            tally_executor: None,
+            weight_rounding: None,
+            bootstrap: None,
+            owner: None,
+            init_hook: None,
+            proposal_deposit: None,
+            tally_hook: None,
+            reward_token: None,
+            min_voting_period: None,
+            max_voting_period: None,
+            unbonding_period: None,
+            poll_creation_fee: None,
+            token: None,
+            extra_denoms: None,
+            restrict_poll_creation: None,
+        };
+        let env = mock_env(TEST_CREATOR, &[]);
+        init(&mut deps, env, msg).unwrap();
+
+        // no polls exist yet, so anyone may propose the first one
+        let env = mock_env_height(TEST_CREATOR, &[], 0, 10000);
+        let msg = create_poll_msg(0, "first".to_string(), None, None);
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        let msg = create_poll_msg(0, "second".to_string(), None, None);
+        match handle(&mut deps, env.clone(), msg.clone()) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Proposer must have voted in 1 of the last 2 polls")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(
+            query_proposer_eligibility(&deps, TEST_CREATOR),
+            ProposerEligibilityResponse {
+                eligible: false,
+                participated_polls: 0,
+                required_polls: 1,
+            }
+        );
+
+        let stake_env = mock_env(TEST_CREATOR, &coins(10, VOTING_TOKEN));
+        handle(&mut deps, stake_env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let vote = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(10u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env.clone(), vote).unwrap();
+        assert_eq!(
+            query_proposer_eligibility(&deps, TEST_CREATOR),
+            ProposerEligibilityResponse {
+                eligible: true,
+                participated_polls: 1,
+                required_polls: 1,
+            }
+        );
+
+        // poll 1 is within the last 2 polls for both poll 2 and poll 3
+        handle(&mut deps, env.clone(), msg).unwrap();
+        let msg = create_poll_msg(0, "third".to_string(), None, None);
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        // now the window is polls 2 and 3, which the creator did not vote in
+        let msg = create_poll_msg(0, "fourth".to_string(), None, None);
+        match handle(&mut deps, env, msg) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Proposer must have voted in 1 of the last 2 polls")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn set_proposer_requirement() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        assert_eq!(
+            query_proposer_eligibility(&deps, TEST_VOTER),
+            ProposerEligibilityResponse {
+                eligible: true,
+                participated_polls: 0,
+                required_polls: 0,
+            }
+        );
+
+        let msg = HandleMsg::SetProposerRequirement {
+            requirement: Some(ProposerRequirement {
+                min_polls: 2,
+                last_polls: 3,
+            }),
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg.clone()) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::Unauthorized { .. }) => {}
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        let handle_res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            handle_res.log,
+            vec![
+                log("action", "set_proposer_requirement"),
+                log("enabled", "true"),
+            ]
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            state.proposer_requirement,
+            Some(ProposerRequirement {
+                min_polls: 2,
+                last_polls: 3,
+            })
+        );
+
+        let msg = HandleMsg::SetProposerRequirement {
+            requirement: Some(ProposerRequirement {
+                min_polls: 4,
+                last_polls: 3,
+            }),
+        };
+        match handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "min_polls cannot be greater than last_polls")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        let msg = HandleMsg::SetProposerRequirement { requirement: None };
+        handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.proposer_requirement, None);
+    }
+
+    #[test]
+    fn batch_query() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &[], 0, 10000);
+        let msg = create_poll_msg(30, "test".to_string(), None, None);
+        handle(&mut deps, env, msg).unwrap();
+        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+
+        let res = query(
+            &deps,
+            QueryMsg::Batch {
+                queries: vec![
+                    QueryMsg::Config {},
+                    QueryMsg::Poll { poll_id: 1 },
+                    QueryMsg::TokenStake {
+                        address: HumanAddr::from(TEST_VOTER),
+                    },
+                ],
+            },
+        )
+        .unwrap();
+        let value: BatchResponse = from_binary(&res).unwrap();
+        assert_eq!(value.results.len(), 3);
+
+        let state: State = from_binary(&value.results[0]).unwrap();
+        assert_eq!(state.poll_count, 1);
+        let poll: PollResponse = from_binary(&value.results[1]).unwrap();
+        assert_eq!(poll.description, "test");
+        let stake: TokenStakeResponse = from_binary(&value.results[2]).unwrap();
+        assert_eq!(stake.token_balance, Uint128::from(11u128));
+    }
+
+    #[test]
+    fn fails_batch_query_nested_or_failing() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let res = query(
+            &deps,
+            QueryMsg::Batch {
+                queries: vec![
+                    QueryMsg::Config {},
+                    QueryMsg::Batch {
+                        queries: vec![QueryMsg::Config {}],
+                    },
+                ],
+            },
+        );
+        match res {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Batch queries cannot be nested")
+            }
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            _ => panic!("Must return error"),
+        }
+
+        // any failing query fails the whole batch
+        let res = query(
+            &deps,
+            QueryMsg::Batch {
+                queries: vec![QueryMsg::Config {}, QueryMsg::Poll { poll_id: 1 }],
+            },
+        );
+        match res {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll does not exist"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            _ => panic!("Must return error"),
+        }
+    }
+
+    #[test]
+    fn poll_audit_lists_votes_and_tally() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        for (voter, vote, weight, height) in &[
+            (TEST_VOTER, VoteOption::Yes, 600u128, 1001u64),
+            (TEST_VOTER_2, VoteOption::No, 400u128, 1002u64),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), *height, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        // totals are only recorded once the poll is tallied
+        let res = query(
+            &deps,
+            QueryMsg::PollAudit {
+                poll_id: 1,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let audit: PollAuditResponse = from_binary(&res).unwrap();
+        assert_eq!(audit.status, PollStatus::InProgress);
+        assert_eq!(audit.staked_weight, None);
+        assert_eq!(audit.votes.len(), 2);
+
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        let res = query(
+            &deps,
+            QueryMsg::PollAudit {
+                poll_id: 1,
+                start_after: None,
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+        let audit: PollAuditResponse = from_binary(&res).unwrap();
+        assert_eq!(audit.status, PollStatus::Passed);
+        assert_eq!(audit.yes_votes, Uint128::from(600u128));
+        assert_eq!(audit.no_votes, Uint128::from(400u128));
+        assert_eq!(audit.staked_weight, Some(Uint128::from(1000u128)));
+        assert_eq!(
+            audit.votes,
+            vec![VoteRecord {
+                voter: HumanAddr::from(TEST_VOTER),
+                vote: VoteOption::Yes,
+                weight: Uint128::from(600u128),
+                voted_at_height: 1001,
+                rationale: None,
+            }]
+        );
+
+        let res = query(
+            &deps,
+            QueryMsg::PollAudit {
+                poll_id: 1,
+                start_after: Some(HumanAddr::from(TEST_VOTER)),
+                limit: None,
+            },
+        )
+        .unwrap();
+        let audit: PollAuditResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            audit.votes,
+            vec![VoteRecord {
+                voter: HumanAddr::from(TEST_VOTER_2),
+                vote: VoteOption::No,
+                weight: Uint128::from(400u128),
+                voted_at_height: 1002,
+                rationale: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn query_voters_is_paginated() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        for (voter, vote, weight) in &[
+            (TEST_VOTER_2, VoteOption::No, 400u128),
+            (TEST_VOTER, VoteOption::Yes, 600u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        // listed in address order, not the order the votes were cast
+        let msg = QueryMsg::Voters {
+            poll_id: 1,
+            start_after: None,
+            limit: Some(1),
+        };
+        let res: VotersResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.voters,
+            vec![VoterInfo {
+                voter: HumanAddr::from(TEST_VOTER),
+                vote: VoteOption::Yes,
+                weight: Uint128::from(600u128),
+                rationale: None,
+            }]
+        );
+        let msg = QueryMsg::Voters {
+            poll_id: 1,
+            start_after: Some(HumanAddr::from(TEST_VOTER)),
+            limit: None,
+        };
+        let res: VotersResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.voters,
+            vec![VoterInfo {
+                voter: HumanAddr::from(TEST_VOTER_2),
+                vote: VoteOption::No,
+                weight: Uint128::from(400u128),
+                rationale: None,
+            }]
+        );
+
+        let msg = QueryMsg::Voters {
+            poll_id: 2,
+            start_after: None,
+            limit: None,
+        };
+        match query(&deps, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll does not exist"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn update_vote_replaces_ballot() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(600, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Uint128::from(500u128),
+            rationale: None,
+        };
+        match handle(&mut deps, env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "User has not voted."),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(200u128)),
+            rationale: Some("cheap enough".to_string()),
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+        let msg = HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Uint128::from(500u128),
+            rationale: None,
+        };
+        let res = handle(&mut deps, env.clone(), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "vote_updated"),
+                log("poll_id", 1),
+                log("weight", 500),
+                log("voter", TEST_VOTER),
+            ]
+        );
+
+        let msg = QueryMsg::Vote {
+            poll_id: 1,
+            voter: HumanAddr::from(TEST_VOTER),
+        };
+        let res: VoteResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.ballot,
+            Some(Ballot {
+                vote: VoteOption::No,
+                weight: Uint128::from(500u128),
+            })
+        );
+        let msg = QueryMsg::ParticipatedPolls {
+            address: HumanAddr::from(TEST_VOTER),
+            start_after: None,
+            limit: None,
+        };
+        let res: ParticipatedPollsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.polls,
+            vec![ParticipatedPoll {
+                poll_id: 1,
+                locked_weight: Uint128::from(500u128),
+            }]
+        );
+
+        let msg = HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(601u128),
+            rationale: None,
+        };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User does not have enough staked tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let env = mock_env_height(TEST_VOTER, &[], 1010, 10000);
+        let msg = HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(500u128),
+            rationale: None,
+        };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Voting period has expired."),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn retract_vote_removes_ballot() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(600, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(600u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::RetractVote { poll_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "vote_retracted"),
+                log("poll_id", 1),
+                log("voter", TEST_VOTER),
+            ]
+        );
+        let msg = QueryMsg::Voters {
+            poll_id: 1,
+            start_after: None,
+            limit: None,
+        };
+        let res: VotersResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert!(res.voters.is_empty());
+        let msg = QueryMsg::ParticipatedPolls {
+            address: HumanAddr::from(TEST_VOTER),
+            start_after: None,
+            limit: None,
+        };
+        let res: ParticipatedPollsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert!(res.polls.is_empty());
+        assert!(query_voter_history(&deps, TEST_VOTER, None).is_empty());
+
+        // the unlocked stake can be withdrawn
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: None,
+            denom: None,
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        match handle(&mut deps, env, HandleMsg::RetractVote { poll_id: 1 }) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "User has not voted."),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn running_vote_totals() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        for (voter, vote, weight) in &[
+            (TEST_VOTER, VoteOption::Yes, 60u128),
+            (TEST_VOTER_2, VoteOption::NoWithVeto, 25u128),
+            ("voter3", VoteOption::Abstain, 15u128),
+        ] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: *vote,
+                weight: Some(Uint128::from(*weight)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+        let totals = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            let res = query(deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+            let value: PollResponse = from_binary(&res).unwrap();
+            (
+                value.yes_votes.u128(),
+                value.no_votes.u128(),
+                value.abstain_votes.u128(),
+                value.veto_votes.u128(),
+            )
+        };
+        assert_eq!(totals(&deps), (60, 25, 15, 25));
+
+        let env = mock_env_height(TEST_VOTER, &[], 1002, 10000);
+        let msg = HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Uint128::from(40u128),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        assert_eq!(totals(&deps), (0, 65, 15, 25));
+
+        let env = mock_env_height(TEST_VOTER_2, &[], 1002, 10000);
+        handle(&mut deps, env, HandleMsg::RetractVote { poll_id: 1 }).unwrap();
+        assert_eq!(totals(&deps), (0, 40, 15, 0));
+
+        // the tally arrives at the same totals
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        assert_eq!(totals(&deps), (0, 40, 15, 0));
+    }
+
+    #[test]
+    fn cancel_poll_before_votes() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env.clone(), msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(600, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(600u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env.clone(), msg.clone()).unwrap();
+
+        match handle(&mut deps, env.clone(), HandleMsg::CancelPoll { poll_id: 1 }) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match handle(
+            &mut deps,
+            creator_env.clone(),
+            HandleMsg::CancelPoll { poll_id: 1 },
+        ) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll already has votes"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // once the only vote is retracted the poll can be cancelled
+        handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::RetractVote { poll_id: 1 },
+        )
+        .unwrap();
+        let res = handle(&mut deps, creator_env, HandleMsg::CancelPoll { poll_id: 1 }).unwrap();
+        assert_eq!(
+            res,
+            HandleResponse {
+                messages: vec![],
+                log: vec![log("action", "cancel_poll"), log("poll_id", 1)],
+                data: None,
+            }
+        );
+        let value: PollResponse =
+            from_binary(&query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap()).unwrap();
+        assert_eq!(value.status, PollStatus::Cancelled);
+
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll is not in progress"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn cancel_funding_round_refunds_matching_pool() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &coins(1000, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, creator_env.clone(), funding_round_msg(1010)).unwrap();
+
+        let res = handle(
+            &mut deps,
+            creator_env.clone(),
+            HandleMsg::CancelPoll { poll_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: creator_env.contract.address,
+                to_address: HumanAddr::from(TEST_CREATOR),
+                amount: coins(1000, VOTING_TOKEN),
+            })]
+        );
+    }
+
+    #[test]
+    fn voting_period_bounds() {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut msg = init_msg();
+        msg.min_voting_period = Some(100);
+        msg.max_voting_period = Some(1000);
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        for (start_height, end_height, error) in &[
+            (
+                None,
+                Some(1099),
+                "Voting period must be at least 100 blocks",
+            ),
+            (
+                Some(1500),
+                Some(1599),
+                "Voting period must be at least 100 blocks",
+            ),
+            (
+                None,
+                Some(2001),
+                "Voting period must be at most 1000 blocks",
+            ),
+            (None, None, "Voting period must be at most 1000 blocks"),
+        ] {
+            let msg = create_poll_msg(0, "test".to_string(), *start_height, *end_height);
+            match handle(&mut deps, env.clone(), msg) {
+                Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, *error),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+        // the period runs from the start height when there is one
+        let msg = create_poll_msg(0, "test".to_string(), Some(1500), Some(1600));
+        handle(&mut deps, env.clone(), msg).unwrap();
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(2000));
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        let msg = HandleMsg::SetVotingPeriod {
+            min_voting_period: None,
+            max_voting_period: None,
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, env.clone(), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "set_voting_period"),
+                log("min_voting_period", 0),
+                log("max_voting_period", "none"),
+            ]
+        );
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        let msg = HandleMsg::SetVotingPeriod {
+            min_voting_period: Some(10),
+            max_voting_period: Some(5),
+        };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "min_voting_period must not exceed max_voting_period")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn weighted_staking_denoms() {
+        const LP_TOKEN: &str = "lp_token";
+        let lp_weight = |weight: u64| DenomWeight {
+            denom: String::from(LP_TOKEN),
+            weight,
+        };
+        let mut deps = mock_dependencies(20, &[]);
+        let mut msg = init_msg();
+        for (extra_denoms, error) in &[
+            (vec![lp_weight(0)], "Denom weights must be positive"),
+            (
+                vec![lp_weight(2), lp_weight(3)],
+                "Staking denoms must be unique",
+            ),
+        ] {
+            msg.extra_denoms = Some(extra_denoms.clone());
+            match init(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+                Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, *error),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+        msg.extra_denoms = Some(vec![lp_weight(2)]);
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let sent = vec![
+            coin(100, VOTING_TOKEN),
+            coin(50, LP_TOKEN),
+            coin(10, "other"),
+        ];
+        let env = mock_env_height(TEST_VOTER, &sent, 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let stake = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            let msg = QueryMsg::TokenStake {
+                address: HumanAddr::from(TEST_VOTER),
+            };
+            from_binary::<TokenStakeResponse>(&query(deps, msg).unwrap()).unwrap()
+        };
+        assert_eq!(
+            stake(&deps),
+            TokenStakeResponse {
+                token_balance: Uint128::from(200u128),
+                extra_balances: coins(50, LP_TOKEN),
+            }
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(200u128));
+
+        let withdraw = |amount: Option<u128>, denom: &str| HandleMsg::WithdrawVotingTokens {
+            amount: amount.map(Uint128::from),
+            denom: Some(String::from(denom)),
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+        match handle(&mut deps, env.clone(), withdraw(None, "other")) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Not a staking denom"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match handle(&mut deps, env.clone(), withdraw(Some(51), LP_TOKEN)) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, env.clone(), withdraw(Some(10), LP_TOKEN)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER),
+                amount: coins(10, LP_TOKEN),
+            })]
+        );
+        assert_eq!(stake(&deps).token_balance, Uint128::from(180u128));
+
+        // votes count the lp tokens at their weight
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(
+            &mut deps,
+            mock_env_height(TEST_CREATOR, &[], 1000, 10000),
+            msg,
+        )
+        .unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(150u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.yes_votes, Uint128::from(150u128));
+
+        // all of the staking denom, but no more than the unlocked votes
+        match handle(&mut deps, env.clone(), withdraw(None, VOTING_TOKEN)) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, env, withdraw(Some(30), VOTING_TOKEN)).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER),
+                amount: coins(30, VOTING_TOKEN),
+            })]
+        );
+        assert_eq!(
+            stake(&deps),
+            TokenStakeResponse {
+                token_balance: Uint128::from(150u128),
+                extra_balances: coins(40, LP_TOKEN),
+            }
+        );
+    }
+
+    #[test]
+    fn unbonding_period() {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut msg = init_msg();
+        msg.unbonding_period = Some(Duration::Blocks(100));
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        stake_without_voting(&mut deps, TEST_VOTER, 100);
+
+        let withdraw = |amount: u128| HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(amount)),
+            denom: None,
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+        let res = handle(&mut deps, env, withdraw(40)).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "unbond"),
+                log("amount", 40),
+                log("denom", VOTING_TOKEN),
+                log("release_at", "height 1100"),
+            ]
+        );
+        let env = mock_env_height(TEST_VOTER, &[], 1050, 10000);
+        handle(&mut deps, env, withdraw(10)).unwrap();
+        // withdrawn stake stops counting straight away
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(50u128));
+
+        let query_claims = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            let msg = QueryMsg::Claims {
+                address: HumanAddr::from(TEST_VOTER),
+            };
+            from_binary::<ClaimsResponse>(&query(deps, msg).unwrap())
+                .unwrap()
+                .claims
+        };
+        assert_eq!(
+            query_claims(&deps),
+            vec![
+                Claim {
+                    amount: Uint128::from(40u128),
+                    release_at: Expiration::AtHeight(1100),
+                    denom: None,
+                },
+                Claim {
+                    amount: Uint128::from(10u128),
+                    release_at: Expiration::AtHeight(1150),
+                    denom: None,
+                },
+            ]
+        );
+
+        let env = mock_env_height(TEST_VOTER, &[], 1099, 10000);
+        match handle(&mut deps, env, HandleMsg::ClaimUnbonded {}) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Nothing to claim"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(TEST_VOTER, &[], 1100, 10000);
+        let res = handle(&mut deps, env, HandleMsg::ClaimUnbonded {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER),
+                amount: coins(40, VOTING_TOKEN),
+            })]
+        );
+        assert_eq!(query_claims(&deps).len(), 1);
+
+        let msg = HandleMsg::UpdateConfig {
+            denom: None,
+            min_description_length: None,
+            max_description_length: None,
+            weight_rounding: None,
+            unbonding_period: Some(Duration::Seconds(60)),
+            restrict_poll_creation: None,
+        };
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[1], log("unbonding_period", "60 seconds"));
+        let env = mock_env_height(TEST_VOTER, &[], 1100, 10000);
+        let res = handle(&mut deps, env, withdraw(50)).unwrap();
+        assert_eq!(res.log[3], log("release_at", "time 10060"));
+
+        // claims made under either period are released together
+        let env = mock_env_height(TEST_VOTER, &[], 1150, 10060);
+        let res = handle(&mut deps, env, HandleMsg::ClaimUnbonded {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER),
+                amount: coins(60, VOTING_TOKEN),
+            })]
+        );
+        assert!(query_claims(&deps).is_empty());
+    }
+
+    #[test]
+    fn update_config() {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut msg = init_msg();
+        msg.reward_denom = Some("reward".to_string());
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let msg = HandleMsg::UpdateConfig {
+            denom: Some("other".to_string()),
+            min_description_length: Some(5),
+            max_description_length: Some(10),
+            weight_rounding: Some(Rounding::Ceil),
+            unbonding_period: None,
+            restrict_poll_creation: None,
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "config_updated"),
+                log("denom", "other"),
+                log("min_description_length", 5),
+                log("max_description_length", 10),
+                log("weight_rounding", "ceil"),
+            ]
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.denom, "other");
+        assert_eq!(state.weight_rounding, Rounding::Ceil);
+
+        let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+        for (description, error) in &[
+            ("test", "Description too short"),
+            ("test test test", "Description too long"),
+        ] {
+            let msg = create_poll_msg(0, description.to_string(), None, None);
+            match handle(&mut deps, env.clone(), msg) {
+                Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, *error),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+        let msg = create_poll_msg(0, "test test".to_string(), None, None);
+        handle(&mut deps, env, msg).unwrap();
+
+        for (msg, error) in &[
+            (
+                HandleMsg::UpdateConfig {
+                    denom: None,
+                    min_description_length: Some(11),
+                    max_description_length: None,
+                    weight_rounding: None,
+                    unbonding_period: None,
+                    restrict_poll_creation: None,
+                },
+                "min_description_length must not exceed max_description_length",
+            ),
+            (
+                HandleMsg::UpdateConfig {
+                    denom: Some("reward".to_string()),
+                    min_description_length: None,
+                    max_description_length: None,
+                    weight_rounding: None,
+                    unbonding_period: None,
+                    restrict_poll_creation: None,
+                },
+                "Reward denom must differ from the staking denom",
+            ),
+        ] {
+            match handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+                Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, *error),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+
+        let env = mock_env_height(TEST_VOTER, &coins(10, "other"), 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::UpdateConfig {
+            denom: Some(VOTING_TOKEN.to_string()),
+            min_description_length: None,
+            max_description_length: None,
+            weight_rounding: None,
+            unbonding_period: None,
+            restrict_poll_creation: None,
+        };
+        match handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Denom can only change while nothing is staked or held for polls"
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn poll_creator_allowlist() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let msg = HandleMsg::UpdateConfig {
+            denom: None,
+            min_description_length: None,
+            max_description_length: None,
+            weight_rounding: None,
+            unbonding_period: None,
+            restrict_poll_creation: Some(true),
+        };
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[1], log("restrict_poll_creation", true));
+
+        let create_poll = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+            let msg = create_poll_msg(0, "test".to_string(), None, None);
+            handle(deps, env, msg)
+        };
+        match create_poll(&mut deps) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Only allowlisted addresses can create polls")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let add = |address: &str| HandleMsg::AddPollCreator {
+            address: HumanAddr::from(address),
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), add(TEST_VOTER)) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), add(TEST_VOTER)).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "add_poll_creator"),
+                log("address", TEST_VOTER),
+            ]
+        );
+        handle(&mut deps, mock_env(TEST_CREATOR, &[]), add(TEST_VOTER_2)).unwrap();
+        match handle(&mut deps, mock_env(TEST_CREATOR, &[]), add(TEST_VOTER)) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address is already a poll creator")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let msg = QueryMsg::PollCreators {
+            start_after: None,
+            limit: Some(1),
+        };
+        let res: PollCreatorsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.creators, vec![HumanAddr::from(TEST_VOTER)]);
+        let msg = QueryMsg::PollCreators {
+            start_after: Some(HumanAddr::from(TEST_VOTER)),
+            limit: None,
+        };
+        let res: PollCreatorsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.creators, vec![HumanAddr::from(TEST_VOTER_2)]);
+
+        create_poll(&mut deps).unwrap();
+
+        let remove = HandleMsg::RemovePollCreator {
+            address: HumanAddr::from(TEST_VOTER),
+        };
+        handle(&mut deps, mock_env(TEST_CREATOR, &[]), remove.clone()).unwrap();
+        match create_poll(&mut deps) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Only allowlisted addresses can create polls")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match handle(&mut deps, mock_env(TEST_CREATOR, &[]), remove) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address is not a poll creator")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn staking_info() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let query_info = |deps: &Extern<MockStorage, MockApi, MockQuerier>| -> StakingInfoResponse {
+            from_binary(&query(deps, QueryMsg::StakingInfo {}).unwrap()).unwrap()
+        };
+        for (voter, amount) in &[(TEST_VOTER, 600u128), (TEST_VOTER_2, 400u128)] {
+            let env = mock_env_height(voter, &coins(*amount, VOTING_TOKEN), 1000, 10000);
+            handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        }
+        for _ in 0..2 {
+            let env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+            handle(&mut deps, env, msg).unwrap();
+        }
+        assert_eq!(
+            query_info(&deps),
+            StakingInfoResponse {
+                total_staked: Uint128::from(1000u128),
+                stakers: 2,
+                active_polls: 2,
+                largest_stake: Uint128::from(600u128),
+            }
+        );
+
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: None,
+            denom: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(100u128)),
+            denom: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER_2, &[]), msg).unwrap();
+        let info = query_info(&deps);
+        assert_eq!(info.total_staked, Uint128::from(300u128));
+        assert_eq!(info.stakers, 1);
+        assert_eq!(info.largest_stake, Uint128::from(300u128));
+
+        // polls stop being active once they end or are cancelled
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        assert_eq!(query_info(&deps).active_polls, 1);
+        let env = mock_env_height(TEST_CREATOR, &[], 1002, 10000);
+        handle(&mut deps, env, HandleMsg::CancelPoll { poll_id: 2 }).unwrap();
+        assert_eq!(query_info(&deps).active_polls, 0);
+    }
+
+    #[test]
+    fn two_step_ownership_transfer() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::TransferOwnership {
+            new_owner: HumanAddr::from(TEST_VOTER),
+        };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "transfer_ownership"),
+                log("new_owner", TEST_VOTER),
+            ]
+        );
+        let res = query(&deps, QueryMsg::Ownership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            OwnershipResponse {
+                owner: HumanAddr::from(TEST_CREATOR),
+                pending_owner: Some(HumanAddr::from(TEST_VOTER)),
+            }
+        );
+
+        // only the address offered ownership can accept it
+        match handle(
+            &mut deps,
+            mock_env(TEST_VOTER_2, &[]),
+            HandleMsg::AcceptOwnership {},
+        ) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(
+            &mut deps,
+            mock_env(TEST_VOTER, &[]),
+            HandleMsg::AcceptOwnership {},
+        )
+        .unwrap();
+        assert_eq!(
+            res.log,
+            vec![log("action", "accept_ownership"), log("owner", TEST_VOTER)]
+        );
+        let res = query(&deps, QueryMsg::Ownership {}).unwrap();
+        let value: OwnershipResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            OwnershipResponse {
+                owner: HumanAddr::from(TEST_VOTER),
+                pending_owner: None,
+            }
+        );
+
+        // the previous owner has lost its rights
+        let msg = HandleMsg::SetVotingPeriod {
+            min_voting_period: None,
+            max_voting_period: None,
+        };
+        match handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+        match handle(
+            &mut deps,
+            mock_env(TEST_VOTER, &[]),
+            HandleMsg::AcceptOwnership {},
+        ) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn proposal_deposit_refunded_only_when_quorum_reached() {
+        // 100 staked and the deposits of polls 1 and 2 once poll 3 is cancelled
+        let mut deps = mock_dependencies(20, &coins(200, VOTING_TOKEN));
+        let mut msg = init_msg();
+        msg.proposal_deposit = Some(Uint128::from(50u128));
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER_2, &coins(49, VOTING_TOKEN), 1000, 10000);
+        let msg = create_poll_msg(30, "test".to_string(), None, Some(1010));
+        match handle(&mut deps, env, msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(
+                    msg,
+                    format!("Proposal deposit of 50{} required", VOTING_TOKEN)
+                )
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let proposer_env = mock_env_height(TEST_VOTER_2, &coins(50, VOTING_TOKEN), 1000, 10000);
+        for _ in 0..3 {
+            handle(&mut deps, proposer_env.clone(), msg.clone()).unwrap();
+        }
+        let res = handle(
+            &mut deps,
+            proposer_env.clone(),
+            HandleMsg::CancelPoll { poll_id: 3 },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER_2),
+                amount: coins(50, VOTING_TOKEN),
+            })]
+        );
+
+        let env = mock_env_height(TEST_VOTER, &coins(100, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(40u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        // deposits held don't count as stake, so 40 of 100 reaches the 30% quorum
+        let env = mock_env_height(TEST_CREATOR, &[], 1011, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        let res = handle(&mut deps, env.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER_2),
+                amount: coins(50, VOTING_TOKEN),
+            })]
+        );
+
+        let msg = HandleMsg::EndPoll {
+            poll_id: 2,
+            limit: None,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_CREATOR),
+                amount: coins(50, VOTING_TOKEN),
+            })]
+        );
+        let value: PollResponse =
+            from_binary(&query(&deps, QueryMsg::Poll { poll_id: 2 }).unwrap()).unwrap();
+        assert_eq!(value.deposit, Uint128::zero());
+    }
+
+    #[test]
+    fn query_vote_of_voter() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(600, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Some(Uint128::from(500u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::Vote {
+            poll_id: 1,
+            voter: HumanAddr::from(TEST_VOTER),
+        };
+        let res: VoteResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.ballot,
+            Some(Ballot {
+                vote: VoteOption::No,
+                weight: Uint128::from(500u128),
+            })
+        );
+
+        let msg = QueryMsg::Vote {
+            poll_id: 1,
+            voter: HumanAddr::from(TEST_VOTER_2),
+        };
+        let res: VoteResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.ballot, None);
+
+        let msg = QueryMsg::Vote {
+            poll_id: 2,
+            voter: HumanAddr::from(TEST_VOTER),
+        };
+        match query(&deps, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll does not exist"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn vote_rationale_shown_in_poll_audit() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(10, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(10u128)),
+            rationale: Some("x".repeat(257)),
+        };
+        match handle(&mut deps, env.clone(), msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Rationale too long"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(10u128)),
+            rationale: Some("Funds the audit".to_string()),
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::PollAudit {
+            poll_id: 1,
+            start_after: None,
+            limit: None,
+        };
+        let audit: PollAuditResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            audit.votes[0].rationale,
+            Some("Funds the audit".to_string())
+        );
+
+        // updating the vote keeps its rationale unless it gives a new one
+        let voter_rationale = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            let msg = QueryMsg::Voters {
+                poll_id: 1,
+                start_after: None,
+                limit: None,
+            };
+            let res: VotersResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+            res.voters[0].rationale.clone()
+        };
+        assert_eq!(voter_rationale(&deps), Some("Funds the audit".to_string()));
+        let env = mock_env_height(TEST_VOTER, &[], 1002, 10000);
+        let update_vote = |rationale| HandleMsg::UpdateVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Uint128::from(10u128),
+            rationale,
+        };
+        handle(&mut deps, env.clone(), update_vote(None)).unwrap();
+        assert_eq!(voter_rationale(&deps), Some("Funds the audit".to_string()));
+        let rationale = Some("The audit is already funded".to_string());
+        handle(&mut deps, env, update_vote(rationale.clone())).unwrap();
+        assert_eq!(voter_rationale(&deps), rationale);
+    }
+
+    fn query_results(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        status: Option<PollStatus>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> Vec<PollResult> {
+        let msg = QueryMsg::Results {
+            status,
+            start_after,
+            limit,
+        };
+        let res: ResultsResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+        res.results
+    }
+
+    #[test]
+    fn results_lists_polls_in_order() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        for id in 1..=11u64 {
+            let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            let msg = create_poll_msg(30, format!("poll {}", id), None, Some(1010));
+            handle(&mut deps, creator_env, msg).unwrap();
+        }
+
+        let env = mock_env_height(TEST_VOTER, &coins(1000, VOTING_TOKEN), 1001, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 10,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1000u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 10,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        // ids 10 and 11 come after 9, not after 1 as their storage keys would
+        let results = query_results(&deps, None, Some(8), Some(2));
+        let ids: Vec<u64> = results.iter().map(|result| result.poll_id).collect();
+        assert_eq!(ids, vec![9, 10]);
+        assert_eq!(
+            results[1],
+            PollResult {
+                poll_id: 10,
+                title: "poll 10".to_string(),
+                status: PollStatus::Passed,
+                yes: Uint128::from(1000u128),
+                no: Uint128::zero(),
+                abstain: Uint128::zero(),
+                veto: Uint128::zero(),
+                quorum_pct: Some(percent(30)),
+                passed: true,
+                end_height: 1010,
+            }
+        );
+        assert_eq!(query_results(&deps, None, None, None).len(), 10);
+
+        let passed = query_results(&deps, Some(PollStatus::Passed), None, None);
+        assert_eq!(passed.len(), 1);
+        assert_eq!(passed[0].poll_id, 10);
+        assert!(query_results(&deps, Some(PollStatus::Rejected), None, None).is_empty());
+    }
+
+    #[test]
+    fn results_look_at_a_bounded_number_of_polls() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        for id in 1..=101u64 {
+            let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            let msg = create_poll_msg(30, format!("poll {}", id), None, Some(1010));
+            handle(&mut deps, creator_env, msg).unwrap();
+        }
+
+        let results_page = |start_after: Option<u64>| {
+            let msg = QueryMsg::Results {
+                status: Some(PollStatus::Passed),
+                start_after,
+                limit: None,
+            };
+            from_binary::<ResultsResponse>(&query(&deps, msg).unwrap()).unwrap()
+        };
+        // no poll matches, so the page stops after 100 polls and says where to carry on
+        let res = results_page(None);
+        assert!(res.results.is_empty());
+        assert_eq!(res.last_scanned, Some(100));
+        let res = results_page(res.last_scanned);
+        assert!(res.results.is_empty());
+        assert_eq!(res.last_scanned, None);
+
+        let msg = QueryMsg::Results {
+            status: None,
+            start_after: Some(95),
+            limit: Some(3),
+        };
+        let res: ResultsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.results.len(), 3);
+        assert_eq!(res.last_scanned, Some(98));
+    }
+
+    fn search_polls(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        prefix: &str,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> Vec<u64> {
+        let msg = QueryMsg::SearchPolls {
+            prefix: prefix.to_string(),
+            start_after,
+            limit,
+        };
+        let res: ResultsResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+        res.results.iter().map(|result| result.poll_id).collect()
+    }
+
+    #[test]
+    fn search_polls_by_description_prefix() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            initial_polls: Some(vec![InitialPoll {
+                quorum_percentage: None,
+                approval_quorum_percentage: None,
+                description: "budget for the year 2025".to_string(),
+                start_height: None,
+                end_height: None,
+                title: "Initial poll".to_string(),
+                link: None,
+            }]),
+            ..init_msg()
+        };
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        for description in &[
+            "hiring",
+            "budget for the year 2024",
+            "Budget caps",
+            "budget",
+        ] {
+            let msg = create_poll_msg(0, description.to_string(), None, None);
+            handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        }
+
+        // ordered by the indexed start of the description, then poll_id
+        assert_eq!(search_polls(&deps, "budget", None, None), vec![5, 1, 3]);
+        assert_eq!(search_polls(&deps, "budget", None, Some(2)), vec![5, 1]);
+        assert_eq!(search_polls(&deps, "budget", Some(1), Some(2)), vec![3]);
+        // prefixes longer than the indexed part of the description
+        assert_eq!(
+            search_polls(&deps, "budget for the year 2025", None, None),
+            vec![1]
+        );
+        assert_eq!(search_polls(&deps, "Budget", None, None), vec![4]);
+        assert_eq!(search_polls(&deps, "hiring", None, None), vec![2]);
+        assert!(search_polls(&deps, "zzz", None, None).is_empty());
+    }
+
+    fn polls_by_tag(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        tag: &str,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> Vec<u64> {
+        let msg = QueryMsg::PollsByTag {
+            tag: tag.to_string(),
+            start_after,
+            limit,
+        };
+        let res: ResultsResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+        res.results.iter().map(|result| result.poll_id).collect()
+    }
+
+    fn create_tagged_poll_msg(tags: &[&str]) -> HandleMsg {
+        let mut msg = create_poll_msg(0, "test".to_string(), None, None);
+        if let HandleMsg::CreatePoll {
+            tags: poll_tags, ..
+        } = &mut msg
+        {
+            *poll_tags = Some(tags.iter().map(|tag| tag.to_string()).collect());
+        }
+        msg
+    }
+
+    #[test]
+    fn polls_filtered_by_tag() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        for tags in &[
+            &["treasury"][..],
+            &["parameters"],
+            &["treasury", "elections"],
+            &[],
+            &["treasury"],
+        ] {
+            let msg = create_tagged_poll_msg(tags);
+            handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        }
+
+        assert_eq!(polls_by_tag(&deps, "treasury", None, None), vec![1, 3, 5]);
+        assert_eq!(polls_by_tag(&deps, "treasury", None, Some(2)), vec![1, 3]);
+        assert_eq!(polls_by_tag(&deps, "treasury", Some(3), None), vec![5]);
+        assert_eq!(polls_by_tag(&deps, "elections", None, None), vec![3]);
+        assert!(polls_by_tag(&deps, "budget", None, None).is_empty());
+        let res = query(&deps, QueryMsg::Poll { poll_id: 3 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.tags, vec!["treasury", "elections"]);
+
+        for (tags, error) in &[
+            (
+                &["a", "b", "c", "d", "e", "f"][..],
+                "A poll can have at most 5 tags",
+            ),
+            (
+                &[""],
+                "Tags must be 1 to 16 lowercase letters, digits or dashes",
+            ),
+            (
+                &["Treasury"],
+                "Tags must be 1 to 16 lowercase letters, digits or dashes",
+            ),
+            (
+                &["a-tag-that-is-too-long"],
+                "Tags must be 1 to 16 lowercase letters, digits or dashes",
+            ),
+            (&["treasury", "treasury"], "Duplicate tag"),
+        ] {
+            let msg = create_tagged_poll_msg(tags);
+            match handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg) {
+                Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, *error),
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
+    }
+
+    const REWARD_TOKEN: &str = "reward_token";
+
+    const RATE_SOURCE: &str = "staking";
+
+    // Answers exchange rate queries for RATE_SOURCE, everything else goes to the MockQuerier
+    struct RateQuerier {
+        base: MockQuerier,
+        investment: InvestmentResponse,
+    }
+
+    impl Querier for RateQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg })
+                    if contract_addr.as_str() == RATE_SOURCE =>
+                {
+                    let RateSourceQueryMsg::Investment {} = from_binary(msg).unwrap();
+                    Ok(to_binary(&self.investment))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    fn set_exchange_rate(
+        deps: &mut Extern<MockStorage, MockApi, RateQuerier>,
+        staked_tokens: u128,
+        token_supply: u128,
+    ) {
+        deps.querier.investment = InvestmentResponse {
+            token_supply: Uint128::from(token_supply),
+            staked_tokens: coin(staked_tokens, "ustake"),
+        };
+    }
+
+    #[test]
+    fn tally_counts_underlying_tokens() {
+        let mut deps = Extern {
+            storage: MockStorage::default(),
+            api: MockApi::new(20),
+            querier: RateQuerier {
+                base: MockQuerier::new(&[(
+                    &HumanAddr::from(MOCK_CONTRACT_ADDR),
+                    &coins(1000, VOTING_TOKEN),
+                )]),
+                investment: InvestmentResponse {
+                    token_supply: Uint128::zero(),
+                    staked_tokens: coin(0, "ustake"),
+                },
+            },
+        };
+        let msg = InitMsg {
+            rate_source: Some(HumanAddr::from(RATE_SOURCE)),
+            ..init_msg()
+        };
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(1000, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+
+        // each poll is tallied at the exchange rate of its own end height
+        for (poll_id, staked_tokens, token_supply, expected) in &[
+            (1u64, 1500u128, 1000u128, 1500u128),
+            (2u64, 1000u128, 2000u128, 500u128),
+            (3u64, 0u128, 0u128, 1000u128),
+        ] {
+            let end_height = 1000 + 10 * poll_id;
+            let creator_env = mock_env_height(TEST_CREATOR, &[], end_height - 5, 10000);
+            let msg = create_poll_msg(0, "test".to_string(), None, Some(end_height));
+            handle(&mut deps, creator_env, msg).unwrap();
+
+            let env = mock_env_height(TEST_VOTER, &[], end_height - 1, 10000);
+            let msg = HandleMsg::CastVote {
+                poll_id: *poll_id,
+                vote: VoteOption::Yes,
+                weight: Some(Uint128::from(1000u128)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+
+            set_exchange_rate(&mut deps, *staked_tokens, *token_supply);
+            let end_env = mock_env_height(TEST_CREATOR, &[], end_height, 10000);
+            let msg = HandleMsg::EndPoll {
+                poll_id: *poll_id,
+                limit: None,
+            };
+            handle(&mut deps, end_env, msg).unwrap();
+
+            let res = query(
+                &deps,
+                QueryMsg::PollAudit {
+                    poll_id: *poll_id,
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+            let audit: PollAuditResponse = from_binary(&res).unwrap();
+            assert_eq!(audit.status, PollStatus::Passed);
+            assert_eq!(audit.yes_votes, Uint128::from(*expected));
+            assert_eq!(audit.staked_weight, Some(Uint128::from(*expected)));
+            // the votes themselves stay in staked units
+            assert_eq!(audit.votes[0].weight, Uint128::from(1000u128));
+        }
+    }
+
+    #[test]
+    fn tally_rounds_converted_weights() {
+        let mut deps = Extern {
+            storage: MockStorage::default(),
+            api: MockApi::new(20),
+            querier: RateQuerier {
+                base: MockQuerier::new(&[(
+                    &HumanAddr::from(MOCK_CONTRACT_ADDR),
+                    &coins(1000, VOTING_TOKEN),
+                )]),
+                investment: InvestmentResponse {
+                    token_supply: Uint128::from(3000u128),
+                    staked_tokens: coin(1000, "ustake"),
+                },
+            },
+        };
+        let msg = InitMsg {
+            rate_source: Some(HumanAddr::from(RATE_SOURCE)),
+            weight_rounding: Some(Rounding::Ceil),
+            ..init_msg()
+        };
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &coins(1000, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1000u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let msg = QueryMsg::PollAudit {
+            poll_id: 1,
+            start_after: None,
+            limit: None,
+        };
+        let audit: PollAuditResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        // 1000 units at 1/3 token each
+        assert_eq!(audit.yes_votes, Uint128::from(334u128));
+        assert_eq!(audit.staked_weight, Some(Uint128::from(334u128)));
+    }
+
+    #[test]
+    fn weight_rounding_is_monotonic_and_ordered() {
+        let policies = [Rounding::Floor, Rounding::Round, Rounding::Ceil];
+        for &(numerator, denominator) in &[(1u128, 3u128), (2, 3), (7, 4), (5, 10), (999, 1000)] {
+            for &policy in &policies {
+                let mut previous = 0u128;
+                for amount in 0..500u128 {
+                    let weight = mul_ratio(amount, numerator, denominator, policy).unwrap();
+                    assert!(weight >= previous);
+                    previous = weight;
+                }
+            }
+            for amount in 0..500u128 {
+                let floor = mul_ratio(amount, numerator, denominator, Rounding::Floor).unwrap();
+                let round = mul_ratio(amount, numerator, denominator, Rounding::Round).unwrap();
+                let ceil = mul_ratio(amount, numerator, denominator, Rounding::Ceil).unwrap();
+                assert!(floor <= round && round <= ceil && ceil - floor <= 1);
+                // exact results are never rounded
+                if amount * numerator % denominator == 0 {
+                    assert_eq!(floor, ceil);
+                }
+            }
+        }
+        // halves round up
+        assert_eq!(mul_ratio(1, 5, 10, Rounding::Round).unwrap(), 1);
+        assert_eq!(mul_ratio(1, 4, 10, Rounding::Round).unwrap(), 0);
+    }
+
+    #[test]
+    fn set_rate_source() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+
+        let msg = HandleMsg::SetRateSource {
+            rate_source: Some(HumanAddr::from(RATE_SOURCE)),
+        };
+        let env = mock_env(TEST_VOTER, &[]);
+        match handle(&mut deps, env, msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let env = mock_env(TEST_CREATOR, &[]);
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![log("action", "set_rate_source"), log("enabled", "true")]
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            state.rate_source,
+            Some(
+                deps.api
+                    .canonical_address(&HumanAddr::from(RATE_SOURCE))
+                    .unwrap()
+            )
+        );
+
+        // a rate source that doesn't answer fails the tally instead of miscounting votes
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        assert!(handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None
+            }
+        )
+        .is_err());
+
+        let msg = HandleMsg::SetRateSource { rate_source: None };
+        let env = mock_env(TEST_CREATOR, &[]);
+        handle(&mut deps, env, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.rate_source, None);
+    }
+
+    #[test]
+    fn tally_executor_ends_polls_during_grace_period() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        for description in &["first", "second"] {
+            let msg = create_poll_msg(0, description.to_string(), None, Some(1010));
+            handle(&mut deps, creator_env.clone(), msg).unwrap();
+        }
+
+        // without an executor anyone can end a closed poll
+        let env = mock_env_height(TEST_VOTER, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        let msg = HandleMsg::SetTallyExecutor {
+            tally_executor: Some(HumanAddr::from(TEST_VOTER_2)),
+        };
+        let env = mock_env(TEST_VOTER, &[]);
+        match handle(&mut deps, env, msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env(TEST_CREATOR, &[]);
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![log("action", "set_tally_executor"), log("enabled", "true")]
+        );
+
+        let last_grace_height = 1010 + TALLY_GRACE_PERIOD_BLOCKS - 1;
+        let env = mock_env_height(TEST_VOTER, &[], last_grace_height, 10000);
+        match handle(&mut deps, env, HandleMsg::EndPoll { poll_id: 2, limit: None }) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Only the tally executor or the poll's editors can end the poll during the grace period"
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(TEST_VOTER_2, &[], last_grace_height, 10000);
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 2,
+                limit: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn anyone_can_end_poll_after_grace_period() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            tally_executor: Some(HumanAddr::from(TEST_VOTER_2)),
+            ..init_msg()
+        };
+        let env = mock_env(TEST_CREATOR, &[]);
+        init(&mut deps, env, msg).unwrap();
+
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+
+        let env = mock_env_height(TEST_VOTER, &[], 1010 + TALLY_GRACE_PERIOD_BLOCKS, 10000);
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn co_creators_edit_and_end_poll() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            tally_executor: Some(HumanAddr::from("executor")),
+            ..init_msg()
+        };
+        let env = mock_env(TEST_CREATOR, &[]);
+        init(&mut deps, env, msg).unwrap();
+
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "draft".to_string(), Some(1010), Some(1020));
+        handle(&mut deps, creator_env.clone(), msg).unwrap();
+
+        let add_msg = HandleMsg::AddCoCreator {
+            poll_id: 1,
+            co_creator: HumanAddr::from(TEST_VOTER),
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+        match handle(&mut deps, env, add_msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, creator_env.clone(), add_msg.clone()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "add_co_creator"),
+                log("poll_id", "1"),
+                log("co_creator", TEST_VOTER),
+            ]
+        );
+        match handle(&mut deps, creator_env.clone(), add_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address can already edit the poll")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let edit_msg = HandleMsg::EditPoll {
             poll_id: 1,
-            vote: "yes".to_string(),
-            weight: Uint128::from(1u128),
+            description: "final".to_string(),
         };
-
-        let res = handle(&mut deps, env, msg);
-
-        match res {
-            Ok(_) => panic!("Must return error"),
+        let env = mock_env_height(TEST_VOTER_2, &[], 1000, 10000);
+        match handle(&mut deps, env, edit_msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(TEST_VOTER, &[], 1009, 10000);
+        handle(&mut deps, env, edit_msg.clone()).unwrap();
+        let env = mock_env_height(TEST_VOTER, &[], 1010, 10000);
+        match handle(&mut deps, env, edit_msg) {
             Err(StdError::GenericErr { msg, .. }) => {
-                assert_eq!(msg, "User does not have enough staked tokens.")
+                assert_eq!(msg, "Poll can only be edited before voting starts")
             }
-            Err(e) => panic!("Unexpected error: {:?}", e),
+            res => panic!("Unexpected result: {:?}", res),
         }
+
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.description, "final");
+        assert_eq!(value.co_creators, vec![HumanAddr::from(TEST_VOTER)]);
+        assert_eq!(search_polls(&deps, "final", None, None), vec![1]);
+        assert!(search_polls(&deps, "draft", None, None).is_empty());
+
+        // co-creators can end the poll during the tally executor's grace period
+        let env = mock_env_height(TEST_VOTER, &[], 1020, 10000);
+        handle(
+            &mut deps,
+            env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
     }
 
     #[test]
-    fn happy_days_cast_vote() {
+    fn creator_removes_co_creator() {
         let mut deps = mock_dependencies(20, &[]);
         mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "draft".to_string(), Some(1010), Some(1020));
+        handle(&mut deps, creator_env.clone(), msg).unwrap();
+        let msg = HandleMsg::AddCoCreator {
+            poll_id: 1,
+            co_creator: HumanAddr::from(TEST_VOTER),
+        };
+        handle(&mut deps, creator_env.clone(), msg).unwrap();
 
-        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
-
-        let quorum_percentage = 30;
-
-        let msg = create_poll_msg(quorum_percentage, "test".to_string(), None, None);
-
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_create_poll_result(
-            1,
-            quorum_percentage,
-            DEFAULT_END_HEIGHT,
-            0,
-            TEST_CREATOR,
-            handle_res,
-            &mut deps,
+        let remove_msg = HandleMsg::RemoveCoCreator {
+            poll_id: 1,
+            co_creator: HumanAddr::from(TEST_VOTER),
+        };
+        let res = handle(&mut deps, creator_env.clone(), remove_msg.clone()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "remove_co_creator"),
+                log("poll_id", "1"),
+                log("co_creator", TEST_VOTER),
+            ]
         );
+        match handle(&mut deps, creator_env, remove_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Address is not a co-creator")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
 
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let msg = HandleMsg::EditPoll {
+            poll_id: 1,
+            description: "final".to_string(),
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+        match handle(&mut deps, env, msg) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
 
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_stake_tokens_result(11, Some(1), handle_res, &mut deps);
+    #[test]
+    fn bootstrap_defers_poll_creation() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            initial_polls: Some(vec![InitialPoll {
+                quorum_percentage: None,
+                approval_quorum_percentage: None,
+                description: "seeded".to_string(),
+                start_height: None,
+                end_height: None,
+                title: "Initial poll".to_string(),
+                link: None,
+            }]),
+            bootstrap: Some(Bootstrap { until_height: None }),
+            ..init_msg()
+        };
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
 
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
-        let weight = 10u128;
+        // staking and voting on the seeded poll work
+        let env = mock_env(TEST_VOTER, &coins(10, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
         let msg = HandleMsg::CastVote {
             poll_id: 1,
-            vote: "yes".to_string(),
-            weight: Uint128::from(weight),
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(10u128)),
+            rationale: None,
         };
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+
+        for sender in &[TEST_VOTER, TEST_CREATOR] {
+            let msg = create_poll_msg(0, "test".to_string(), None, None);
+            match handle(&mut deps, mock_env(*sender, &[]), msg) {
+                Err(StdError::GenericErr { msg, .. }) => {
+                    assert_eq!(msg, "Poll creation is disabled while bootstrapping")
+                }
+                res => panic!("Unexpected result: {:?}", res),
+            }
+        }
 
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_cast_vote_success(TEST_VOTER, weight, 1, handle_res);
+        match handle(
+            &mut deps,
+            mock_env(TEST_VOTER, &[]),
+            HandleMsg::EndBootstrap {},
+        ) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(
+            &mut deps,
+            mock_env(TEST_CREATOR, &[]),
+            HandleMsg::EndBootstrap {},
+        )
+        .unwrap();
+        assert_eq!(res.log, vec![log("action", "end_bootstrap")]);
+        assert_eq!(config_read(&deps.storage).load().unwrap().bootstrap, None);
+
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
     }
 
     #[test]
-    fn happy_days_withdraw_voting_tokens() {
+    fn bootstrap_ends_at_height() {
         let mut deps = mock_dependencies(20, &[]);
-        mock_init(&mut deps);
+        let msg = InitMsg {
+            bootstrap: Some(Bootstrap {
+                until_height: Some(1000),
+            }),
+            ..init_msg()
+        };
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
 
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let env = mock_env_height(TEST_VOTER, &[], 999, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        assert!(handle(&mut deps, env, msg).is_err());
 
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_stake_tokens_result(11, None, handle_res, &mut deps);
+        let env = mock_env_height(TEST_VOTER, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, env, msg).unwrap();
+    }
 
-        let state = config_read(&mut deps.storage).load().unwrap();
+    fn mock_init_with_rewards(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let msg = InitMsg {
+            denom: String::from(VOTING_TOKEN),
+            proposer_requirement: None,
+            reward_denom: Some(String::from(REWARD_TOKEN)),
+            rate_source: None,
+            initial_polls: None,
+            tally_executor: None,
+            weight_rounding: None,
+            bootstrap: None,
+            owner: None,
+            init_hook: None,
+            proposal_deposit: None,
+            tally_hook: None,
+            reward_token: None,
+            min_voting_period: None,
+            max_voting_period: None,
+            unbonding_period: None,
+            poll_creation_fee: None,
+            token: None,
+            extra_denoms: None,
+            restrict_poll_creation: None,
+        };
+        let env = mock_env(TEST_CREATOR, &[]);
+        init(deps, env, msg).unwrap();
+    }
+
+    fn query_pending_rewards(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        address: &str,
+    ) -> u128 {
+        let res = query(
+            deps,
+            QueryMsg::Rewards {
+                address: HumanAddr::from(address),
+            },
+        )
+        .unwrap();
+        let value: RewardsResponse = from_binary(&res).unwrap();
+        value.pending_rewards.u128()
+    }
+
+    #[test]
+    fn happy_days_rewards() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init_with_rewards(&mut deps);
+
+        let env = mock_env(TEST_VOTER, &coins(100, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let env = mock_env(TEST_VOTER_2, &coins(300, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+
+        let env = mock_env_height(TEST_CREATOR, &coins(40, REWARD_TOKEN), 10, 1000);
+        let handle_res = handle(&mut deps, env, HandleMsg::FundRewards {}).unwrap();
         assert_eq!(
-            state,
-            State {
-                denom: String::from(VOTING_TOKEN),
-                owner: deps
-                    .api
-                    .canonical_address(&HumanAddr::from(TEST_CREATOR))
-                    .unwrap(),
-                poll_count: 0,
-                staked_tokens: Uint128::from(11u128),
-            }
+            handle_res.log,
+            vec![
+                log("action", "fund_rewards"),
+                log("amount", "40"),
+                log("index", "100000000000"),
+            ]
         );
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER), 10);
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER_2), 30);
 
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
-        let msg = HandleMsg::WithdrawVotingTokens {
-            amount: Some(Uint128::from(11u128)),
-        };
-
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        let msg = handle_res.messages.get(0).expect("no message");
+        // stakers joining later don't earn earlier rewards
+        let env = mock_env(TEST_CREATOR, &coins(400, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        assert_eq!(query_pending_rewards(&deps, TEST_CREATOR), 0);
 
+        let env = mock_env(TEST_VOTER, &[]);
+        let handle_res = handle(&mut deps, env.clone(), HandleMsg::ClaimRewards {}).unwrap();
         assert_eq!(
-            msg,
-            &CosmosMsg::Bank(BankMsg::Send {
+            handle_res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
                 from_address: HumanAddr::from("cosmos2contract"),
                 to_address: HumanAddr::from(TEST_VOTER),
-                amount: coins(11, VOTING_TOKEN),
-            })
+                amount: coins(10, REWARD_TOKEN),
+            })]
         );
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER), 0);
 
-        let state = config_read(&mut deps.storage).load().unwrap();
+        match handle(&mut deps, env, HandleMsg::ClaimRewards {}) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "No rewards to claim"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
+
+        // a year later another 80 is split over all 800 staked tokens
+        let env = mock_env_height(
+            TEST_CREATOR,
+            &coins(80, REWARD_TOKEN),
+            20,
+            1000 + 31_536_000,
+        );
+        handle(&mut deps, env, HandleMsg::FundRewards {}).unwrap();
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER), 10);
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER_2), 60);
+        assert_eq!(query_pending_rewards(&deps, TEST_CREATOR), 40);
+
+        let res = query(&deps, QueryMsg::RewardRate {}).unwrap();
+        let value: RewardRateResponse = from_binary(&res).unwrap();
         assert_eq!(
-            state,
-            State {
-                denom: String::from(VOTING_TOKEN),
-                owner: deps
-                    .api
-                    .canonical_address(&HumanAddr::from(TEST_CREATOR))
-                    .unwrap(),
-                poll_count: 0,
-                staked_tokens: Uint128::zero(),
+            value,
+            RewardRateResponse {
+                denom: String::from(REWARD_TOKEN),
+                total_funded: Uint128::from(120u128),
+                accrued_fees: Uint128::zero(),
+                staked_tokens: Uint128::from(800u128),
+                apr: Some(Decimal::percent(15)),
+                token: None,
             }
         );
     }
 
     #[test]
-    fn fails_withdraw_voting_tokens_no_stake() {
+    fn fails_fund_rewards() {
         let mut deps = mock_dependencies(20, &[]);
         mock_init(&mut deps);
+        let env = mock_env(TEST_CREATOR, &coins(40, REWARD_TOKEN));
+        match handle(&mut deps, env.clone(), HandleMsg::FundRewards {}) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Rewards are not enabled"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
 
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
-        let msg = HandleMsg::WithdrawVotingTokens {
-            amount: Some(Uint128::from(11u128)),
-        };
-
-        let res = handle(&mut deps, env, msg);
-
-        match res {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init_with_rewards(&mut deps);
+        match handle(&mut deps, env, HandleMsg::FundRewards {}) {
             Ok(_) => panic!("Must return error"),
             Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Nothing staked"),
             Err(e) => panic!("Unexpected error: {:?}", e),
         }
+
+        let env = mock_env(TEST_CREATOR, &coins(40, VOTING_TOKEN));
+        match handle(&mut deps, env, HandleMsg::FundRewards {}) {
+            Ok(_) => panic!("Must return error"),
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "No reward funds sent"),
+            Err(e) => panic!("Unexpected error: {:?}", e),
+        }
     }
 
     #[test]
-    fn fails_withdraw_too_many_tokens() {
+    fn erc20_rewards() {
+        const REWARD_CONTRACT: &str = "reward_contract";
         let mut deps = mock_dependencies(20, &[]);
-        mock_init(&mut deps);
+        let mut msg = init_msg();
+        msg.reward_token = Some(HumanAddr::from(REWARD_CONTRACT));
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
 
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(10, VOTING_TOKEN));
+        let env = mock_env(TEST_VOTER, &coins(100, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        let env = mock_env(TEST_VOTER_2, &coins(300, VOTING_TOKEN));
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
 
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_stake_tokens_result(10, None, handle_res, &mut deps);
+        let env = mock_env(TEST_CREATOR, &coins(40, REWARD_TOKEN));
+        match handle(&mut deps, env, HandleMsg::FundRewards {}) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Rewards are funded by sending the reward token")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
 
-        let env = mock_env(TEST_VOTER, &[]);
-        let msg = HandleMsg::WithdrawVotingTokens {
-            amount: Some(Uint128::from(11u128)),
-        };
+        let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: HumanAddr::from(TEST_CREATOR),
+            amount: Uint128::from(40u128),
+            msg: None,
+        });
+        let env = mock_env("other_token", &[]);
+        match handle(&mut deps, env, receive_msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env(REWARD_CONTRACT, &[]);
+        let handle_res = handle(&mut deps, env, receive_msg).unwrap();
+        assert_eq!(handle_res.log[1], log("amount", "40"));
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER), 10);
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER_2), 30);
 
-        let res = handle(&mut deps, env, msg);
+        let env = mock_env(TEST_VOTER_2, &[]);
+        let handle_res = handle(&mut deps, env, HandleMsg::ClaimRewards {}).unwrap();
+        assert_eq!(
+            handle_res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr::from(REWARD_CONTRACT),
+                msg: to_binary(&TokenHandleMsg::Transfer {
+                    recipient: HumanAddr::from(TEST_VOTER_2),
+                    amount: Uint128::from(30u128),
+                    tx_key: None,
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER_2), 0);
 
-        match res {
-            Ok(_) => panic!("Must return error"),
+        let res = query(&deps, QueryMsg::RewardRate {}).unwrap();
+        let value: RewardRateResponse = from_binary(&res).unwrap();
+        assert_eq!(value.denom, "");
+        assert_eq!(value.token, Some(HumanAddr::from(REWARD_CONTRACT)));
+        assert_eq!(value.total_funded, Uint128::from(40u128));
+
+        let mut deps = mock_dependencies(20, &[]);
+        let mut msg = init_msg();
+        msg.reward_denom = Some(String::from(REWARD_TOKEN));
+        msg.reward_token = Some(HumanAddr::from(REWARD_CONTRACT));
+        match init(&mut deps, mock_env(TEST_CREATOR, &[]), msg) {
             Err(StdError::GenericErr { msg, .. }) => {
-                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+                assert_eq!(
+                    msg,
+                    "Rewards are paid in either reward_denom or reward_token"
+                )
             }
-            Err(e) => panic!("Unexpected error: {:?}", e),
+            res => panic!("Unexpected result: {:?}", res),
         }
     }
 
     #[test]
-    fn fails_cast_vote_twice() {
+    fn erc20_staking() {
+        const STAKING_CONTRACT: &str = "staking_contract";
         let mut deps = mock_dependencies(20, &[]);
-        mock_init(&mut deps);
+        let mut msg = init_msg();
+        msg.token = Some(HumanAddr::from(STAKING_CONTRACT));
+        msg.proposal_deposit = Some(Uint128::from(10u128));
+        match init(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Proposal deposits need a native staking denom")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        msg.proposal_deposit = None;
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
 
-        let env = mock_env_height(TEST_CREATOR, &coins(2, VOTING_TOKEN), 0, 10000);
+        let env = mock_env(TEST_VOTER, &coins(100, VOTING_TOKEN));
+        match handle(&mut deps, env, HandleMsg::StakeVotingTokens {}) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Tokens are staked by sending the staking token")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
 
-        let quorum_percentage = 30;
-        let msg = create_poll_msg(quorum_percentage, "test".to_string(), None, None);
-        let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: HumanAddr::from(TEST_VOTER),
+            amount: Uint128::from(100u128),
+            msg: None,
+        });
+        let env = mock_env(STAKING_CONTRACT, &[]);
+        handle(&mut deps, env, receive_msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(100u128));
+        let res = query(
+            &deps,
+            QueryMsg::TokenStake {
+                address: HumanAddr::from(TEST_VOTER),
+            },
+        )
+        .unwrap();
+        let value: TokenStakeResponse = from_binary(&res).unwrap();
+        assert_eq!(value.token_balance, Uint128::from(100u128));
 
-        assert_create_poll_result(
-            1,
-            quorum_percentage,
-            DEFAULT_END_HEIGHT,
-            0,
-            TEST_CREATOR,
-            handle_res,
-            &mut deps,
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(40u128)),
+            denom: None,
+        };
+        let handle_res = handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+        assert_eq!(
+            handle_res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr::from(STAKING_CONTRACT),
+                msg: to_binary(&TokenHandleMsg::Transfer {
+                    recipient: HumanAddr::from(TEST_VOTER),
+                    amount: Uint128::from(40u128),
+                    tx_key: None,
+                })
+                .unwrap(),
+                send: vec![],
+            })]
         );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(60u128));
 
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
-        let msg = HandleMsg::StakeVotingTokens {};
-
-        let handle_res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
-        assert_stake_tokens_result(11, Some(1), handle_res, &mut deps);
-
-        let weight = 1u128;
-        let msg = HandleMsg::CastVote {
-            poll_id: 1,
-            vote: "yes".to_string(),
-            weight: Uint128::from(weight),
+        let msg = HandleMsg::SetProposalDeposit {
+            amount: Some(Uint128::from(10u128)),
         };
-        let handle_res = handle(&mut deps, env.clone(), msg).unwrap();
-        assert_cast_vote_success(TEST_VOTER, weight, 1, handle_res);
+        match handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Proposal deposits need a native staking denom")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
 
-        let msg = HandleMsg::CastVote {
-            poll_id: 1,
-            vote: "yes".to_string(),
-            weight: Uint128::from(weight),
+    // runs the erc20 contract at TOKEN next to this one, passing the messages between them
+    #[test]
+    fn erc20_send_stakes_and_withdraw_transfers_back() {
+        use cw_erc20::contract as erc20;
+        use cw_erc20::msg as erc20_msg;
+
+        const TOKEN: &str = "token";
+        let token_env = |sender: &str| {
+            let mut env = mock_env(sender, &[]);
+            env.contract.address = HumanAddr::from(TOKEN);
+            env
         };
-        let res = handle(&mut deps, env.clone(), msg);
+        let token_balance = |token: &Extern<MockStorage, MockApi, MockQuerier>, address: &str| {
+            let msg = erc20_msg::QueryMsg::Balance {
+                address: HumanAddr::from(address),
+            };
+            let res: erc20_msg::BalanceResponse =
+                from_binary(&erc20::query(token, msg).unwrap()).unwrap();
+            res.balance
+        };
+        let mut token = mock_dependencies(20, &[]);
+        let token_init = erc20_msg::InitMsg {
+            name: "Voting Token".to_string(),
+            symbol: "VOTE".to_string(),
+            decimals: 6,
+            initial_balances: vec![erc20_msg::InitialBalance {
+                address: HumanAddr::from(TEST_VOTER),
+                amount: Uint128::from(100u128),
+            }],
+            compliance: None,
+            blocked_recipients: None,
+            restricted_transfers: None,
+            bootstrap_grant: None,
+            bootstrap_min_transfer: None,
+            daily_outflow_cap: None,
+            governance_contracts: None,
+            burner: None,
+            hashed_references: None,
+        };
+        erc20::init(&mut token, token_env(TEST_CREATOR), token_init).unwrap();
+        let mut deps = mock_dependencies(20, &[]);
+        let mut msg = init_msg();
+        msg.token = Some(HumanAddr::from(TOKEN));
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+
+        let stake_msg = to_binary(&ReceiveMsg::StakeVotingTokens {}).unwrap();
+        let send_msg = erc20_msg::HandleMsg::Send {
+            contract: HumanAddr::from(MOCK_CONTRACT_ADDR),
+            amount: Uint128::from(100u128),
+            msg: Some(stake_msg.clone()),
+            tx_key: None,
+        };
+        let res = erc20::handle(&mut token, token_env(TEST_VOTER), send_msg).unwrap();
+        let receive_msg = match &res.messages[..] {
+            [CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                send,
+            })] => {
+                assert_eq!(contract_addr, &HumanAddr::from(MOCK_CONTRACT_ADDR));
+                assert!(send.is_empty());
+                from_binary::<HandleMsg>(msg).unwrap()
+            }
+            messages => panic!("Unexpected messages: {:?}", messages),
+        };
+        handle(&mut deps, mock_env(TOKEN, &[]), receive_msg).unwrap();
+        let res = query(
+            &deps,
+            QueryMsg::TokenStake {
+                address: HumanAddr::from(TEST_VOTER),
+            },
+        )
+        .unwrap();
+        let value: TokenStakeResponse = from_binary(&res).unwrap();
+        assert_eq!(value.token_balance, Uint128::from(100u128));
+        assert_eq!(
+            token_balance(&token, MOCK_CONTRACT_ADDR),
+            Uint128::from(100u128)
+        );
 
-        match res {
-            Ok(_) => panic!("Must return error"),
-            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "User has already voted."),
-            Err(e) => panic!("Unexpected error: {:?}", e),
+        // other tokens can't be staked
+        let receive_msg = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: HumanAddr::from(TEST_VOTER),
+            amount: Uint128::from(100u128),
+            msg: Some(stake_msg),
+        });
+        match handle(&mut deps, mock_env("other_token", &[]), receive_msg) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Only the staking token can be staked")
+            }
+            res => panic!("Unexpected result: {:?}", res),
         }
+
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(60u128)),
+            denom: None,
+        };
+        let res = handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+        let transfer_msg = match &res.messages[..] {
+            [CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            })] => {
+                assert_eq!(contract_addr, &HumanAddr::from(TOKEN));
+                from_binary::<erc20_msg::HandleMsg>(msg).unwrap()
+            }
+            messages => panic!("Unexpected messages: {:?}", messages),
+        };
+        erc20::handle(&mut token, token_env(MOCK_CONTRACT_ADDR), transfer_msg).unwrap();
+        assert_eq!(token_balance(&token, TEST_VOTER), Uint128::from(60u128));
+        assert_eq!(
+            token_balance(&token, MOCK_CONTRACT_ADDR),
+            Uint128::from(40u128)
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(40u128));
     }
 
     #[test]
-    fn fails_cast_vote_without_poll() {
+    fn poll_creation_fees() {
         let mut deps = mock_dependencies(20, &[]);
-        mock_init(&mut deps);
-
-        let msg = HandleMsg::CastVote {
-            poll_id: 0,
-            vote: "yes".to_string(),
-            weight: Uint128::from(1u128),
-        };
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let mut msg = init_msg();
+        msg.poll_creation_fee = Some(Uint128::from(10u128));
+        match init(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Poll creation fees are paid in reward_denom")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        msg.reward_denom = Some(String::from(REWARD_TOKEN));
+        init(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        stake_without_voting(&mut deps, TEST_VOTER, 100);
+        stake_without_voting(&mut deps, TEST_VOTER_2, 300);
 
-        let res = handle(&mut deps, env, msg);
+        let env = mock_env(TEST_VOTER, &[]);
+        match handle(&mut deps, env.clone(), HandleMsg::DistributeRewards {}) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "No fees to distribute"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        match handle(&mut deps, env.clone(), msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                format!("Poll creation fee of 10{} required", REWARD_TOKEN)
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let fee_env = mock_env_height(TEST_VOTER, &coins(10, REWARD_TOKEN), 1000, 10000);
+        handle(&mut deps, fee_env.clone(), msg.clone()).unwrap();
+        handle(&mut deps, fee_env, msg.clone()).unwrap();
+
+        let res = query(&deps, QueryMsg::RewardRate {}).unwrap();
+        let value: RewardRateResponse = from_binary(&res).unwrap();
+        assert_eq!(value.accrued_fees, Uint128::from(20u128));
+        // fees only reach stakers once distributed
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER_2), 0);
+
+        let res = handle(&mut deps, env.clone(), HandleMsg::DistributeRewards {}).unwrap();
+        assert_eq!(res.log[0], log("action", "distribute_rewards"));
+        assert_eq!(res.log[1], log("amount", 20));
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER), 5);
+        assert_eq!(query_pending_rewards(&deps, TEST_VOTER_2), 15);
+        let res = query(&deps, QueryMsg::RewardRate {}).unwrap();
+        let value: RewardRateResponse = from_binary(&res).unwrap();
+        assert_eq!(value.accrued_fees, Uint128::zero());
+        assert_eq!(value.total_funded, Uint128::from(20u128));
+
+        let res = handle(&mut deps, env.clone(), HandleMsg::ClaimRewards {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER),
+                amount: coins(5, REWARD_TOKEN),
+            })]
+        );
 
-        match res {
-            Ok(_) => panic!("Must return error"),
-            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll does not exist"),
-            Err(e) => panic!("Unexpected error: {:?}", e),
+        let msg = HandleMsg::SetPollCreationFee { amount: None };
+        match handle(&mut deps, env.clone(), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
         }
+        handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, env, msg).unwrap();
+    }
+
+    fn check_invariants(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        limit: Option<u32>,
+    ) -> InvariantReport {
+        let env = mock_env(TEST_CREATOR, &[]);
+        let handle_res = handle(deps, env, HandleMsg::CheckInvariants { limit }).unwrap();
+        from_binary(&handle_res.data.unwrap()).unwrap()
     }
 
     #[test]
-    fn happy_days_stake_voting_tokens() {
+    fn check_invariants_healthy_state() {
         let mut deps = mock_dependencies(20, &[]);
         mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &[], 0, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, env, msg).unwrap();
+
+        for voter in &[TEST_VOTER, TEST_VOTER_2] {
+            let env = mock_env(*voter, &coins(10, VOTING_TOKEN));
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: VoteOption::Yes,
+                weight: Some(Uint128::from(10u128)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
 
-        let msg = HandleMsg::StakeVotingTokens {};
-
-        let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
+        let report = check_invariants(&mut deps, Some(1));
+        assert_eq!(
+            report,
+            InvariantReport {
+                complete: false,
+                accounts_checked: 1,
+                violations: vec![],
+            }
+        );
+        let report = check_invariants(&mut deps, Some(1));
+        assert_eq!(report.accounts_checked, 2);
+        assert!(!report.complete);
+        let report = check_invariants(&mut deps, Some(1));
+        assert_eq!(
+            report,
+            InvariantReport {
+                complete: true,
+                accounts_checked: 2,
+                violations: vec![],
+            }
+        );
 
-        let handle_res = handle(&mut deps, env, msg.clone()).unwrap();
-        assert_stake_tokens_result(11, None, handle_res, &mut deps);
+        // a finished check starts over
+        let report = check_invariants(&mut deps, None);
+        assert_eq!(report.accounts_checked, 2);
+        assert!(report.complete);
     }
 
     #[test]
-    fn fails_insufficient_funds() {
+    fn check_invariants_reports_violations() {
         let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
 
-        // initialize the store
-        let msg = init_msg();
-        let env = mock_env(TEST_VOTER, &coins(2, VOTING_TOKEN));
-        let init_res = init(&mut deps, env, msg).unwrap();
-        assert_eq!(0, init_res.messages.len());
-
-        // insufficient token
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(0, VOTING_TOKEN));
-
-        let res = handle(&mut deps, env, msg);
+        let voter = deps
+            .api
+            .canonical_address(&HumanAddr::from(TEST_VOTER))
+            .unwrap();
+        let corrupted = TokenManager {
+            token_balance: Uint128::from(5u128),
+            ..TokenManager::default()
+        };
+        bank(&mut deps.storage)
+            .save(voter.as_slice(), &corrupted)
+            .unwrap();
+        locks(&mut deps.storage, &voter)
+            .save(&7u64.to_be_bytes(), &Uint128::from(5u128))
+            .unwrap();
+
+        let report = check_invariants(&mut deps, None);
+        assert_eq!(
+            report,
+            InvariantReport {
+                complete: true,
+                accounts_checked: 1,
+                violations: vec![
+                    "voter1 has tokens locked in poll 7 which does not exist".to_string(),
+                    "Staked balances add up to 5 but staked_tokens is 0".to_string(),
+                ],
+            }
+        );
 
-        match res {
+        let env = mock_env(TEST_VOTER, &[]);
+        match handle(&mut deps, env, HandleMsg::CheckInvariants { limit: None }) {
             Ok(_) => panic!("Must return error"),
-            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Insufficient funds sent"),
+            Err(StdError::Unauthorized { .. }) => {}
             Err(e) => panic!("Unexpected error: {:?}", e),
         }
     }
 
     #[test]
-    fn fails_staking_wrong_token() {
+    fn emergency_unlock_all_clears_stuck_locks() {
         let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_CREATOR, &[], 0, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, None);
+        handle(&mut deps, env, msg).unwrap();
+
+        // both voters are stuck in a poll that no longer exists, voter1 also votes in poll 1
+        for voter in &[TEST_VOTER, TEST_VOTER_2] {
+            let env = mock_env(*voter, &coins(10, VOTING_TOKEN));
+            handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+            let voter_raw = deps
+                .api
+                .canonical_address(&HumanAddr::from(*voter))
+                .unwrap();
+            locks(&mut deps.storage, &voter_raw)
+                .save(&7u64.to_be_bytes(), &Uint128::from(10u128))
+                .unwrap();
+        }
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(5u128)),
+            rationale: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
 
-        // initialize the store
-        let msg = init_msg();
-        let env = mock_env(TEST_VOTER, &coins(2, VOTING_TOKEN));
-        let init_res = init(&mut deps, env, msg).unwrap();
-        assert_eq!(0, init_res.messages.len());
-
-        // wrong token
-        let msg = HandleMsg::StakeVotingTokens {};
-        let env = mock_env(TEST_VOTER, &coins(11, "play money"));
-
-        let res = handle(&mut deps, env, msg);
-
-        match res {
-            Ok(_) => panic!("Must return error"),
-            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Insufficient funds sent"),
-            Err(e) => panic!("Unexpected error: {:?}", e),
+        let env = mock_env(TEST_VOTER, &[]);
+        match handle(
+            &mut deps,
+            env,
+            HandleMsg::EmergencyUnlockAll { limit: None },
+        ) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
         }
+
+        let msg = HandleMsg::EmergencyUnlockAll { limit: Some(1) };
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "emergency_unlock_all"),
+                log("complete", "false"),
+                log("accounts", "1"),
+                log("unlocked", "1"),
+            ]
+        );
+        let msg = HandleMsg::EmergencyUnlockAll { limit: Some(1) };
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[3], log("unlocked", "1"));
+        let msg = HandleMsg::EmergencyUnlockAll { limit: Some(1) };
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[1], log("complete", "true"));
+        assert_eq!(res.log[2], log("accounts", "0"));
+
+        // the lock in the poll in progress stays, the rest can be withdrawn
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(6u128)),
+            denom: None,
+        };
+        assert!(handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).is_err());
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(5u128)),
+            denom: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER, &[]), msg).unwrap();
+        let msg = HandleMsg::WithdrawVotingTokens {
+            amount: None,
+            denom: None,
+        };
+        handle(&mut deps, mock_env(TEST_VOTER_2, &[]), msg).unwrap();
     }
 
     // helper to confirm the expected create_poll response
@@ -831,12 +5645,31 @@ mod tests {
                     .unwrap(),
                 poll_count: 1,
                 staked_tokens: Uint128::zero(),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: 0,
+                active_polls: 1,
             }
         );
     }
 
     fn assert_stake_tokens_result(
         staked_tokens: u128,
+        stakers: u64,
         poll_count: Option<u64>,
         handle_res: HandleResponse,
         deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
@@ -854,6 +5687,24 @@ mod tests {
                     .unwrap(),
                 poll_count: poll_count.unwrap_or_default(),
                 staked_tokens: Uint128::from(staked_tokens),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: stakers,
+                active_polls: poll_count.unwrap_or_default(),
             }
         );
     }
@@ -874,4 +5725,690 @@ mod tests {
             ]
         );
     }
+
+    // stores votes inline in the poll, the way polls kept them before the vote bucket, with
+    // the vote as the string CastVote was sent
+    fn store_legacy_votes(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        poll_id: u64,
+        legacy_votes: &[(&str, &str, u128)],
+    ) {
+        let key = poll_id.to_string();
+        let a_poll = poll_read(&deps.storage).load(key.as_bytes()).unwrap();
+        let mut voters = vec![];
+        let mut voter_info = vec![];
+        for (voter, vote, weight) in legacy_votes {
+            let voter_raw = deps
+                .api
+                .canonical_address(&HumanAddr::from(*voter))
+                .unwrap();
+            voters.push(voter_raw);
+            voter_info.push(format!(
+                "{{\"vote\":\"{}\",\"weight\":\"{}\"}}",
+                vote, weight
+            ));
+        }
+        let mut raw = to_vec(&a_poll).unwrap();
+        raw.pop();
+        raw.extend_from_slice(b",\"voters\":");
+        raw.extend(to_vec(&voters).unwrap());
+        raw.extend_from_slice(format!(",\"voter_info\":[{}]}}", voter_info.join(",")).as_bytes());
+        let mut storage_key = to_length_prefixed(b"polls");
+        storage_key.extend_from_slice(key.as_bytes());
+        deps.storage.set(&storage_key, &raw);
+    }
+
+    fn query_vote_totals(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        poll_id: u64,
+    ) -> PollVoteTotalsResponse {
+        let msg = QueryMsg::PollVoteTotals { poll_id };
+        from_binary(&query(deps, msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn migrate_moves_legacy_votes() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        for (voter, weight) in &[(TEST_VOTER, 600u128), (TEST_VOTER_2, 400u128)] {
+            let env = mock_env_height(voter, &coins(*weight, VOTING_TOKEN), 1000, 10000);
+            handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        }
+        for _ in 0..2 {
+            let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+            handle(&mut deps, creator_env, msg).unwrap();
+        }
+        store_legacy_votes(
+            &mut deps,
+            1,
+            &[(TEST_VOTER, "yes", 600), (TEST_VOTER_2, "nay", 400)],
+        );
+        // only "yes" counted as yes, so this is a no
+        store_legacy_votes(&mut deps, 2, &[(TEST_VOTER, "Yes", 600)]);
+        // contracts with inline votes predate the stored version
+        contract_version(&mut deps.storage).remove();
+
+        // votes not migrated yet can still be looked up
+        let msg = QueryMsg::Vote {
+            poll_id: 2,
+            voter: HumanAddr::from(TEST_VOTER),
+        };
+        let res: VoteResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(
+            res.ballot,
+            Some(Ballot {
+                vote: VoteOption::No,
+                weight: Uint128::from(600u128),
+            })
+        );
+
+        let before = query_vote_totals(&deps, 1);
+        assert_eq!(before.migrated, VoteTotals::default());
+        assert_eq!(
+            before.legacy,
+            VoteTotals {
+                votes: 2,
+                yes: Uint128::from(600u128),
+                no: Uint128::from(400u128),
+                abstain: Uint128::zero(),
+                no_with_veto: Uint128::zero(),
+            }
+        );
+
+        let msg = MigrateMsg { limit: Some(1) };
+        let res = migrate(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "migrate"),
+                log("to_version", CONTRACT_VERSION),
+                log("locks", 0),
+                log("locks_complete", true),
+                log("complete", false),
+                log("last_poll_id", 1),
+                log("votes", 2),
+            ]
+        );
+        // the tally is unchanged, it just moved
+        let after = query_vote_totals(&deps, 1);
+        assert_eq!(after.legacy, VoteTotals::default());
+        assert_eq!(after.migrated, before.legacy);
+        // and the open poll's running totals start from it
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.yes_votes, Uint128::from(600u128));
+        assert_eq!(value.no_votes, Uint128::from(400u128));
+
+        // polls not backfilled yet are migrated before a vote is cast
+        let env = mock_env_height(TEST_VOTER, &[], 1002, 10000);
+        let msg = HandleMsg::CastVote {
+            poll_id: 2,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(600u128)),
+            rationale: None,
+        };
+        match handle(&mut deps, env, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "User has already voted."),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let msg = HandleMsg::BackfillVotes { limit: None };
+        match handle(&mut deps, mock_env(TEST_VOTER, &[]), msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[2], log("locks_complete", true));
+        assert_eq!(res.log[3], log("complete", true));
+        assert_eq!(res.log[5], log("votes", 0));
+
+        let res: VoteBackfillResponse =
+            from_binary(&query(&deps, QueryMsg::VoteBackfill {}).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            VoteBackfillResponse {
+                last_poll_id: 2,
+                until_poll_id: 2,
+                polls_migrated: 2,
+                votes_migrated: 3,
+                locks_migrated: 0,
+                locks_complete: true,
+                complete: true,
+            }
+        );
+        assert_eq!(
+            query_vote_totals(&deps, 2).migrated.no,
+            Uint128::from(600u128)
+        );
+        // the heights of legacy votes weren't recorded
+        let msg = QueryMsg::PollAudit {
+            poll_id: 2,
+            start_after: None,
+            limit: None,
+        };
+        let audit: PollAuditResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(audit.votes[0].voted_at_height, 0);
+
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        handle(
+            &mut deps,
+            end_env,
+            HandleMsg::EndPoll {
+                poll_id: 1,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let msg = QueryMsg::PollAudit {
+            poll_id: 1,
+            start_after: None,
+            limit: None,
+        };
+        let audit: PollAuditResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(audit.status, PollStatus::Passed);
+        assert_eq!(audit.yes_votes, Uint128::from(600u128));
+        assert_eq!(audit.no_votes, Uint128::from(400u128));
+    }
+
+    // the key of `key` in a bucket or singleton under `namespace`
+    fn storage_key(namespace: &[u8], key: &[u8]) -> Vec<u8> {
+        let mut storage_key = to_length_prefixed(namespace);
+        storage_key.extend_from_slice(key);
+        storage_key
+    }
+
+    #[test]
+    fn migrate_from_baseline_storage() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        let address = |deps: &Extern<MockStorage, MockApi, MockQuerier>, human: &str| {
+            let raw = deps.api.canonical_address(&HumanAddr::from(human)).unwrap();
+            String::from_utf8(to_vec(&raw).unwrap()).unwrap()
+        };
+        let creator = address(&deps, TEST_CREATOR);
+        let voter = address(&deps, TEST_VOTER);
+        let voter_2 = address(&deps, TEST_VOTER_2);
+
+        // storage as the first version of the contract wrote it
+        let stored = vec![
+            (
+                storage_key(b"config", b""),
+                format!(
+                    r#"{{"denom":"voting_token","owner":{},"poll_count":2,"staked_tokens":"1000"}}"#,
+                    creator
+                ),
+            ),
+            (
+                storage_key(b"polls", b"1"),
+                format!(
+                    concat!(
+                        r#"{{"creator":{},"status":"InProgress","quorum_percentage":30,"#,
+                        r#""yes_votes":"0","no_votes":"0","voters":[{},{}],"#,
+                        r#""voter_info":[{{"vote":"yes","weight":"600"}},{{"vote":"no","weight":"400"}}],"#,
+                        r#""end_height":1010,"start_height":null,"description":"test""#,
+                        "}}"
+                    ),
+                    creator, voter, voter_2
+                ),
+            ),
+            (
+                storage_key(b"polls", b"2"),
+                format!(
+                    concat!(
+                        r#"{{"creator":{},"status":"Rejected","quorum_percentage":null,"#,
+                        r#""yes_votes":"0","no_votes":"0","voters":[],"voter_info":[],"#,
+                        r#""end_height":900,"start_height":null,"description":"old poll""#,
+                        "}}"
+                    ),
+                    creator
+                ),
+            ),
+            (
+                storage_key(
+                    b"bank",
+                    &deps
+                        .api
+                        .canonical_address(&HumanAddr::from(TEST_VOTER))
+                        .unwrap(),
+                ),
+                r#"{"token_balance":"600","locked_tokens":[[1,"600"]],"participated_polls":[1]}"#
+                    .to_string(),
+            ),
+            (
+                storage_key(
+                    b"bank",
+                    &deps
+                        .api
+                        .canonical_address(&HumanAddr::from(TEST_VOTER_2))
+                        .unwrap(),
+                ),
+                r#"{"token_balance":"400","locked_tokens":[[1,"400"]],"participated_polls":[1]}"#
+                    .to_string(),
+            ),
+        ];
+        for (key, value) in &stored {
+            deps.storage.set(key, value.as_bytes());
+        }
+
+        let msg = MigrateMsg { limit: None };
+        let res = migrate(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "migrate"),
+                log("to_version", CONTRACT_VERSION),
+                log("locks", 2),
+                log("locks_complete", true),
+                log("complete", true),
+                log("last_poll_id", 2),
+                log("votes", 2),
+            ]
+        );
+        // everything is saved again in the current layout
+        for (key, value) in &stored[1..] {
+            let migrated = deps.storage.get(key).unwrap();
+            assert_ne!(migrated, value.as_bytes().to_vec());
+            let migrated = String::from_utf8(migrated).unwrap();
+            assert!(!migrated.contains("voter_info") && !migrated.contains("locked_tokens"));
+        }
+
+        let res = query(&deps, QueryMsg::Config {}).unwrap();
+        let config: State = from_binary(&res).unwrap();
+        assert_eq!(config.weight_rounding, Rounding::Floor);
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::InProgress);
+        assert_eq!(value.quorum_percentage, Some(percent(30)));
+        assert_eq!(value.yes_votes, Uint128::from(600u128));
+        assert_eq!(value.no_votes, Uint128::from(400u128));
+        let res = query(&deps, QueryMsg::Poll { poll_id: 2 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Rejected);
+        assert_eq!(value.co_creators, Vec::<HumanAddr>::new());
+
+        // the moved locks still hold the stake until the poll ends
+        let withdraw = HandleMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(400u128)),
+            denom: None,
+        };
+        let env = mock_env_height(TEST_VOTER_2, &[], 1005, 10000);
+        match handle(&mut deps, env, withdraw.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "User is trying to withdraw too many tokens.")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Passed);
+
+        let env = mock_env_height(TEST_VOTER_2, &[], 1011, 10000);
+        let res = handle(&mut deps, env, withdraw).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                to_address: HumanAddr::from(TEST_VOTER_2),
+                amount: coins(400, VOTING_TOKEN),
+            })]
+        );
+    }
+
+    #[test]
+    fn migrate_moves_locks_in_pages() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        for i in 0..31 {
+            let voter = format!("voter{:02}", i);
+            let env = mock_env_height(&voter, &coins(10, VOTING_TOKEN), 1000, 10000);
+            handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        }
+        contract_version(&mut deps.storage).remove();
+
+        let msg = MigrateMsg { limit: None };
+        let res = migrate(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[3], log("locks_complete", false));
+
+        // nothing else goes through until every lock has moved
+        let env = mock_env_height("voter00", &coins(10, VOTING_TOKEN), 1001, 10000);
+        match handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                "Locks are still being migrated, the owner must send BackfillVotes"
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = HandleMsg::BackfillVotes { limit: None };
+        let res = handle(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(res.log[2], log("locks_complete", true));
+        let res: VoteBackfillResponse =
+            from_binary(&query(&deps, QueryMsg::VoteBackfill {}).unwrap()).unwrap();
+        assert!(res.locks_complete && res.complete);
+
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+    }
+
+    fn store_contract_version(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        contract: &str,
+        version: &str,
+    ) {
+        let stored = ContractVersion {
+            contract: contract.to_string(),
+            version: version.to_string(),
+        };
+        contract_version(&mut deps.storage).save(&stored).unwrap();
+    }
+
+    #[test]
+    fn migrate_checks_contract_version() {
+        let mut deps = mock_dependencies(20, &[]);
+        mock_init(&mut deps);
+        let res = query(&deps, QueryMsg::ContractVersion {}).unwrap();
+        let value: ContractVersion = from_binary(&res).unwrap();
+        assert_eq!(
+            value,
+            ContractVersion {
+                contract: CONTRACT_NAME.to_string(),
+                version: CONTRACT_VERSION.to_string(),
+            }
+        );
+
+        store_contract_version(&mut deps, "crates.io:cw-erc20", CONTRACT_VERSION);
+        let msg = MigrateMsg { limit: None };
+        match migrate(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Cannot migrate from crates.io:cw-erc20")
+            }
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        store_contract_version(&mut deps, CONTRACT_NAME, "99.0.0");
+        match migrate(&mut deps, mock_env(TEST_CREATOR, &[]), msg.clone()) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+                msg,
+                format!(
+                    "Cannot migrate from version 99.0.0 to the older {}",
+                    CONTRACT_VERSION
+                )
+            ),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        store_contract_version(&mut deps, CONTRACT_NAME, "0.1.0-beta");
+        let res = migrate(&mut deps, mock_env(TEST_CREATOR, &[]), msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "migrate"),
+                log("from_version", "0.1.0-beta"),
+                log("to_version", CONTRACT_VERSION),
+            ]
+        );
+        let res = query(&deps, QueryMsg::ContractVersion {}).unwrap();
+        let value: ContractVersion = from_binary(&res).unwrap();
+        assert_eq!(value.version, CONTRACT_VERSION);
+    }
+
+    #[test]
+    fn prune_polls_archives_ended_polls() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let env = mock_env_height(TEST_VOTER, &coins(1000, VOTING_TOKEN), 1000, 10000);
+        handle(&mut deps, env, HandleMsg::StakeVotingTokens {}).unwrap();
+        for _ in 0..3 {
+            let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+            let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+            handle(&mut deps, creator_env, msg).unwrap();
+        }
+        let env = mock_env_height(TEST_VOTER, &[], 1001, 10000);
+        let msg = HandleMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Some(Uint128::from(1000u128)),
+            rationale: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        // polls 1 and 2 end, poll 3 is left open
+        for poll_id in 1..=2 {
+            let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+            let msg = HandleMsg::EndPoll {
+                poll_id,
+                limit: None,
+            };
+            handle(&mut deps, end_env, msg).unwrap();
+        }
+        assert_eq!(query_voter_history(&deps, TEST_VOTER, None).len(), 1);
+
+        let msg = HandleMsg::PrunePolls {
+            up_to_poll_id: 3,
+            limit: None,
+        };
+        let env = mock_env_height(TEST_VOTER, &[], 1010 + POLL_RETENTION_BLOCKS, 10000);
+        match handle(&mut deps, env, msg.clone()) {
+            Err(StdError::Unauthorized { .. }) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        // nothing is pruned within the retention window
+        let env = mock_env_height(TEST_CREATOR, &[], 1009 + POLL_RETENTION_BLOCKS, 10000);
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        assert_eq!(res.log[1], log("pruned_through", 0));
+        assert_eq!(res.log[2], log("polls_pruned", 0));
+
+        let env = mock_env_height(TEST_CREATOR, &[], 1010 + POLL_RETENTION_BLOCKS, 10000);
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "prune_polls"),
+                log("pruned_through", 2),
+                log("polls_pruned", 2),
+            ]
+        );
+        let res: PollArchive =
+            from_binary(&query(&deps, QueryMsg::PollArchive {}).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            PollArchive {
+                pruned_through: 2,
+                polls_pruned: 2,
+            }
+        );
+
+        match query(&deps, QueryMsg::Poll { poll_id: 1 }) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll 1 is archived"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        match query(&deps, QueryMsg::Poll { poll_id: 4 }) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll does not exist"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        let msg = QueryMsg::Vote {
+            poll_id: 1,
+            voter: HumanAddr::from(TEST_VOTER),
+        };
+        match query(&deps, msg) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll 1 is archived"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert_eq!(query_voter_history(&deps, TEST_VOTER, None), vec![]);
+        let msg = QueryMsg::Results {
+            status: None,
+            start_after: None,
+            limit: None,
+        };
+        let res: ResultsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        let poll_ids: Vec<u64> = res.results.iter().map(|result| result.poll_id).collect();
+        assert_eq!(poll_ids, vec![3]);
+    }
+
+    #[test]
+    fn prune_polls_resumes_polls_with_many_votes() {
+        let mut deps = mock_dependencies(20, &coins(1000, VOTING_TOKEN));
+        mock_init(&mut deps);
+        let creator_env = mock_env_height(TEST_CREATOR, &[], 1000, 10000);
+        let msg = create_poll_msg(0, "test".to_string(), None, Some(1010));
+        handle(&mut deps, creator_env, msg).unwrap();
+        for i in 0..100 {
+            let voter = format!("voter{:03}", i);
+            let env = mock_env_height(&voter, &coins(10, VOTING_TOKEN), 1001, 10000);
+            handle(&mut deps, env.clone(), HandleMsg::StakeVotingTokens {}).unwrap();
+            let msg = HandleMsg::CastVote {
+                poll_id: 1,
+                vote: VoteOption::Yes,
+                weight: Some(Uint128::from(10u128)),
+                rationale: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+        let end_env = mock_env_height(TEST_CREATOR, &[], 1010, 10000);
+        let msg = HandleMsg::EndPoll {
+            poll_id: 1,
+            limit: Some(300),
+        };
+        handle(&mut deps, end_env, msg).unwrap();
+
+        // the votes and their tree are more than one message deletes
+        let msg = HandleMsg::PrunePolls {
+            up_to_poll_id: 1,
+            limit: None,
+        };
+        let env = mock_env_height(TEST_CREATOR, &[], 1010 + POLL_RETENTION_BLOCKS, 10000);
+        let res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        assert_eq!(res.log[1], log("pruned_through", 0));
+        assert_eq!(res.log[2], log("polls_pruned", 0));
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Passed);
+
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(res.log[1], log("pruned_through", 1));
+        assert_eq!(res.log[2], log("polls_pruned", 1));
+        let tree_nodes = vote_tree_read(&deps.storage, 1)
+            .range(None, None, Order::Ascending)
+            .count();
+        assert_eq!(tree_nodes, 0);
+        match query(&deps, QueryMsg::Poll { poll_id: 1 }) {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, "Poll 1 is archived"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    // A recorded run of the contract, e.g. captured on testnet. Steps that failed carry their
+    // error and leave no trace, as the chain reverts them.
+    #[derive(Deserialize)]
+    struct Transcript {
+        init: TranscriptStep<InitMsg>,
+        steps: Vec<TranscriptStep<HandleMsg>>,
+        /// queries run after the last step, with the responses they must return
+        checks: Vec<TranscriptCheck>,
+    }
+
+    #[derive(Deserialize)]
+    struct TranscriptCheck {
+        query: QueryMsg,
+        response: serde_json::Value,
+    }
+
+    #[derive(Deserialize)]
+    struct TranscriptStep<M> {
+        env: Env,
+        msg: M,
+        #[serde(default)]
+        error: Option<String>,
+    }
+
+    fn add_coins(balance: &mut Vec<Coin>, funds: &[Coin]) {
+        for fund in funds {
+            match balance.iter_mut().find(|coin| coin.denom == fund.denom) {
+                Some(coin) => coin.amount += fund.amount,
+                None => balance.push(fund.clone()),
+            }
+        }
+    }
+
+    /// Runs a transcript against a fresh instance and checks the query responses it lists. The
+    /// contract's balance follows the funds sent to it and the BankMsg::Send it returns.
+    fn replay(transcript: &str) -> Extern<MockStorage, MockApi, MockQuerier> {
+        let transcript: Transcript = serde_json::from_str(transcript).unwrap();
+        let mut deps = mock_dependencies(20, &transcript.init.env.message.sent_funds);
+        init(&mut deps, transcript.init.env, transcript.init.msg).unwrap();
+
+        for (index, step) in transcript.steps.into_iter().enumerate() {
+            let snapshot: Vec<_> = deps.storage.range(None, None, Order::Ascending).collect();
+            let before = deps.querier.query_all_balances(MOCK_CONTRACT_ADDR).unwrap();
+            let mut balance = before.clone();
+            add_coins(&mut balance, &step.env.message.sent_funds);
+            deps.querier
+                .update_balance(MOCK_CONTRACT_ADDR, balance.clone());
+
+            match (handle(&mut deps, step.env, step.msg), step.error) {
+                (Ok(res), None) => {
+                    for msg in res.messages {
+                        if let CosmosMsg::Bank(BankMsg::Send { amount, .. }) = msg {
+                            for sent in amount {
+                                let coin = balance
+                                    .iter_mut()
+                                    .find(|coin| coin.denom == sent.denom)
+                                    .unwrap();
+                                coin.amount = (coin.amount - sent.amount).unwrap();
+                            }
+                        }
+                    }
+                    deps.querier.update_balance(MOCK_CONTRACT_ADDR, balance);
+                }
+                (Err(err), Some(expected)) => {
+                    let error = match err {
+                        StdError::GenericErr { msg, .. } => msg,
+                        err => err.to_string(),
+                    };
+                    assert_eq!(error, expected, "step {}", index);
+                    let keys: Vec<_> = deps
+                        .storage
+                        .range(None, None, Order::Ascending)
+                        .map(|(key, _)| key)
+                        .collect();
+                    for key in keys {
+                        deps.storage.remove(&key);
+                    }
+                    for (key, value) in snapshot {
+                        deps.storage.set(&key, &value);
+                    }
+                    deps.querier.update_balance(MOCK_CONTRACT_ADDR, before);
+                }
+                (res, expected) => panic!(
+                    "step {}: expected error {:?}, got {:?}",
+                    index,
+                    expected,
+                    res.map(|res| res.log)
+                ),
+            }
+        }
+        for (index, check) in transcript.checks.into_iter().enumerate() {
+            let res = query(&deps, check.query).unwrap();
+            let response: serde_json::Value = serde_json::from_slice(res.as_slice()).unwrap();
+            assert_eq!(response, check.response, "check {}", index);
+        }
+        deps
+    }
+
+    #[test]
+    fn replay_end_poll_transcript() {
+        let deps = replay(include_str!("../tests/fixtures/replay/end_poll.json"));
+        let res = query(&deps, QueryMsg::Poll { poll_id: 1 }).unwrap();
+        let value: PollResponse = from_binary(&res).unwrap();
+        assert_eq!(value.status, PollStatus::Passed);
+        let balance = deps
+            .querier
+            .query_balance(MOCK_CONTRACT_ADDR, VOTING_TOKEN)
+            .unwrap();
+        assert_eq!(balance.amount, Uint128::from(600u128));
+    }
 }
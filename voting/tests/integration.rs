@@ -19,15 +19,18 @@
 //! 4. Anywhere you see query(&deps, ...) you must replace it with query(&mut deps, ...)
 
 use cosmwasm_std::{
-    coins, from_binary, log, BankMsg, CosmosMsg, Env, HandleResponse, HandleResult, HumanAddr,
-    InitResponse, StdError, Uint128,
+    coins, from_binary, log, BankMsg, CosmosMsg, Decimal, Env, HandleResponse, HandleResult,
+    HumanAddr, InitResponse, StdError, Uint128,
 };
 use cosmwasm_storage::to_length_prefixed;
 use cosmwasm_vm::testing::{handle, init, mock_env, mock_instance, query};
 use cosmwasm_vm::{from_slice, Api, Storage};
+use cw_math::Rounding;
 use cw_voting::contract::VOTING_TOKEN;
-use cw_voting::msg::{HandleMsg, InitMsg, PollResponse, QueryMsg};
-use cw_voting::state::{PollStatus, State};
+use cw_voting::msg::{
+    HandleMsg, InitMsg, PollResponse, ProposerEligibilityResponse, QueryMsg, VoteOption,
+};
+use cw_voting::state::{PollStatus, ProposerRequirement, State};
 
 // This line will test the output of cargo wasm
 static WASM: &[u8] = include_bytes!("../target/wasm32-unknown-unknown/release/cw_voting.wasm");
@@ -49,6 +52,25 @@ fn mock_env_height(signer: &HumanAddr, height: u64, time: u64) -> Env {
 fn init_msg() -> InitMsg {
     InitMsg {
         denom: String::from(VOTING_TOKEN),
+        proposer_requirement: None,
+        reward_denom: None,
+        rate_source: None,
+        initial_polls: None,
+        tally_executor: None,
+        weight_rounding: None,
+        bootstrap: None,
+        owner: None,
+        init_hook: None,
+        proposal_deposit: None,
+        tally_hook: None,
+        reward_token: None,
+        min_voting_period: None,
+        max_voting_period: None,
+        unbonding_period: None,
+        poll_creation_fee: None,
+        token: None,
+        extra_denoms: None,
+        restrict_poll_creation: None,
     }
 }
 
@@ -88,6 +110,24 @@ fn proper_initialization() {
                     .unwrap(),
                 poll_count: 0,
                 staked_tokens: Uint128::zero(),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: 0,
+                active_polls: 0,
             }
         );
         Ok(())
@@ -162,10 +202,20 @@ fn create_poll_msg(
     end_height: Option<u64>,
 ) -> HandleMsg {
     let msg = HandleMsg::CreatePoll {
-        quorum_percentage: Some(quorum_percentage),
+        quorum_percentage: Some(Decimal::from_ratio(quorum_percentage, 1u8)),
         description,
         start_height,
         end_height,
+        approval_quorum_percentage: None,
+        execution: None,
+        poll_type: None,
+        veto_threshold: None,
+        title: "Test poll".to_string(),
+        link: None,
+        reveal_blocks: None,
+        threshold_percentage: None,
+        tags: None,
+        max_vote_weight: None,
     };
     msg
 }
@@ -242,7 +292,10 @@ fn fails_end_poll_before_end_height() {
     let value: PollResponse = from_binary(&res).unwrap();
     assert_eq!(Some(10001), value.end_height);
 
-    let msg = HandleMsg::EndPoll { poll_id: 1 };
+    let msg = HandleMsg::EndPoll {
+        poll_id: 1,
+        limit: None,
+    };
 
     let handle_res: HandleResult = handle(&mut deps, env.clone(), msg);
 
@@ -292,8 +345,9 @@ fn happy_days_end_poll() {
 
     let msg = HandleMsg::CastVote {
         poll_id: POLL_ID,
-        vote: "yes".to_string(),
-        weight: Uint128::from(stake_amount),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(stake_amount)),
+        rationale: None,
     };
     let handle_res: HandleResponse = handle(&mut deps, env.clone(), msg).unwrap();
 
@@ -308,7 +362,10 @@ fn happy_days_end_poll() {
     );
     creator_env.block.height = &creator_env.block.height + 1;
 
-    let msg = HandleMsg::EndPoll { poll_id: POLL_ID };
+    let msg = HandleMsg::EndPoll {
+        poll_id: POLL_ID,
+        limit: None,
+    };
 
     let handle_res: HandleResponse = handle(&mut deps, creator_env.clone(), msg).unwrap();
 
@@ -343,7 +400,10 @@ fn end_poll_zero_quorum() {
     assert_create_poll_result(1, 0, 1001, 0, creator, handle_res);
 
     //end poll
-    let msg = HandleMsg::EndPoll { poll_id: 1 };
+    let msg = HandleMsg::EndPoll {
+        poll_id: 1,
+        limit: None,
+    };
     let handle_res: HandleResponse = handle(&mut deps, env2.clone(), msg).unwrap();
 
     assert_eq!(
@@ -391,8 +451,9 @@ fn end_poll_quorum_rejected() {
 
     let msg = HandleMsg::CastVote {
         poll_id: 1,
-        vote: "yes".to_string(),
-        weight: Uint128::from(10u128),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(10u128)),
+        rationale: None,
     };
     let handle_res: HandleResponse = handle(&mut deps, env.clone(), msg).unwrap();
 
@@ -406,7 +467,10 @@ fn end_poll_quorum_rejected() {
         ]
     );
 
-    let msg = HandleMsg::EndPoll { poll_id: 1 };
+    let msg = HandleMsg::EndPoll {
+        poll_id: 1,
+        limit: None,
+    };
 
     env.block.height = &env.block.height + 2;
 
@@ -472,13 +536,17 @@ fn end_poll_nay_rejected() {
     let env = mock_env(TEST_VOTER_2, &[]);
     let msg = HandleMsg::CastVote {
         poll_id: 1,
-        vote: "no".to_string(),
-        weight: Uint128::from(voter2_stake),
+        vote: VoteOption::No,
+        weight: Some(Uint128::from(voter2_stake)),
+        rationale: None,
     };
     let handle_res: HandleResponse = handle(&mut deps, env, msg).unwrap();
     assert_cast_vote_success(TEST_VOTER_2, voter2_stake, 1, handle_res);
 
-    let msg = HandleMsg::EndPoll { poll_id: 1 };
+    let msg = HandleMsg::EndPoll {
+        poll_id: 1,
+        limit: None,
+    };
 
     creator_env.block.height = &creator_env.block.height + 2;
     let handle_res: HandleResponse = handle(&mut deps, creator_env.clone(), msg.clone()).unwrap();
@@ -523,7 +591,10 @@ fn fails_end_poll_before_start_height() {
         &HumanAddr(TEST_CREATOR.to_string()),
         handle_res,
     );
-    let msg = HandleMsg::EndPoll { poll_id: 1 };
+    let msg = HandleMsg::EndPoll {
+        poll_id: 1,
+        limit: None,
+    };
 
     let handle_res: HandleResult = handle(&mut deps, env.clone(), msg);
 
@@ -557,8 +628,9 @@ fn fails_cast_vote_not_enough_staked() {
     let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
     let msg = HandleMsg::CastVote {
         poll_id: 1,
-        vote: "yes".to_string(),
-        weight: Uint128::from(1u128),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(1u128)),
+        rationale: None,
     };
 
     let res: HandleResult = handle(&mut deps, env, msg);
@@ -605,8 +677,9 @@ fn happy_days_cast_vote() {
     let weight = 10u128;
     let msg = HandleMsg::CastVote {
         poll_id: 1,
-        vote: "yes".to_string(),
-        weight: Uint128::from(weight),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(weight)),
+        rationale: None,
     };
 
     let handle_res: HandleResponse = handle(&mut deps, env, msg.clone()).unwrap();
@@ -644,6 +717,24 @@ fn happy_days_withdraw_voting_tokens() {
                     .unwrap(),
                 poll_count: 0,
                 staked_tokens: Uint128::from(staked_tokens),
+                proposer_requirement: None,
+                rate_source: None,
+                tally_executor: None,
+                weight_rounding: Rounding::Floor,
+                bootstrap: None,
+                proposal_deposit: None,
+                tally_hook: None,
+                min_voting_period: None,
+                max_voting_period: None,
+                min_description_length: None,
+                max_description_length: None,
+                unbonding_period: None,
+                poll_creation_fee: None,
+                token: None,
+                extra_denoms: vec![],
+                restrict_poll_creation: false,
+                staker_count: 1,
+                active_polls: 0,
             }
         );
         Ok(())
@@ -654,6 +745,7 @@ fn happy_days_withdraw_voting_tokens() {
     let env = mock_env(TEST_VOTER, &coins(staked_tokens, VOTING_TOKEN));
     let msg = HandleMsg::WithdrawVotingTokens {
         amount: Some(Uint128::from(staked_tokens)),
+        denom: None,
     };
 
     let handle_res: HandleResponse = handle(&mut deps, env, msg.clone()).unwrap();
@@ -683,6 +775,24 @@ fn happy_days_withdraw_voting_tokens() {
                         .unwrap(),
                     poll_count: 0,
                     staked_tokens: Uint128::zero(),
+                    proposer_requirement: None,
+                    rate_source: None,
+                    tally_executor: None,
+                    weight_rounding: Rounding::Floor,
+                    bootstrap: None,
+                    proposal_deposit: None,
+                    tally_hook: None,
+                    min_voting_period: None,
+                    max_voting_period: None,
+                    min_description_length: None,
+                    max_description_length: None,
+                    unbonding_period: None,
+                    poll_creation_fee: None,
+                    token: None,
+                    extra_denoms: vec![],
+                    restrict_poll_creation: false,
+                    staker_count: 0,
+                    active_polls: 0,
                 }
             );
             Ok(())
@@ -701,6 +811,7 @@ fn fails_withdraw_voting_tokens_no_stake() {
 
     let msg = HandleMsg::WithdrawVotingTokens {
         amount: Some(Uint128::from(11u128)),
+        denom: None,
     };
 
     let res: HandleResult = handle(&mut deps, env, msg);
@@ -730,6 +841,7 @@ fn fails_withdraw_too_many_tokens() {
     let env = mock_env(TEST_VOTER, &[]);
     let msg = HandleMsg::WithdrawVotingTokens {
         amount: Some(Uint128::from(11u128)),
+        denom: None,
     };
 
     let res: HandleResult = handle(&mut deps, env, msg);
@@ -774,16 +886,18 @@ fn fails_cast_vote_twice() {
     let weight = 1u128;
     let msg = HandleMsg::CastVote {
         poll_id: 1,
-        vote: "yes".to_string(),
-        weight: Uint128::from(weight),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(weight)),
+        rationale: None,
     };
     let handle_res: HandleResponse = handle(&mut deps, env.clone(), msg).unwrap();
     assert_cast_vote_success(TEST_VOTER, weight, 1, handle_res);
 
     let msg = HandleMsg::CastVote {
         poll_id: 1,
-        vote: "yes".to_string(),
-        weight: Uint128::from(weight),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(weight)),
+        rationale: None,
     };
     let res: HandleResult = handle(&mut deps, env.clone(), msg);
 
@@ -805,8 +919,9 @@ fn fails_cast_vote_without_poll() {
 
     let msg = HandleMsg::CastVote {
         poll_id: 0,
-        vote: "yes".to_string(),
-        weight: Uint128::from(1u128),
+        vote: VoteOption::Yes,
+        weight: Some(Uint128::from(1u128)),
+        rationale: None,
     };
     let env = mock_env(TEST_VOTER, &coins(11, VOTING_TOKEN));
 
@@ -879,6 +994,63 @@ fn fails_staking_wrong_token() {
     }
 }
 
+#[test]
+fn fails_create_poll_without_participation() {
+    let mut deps = mock_instance(WASM, &[]);
+    let msg = InitMsg {
+        denom: String::from(VOTING_TOKEN),
+        proposer_requirement: Some(ProposerRequirement {
+            min_polls: 1,
+            last_polls: 1,
+        }),
+        reward_denom: None,
+        rate_source: None,
+        initial_polls: None,
+        tally_executor: None,
+        weight_rounding: None,
+        bootstrap: None,
+        owner: None,
+        init_hook: None,
+        proposal_deposit: None,
+        tally_hook: None,
+        reward_token: None,
+        min_voting_period: None,
+        max_voting_period: None,
+        unbonding_period: None,
+        poll_creation_fee: None,
+        token: None,
+        extra_denoms: None,
+        restrict_poll_creation: None,
+    };
+    let creator = &address(0);
+    let env = mock_env_height(creator, 0, 0);
+    let _res: InitResponse = init(&mut deps, env.clone(), msg).unwrap();
+
+    let msg = create_poll_msg(0, "first".to_string(), None, None);
+    let _res: HandleResponse = handle(&mut deps, env.clone(), msg).unwrap();
+
+    let msg = create_poll_msg(0, "second".to_string(), None, None);
+    let res: HandleResult = handle(&mut deps, env, msg);
+    match res {
+        Ok(_) => panic!("Must return error"),
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Proposer must have voted in 1 of the last 1 polls")
+        }
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+
+    let res = query(
+        &mut deps,
+        QueryMsg::ProposerEligibility {
+            address: creator.clone(),
+        },
+    )
+    .unwrap();
+    let value: ProposerEligibilityResponse = from_binary(&res).unwrap();
+    assert!(!value.eligible);
+    assert_eq!(1, value.required_polls);
+}
+
 // helper to confirm the expected create_poll response
 fn assert_create_poll_result(
     poll_id: u64,